@@ -0,0 +1,336 @@
+//! Measures how long Muzak takes to scan a generated library.
+//!
+//! A corpus of short, tagged WAV and FLAC files is generated in a temporary directory, and
+//! `muzak --headless` is started with a separate data directory that only contains that corpus.
+//! Each sample is timed from the start of the process until every file is in the database.
+//!
+//! ```sh
+//! cargo bench --bench scan
+//! MUZAK_BENCH_FILES=20000 cargo bench --bench scan
+//! ```
+//!
+//! The data directory is separated with XDG_DATA_HOME, so this only works on Linux.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use async_std::task;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions};
+
+const SAMPLE_RATE: u64 = 44100;
+const BLOCK_SIZE: usize = 4096;
+/// A little over a second, in whole FLAC blocks.
+const BLOCKS: usize = 11;
+const TRACKS_PER_ALBUM: usize = 12;
+const ALBUMS_PER_ARTIST: usize = 4;
+
+/// The number of files scanned when MUZAK_BENCH_FILES isn't set.
+const DEFAULT_FILES: usize = 2000;
+/// Samples that take longer than this are abandoned.
+const TIMEOUT: Duration = Duration::from_secs(3600);
+
+#[derive(Clone, Copy)]
+enum Format {
+    Wav,
+    Flac,
+}
+
+struct Tags {
+    artist: String,
+    album: String,
+    title: String,
+    track: String,
+    date: String,
+}
+
+impl Tags {
+    fn new(artist: usize, album: usize, track: usize) -> Self {
+        Tags {
+            artist: format!("Artist {}", artist),
+            album: format!("Album {}-{}", artist, album),
+            title: format!("Track {}", track + 1),
+            track: (track + 1).to_string(),
+            date: (1970 + (artist + album) % 50).to_string(),
+        }
+    }
+}
+
+/// A square wave, different for every track so that files aren't identical.
+fn samples(seed: usize) -> Vec<i16> {
+    let period = 50 + seed % 200;
+
+    (0..BLOCK_SIZE * BLOCKS)
+        .map(|i| {
+            if (i / (period / 2)) % 2 == 1 {
+                8000
+            } else {
+                -8000
+            }
+        })
+        .collect()
+}
+
+fn write_wav(path: &Path, data: &[i16], tags: &Tags) {
+    let pcm: Vec<u8> = data.iter().flat_map(|v| v.to_le_bytes()).collect();
+
+    let mut info = b"INFO".to_vec();
+    for (key, value) in [
+        (b"IART", &tags.artist),
+        (b"IPRD", &tags.album),
+        (b"INAM", &tags.title),
+        (b"ITRK", &tags.track),
+        (b"ICRD", &tags.date),
+    ] {
+        let mut encoded = value.as_bytes().to_vec();
+        encoded.push(0);
+        if encoded.len() % 2 == 1 {
+            encoded.push(0);
+        }
+
+        info.extend_from_slice(key);
+        info.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+        info.extend_from_slice(&encoded);
+    }
+
+    let mut fmt = Vec::new();
+    fmt.extend_from_slice(&1u16.to_le_bytes());
+    fmt.extend_from_slice(&1u16.to_le_bytes());
+    fmt.extend_from_slice(&(SAMPLE_RATE as u32).to_le_bytes());
+    fmt.extend_from_slice(&(SAMPLE_RATE as u32 * 2).to_le_bytes());
+    fmt.extend_from_slice(&2u16.to_le_bytes());
+    fmt.extend_from_slice(&16u16.to_le_bytes());
+
+    let mut body = b"WAVE".to_vec();
+    for (id, chunk) in [(b"fmt ", &fmt), (b"LIST", &info), (b"data", &pcm)] {
+        body.extend_from_slice(id);
+        body.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        body.extend_from_slice(chunk);
+    }
+
+    let mut out = b"RIFF".to_vec();
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend_from_slice(&body);
+
+    fs::write(path, out).expect("couldn't write WAV file");
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+
+    for byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+/// Writes a mono, 16 bit FLAC file with uncompressed (verbatim) subframes.
+fn write_flac(path: &Path, data: &[i16], tags: &Tags) {
+    let mut streaminfo = Vec::new();
+    streaminfo.extend_from_slice(&(BLOCK_SIZE as u16).to_be_bytes());
+    streaminfo.extend_from_slice(&(BLOCK_SIZE as u16).to_be_bytes());
+    streaminfo.extend_from_slice(&[0; 6]);
+    // sample rate (20 bits), channels - 1 (3), bits per sample - 1 (5), total samples (36)
+    let packed = (SAMPLE_RATE << 44) | (15 << 36) | data.len() as u64;
+    streaminfo.extend_from_slice(&packed.to_be_bytes());
+    streaminfo.extend_from_slice(&[0; 16]);
+
+    let vendor = b"muzak scan benchmark";
+    let comments = [
+        format!("ARTIST={}", tags.artist),
+        format!("ALBUMARTIST={}", tags.artist),
+        format!("ALBUM={}", tags.album),
+        format!("TITLE={}", tags.title),
+        format!("TRACKNUMBER={}", tags.track),
+        format!("DATE={}", tags.date),
+    ];
+
+    let mut vorbis = Vec::new();
+    vorbis.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    vorbis.extend_from_slice(vendor);
+    vorbis.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for comment in &comments {
+        vorbis.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        vorbis.extend_from_slice(comment.as_bytes());
+    }
+
+    let mut out = b"fLaC".to_vec();
+    out.push(0);
+    out.extend_from_slice(&(streaminfo.len() as u32).to_be_bytes()[1..]);
+    out.extend_from_slice(&streaminfo);
+    out.push(0x84);
+    out.extend_from_slice(&(vorbis.len() as u32).to_be_bytes()[1..]);
+    out.extend_from_slice(&vorbis);
+
+    for (frame, block) in data.chunks(BLOCK_SIZE).enumerate() {
+        // block size of 4096, 44.1kHz, mono, 16 bit, frame number (< 128)
+        let mut body = vec![0xFF, 0xF8, 0xC9, 0x08, frame as u8];
+        body.push(crc8(&body));
+        body.push(0x02);
+        body.extend(block.iter().flat_map(|v| v.to_be_bytes()));
+
+        let crc = crc16(&body);
+        out.extend_from_slice(&body);
+        out.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    fs::write(path, out).expect("couldn't write FLAC file");
+}
+
+fn generate(directory: &Path, count: usize, formats: &[Format]) {
+    for i in 0..count {
+        let track = i % TRACKS_PER_ALBUM;
+        let album = (i / TRACKS_PER_ALBUM) % ALBUMS_PER_ARTIST;
+        let artist = i / (TRACKS_PER_ALBUM * ALBUMS_PER_ARTIST);
+        let format = formats[(i / TRACKS_PER_ALBUM) % formats.len()];
+
+        let folder = directory
+            .join(format!("Artist {}", artist))
+            .join(format!("Album {}", album));
+        fs::create_dir_all(&folder).expect("couldn't create album folder");
+
+        let tags = Tags::new(artist, album, track);
+
+        match format {
+            Format::Wav => write_wav(
+                &folder.join(format!("{:02} Track.wav", track + 1)),
+                &samples(i),
+                &tags,
+            ),
+            Format::Flac => write_flac(
+                &folder.join(format!("{:02} Track.flac", track + 1)),
+                &samples(i),
+                &tags,
+            ),
+        }
+    }
+}
+
+/// The number of tracks in the database, or 0 if it hasn't been created yet.
+fn track_count(database: &Path) -> usize {
+    if !database.exists() {
+        return 0;
+    }
+
+    task::block_on(async {
+        let mut connection = SqliteConnectOptions::new()
+            .filename(database)
+            .read_only(true)
+            .connect()
+            .await
+            .ok()?;
+
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM track")
+            .fetch_one(&mut connection)
+            .await
+            .ok()
+    })
+    .map_or(0, |v| v as usize)
+}
+
+/// Scans the corpus with an empty data directory, and returns how long it took.
+fn scan(workspace: &Path, corpus: &Path, files: usize) -> Duration {
+    let data = workspace.join("data");
+    let _ = fs::remove_dir_all(&data);
+    fs::create_dir_all(data.join("muzak")).expect("couldn't create data directory");
+
+    let settings = serde_json::json!({ "scanning": { "paths": [corpus] } });
+    fs::write(
+        data.join("muzak").join("settings.json"),
+        settings.to_string(),
+    )
+    .expect("couldn't write settings");
+
+    let database = data.join("muzak").join("library.db");
+
+    let start = Instant::now();
+    let mut process = Command::new(env!("CARGO_BIN_EXE_muzak"))
+        .arg("--headless")
+        .env("XDG_DATA_HOME", &data)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("couldn't start muzak");
+
+    let result = loop {
+        let count = track_count(&database);
+
+        if count >= files {
+            break Ok(start.elapsed());
+        }
+        if let Ok(Some(status)) = process.try_wait() {
+            break Err(format!("muzak exited early with {}", status));
+        }
+        if start.elapsed() > TIMEOUT {
+            break Err(format!(
+                "timed out with {} of {} files scanned",
+                count, files
+            ));
+        }
+
+        std::thread::sleep(Duration::from_millis(50));
+    };
+
+    let _ = process.kill();
+    let _ = process.wait();
+
+    result.unwrap_or_else(|e| panic!("{}", e))
+}
+
+fn scan_benchmark(c: &mut Criterion) {
+    let files = std::env::var("MUZAK_BENCH_FILES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_FILES);
+
+    let workspace: PathBuf =
+        std::env::temp_dir().join(format!("muzak-scan-{}", std::process::id()));
+    let corpus = workspace.join("corpus");
+    generate(&corpus, files, &[Format::Wav, Format::Flac]);
+
+    let mut group = c.benchmark_group("scan");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(files as u64));
+    group.bench_function("generated library", |b| {
+        b.iter_custom(|iterations| {
+            (0..iterations)
+                .map(|_| scan(&workspace, &corpus, files))
+                .sum()
+        })
+    });
+    group.finish();
+
+    let _ = fs::remove_dir_all(&workspace);
+}
+
+criterion_group!(benches, scan_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS bookmark (
+    id INTEGER PRIMARY KEY,
+    location TEXT NOT NULL,
+    name TEXT NOT NULL,
+    position REAL NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS bookmark_location_idx ON bookmark (location);
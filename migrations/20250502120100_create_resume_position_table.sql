@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS resume_position (
+    location TEXT PRIMARY KEY,
+    position REAL NOT NULL,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
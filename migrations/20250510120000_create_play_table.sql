@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS play (
+    id INTEGER PRIMARY KEY,
+    location TEXT NOT NULL,
+    started_at DATETIME NOT NULL,
+    listened INTEGER NOT NULL,
+    skipped BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX IF NOT EXISTS play_location_idx ON play (location);
+CREATE INDEX IF NOT EXISTS play_started_at_idx ON play (started_at);
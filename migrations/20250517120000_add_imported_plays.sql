@@ -0,0 +1,11 @@
+ALTER TABLE play ADD COLUMN source TEXT;
+
+CREATE TABLE IF NOT EXISTS unmatched_play (
+    id INTEGER PRIMARY KEY,
+    source TEXT NOT NULL,
+    artist TEXT NOT NULL,
+    title TEXT NOT NULL,
+    album TEXT,
+    played_at DATETIME NOT NULL,
+    UNIQUE (source, artist, title, played_at)
+);
@@ -0,0 +1,14 @@
+CREATE TRIGGER IF NOT EXISTS move_track_trigger AFTER UPDATE OF location ON track
+BEGIN
+    UPDATE play
+    SET location = NEW.location
+    WHERE location = OLD.location;
+
+    UPDATE bookmark
+    SET location = NEW.location
+    WHERE location = OLD.location;
+
+    UPDATE OR REPLACE resume_position
+    SET location = NEW.location
+    WHERE location = OLD.location;
+END;
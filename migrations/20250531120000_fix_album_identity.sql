@@ -0,0 +1,39 @@
+ALTER TABLE album ADD mbid TEXT;
+
+-- albums with the same title and artist are allowed if they were released on different dates or
+-- have different MusicBrainz release IDs
+DROP INDEX IF EXISTS album_title_artist_id_idx;
+CREATE INDEX IF NOT EXISTS album_title_artist_id_idx ON album (title, artist_id);
+CREATE UNIQUE INDEX IF NOT EXISTS album_mbid_idx ON album (mbid);
+
+CREATE TRIGGER IF NOT EXISTS move_album_trigger AFTER UPDATE OF album_id ON track
+BEGIN
+    DELETE FROM album
+    WHERE album.id = OLD.album_id
+    AND NOT EXISTS (
+        SELECT 1
+        FROM track
+        WHERE track.album_id = OLD.album_id
+    );
+END;
+
+-- Tracks that should be read again on the next scan, even if they haven't changed.
+CREATE TABLE IF NOT EXISTS rescan_track (
+    location TEXT PRIMARY KEY
+);
+
+-- Albums used to be identified by their title alone, which merged albums with the same title by
+-- different artists. The album artist of a track isn't stored, so the tracks of albums that look
+-- merged (with tracks by several artists or in several folders) are read again, which moves them
+-- to the right albums.
+INSERT OR IGNORE INTO rescan_track (location)
+SELECT location
+FROM track
+WHERE album_id IN (
+    SELECT album_id
+    FROM track
+    WHERE album_id IS NOT NULL
+    GROUP BY album_id
+    HAVING COUNT(DISTINCT COALESCE(artist_names, '')) > 1
+    OR COUNT(DISTINCT rtrim(location, replace(replace(location, '/', ''), '\', ''))) > 1
+);
@@ -0,0 +1,64 @@
+-- Every artist credited on a track or album, with the role they are credited for. album.artist_id
+-- is kept as the primary album artist, which identifies the album.
+CREATE TABLE IF NOT EXISTS track_artist (
+    track_id INTEGER NOT NULL,
+    artist_id INTEGER NOT NULL,
+    role TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (track_id, artist_id, role),
+    FOREIGN KEY (track_id) REFERENCES track (id) ON DELETE CASCADE,
+    FOREIGN KEY (artist_id) REFERENCES artist (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS track_artist_artist_id_idx ON track_artist (artist_id);
+
+CREATE TABLE IF NOT EXISTS album_artist (
+    album_id INTEGER NOT NULL,
+    artist_id INTEGER NOT NULL,
+    role TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (album_id, artist_id, role),
+    FOREIGN KEY (album_id) REFERENCES album (id) ON DELETE CASCADE,
+    FOREIGN KEY (artist_id) REFERENCES artist (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS album_artist_artist_id_idx ON album_artist (artist_id);
+
+-- artists are kept while anything credits them
+DROP TRIGGER IF EXISTS delete_artist_trigger;
+CREATE TRIGGER IF NOT EXISTS delete_artist_trigger AFTER DELETE ON album
+BEGIN
+    DELETE FROM artist
+    WHERE artist.id = OLD.artist_id
+    AND NOT EXISTS (SELECT 1 FROM album WHERE album.artist_id = OLD.artist_id)
+    AND NOT EXISTS (SELECT 1 FROM album_artist WHERE album_artist.artist_id = OLD.artist_id)
+    AND NOT EXISTS (SELECT 1 FROM track_artist WHERE track_artist.artist_id = OLD.artist_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS delete_track_artist_trigger AFTER DELETE ON track_artist
+BEGIN
+    DELETE FROM artist
+    WHERE artist.id = OLD.artist_id
+    AND NOT EXISTS (SELECT 1 FROM album WHERE album.artist_id = OLD.artist_id)
+    AND NOT EXISTS (SELECT 1 FROM album_artist WHERE album_artist.artist_id = OLD.artist_id)
+    AND NOT EXISTS (SELECT 1 FROM track_artist WHERE track_artist.artist_id = OLD.artist_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS delete_album_artist_trigger AFTER DELETE ON album_artist
+BEGIN
+    DELETE FROM artist
+    WHERE artist.id = OLD.artist_id
+    AND NOT EXISTS (SELECT 1 FROM album WHERE album.artist_id = OLD.artist_id)
+    AND NOT EXISTS (SELECT 1 FROM album_artist WHERE album_artist.artist_id = OLD.artist_id)
+    AND NOT EXISTS (SELECT 1 FROM track_artist WHERE track_artist.artist_id = OLD.artist_id);
+END;
+
+INSERT OR IGNORE INTO album_artist (album_id, artist_id, role, position)
+SELECT id, artist_id, 'primary', 0
+FROM album
+WHERE artist_id IS NOT NULL;
+
+-- the credits of existing tracks are read on the next scan
+INSERT OR IGNORE INTO rescan_track (location)
+SELECT location
+FROM track;
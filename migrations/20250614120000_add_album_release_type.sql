@@ -0,0 +1,8 @@
+-- The MusicBrainz release type of the album, like "album", "single" or "album; live", used to
+-- group the discography on artist pages.
+ALTER TABLE album ADD release_type TEXT;
+
+-- the release types of existing albums are read on the next scan
+INSERT OR IGNORE INTO rescan_track (location)
+SELECT location
+FROM track;
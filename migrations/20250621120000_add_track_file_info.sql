@@ -0,0 +1,9 @@
+-- The average bitrate of the file in kbps, and its format as a lowercase file extension, shown in
+-- the track list.
+ALTER TABLE track ADD bitrate INTEGER;
+ALTER TABLE track ADD format TEXT;
+
+-- existing tracks are given a bitrate and format on the next scan
+INSERT OR IGNORE INTO rescan_track (location)
+SELECT location
+FROM track;
@@ -0,0 +1,30 @@
+-- Genres, split out of track.genres so the library can be browsed by genre. track.genres is kept
+-- as the genre tag as it was written, for display.
+CREATE TABLE IF NOT EXISTS genre (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE
+);
+
+CREATE TABLE IF NOT EXISTS track_genre (
+    track_id INTEGER NOT NULL,
+    genre_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (track_id, genre_id),
+    FOREIGN KEY (track_id) REFERENCES track (id) ON DELETE CASCADE,
+    FOREIGN KEY (genre_id) REFERENCES genre (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS track_genre_genre_id_idx ON track_genre (genre_id);
+
+-- genres are kept while any track has them
+CREATE TRIGGER IF NOT EXISTS delete_track_genre_trigger AFTER DELETE ON track_genre
+BEGIN
+    DELETE FROM genre
+    WHERE genre.id = OLD.genre_id
+    AND NOT EXISTS (SELECT 1 FROM track_genre WHERE track_genre.genre_id = OLD.genre_id);
+END;
+
+-- the genres of existing tracks are read on the next scan
+INSERT OR IGNORE INTO rescan_track (location)
+SELECT location
+FROM track;
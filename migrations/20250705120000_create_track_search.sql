@@ -0,0 +1,100 @@
+-- The text tracks can be searched by, gathered from the track and its album, album artist and
+-- genres. The search index is filled from this, so it always has the same columns.
+CREATE VIEW IF NOT EXISTS track_search_source AS
+SELECT
+    track.id,
+    track.album_id,
+    track.title,
+    COALESCE(track.artist_names, artist.name, '') AS artist,
+    COALESCE(album.title, '') AS album,
+    COALESCE(artist.name, '') AS album_artist,
+    COALESCE(album.label, '') AS label,
+    COALESCE(
+        (
+            SELECT group_concat(genre.name, ' ')
+            FROM track_genre
+            JOIN genre ON genre.id = track_genre.genre_id
+            WHERE track_genre.track_id = track.id
+        ),
+        ''
+    ) AS genre
+FROM
+    track
+    LEFT JOIN album ON track.album_id = album.id
+    LEFT JOIN artist ON album.artist_id = artist.id;
+
+-- The full-text search index, with a row for each track, using the track's id as the rowid.
+CREATE VIRTUAL TABLE IF NOT EXISTS track_search USING fts5 (
+    title,
+    artist,
+    album,
+    album_artist,
+    label,
+    genre,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+-- the index is kept in sync by replacing the rows of the tracks that changed
+CREATE TRIGGER IF NOT EXISTS track_search_insert_trigger AFTER INSERT ON track
+BEGIN
+    INSERT INTO track_search (rowid, title, artist, album, album_artist, label, genre)
+    SELECT id, title, artist, album, album_artist, label, genre
+    FROM track_search_source
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS track_search_update_trigger AFTER UPDATE ON track
+BEGIN
+    DELETE FROM track_search WHERE rowid = OLD.id;
+    INSERT INTO track_search (rowid, title, artist, album, album_artist, label, genre)
+    SELECT id, title, artist, album, album_artist, label, genre
+    FROM track_search_source
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS track_search_delete_trigger AFTER DELETE ON track
+BEGIN
+    DELETE FROM track_search WHERE rowid = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS track_search_album_trigger AFTER UPDATE ON album
+BEGIN
+    DELETE FROM track_search WHERE rowid IN (SELECT id FROM track WHERE album_id = NEW.id);
+    INSERT INTO track_search (rowid, title, artist, album, album_artist, label, genre)
+    SELECT id, title, artist, album, album_artist, label, genre
+    FROM track_search_source
+    WHERE album_id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS track_search_artist_trigger AFTER UPDATE OF name ON artist
+BEGIN
+    DELETE FROM track_search WHERE rowid IN (
+        SELECT id FROM track WHERE album_id IN (SELECT id FROM album WHERE artist_id = NEW.id)
+    );
+    INSERT INTO track_search (rowid, title, artist, album, album_artist, label, genre)
+    SELECT id, title, artist, album, album_artist, label, genre
+    FROM track_search_source
+    WHERE album_id IN (SELECT id FROM album WHERE artist_id = NEW.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS track_search_genre_insert_trigger AFTER INSERT ON track_genre
+BEGIN
+    DELETE FROM track_search WHERE rowid = NEW.track_id;
+    INSERT INTO track_search (rowid, title, artist, album, album_artist, label, genre)
+    SELECT id, title, artist, album, album_artist, label, genre
+    FROM track_search_source
+    WHERE id = NEW.track_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS track_search_genre_delete_trigger AFTER DELETE ON track_genre
+BEGIN
+    DELETE FROM track_search WHERE rowid = OLD.track_id;
+    INSERT INTO track_search (rowid, title, artist, album, album_artist, label, genre)
+    SELECT id, title, artist, album, album_artist, label, genre
+    FROM track_search_source
+    WHERE id = OLD.track_id;
+END;
+
+INSERT INTO track_search (rowid, title, artist, album, album_artist, label, genre)
+SELECT id, title, artist, album, album_artist, label, genre
+FROM track_search_source;
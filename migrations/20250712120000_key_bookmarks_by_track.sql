@@ -0,0 +1,47 @@
+-- Bookmarks and resume positions belong to a track rather than to a file, so that they follow
+-- the track when it is moved and are removed along with it. Those of files that aren't in the
+-- library are dropped.
+
+-- the move trigger refers to both tables, it has to go before they can be replaced
+DROP TRIGGER IF EXISTS move_track_trigger;
+
+CREATE TABLE IF NOT EXISTS track_bookmark (
+    id INTEGER PRIMARY KEY,
+    track_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    position REAL NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (track_id) REFERENCES track (id) ON DELETE CASCADE
+);
+
+INSERT INTO track_bookmark (id, track_id, name, position, created_at)
+    SELECT bookmark.id, track.id, bookmark.name, bookmark.position, bookmark.created_at
+    FROM bookmark
+    JOIN track ON track.location = bookmark.location;
+
+DROP TABLE bookmark;
+ALTER TABLE track_bookmark RENAME TO bookmark;
+
+CREATE INDEX IF NOT EXISTS bookmark_track_id_idx ON bookmark (track_id);
+
+CREATE TABLE IF NOT EXISTS track_resume_position (
+    track_id INTEGER PRIMARY KEY,
+    position REAL NOT NULL,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (track_id) REFERENCES track (id) ON DELETE CASCADE
+);
+
+INSERT OR REPLACE INTO track_resume_position (track_id, position, updated_at)
+    SELECT track.id, resume_position.position, resume_position.updated_at
+    FROM resume_position
+    JOIN track ON track.location = resume_position.location;
+
+DROP TABLE resume_position;
+ALTER TABLE track_resume_position RENAME TO resume_position;
+
+CREATE TRIGGER IF NOT EXISTS move_track_trigger AFTER UPDATE OF location ON track
+BEGIN
+    UPDATE play
+    SET location = NEW.location
+    WHERE location = OLD.location;
+END;
@@ -0,0 +1,20 @@
+-- Plays belong to a track rather than to a file, so that the statistics follow the track when it
+-- is moved. The location is kept for plays of files that aren't in the library, and for plays of
+-- tracks that have been removed.
+ALTER TABLE play ADD COLUMN track_id INTEGER REFERENCES track (id) ON DELETE SET NULL;
+
+-- tracks skipped before they passed the play threshold are recorded, but aren't counted as plays
+ALTER TABLE play ADD COLUMN counted BOOLEAN NOT NULL DEFAULT TRUE;
+
+UPDATE play
+SET track_id = (SELECT id FROM track WHERE track.location = play.location);
+
+CREATE INDEX IF NOT EXISTS play_track_id_idx ON play (track_id);
+
+-- plays of a file that was removed from the library belong to it again once it is added back
+CREATE TRIGGER IF NOT EXISTS relink_play_trigger AFTER INSERT ON track
+BEGIN
+    UPDATE play
+    SET track_id = NEW.id
+    WHERE track_id IS NULL AND location = NEW.location;
+END;
@@ -0,0 +1,11 @@
+-- Every rescan writes the location of each track again, which made the trigger update the plays
+-- of tracks that hadn't moved.
+DROP TRIGGER IF EXISTS move_track_trigger;
+
+CREATE TRIGGER IF NOT EXISTS move_track_trigger AFTER UPDATE OF location ON track
+WHEN OLD.location <> NEW.location
+BEGIN
+    UPDATE play
+    SET location = NEW.location
+    WHERE track_id = NEW.id;
+END;
@@ -0,0 +1,43 @@
+-- An album is identified by its title, album artist, release date and MusicBrainz release ID (see
+-- get_album_id.sql). Albums that were created twice with the same identity are merged into the
+-- oldest one first, since the index can't be created while they exist. Moving the tracks removes
+-- the albums they leave empty (see move_album_trigger).
+UPDATE track
+SET album_id = (
+    SELECT MIN(other.id)
+    FROM album AS other
+    JOIN album AS this ON this.id = track.album_id
+    WHERE other.title = this.title
+    AND other.artist_id IS this.artist_id
+    AND other.release_date IS this.release_date
+    AND other.mbid IS this.mbid
+)
+WHERE album_id IN (
+    SELECT this.id
+    FROM album AS this
+    JOIN album AS other ON other.id < this.id
+    WHERE other.title = this.title
+    AND other.artist_id IS this.artist_id
+    AND other.release_date IS this.release_date
+    AND other.mbid IS this.mbid
+);
+
+DELETE FROM album
+WHERE EXISTS (
+    SELECT 1
+    FROM album AS other
+    WHERE other.id < album.id
+    AND other.title = album.title
+    AND other.artist_id IS album.artist_id
+    AND other.release_date IS album.release_date
+    AND other.mbid IS album.mbid
+);
+
+-- unique indexes treat NULLs as distinct, so missing values are replaced with ones that can't
+-- appear otherwise
+CREATE UNIQUE INDEX IF NOT EXISTS album_identity_idx ON album (
+    title,
+    IFNULL(artist_id, 0),
+    IFNULL(release_date, ''),
+    IFNULL(mbid, '')
+);
@@ -0,0 +1,120 @@
+-- The lyrics and comment tags of tracks, so they can be searched.
+ALTER TABLE track ADD lyrics TEXT;
+ALTER TABLE track ADD comment TEXT;
+
+-- FTS5 tables can't be given new columns, so the search index is made again with them
+DROP TRIGGER IF EXISTS track_search_insert_trigger;
+DROP TRIGGER IF EXISTS track_search_update_trigger;
+DROP TRIGGER IF EXISTS track_search_delete_trigger;
+DROP TRIGGER IF EXISTS track_search_album_trigger;
+DROP TRIGGER IF EXISTS track_search_artist_trigger;
+DROP TRIGGER IF EXISTS track_search_genre_insert_trigger;
+DROP TRIGGER IF EXISTS track_search_genre_delete_trigger;
+DROP TABLE IF EXISTS track_search;
+DROP VIEW IF EXISTS track_search_source;
+
+CREATE VIEW track_search_source AS
+SELECT
+    track.id,
+    track.album_id,
+    track.title,
+    COALESCE(track.artist_names, artist.name, '') AS artist,
+    COALESCE(album.title, '') AS album,
+    COALESCE(artist.name, '') AS album_artist,
+    COALESCE(album.label, '') AS label,
+    COALESCE(
+        (
+            SELECT group_concat(genre.name, ' ')
+            FROM track_genre
+            JOIN genre ON genre.id = track_genre.genre_id
+            WHERE track_genre.track_id = track.id
+        ),
+        ''
+    ) AS genre,
+    COALESCE(track.lyrics, '') AS lyrics,
+    COALESCE(track.comment, '') AS comment
+FROM
+    track
+    LEFT JOIN album ON track.album_id = album.id
+    LEFT JOIN artist ON album.artist_id = artist.id;
+
+CREATE VIRTUAL TABLE track_search USING fts5 (
+    title,
+    artist,
+    album,
+    album_artist,
+    label,
+    genre,
+    lyrics,
+    comment,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE TRIGGER track_search_insert_trigger AFTER INSERT ON track
+BEGIN
+    INSERT INTO track_search (rowid, title, artist, album, album_artist, label, genre, lyrics, comment)
+    SELECT id, title, artist, album, album_artist, label, genre, lyrics, comment
+    FROM track_search_source
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER track_search_update_trigger AFTER UPDATE ON track
+BEGIN
+    DELETE FROM track_search WHERE rowid = OLD.id;
+    INSERT INTO track_search (rowid, title, artist, album, album_artist, label, genre, lyrics, comment)
+    SELECT id, title, artist, album, album_artist, label, genre, lyrics, comment
+    FROM track_search_source
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER track_search_delete_trigger AFTER DELETE ON track
+BEGIN
+    DELETE FROM track_search WHERE rowid = OLD.id;
+END;
+
+CREATE TRIGGER track_search_album_trigger AFTER UPDATE ON album
+BEGIN
+    DELETE FROM track_search WHERE rowid IN (SELECT id FROM track WHERE album_id = NEW.id);
+    INSERT INTO track_search (rowid, title, artist, album, album_artist, label, genre, lyrics, comment)
+    SELECT id, title, artist, album, album_artist, label, genre, lyrics, comment
+    FROM track_search_source
+    WHERE album_id = NEW.id;
+END;
+
+CREATE TRIGGER track_search_artist_trigger AFTER UPDATE OF name ON artist
+BEGIN
+    DELETE FROM track_search WHERE rowid IN (
+        SELECT id FROM track WHERE album_id IN (SELECT id FROM album WHERE artist_id = NEW.id)
+    );
+    INSERT INTO track_search (rowid, title, artist, album, album_artist, label, genre, lyrics, comment)
+    SELECT id, title, artist, album, album_artist, label, genre, lyrics, comment
+    FROM track_search_source
+    WHERE album_id IN (SELECT id FROM album WHERE artist_id = NEW.id);
+END;
+
+CREATE TRIGGER track_search_genre_insert_trigger AFTER INSERT ON track_genre
+BEGIN
+    DELETE FROM track_search WHERE rowid = NEW.track_id;
+    INSERT INTO track_search (rowid, title, artist, album, album_artist, label, genre, lyrics, comment)
+    SELECT id, title, artist, album, album_artist, label, genre, lyrics, comment
+    FROM track_search_source
+    WHERE id = NEW.track_id;
+END;
+
+CREATE TRIGGER track_search_genre_delete_trigger AFTER DELETE ON track_genre
+BEGIN
+    DELETE FROM track_search WHERE rowid = OLD.track_id;
+    INSERT INTO track_search (rowid, title, artist, album, album_artist, label, genre, lyrics, comment)
+    SELECT id, title, artist, album, album_artist, label, genre, lyrics, comment
+    FROM track_search_source
+    WHERE id = OLD.track_id;
+END;
+
+INSERT INTO track_search (rowid, title, artist, album, album_artist, label, genre, lyrics, comment)
+SELECT id, title, artist, album, album_artist, label, genre, lyrics, comment
+FROM track_search_source;
+
+-- the lyrics and comments of existing tracks are read on the next scan
+INSERT OR IGNORE INTO rescan_track (location)
+SELECT location
+FROM track;
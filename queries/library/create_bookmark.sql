@@ -0,0 +1,3 @@
+INSERT INTO bookmark (track_id, name, position)
+    VALUES ($1, $2, $3)
+    RETURNING id;
@@ -0,0 +1,23 @@
+INSERT INTO
+    play (location, track_id, started_at, listened, skipped, source)
+SELECT
+    $1,
+    track.id,
+    $2,
+    $3,
+    FALSE,
+    $4
+FROM
+    track
+WHERE
+    track.location = $1
+    AND NOT EXISTS (
+        SELECT
+            1
+        FROM
+            play
+        WHERE
+            play.track_id = track.id
+            AND play.counted
+            AND play.started_at BETWEEN $5 AND $6
+    );
@@ -0,0 +1,4 @@
+INSERT INTO
+    play (location, track_id, started_at, listened, skipped, counted)
+VALUES
+    ($1, (SELECT id FROM track WHERE location = $1), $2, $3, $4, $5);
@@ -0,0 +1,4 @@
+INSERT OR IGNORE INTO
+    unmatched_play (source, artist, title, album, played_at)
+VALUES
+    ($1, $2, $3, $4, $5);
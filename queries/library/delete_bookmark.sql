@@ -0,0 +1,2 @@
+DELETE FROM bookmark
+WHERE id = $1;
@@ -0,0 +1,2 @@
+DELETE FROM resume_position
+WHERE track_id = $1;
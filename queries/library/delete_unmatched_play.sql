@@ -0,0 +1,3 @@
+DELETE FROM unmatched_play
+WHERE
+    id = $1;
@@ -1,2 +1,16 @@
-SELECT * FROM album
-WHERE id = $1;
+SELECT
+    album.*,
+    (
+        SELECT
+            COUNT(*)
+        FROM
+            play
+            JOIN track ON track.id = play.track_id
+        WHERE
+            track.album_id = album.id
+            AND play.counted
+    ) AS play_count
+FROM
+    album
+WHERE
+    id = $1;
@@ -0,0 +1,18 @@
+SELECT
+    id,
+    title
+FROM
+    album
+WHERE
+    artist_id = $1
+    OR id IN (
+        SELECT album_id FROM album_artist WHERE artist_id = $1
+        UNION
+        SELECT track.album_id
+        FROM track_artist
+        JOIN track ON track.id = track_artist.track_id
+        WHERE track_artist.artist_id = $1
+    )
+ORDER BY
+    release_date ASC,
+    title_sortable COLLATE NOCASE ASC;
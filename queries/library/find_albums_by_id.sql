@@ -0,0 +1,16 @@
+SELECT
+    album.*,
+    (
+        SELECT
+            COUNT(*)
+        FROM
+            play
+            JOIN track ON track.id = play.track_id
+        WHERE
+            track.album_id = album.id
+            AND play.counted
+    ) AS play_count
+FROM
+    album
+WHERE
+    id IN (SELECT value FROM json_each($1));
@@ -0,0 +1,12 @@
+SELECT
+    album.id,
+    album.title_sortable
+FROM
+    album
+    LEFT JOIN track ON track.album_id = album.id
+    LEFT JOIN play ON play.track_id = track.id AND play.counted
+GROUP BY
+    album.id
+ORDER BY
+    COUNT(play.id) DESC,
+    album.title_sortable COLLATE NOCASE ASC;
@@ -0,0 +1,8 @@
+SELECT
+    id,
+    title_sortable
+FROM
+    album
+ORDER BY
+    created_at DESC,
+    title_sortable COLLATE NOCASE ASC;
@@ -1,5 +0,0 @@
-SELECT
-    id,
-    title
-FROM
-    album;
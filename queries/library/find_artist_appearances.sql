@@ -0,0 +1,23 @@
+SELECT
+    id,
+    title,
+    release_date,
+    release_type,
+    thumb
+FROM
+    album
+WHERE
+    id IN (
+        SELECT
+            track.album_id
+        FROM
+            track_artist
+            JOIN track ON track.id = track_artist.track_id
+        WHERE
+            track_artist.artist_id = $1
+    )
+    AND artist_id IS NOT $1
+    AND id NOT IN (SELECT album_id FROM album_artist WHERE artist_id = $1)
+ORDER BY
+    release_date DESC,
+    title_sortable COLLATE NOCASE ASC;
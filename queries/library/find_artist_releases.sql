@@ -0,0 +1,14 @@
+SELECT
+    id,
+    title,
+    release_date,
+    release_type,
+    thumb
+FROM
+    album
+WHERE
+    artist_id = $1
+    OR id IN (SELECT album_id FROM album_artist WHERE artist_id = $1)
+ORDER BY
+    release_date DESC,
+    title_sortable COLLATE NOCASE ASC;
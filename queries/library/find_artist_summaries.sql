@@ -0,0 +1,38 @@
+SELECT
+    artist.id,
+    artist.name,
+    artist.name_sortable,
+    (
+        SELECT
+            COUNT(*)
+        FROM
+            album
+        WHERE
+            album.artist_id = artist.id
+            OR album.id IN (
+                SELECT album_id FROM album_artist WHERE album_artist.artist_id = artist.id
+            )
+    ) AS albums,
+    (
+        SELECT
+            COUNT(DISTINCT track_id)
+        FROM
+            track_artist
+        WHERE
+            track_artist.artist_id = artist.id
+    ) AS tracks,
+    (
+        SELECT
+            COUNT(*)
+        FROM
+            play
+        WHERE
+            play.counted
+            AND play.track_id IN (
+                SELECT track_id FROM track_artist WHERE track_artist.artist_id = artist.id
+            )
+    ) AS plays
+FROM
+    artist
+ORDER BY
+    artist.name_sortable COLLATE NOCASE ASC;
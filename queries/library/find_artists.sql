@@ -0,0 +1,7 @@
+SELECT
+    id,
+    name
+FROM
+    artist
+ORDER BY
+    name_sortable COLLATE NOCASE ASC;
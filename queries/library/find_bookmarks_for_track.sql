@@ -0,0 +1,3 @@
+SELECT * FROM bookmark
+WHERE track_id = $1
+ORDER BY position ASC;
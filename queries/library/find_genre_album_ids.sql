@@ -0,0 +1,8 @@
+SELECT DISTINCT
+    track.album_id
+FROM
+    track_genre
+    JOIN track ON track.id = track_genre.track_id
+WHERE
+    track_genre.genre_id = $1
+    AND track.album_id IS NOT NULL;
@@ -0,0 +1,20 @@
+SELECT
+    album.id,
+    album.title,
+    album.release_date,
+    album.release_type,
+    album.thumb
+FROM
+    album
+    LEFT JOIN artist ON album.artist_id = artist.id
+WHERE
+    album.id IN (
+        SELECT track.album_id
+        FROM track_genre
+        JOIN track ON track.id = track_genre.track_id
+        WHERE track_genre.genre_id = $1
+    )
+ORDER BY
+    artist.name_sortable COLLATE NOCASE ASC,
+    album.release_date ASC,
+    album.title_sortable COLLATE NOCASE ASC;
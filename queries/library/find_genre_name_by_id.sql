@@ -0,0 +1 @@
+SELECT name FROM genre WHERE id = $1;
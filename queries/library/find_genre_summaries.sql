@@ -0,0 +1,13 @@
+SELECT
+    genre.id,
+    genre.name,
+    COUNT(DISTINCT track.album_id) AS albums,
+    COUNT(track.id) AS tracks
+FROM
+    genre
+    JOIN track_genre ON track_genre.genre_id = genre.id
+    JOIN track ON track.id = track_genre.track_id
+GROUP BY
+    genre.id
+ORDER BY
+    genre.name COLLATE NOCASE ASC;
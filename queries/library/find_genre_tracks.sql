@@ -0,0 +1,24 @@
+SELECT
+    track.id,
+    track.title,
+    track.album_id,
+    track.track_number,
+    track.disc_number,
+    track.duration,
+    track.location,
+    track.artist_names,
+    album.title AS album_title,
+    album.release_date,
+    artist.name AS album_artist
+FROM
+    track_genre
+    JOIN track ON track.id = track_genre.track_id
+    LEFT JOIN album ON track.album_id = album.id
+    LEFT JOIN artist ON album.artist_id = artist.id
+WHERE
+    track_genre.genre_id = $1
+ORDER BY
+    artist.name_sortable COLLATE NOCASE ASC,
+    album.title_sortable COLLATE NOCASE ASC,
+    track.disc_number ASC,
+    track.track_number ASC;
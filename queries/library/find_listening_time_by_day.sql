@@ -0,0 +1,12 @@
+SELECT
+    date(started_at, 'localtime') AS day,
+    SUM(listened) AS listened
+FROM
+    play
+WHERE
+    started_at >= $1
+    AND started_at < $2
+GROUP BY
+    day
+ORDER BY
+    day ASC;
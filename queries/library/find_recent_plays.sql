@@ -0,0 +1,19 @@
+SELECT
+    play.id,
+    play.location,
+    play.started_at,
+    play.listened,
+    play.skipped,
+    play.counted,
+    track.title,
+    track.album_id,
+    COALESCE(track.artist_names, artist.name) AS artist_name
+FROM
+    play
+    LEFT JOIN track ON track.id = play.track_id
+    LEFT JOIN album ON album.id = track.album_id
+    LEFT JOIN artist ON artist.id = album.artist_id
+ORDER BY
+    play.started_at DESC
+LIMIT
+    $1;
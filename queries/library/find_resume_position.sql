@@ -0,0 +1,2 @@
+SELECT position FROM resume_position
+WHERE track_id = $1;
@@ -0,0 +1,21 @@
+SELECT
+    'album' AS kind,
+    album.id,
+    album.title,
+    artist.name AS artist_name,
+    NULL AS album_title,
+    album.label
+FROM
+    track_search
+    JOIN track ON track.id = track_search.rowid
+    JOIN album ON track.album_id = album.id
+    LEFT JOIN artist ON album.artist_id = artist.id
+WHERE
+    track_search MATCH $1
+    AND ($2 IS NULL OR CAST(substr(album.release_date, 1, 4) AS INTEGER) >= $2)
+    AND ($3 IS NULL OR CAST(substr(album.release_date, 1, 4) AS INTEGER) <= $3)
+GROUP BY
+    album.id
+ORDER BY
+    MIN(track_search.rank)
+LIMIT $4;
@@ -0,0 +1,24 @@
+SELECT
+    'album' AS kind,
+    album.id,
+    album.title,
+    artist.name AS artist_name,
+    NULL AS album_title,
+    album.label
+FROM
+    album
+    LEFT JOIN artist ON album.artist_id = artist.id
+WHERE
+    EXISTS (
+        SELECT 1
+        FROM track
+        WHERE
+            track.album_id = album.id
+            AND ($1 IS NULL OR track.id NOT IN (SELECT rowid FROM track_search WHERE track_search MATCH $1))
+    )
+    AND ($2 IS NULL OR CAST(substr(album.release_date, 1, 4) AS INTEGER) >= $2)
+    AND ($3 IS NULL OR CAST(substr(album.release_date, 1, 4) AS INTEGER) <= $3)
+ORDER BY
+    artist.name_sortable COLLATE NOCASE ASC,
+    album.title_sortable COLLATE NOCASE ASC
+LIMIT $4;
@@ -0,0 +1,26 @@
+SELECT
+    'artist' AS kind,
+    artist.id,
+    artist.name AS title,
+    NULL AS artist_name,
+    NULL AS album_title,
+    NULL AS label
+FROM
+    track_search
+    JOIN track ON track.id = track_search.rowid
+    LEFT JOIN album ON track.album_id = album.id
+    JOIN artist ON artist.id = album.artist_id OR artist.id IN (
+        SELECT track_artist.artist_id
+        FROM track_artist
+        WHERE track_artist.track_id = track.id AND track_artist.role IN ('primary', 'featured')
+    )
+WHERE
+    track_search MATCH $1
+    AND artist.name IS NOT NULL
+    AND ($2 IS NULL OR CAST(substr(album.release_date, 1, 4) AS INTEGER) >= $2)
+    AND ($3 IS NULL OR CAST(substr(album.release_date, 1, 4) AS INTEGER) <= $3)
+GROUP BY
+    artist.id
+ORDER BY
+    MIN(track_search.rank)
+LIMIT $4;
@@ -0,0 +1,33 @@
+SELECT
+    'artist' AS kind,
+    artist.id,
+    artist.name AS title,
+    NULL AS artist_name,
+    NULL AS album_title,
+    NULL AS label
+FROM
+    artist
+WHERE
+    artist.name IS NOT NULL
+    AND EXISTS (
+        SELECT 1
+        FROM
+            track
+            LEFT JOIN album ON track.album_id = album.id
+        WHERE
+            (
+                album.artist_id = artist.id
+                OR track.id IN (
+                    SELECT track_artist.track_id
+                    FROM track_artist
+                    WHERE track_artist.artist_id = artist.id
+                    AND track_artist.role IN ('primary', 'featured')
+                )
+            )
+            AND ($1 IS NULL OR track.id NOT IN (SELECT rowid FROM track_search WHERE track_search MATCH $1))
+            AND ($2 IS NULL OR CAST(substr(album.release_date, 1, 4) AS INTEGER) >= $2)
+            AND ($3 IS NULL OR CAST(substr(album.release_date, 1, 4) AS INTEGER) <= $3)
+    )
+ORDER BY
+    artist.name_sortable COLLATE NOCASE ASC
+LIMIT $4;
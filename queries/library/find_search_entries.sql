@@ -0,0 +1,34 @@
+SELECT
+    'artist' AS kind,
+    artist.id,
+    artist.name AS title,
+    NULL AS artist_name,
+    NULL AS album_title,
+    NULL AS label
+FROM
+    artist
+WHERE
+    artist.name IS NOT NULL
+UNION ALL
+SELECT
+    'album' AS kind,
+    album.id,
+    album.title,
+    artist.name AS artist_name,
+    NULL AS album_title,
+    album.label
+FROM
+    album
+    LEFT JOIN artist ON album.artist_id = artist.id
+UNION ALL
+SELECT
+    'track' AS kind,
+    track.id,
+    track.title,
+    COALESCE(track.artist_names, artist.name) AS artist_name,
+    album.title AS album_title,
+    NULL AS label
+FROM
+    track
+    LEFT JOIN album ON track.album_id = album.id
+    LEFT JOIN artist ON album.artist_id = artist.id;
@@ -0,0 +1,24 @@
+SELECT
+    track.id,
+    track.title,
+    track.album_id,
+    track.track_number,
+    track.disc_number,
+    track.duration,
+    track.location,
+    track.artist_names,
+    album.title AS album_title,
+    album.release_date,
+    artist.name AS album_artist
+FROM
+    track_search
+    JOIN track ON track.id = track_search.rowid
+    LEFT JOIN album ON track.album_id = album.id
+    LEFT JOIN artist ON album.artist_id = artist.id
+WHERE
+    track_search MATCH $1
+    AND ($2 IS NULL OR CAST(substr(album.release_date, 1, 4) AS INTEGER) >= $2)
+    AND ($3 IS NULL OR CAST(substr(album.release_date, 1, 4) AS INTEGER) <= $3)
+ORDER BY
+    track_search.rank
+LIMIT $4;
@@ -0,0 +1,22 @@
+SELECT
+    album.id,
+    album.title,
+    artist.name AS artist_name,
+    COUNT(*) AS plays,
+    SUM(play.listened) AS listened
+FROM
+    play
+    JOIN track ON track.id = play.track_id
+    JOIN album ON album.id = track.album_id
+    LEFT JOIN artist ON artist.id = album.artist_id
+WHERE
+    play.counted
+    AND play.started_at >= $1
+    AND play.started_at < $2
+GROUP BY
+    album.id
+ORDER BY
+    plays DESC,
+    listened DESC
+LIMIT
+    $3;
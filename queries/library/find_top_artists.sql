@@ -0,0 +1,21 @@
+SELECT
+    artist.id,
+    artist.name,
+    COUNT(*) AS plays,
+    SUM(play.listened) AS listened
+FROM
+    play
+    JOIN track ON track.id = play.track_id
+    JOIN album ON album.id = track.album_id
+    JOIN artist ON artist.id = album.artist_id
+WHERE
+    play.counted
+    AND play.started_at >= $1
+    AND play.started_at < $2
+GROUP BY
+    artist.id
+ORDER BY
+    plays DESC,
+    listened DESC
+LIMIT
+    $3;
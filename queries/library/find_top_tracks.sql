@@ -0,0 +1,23 @@
+SELECT
+    track.id,
+    track.title,
+    track.album_id,
+    COALESCE(track.artist_names, artist.name) AS artist_name,
+    COUNT(*) AS plays,
+    SUM(play.listened) AS listened
+FROM
+    play
+    JOIN track ON track.id = play.track_id
+    LEFT JOIN album ON album.id = track.album_id
+    LEFT JOIN artist ON artist.id = album.artist_id
+WHERE
+    play.counted
+    AND play.started_at >= $1
+    AND play.started_at < $2
+GROUP BY
+    track.id
+ORDER BY
+    plays DESC,
+    listened DESC
+LIMIT
+    $3;
@@ -0,0 +1,12 @@
+SELECT
+    track_artist.artist_id
+FROM
+    track_artist
+    JOIN track ON track.id = track_artist.track_id
+WHERE
+    track.location = $1
+    AND track_artist.role = 'primary'
+ORDER BY
+    track_artist.position ASC
+LIMIT
+    1;
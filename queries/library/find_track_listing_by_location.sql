@@ -0,0 +1,18 @@
+SELECT
+    track.id,
+    track.title,
+    track.album_id,
+    track.track_number,
+    track.disc_number,
+    track.duration,
+    track.location,
+    track.artist_names,
+    album.title AS album_title,
+    album.release_date,
+    artist.name AS album_artist
+FROM
+    track
+    LEFT JOIN album ON track.album_id = album.id
+    LEFT JOIN artist ON album.artist_id = artist.id
+WHERE
+    track.location = $1;
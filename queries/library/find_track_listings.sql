@@ -0,0 +1,21 @@
+SELECT
+    track.id,
+    track.title,
+    track.album_id,
+    track.track_number,
+    track.disc_number,
+    track.duration,
+    track.location,
+    track.artist_names,
+    album.title AS album_title,
+    album.release_date,
+    artist.name AS album_artist
+FROM
+    track
+    LEFT JOIN album ON track.album_id = album.id
+    LEFT JOIN artist ON album.artist_id = artist.id
+ORDER BY
+    artist.name_sortable COLLATE NOCASE ASC,
+    album.title_sortable COLLATE NOCASE ASC,
+    track.disc_number ASC,
+    track.track_number ASC;
@@ -0,0 +1,26 @@
+SELECT
+    track.id,
+    track.location,
+    track.album_id
+FROM
+    track
+    LEFT JOIN album ON track.album_id = album.id
+    LEFT JOIN artist ON album.artist_id = artist.id
+ORDER BY
+    CASE WHEN $1 = 'title' THEN track.title_sortable END COLLATE NOCASE,
+    CASE WHEN $1 = 'artist' THEN COALESCE(track.artist_names, artist.name) END COLLATE NOCASE,
+    CASE WHEN $1 = 'album' THEN album.title_sortable END COLLATE NOCASE,
+    CASE WHEN $1 = 'duration' THEN track.duration END,
+    CASE WHEN $1 = 'genre' THEN track.genres END COLLATE NOCASE,
+    CASE WHEN $1 = 'added' THEN track.created_at END,
+    CASE WHEN $1 = 'plays' THEN (
+        SELECT COUNT(*) FROM play WHERE play.track_id = track.id AND play.counted
+    ) END,
+    CASE WHEN $1 = 'bitrate' THEN track.bitrate END,
+    CASE WHEN $1 = 'format' THEN track.format END,
+    CASE WHEN $1 = 'path' THEN track.location END,
+    artist.name_sortable COLLATE NOCASE ASC,
+    album.title_sortable COLLATE NOCASE ASC,
+    track.disc_number ASC,
+    track.track_number ASC,
+    track.id ASC;
@@ -0,0 +1,21 @@
+SELECT
+    track.id,
+    track.title,
+    track.album_id,
+    COALESCE(track.artist_names, artist.name) AS artist_name,
+    album.title AS album_title,
+    track.duration,
+    track.track_number,
+    track.disc_number,
+    track.genres,
+    track.created_at,
+    (SELECT COUNT(*) FROM play WHERE play.track_id = track.id AND play.counted) AS plays,
+    track.bitrate,
+    track.format,
+    track.location
+FROM
+    track
+    LEFT JOIN album ON track.album_id = album.id
+    LEFT JOIN artist ON album.artist_id = artist.id
+WHERE
+    track.id IN (SELECT value FROM json_each($1));
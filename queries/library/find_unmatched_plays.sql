@@ -0,0 +1,11 @@
+SELECT
+    id,
+    source,
+    artist,
+    title,
+    album,
+    played_at
+FROM
+    unmatched_play
+ORDER BY
+    played_at ASC;
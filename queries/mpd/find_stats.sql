@@ -0,0 +1,9 @@
+SELECT
+    COUNT(DISTINCT artist.name),
+    COUNT(DISTINCT track.album_id),
+    COUNT(track.id),
+    COALESCE(SUM(track.duration), 0)
+FROM
+    track
+    LEFT JOIN album ON track.album_id = album.id
+    LEFT JOIN artist ON album.artist_id = artist.id;
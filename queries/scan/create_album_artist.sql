@@ -0,0 +1,3 @@
+INSERT INTO album_artist (album_id, artist_id, role, position)
+    VALUES ($1, $2, $3, $4)
+    ON CONFLICT (album_id, artist_id, role) DO UPDATE SET position = excluded.position;
@@ -0,0 +1,4 @@
+INSERT INTO genre (name)
+    VALUES ($1)
+    ON CONFLICT (name) DO NOTHING
+    RETURNING id;
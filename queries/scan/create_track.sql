@@ -1,5 +1,5 @@
-INSERT INTO track (title, title_sortable, album_id, track_number, disc_number, duration, location, genres, artist_names)
-    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+INSERT INTO track (title, title_sortable, album_id, track_number, disc_number, duration, location, genres, artist_names, bitrate, format, lyrics, comment)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
     ON CONFLICT (location) DO UPDATE SET
         title = EXCLUDED.title,
         title_sortable = EXCLUDED.title_sortable,
@@ -9,5 +9,9 @@ INSERT INTO track (title, title_sortable, album_id, track_number, disc_number, d
         duration = EXCLUDED.duration,
         location = EXCLUDED.location,
         genres = EXCLUDED.genres,
-        artist_names = EXCLUDED.artist_names
+        artist_names = EXCLUDED.artist_names,
+        bitrate = EXCLUDED.bitrate,
+        format = EXCLUDED.format,
+        lyrics = EXCLUDED.lyrics,
+        comment = EXCLUDED.comment
     RETURNING id;
@@ -0,0 +1,2 @@
+INSERT OR IGNORE INTO track_genre (track_id, genre_id, position)
+    VALUES ($1, $2, $3);
@@ -0,0 +1 @@
+DELETE FROM track WHERE substr(location, 1, length($1)) = $1;
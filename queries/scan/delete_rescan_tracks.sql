@@ -0,0 +1 @@
+DELETE FROM rescan_track;
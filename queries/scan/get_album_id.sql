@@ -1 +1,11 @@
-SELECT id FROM album WHERE title = $1;
+SELECT id, mbid
+FROM album
+WHERE mbid = $4
+OR (
+    title = $1
+    AND artist_id IS $2
+    AND release_date IS $3
+    AND (mbid IS NULL OR $4 IS NULL)
+)
+ORDER BY mbid IS $4 DESC
+LIMIT 1;
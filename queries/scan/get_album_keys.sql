@@ -0,0 +1,3 @@
+SELECT album.title, artist.name, album.release_date, album.mbid
+FROM album
+LEFT JOIN artist ON album.artist_id = artist.id;
@@ -0,0 +1 @@
+SELECT id FROM genre WHERE name = $1;
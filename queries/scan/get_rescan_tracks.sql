@@ -0,0 +1 @@
+SELECT location FROM rescan_track;
@@ -0,0 +1 @@
+SELECT location FROM track;
@@ -0,0 +1,3 @@
+UPDATE track
+SET location = $2 || substr(location, length($1) + 1)
+WHERE substr(location, 1, length($1)) = $1;
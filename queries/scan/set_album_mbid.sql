@@ -0,0 +1 @@
+UPDATE album SET mbid = $2 WHERE id = $1 AND mbid IS NULL;
@@ -0,0 +1 @@
+UPDATE album SET release_type = $2 WHERE id = $1 AND release_type IS NOT $2;
@@ -0,0 +1,263 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::{
+    control::{
+        errors::ControlError, send_request, ControlRequest, ControlResponse, OpenMode,
+        PlaybackStatus,
+    },
+    library::{
+        db::create_pool,
+        import::{
+            errors::ImportError,
+            foobar2000, import_plays,
+            lastfm::{read_history, LastFMHistory, RecentTracksSource, RecordedHistory},
+            listenbrainz, rematch_plays, ImportSummary, PlaySource,
+        },
+    },
+    services::mmb::lastfm::{client::LastFMClient, LASTFM_API_KEY, LASTFM_API_SECRET},
+    settings::create_settings,
+    ui::app::get_dirs,
+};
+
+#[derive(Parser, Debug)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+pub struct Args {
+    /// Files to add to the queue.
+    #[arg()]
+    pub files: Vec<PathBuf>,
+
+    /// Run without a window. Playback is controlled with the subcommands below.
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Add the files to the end of the queue. This is the default.
+    #[arg(long, group = "mode")]
+    pub enqueue: bool,
+
+    /// Play the files after the current track.
+    #[arg(long, group = "mode")]
+    pub next: bool,
+
+    /// Replace the queue with the files.
+    #[arg(long, group = "mode")]
+    pub replace: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Args {
+    pub fn open_mode(&self) -> OpenMode {
+        if self.next {
+            OpenMode::Next
+        } else if self.replace {
+            OpenMode::Replace
+        } else {
+            OpenMode::Enqueue
+        }
+    }
+}
+
+/// Commands sent to an already running instance of Muzak. Imports are run by this process.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Replace the queue with the given files and play them, or resume playback if no files are
+    /// given.
+    Play { paths: Vec<PathBuf> },
+    /// Pause playback.
+    Pause,
+    /// Skip to the next track.
+    Next,
+    /// Skip to the previous track.
+    Previous,
+    /// Stop playback.
+    Stop,
+    /// Show the currently playing track.
+    Status,
+    /// Scan the library for changes.
+    Scan,
+    /// Turn the private session on or off. Nothing is scrobbled during a private session.
+    PrivateSession {
+        #[arg(action = clap::ArgAction::Set, value_parser = clap::builder::BoolishValueParser::new())]
+        enabled: bool,
+    },
+    /// Import play history from another service or player into the local statistics.
+    Import {
+        #[command(subcommand)]
+        source: ImportCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ImportCommand {
+    /// Import the scrobbles of a last.fm user.
+    Lastfm {
+        user: String,
+        /// Read `user.getRecentTracks` responses from a directory (page-1.json, page-2.json, ...)
+        /// instead of requesting them from last.fm.
+        #[arg(long)]
+        recorded: Option<PathBuf>,
+    },
+    /// Import a ListenBrainz listen export (JSON or JSON lines).
+    Listenbrainz { file: PathBuf },
+    /// Import a foobar2000 playback statistics export (JSON).
+    Foobar2000 { file: PathBuf },
+    /// Try to match the plays that couldn't be matched to a track when they were imported.
+    Rematch,
+}
+
+/// The running instance may have a different working directory, so paths are made absolute
+/// before they are sent.
+pub fn absolute_paths(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    paths
+        .into_iter()
+        .map(|path| std::fs::canonicalize(&path).unwrap_or(path))
+        .collect()
+}
+
+async fn import(command: ImportCommand) -> Result<ImportSummary, ImportError> {
+    let directory = get_dirs().data_dir().to_path_buf();
+    std::fs::create_dir_all(&directory)?;
+    let pool = create_pool(directory.join("library.db")).await?;
+
+    match command {
+        ImportCommand::Lastfm { user, recorded } => {
+            let mut source: Box<dyn RecentTracksSource> = match recorded {
+                Some(path) => Box::new(RecordedHistory::new(path)),
+                None => {
+                    let (Some(key), Some(secret)) = (LASTFM_API_KEY, LASTFM_API_SECRET) else {
+                        return Err(ImportError::LastFMUnavailable);
+                    };
+
+                    let mut client = LastFMClient::new(key.to_string(), secret);
+                    let settings = create_settings(&directory.join("settings.json"));
+                    client.set_api_url(settings.lastfm.api_url);
+
+                    Box::new(LastFMHistory::new(client, user))
+                }
+            };
+
+            let plays = read_history(source.as_mut()).await?;
+            import_plays(&pool, PlaySource::LastFM, plays).await
+        }
+        ImportCommand::Listenbrainz { file } => {
+            let plays = listenbrainz::read_export(&std::fs::read_to_string(file)?)?;
+            import_plays(&pool, PlaySource::ListenBrainz, plays).await
+        }
+        ImportCommand::Foobar2000 { file } => {
+            let plays = foobar2000::read_statistics(&std::fs::read_to_string(file)?)?;
+            import_plays(&pool, PlaySource::Foobar2000, plays).await
+        }
+        ImportCommand::Rematch => rematch_plays(&pool).await,
+    }
+}
+
+async fn run_import(command: ImportCommand) -> i32 {
+    match import(command).await {
+        Ok(summary) => {
+            println!("imported: {}", summary.imported);
+            println!("already in the history: {}", summary.duplicates);
+            println!("unmatched: {}", summary.unmatched);
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+fn print_status(status: &PlaybackStatus) {
+    let Some(track) = &status.track else {
+        println!("{:?}, nothing playing", status.state);
+        return;
+    };
+
+    let title = status
+        .title
+        .clone()
+        .unwrap_or_else(|| track.to_string_lossy().to_string());
+
+    match &status.artist {
+        Some(artist) => println!("{:?}: {} - {}", status.state, artist, title),
+        None => println!("{:?}: {}", status.state, title),
+    }
+
+    if let Some(album) = &status.album {
+        println!("album: {}", album);
+    }
+
+    println!(
+        "position: {:02}:{:02} / {:02}:{:02}",
+        status.position / 60,
+        status.position % 60,
+        status.duration / 60,
+        status.duration % 60
+    );
+    println!(
+        "queue: {} of {}",
+        status.queue_position + 1,
+        status.queue_length
+    );
+    println!("volume: {:.0}%", status.volume * 100.0);
+    println!("library: {:?}", status.scan);
+}
+
+/// Sends the command to the running instance of Muzak and prints the result. Returns the exit
+/// code for the process.
+pub async fn run_command(command: Command) -> i32 {
+    let request = match command {
+        Command::Play { paths } => ControlRequest::Play {
+            paths: absolute_paths(paths),
+        },
+        Command::Pause => ControlRequest::Pause,
+        Command::Next => ControlRequest::Next,
+        Command::Previous => ControlRequest::Previous,
+        Command::Stop => ControlRequest::Stop,
+        Command::Status => ControlRequest::Status,
+        Command::Scan => ControlRequest::Scan,
+        Command::PrivateSession { enabled } => ControlRequest::PrivateSession { enabled },
+        Command::Import { source } => return run_import(source).await,
+    };
+
+    match send_request(&request) {
+        Ok(ControlResponse::Ok) => 0,
+        Ok(ControlResponse::Status(status)) => {
+            print_status(&status);
+            0
+        }
+        Ok(ControlResponse::Error { message }) => {
+            eprintln!("error: {}", message);
+            1
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+/// Forwards the files from the arguments to an already running instance of Muzak, which brings
+/// its window to the front. Returns false if there is no running instance, in which case this
+/// process should start normally.
+pub fn forward_to_running_instance(args: &Args) -> bool {
+    let request = ControlRequest::Open {
+        paths: absolute_paths(args.files.clone()),
+        mode: args.open_mode(),
+    };
+
+    match send_request(&request) {
+        Ok(ControlResponse::Error { message }) => {
+            eprintln!("error: {}", message);
+            true
+        }
+        Ok(_) => true,
+        Err(ControlError::NotRunning(_)) => false,
+        Err(e) => {
+            eprintln!("unable to contact the running instance: {}", e);
+            false
+        }
+    }
+}
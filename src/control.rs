@@ -0,0 +1,275 @@
+pub mod errors;
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    path::PathBuf,
+    sync::Arc,
+    thread,
+};
+
+use errors::ControlError;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+#[cfg(not(unix))]
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+use crate::{
+    library::scan::ScanEvent,
+    playback::{events::PlaybackEvent, thread::PlaybackState},
+};
+
+#[cfg(unix)]
+use crate::ui::app::get_dirs;
+
+/// The port the control server listens on, on platforms without Unix domain sockets. The server
+/// only binds to the loopback interface.
+#[cfg(not(unix))]
+const CONTROL_PORT: u16 = 51341;
+
+/// A request sent to a running instance of Muzak over the control socket. Requests are sent as a
+/// single line of JSON, and are answered with a single line of JSON containing a ControlResponse.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlRequest {
+    /// Replaces the queue with the given files and starts playing them. If no files are given,
+    /// playback is resumed instead.
+    Play { paths: Vec<PathBuf> },
+    /// Pauses playback.
+    Pause,
+    /// Skips to the next track in the queue.
+    Next,
+    /// Skips to the previous track in the queue.
+    Previous,
+    /// Stops playback.
+    Stop,
+    /// Requests a PlaybackStatus describing the current state of the player.
+    Status,
+    /// Starts a library scan.
+    Scan,
+    /// Sent when Muzak is started while another instance is already running. The files are added
+    /// to the queue of the running instance according to the mode, and its window is brought to
+    /// the front.
+    Open { paths: Vec<PathBuf>, mode: OpenMode },
+    /// Starts or ends a private session. Nothing is scrobbled during a private session, including
+    /// the track that is playing when it starts.
+    PrivateSession { enabled: bool },
+}
+
+/// How files forwarded from another launch of Muzak are added to the queue.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenMode {
+    /// Add the files to the end of the queue.
+    #[default]
+    Enqueue,
+    /// Insert the files directly after the current track.
+    Next,
+    /// Replace the queue with the files.
+    Replace,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum ControlResponse {
+    Ok,
+    Status(Box<PlaybackStatus>),
+    Error { message: String },
+}
+
+/// A snapshot of the state of the player, as returned by ControlRequest::Status.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlaybackStatus {
+    pub state: PlaybackState,
+    pub track: Option<PathBuf>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// The position in the current track, in seconds.
+    pub position: u64,
+    /// The duration of the current track, in seconds.
+    pub duration: u64,
+    pub volume: f64,
+    pub speed: f64,
+    pub shuffling: bool,
+    pub queue_position: usize,
+    pub queue_length: usize,
+    pub scan: ScanEvent,
+}
+
+impl Default for PlaybackStatus {
+    fn default() -> Self {
+        PlaybackStatus {
+            state: PlaybackState::Stopped,
+            track: None,
+            title: None,
+            artist: None,
+            album: None,
+            position: 0,
+            duration: 0,
+            volume: 1.0,
+            speed: 1.0,
+            shuffling: false,
+            queue_position: 0,
+            queue_length: 0,
+            scan: ScanEvent::ScanCompleteIdle,
+        }
+    }
+}
+
+impl PlaybackStatus {
+    /// Updates the status with an event from the playback thread. The queue length and scan state
+    /// are not part of the playback events, and have to be filled in separately.
+    pub fn update(&mut self, event: &PlaybackEvent) {
+        match event {
+            PlaybackEvent::StateChanged(v) => {
+                self.state = *v;
+
+                if *v == PlaybackState::Stopped {
+                    self.track = None;
+                }
+            }
+            PlaybackEvent::SongChanged(v) => {
+                self.track = Some(v.clone());
+                self.title = None;
+                self.artist = None;
+                self.album = None;
+            }
+            PlaybackEvent::DurationChanged(v) => self.duration = *v,
+            PlaybackEvent::PositionChanged(v) => self.position = *v,
+            PlaybackEvent::VolumeChanged(v) => self.volume = *v,
+            PlaybackEvent::SpeedChanged(v) => self.speed = *v,
+            PlaybackEvent::ShuffleToggled(v, _) => self.shuffling = *v,
+            PlaybackEvent::QueuePositionChanged(v) => self.queue_position = *v,
+            PlaybackEvent::MetadataUpdate(v) => {
+                self.title = v.name.clone();
+                self.artist = v.artist.clone();
+                self.album = v.album.clone();
+            }
+            _ => (),
+        }
+    }
+}
+
+type RequestHandler = dyn Fn(ControlRequest) -> ControlResponse + Send + Sync;
+
+/// Returns the path of the control socket.
+#[cfg(unix)]
+pub fn socket_path() -> PathBuf {
+    get_dirs().data_dir().join("control.sock")
+}
+
+#[cfg(unix)]
+fn bind() -> Result<UnixListener, ControlError> {
+    let path = socket_path();
+
+    if path.exists() {
+        if UnixStream::connect(&path).is_ok() {
+            return Err(ControlError::AlreadyRunning);
+        }
+
+        // left behind by an instance that did not exit cleanly
+        std::fs::remove_file(&path)?;
+    }
+
+    Ok(UnixListener::bind(&path)?)
+}
+
+#[cfg(not(unix))]
+fn bind() -> Result<TcpListener, ControlError> {
+    TcpListener::bind(("127.0.0.1", CONTROL_PORT)).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AddrInUse => ControlError::AlreadyRunning,
+        _ => ControlError::Io(e),
+    })
+}
+
+#[cfg(unix)]
+fn connect() -> std::io::Result<UnixStream> {
+    UnixStream::connect(socket_path())
+}
+
+#[cfg(not(unix))]
+fn connect() -> std::io::Result<TcpStream> {
+    TcpStream::connect(("127.0.0.1", CONTROL_PORT))
+}
+
+/// Starts listening on the control socket. Connections are accepted on a new thread, and each
+/// connection is handled on its own thread, so the handler may block while the request is
+/// processed.
+pub fn start_server(
+    handler: impl Fn(ControlRequest) -> ControlResponse + Send + Sync + 'static,
+) -> Result<(), ControlError> {
+    let listener = bind()?;
+    let handler: Arc<RequestHandler> = Arc::new(handler);
+
+    thread::Builder::new()
+        .name("control".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        warn!("Failed to accept control connection: {:?}", e);
+                        continue;
+                    }
+                };
+                let handler = handler.clone();
+
+                thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream, handler.as_ref()) {
+                        warn!("Failed to handle control request: {}", e);
+                    }
+                });
+            }
+        })
+        .expect("could not start control thread");
+
+    Ok(())
+}
+
+fn handle_connection<S: Read + Write>(
+    stream: S,
+    handler: &RequestHandler,
+) -> Result<(), ControlError> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+
+    let response = match serde_json::from_str::<ControlRequest>(&line) {
+        Ok(request) => {
+            debug!("Control request: {:?}", request);
+            handler(request)
+        }
+        Err(e) => ControlResponse::Error {
+            message: format!("invalid request: {}", e),
+        },
+    };
+
+    let mut stream = reader.into_inner();
+    serde_json::to_writer(&mut stream, &response)?;
+    stream.write_all(b"\n")?;
+    stream.flush()?;
+
+    Ok(())
+}
+
+/// Whether another instance of Muzak is listening on the control socket.
+pub fn is_running() -> bool {
+    connect().is_ok()
+}
+
+/// Sends a request to the running instance of Muzak and waits for the response.
+pub fn send_request(request: &ControlRequest) -> Result<ControlResponse, ControlError> {
+    let mut stream = connect().map_err(ControlError::NotRunning)?;
+    serde_json::to_writer(&mut stream, request)?;
+    stream.write_all(b"\n")?;
+    stream.flush()?;
+
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+
+    Ok(serde_json::from_str(&line)?)
+}
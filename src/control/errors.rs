@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ControlError {
+    #[error("Muzak is not running (could not connect to the control socket: {0})")]
+    NotRunning(std::io::Error),
+    #[error("Another instance of Muzak is already running")]
+    AlreadyRunning,
+    #[error("Control socket IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid control message: {0}")]
+    Protocol(#[from] serde_json::Error),
+}
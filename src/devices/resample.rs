@@ -166,6 +166,10 @@ pub fn match_bit_depth(target_frame: PlaybackFrame, target_depth: SampleFormat)
 pub struct Resampler {
     resampler: FftFixedIn<f32>,
     duration: u64,
+    /// Samples that have been submitted but not yet resampled. This is only used when frames that
+    /// aren't exactly one chunk long are submitted (for example, when time-stretching or at the
+    /// ends of an A-B loop).
+    pending: Vec<Vec<f32>>,
 }
 
 impl Resampler {
@@ -189,6 +193,7 @@ impl Resampler {
         Resampler {
             resampler,
             duration,
+            pending: vec![Vec::new(); channels as usize],
         }
     }
 
@@ -202,14 +207,12 @@ impl Resampler {
         }
         let source: Vec<Vec<f32>> = convert_samples(frame.samples);
 
-        let resampled = if source[0].len() < self.duration as usize {
-            self.resampler
-                .process_partial(Some(&source), None)
-                .expect("resampler error")
-        } else {
+        let resampled = if self.pending[0].is_empty() && source[0].len() == self.duration as usize {
             self.resampler
                 .process(&source, None)
                 .expect("resampler error")
+        } else {
+            self.process_buffered(source)
         };
 
         match_bit_depth(
@@ -220,4 +223,119 @@ impl Resampler {
             target_format.sample_type,
         )
     }
+
+    /// Resamples the samples that are still waiting for a full chunk, padded with silence. This
+    /// should be called at the end of a track, as its last frame is usually shorter than a chunk.
+    pub fn flush(&mut self, target_format: &FormatInfo) -> Option<PlaybackFrame> {
+        if self.pending[0].is_empty() {
+            return None;
+        }
+
+        let channels = self.pending.len();
+        let source = std::mem::replace(&mut self.pending, vec![Vec::new(); channels]);
+        let resampled = self
+            .resampler
+            .process_partial(Some(&source), None)
+            .expect("resampler error");
+
+        Some(match_bit_depth(
+            PlaybackFrame {
+                samples: Samples::Float32(resampled),
+                rate: target_format.sample_rate,
+            },
+            target_format.sample_type,
+        ))
+    }
+
+    /// Resamples frames that aren't exactly one chunk long. Full chunks are resampled immediately,
+    /// and the remainder is kept until the next call.
+    fn process_buffered(&mut self, source: Vec<Vec<f32>>) -> Vec<Vec<f32>> {
+        let chunk = self.duration as usize;
+
+        for (pending, new) in self.pending.iter_mut().zip(source) {
+            pending.extend(new);
+        }
+
+        let mut output: Vec<Vec<f32>> = vec![Vec::new(); self.pending.len()];
+
+        while self.pending[0].len() >= chunk {
+            let input: Vec<Vec<f32>> = self
+                .pending
+                .iter_mut()
+                .map(|v| v.drain(..chunk).collect())
+                .collect();
+
+            let resampled = self
+                .resampler
+                .process(&input, None)
+                .expect("resampler error");
+
+            for (out, new) in output.iter_mut().zip(resampled) {
+                out.extend(new);
+            }
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::format::{BufferSize, ChannelSpec};
+
+    const CHUNK: usize = 1024;
+
+    fn format() -> FormatInfo {
+        FormatInfo {
+            originating_provider: "test",
+            sample_type: SampleFormat::Float32,
+            sample_rate: 88200,
+            buffer_size: BufferSize::Unknown,
+            channels: ChannelSpec::Count(2),
+            rate_channel_ratio: 2,
+            rate_channel_ratio_fixed: false,
+        }
+    }
+
+    fn frame(length: usize) -> PlaybackFrame {
+        PlaybackFrame {
+            samples: Samples::Float32(vec![vec![0.5; length]; 2]),
+            rate: 44100,
+        }
+    }
+
+    fn resample(resampler: &mut Resampler, lengths: &[usize]) -> usize {
+        lengths
+            .iter()
+            .map(|length| {
+                resampler
+                    .convert_formats(frame(*length), &format())
+                    .samples
+                    .len()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn loop_boundaries_add_no_samples() {
+        let mut resampler = Resampler::new(44100, 88200, CHUNK as u64, 2);
+
+        // a loop ending 300 samples into the second frame, then starting 300 samples into a frame
+        let output = resample(&mut resampler, &[CHUNK, 300, CHUNK - 300, CHUNK]);
+
+        assert_eq!(output, CHUNK * 3 * 2);
+        assert!(resampler.flush(&format()).is_none());
+    }
+
+    #[test]
+    fn the_end_of_a_track_is_flushed() {
+        let mut resampler = Resampler::new(44100, 88200, CHUNK as u64, 2);
+
+        let output = resample(&mut resampler, &[CHUNK, 300]);
+        let flushed = resampler.flush(&format()).expect("samples were left");
+
+        assert_eq!(output, CHUNK * 2);
+        assert_eq!(flushed.samples.len(), CHUNK * 2);
+    }
 }
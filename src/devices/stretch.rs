@@ -0,0 +1,135 @@
+use std::f32::consts::PI;
+
+/// The length of each analysis window, in frames.
+const WINDOW: usize = 1024;
+/// The distance between two consecutive synthesis windows, in frames. Windows overlap by half.
+const HOP: usize = WINDOW / 2;
+/// How far (in frames, in either direction) the stretcher is allowed to move an analysis window to
+/// find the position that best lines up with the previous window.
+const SEEK: usize = 256;
+
+/// A pitch-preserving time stretcher, implemented with WSOLA (waveform similarity overlap-add).
+///
+/// Audio is cut into overlapping windows which are read from the input at `HOP * speed` frame
+/// intervals, and written to the output at `HOP` frame intervals. Before each window is written,
+/// it is moved by up to `SEEK` frames to the position where it best matches the natural
+/// continuation of the previous window, which avoids the phasing artifacts of plain overlap-add.
+///
+/// The stretcher is streaming: samples can be submitted in chunks of any length, and the output
+/// is returned as soon as enough input is available. The output of a single call may therefore be
+/// empty.
+pub struct TimeStretcher {
+    channels: usize,
+    speed: f64,
+    input: Vec<Vec<f32>>,
+    tail: Vec<Vec<f32>>,
+    analysis_pos: f64,
+    prev_pos: Option<usize>,
+}
+
+fn fade_in(i: usize) -> f32 {
+    0.5 - 0.5 * (PI * i as f32 / HOP as f32).cos()
+}
+
+impl TimeStretcher {
+    pub fn new(channels: usize, speed: f64) -> Self {
+        TimeStretcher {
+            channels,
+            speed,
+            input: vec![Vec::new(); channels],
+            tail: vec![vec![0.0; HOP]; channels],
+            analysis_pos: SEEK as f64,
+            prev_pos: None,
+        }
+    }
+
+    /// Submits new samples to the stretcher and returns all of the output that can be produced.
+    pub fn process(&mut self, samples: Vec<Vec<f32>>) -> Vec<Vec<f32>> {
+        for (buffer, new) in self.input.iter_mut().zip(samples) {
+            buffer.extend(new);
+        }
+
+        let mut output = vec![Vec::new(); self.channels];
+
+        loop {
+            let nominal = self.analysis_pos.round() as usize;
+            if nominal + SEEK + WINDOW > self.input[0].len() {
+                break;
+            }
+
+            let pos = match self.prev_pos {
+                Some(prev) => self.best_position(prev + HOP, nominal),
+                None => nominal,
+            };
+
+            for (channel, out) in output.iter_mut().enumerate() {
+                let segment = &self.input[channel][pos..pos + WINDOW];
+                let tail = &mut self.tail[channel];
+
+                for i in 0..HOP {
+                    let fade = fade_in(i);
+                    out.push(tail[i] * (1.0 - fade) + segment[i] * fade);
+                }
+
+                tail.copy_from_slice(&segment[HOP..WINDOW]);
+            }
+
+            self.prev_pos = Some(pos);
+            self.analysis_pos += HOP as f64 * self.speed;
+        }
+
+        self.drain_consumed();
+
+        output
+    }
+
+    /// Finds the window position within `SEEK` frames of `nominal` that is most similar to the
+    /// window starting at `natural`.
+    fn best_position(&self, natural: usize, nominal: usize) -> usize {
+        let mut best = nominal;
+        let mut best_score = f32::MIN;
+
+        for candidate in (nominal - SEEK)..=(nominal + SEEK) {
+            let mut correlation = 0.0;
+            let mut energy = 0.0;
+
+            for i in 0..HOP {
+                let (mut a, mut b) = (0.0, 0.0);
+                for channel in &self.input {
+                    a += channel[natural + i];
+                    b += channel[candidate + i];
+                }
+                correlation += a * b;
+                energy += b * b;
+            }
+
+            let score = correlation / (energy + f32::EPSILON).sqrt();
+            if score > best_score {
+                best_score = score;
+                best = candidate;
+            }
+        }
+
+        best
+    }
+
+    /// Removes input that can no longer be read by any future window.
+    fn drain_consumed(&mut self) {
+        let next_start = (self.analysis_pos.round() as usize).saturating_sub(SEEK);
+        let consumed = match self.prev_pos {
+            Some(prev) => next_start.min(prev + HOP),
+            None => next_start,
+        };
+
+        if consumed == 0 {
+            return;
+        }
+
+        for channel in self.input.iter_mut() {
+            channel.drain(..consumed.min(channel.len()));
+        }
+
+        self.analysis_pos -= consumed as f64;
+        self.prev_pos = self.prev_pos.map(|v| v - consumed);
+    }
+}
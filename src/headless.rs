@@ -0,0 +1,335 @@
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+    sync::{atomic::AtomicBool, mpsc, Arc, Mutex, RwLock},
+    thread,
+};
+
+use async_std::channel;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use tracing::{error, info, warn};
+
+use crate::{
+    control::{errors::ControlError, start_server, ControlRequest, ControlResponse, OpenMode},
+    library::{
+        db::create_pool,
+        scan::{ScanEvent, ScanInterface, ScanThread},
+    },
+    mpd::start_mpd_server,
+    playback::{
+        headless::HeadlessPlaybackInterface,
+        history::{start_play_history, PlayRecorder},
+        queue::QueueItemData,
+        resume::start_resume_positions,
+        thread::PlaybackThread,
+    },
+    remote::start_remote_server,
+    services::mmb::{
+        lastfm::{client::LastFMClient, types::Session, LastFM, LASTFM_API_KEY, LASTFM_API_SECRET},
+        listenbrainz::{client::ListenBrainzClient, ListenBrainz},
+        scrobbling::ScrobbleFilter,
+        MediaMetadataBroadcastService, PlayerHandle,
+    },
+    settings::{create_settings, Settings},
+    ui::app::get_dirs,
+};
+
+type PendingRequest = (ControlRequest, mpsc::Sender<ControlResponse>);
+
+/// Creates the MMBS services available without a window: Last.fm, if an account has been
+/// connected in the user interface, and ListenBrainz, if a token has been set.
+fn create_services(
+    settings: &Settings,
+    filter: ScrobbleFilter,
+) -> Vec<Box<dyn MediaMetadataBroadcastService>> {
+    let mut services: Vec<Box<dyn MediaMetadataBroadcastService>> = Vec::new();
+    let directory = get_dirs().data_dir().to_path_buf();
+    let path = directory.join("lastfm.json");
+
+    if let (Some(key), Some(secret), Ok(file)) =
+        (LASTFM_API_KEY, LASTFM_API_SECRET, File::open(path))
+    {
+        let reader = std::io::BufReader::new(file);
+
+        match serde_json::from_reader::<_, Session>(reader) {
+            Ok(session) => {
+                let mut client = LastFMClient::new(key.to_string(), secret);
+                client.set_session(session.key);
+                client.set_api_url(settings.lastfm.api_url.clone());
+
+                let cache_path = directory.join("lastfm-scrobbles.json");
+                services.push(Box::new(LastFM::new(client, cache_path, filter.clone())));
+            }
+            Err(e) => {
+                error!("The last.fm session information could not be read: {:?}", e);
+                warn!("You will not be logged in to last.fm.");
+            }
+        }
+    }
+
+    if let Some(token) = settings
+        .listenbrainz
+        .token
+        .clone()
+        .filter(|v| !v.is_empty())
+    {
+        let mut client = ListenBrainzClient::new(token);
+        client.set_api_url(settings.listenbrainz.api_url.clone());
+
+        let cache_path = directory.join("listenbrainz-listens.json");
+        services.push(Box::new(ListenBrainz::new(client, cache_path, filter)));
+    }
+
+    services
+}
+
+/// Watches the settings file, and passes changes to the library folders on to the scanner, like
+/// the settings model does in the user interface. Other settings are only read on startup.
+fn watch_settings(path: PathBuf, scan: Arc<Mutex<ScanInterface>>) {
+    let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+
+    let mut watcher = match notify::recommended_watcher(tx) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            warn!("Could not watch the settings file: {:?}", e);
+            return;
+        }
+    };
+
+    let directory = path
+        .parent()
+        .expect("settings file has no parent directory");
+    if let Err(e) = watcher.watch(directory, RecursiveMode::NonRecursive) {
+        warn!("Could not watch the settings file: {:?}", e);
+        return;
+    }
+
+    thread::Builder::new()
+        .name("headless-settings".to_string())
+        .spawn(move || {
+            // the file stops being watched once the watcher is dropped
+            let _watcher = watcher;
+
+            while let Ok(event) = rx.recv() {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(e) => {
+                        warn!("watch error: {:?}", e);
+                        continue;
+                    }
+                };
+
+                if !event.paths.iter().any(|v| v.ends_with("settings.json")) {
+                    continue;
+                }
+
+                if let EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) =
+                    event.kind
+                {
+                    // the scanner ignores settings where the library folders haven't changed
+                    let settings = create_settings(&path);
+                    scan.lock()
+                        .expect("couldn't get scanner")
+                        .update_settings(settings.scanning);
+                }
+            }
+        })
+        .expect("could not start headless settings thread");
+}
+
+struct Daemon {
+    playback: Mutex<HeadlessPlaybackInterface>,
+    scan: Arc<Mutex<ScanInterface>>,
+    scan_state: Arc<Mutex<ScanEvent>>,
+    queue: Arc<RwLock<Vec<QueueItemData>>>,
+    scrobble_filter: ScrobbleFilter,
+}
+
+impl Daemon {
+    fn handle(&self, request: ControlRequest) -> ControlResponse {
+        let playback = self
+            .playback
+            .lock()
+            .expect("couldn't get playback interface");
+
+        match request {
+            ControlRequest::Play { paths } if paths.is_empty() => playback.play(),
+            ControlRequest::Play { paths } => playback.replace_queue(
+                paths
+                    .into_iter()
+                    .map(|path| QueueItemData::new(path, None, None))
+                    .collect(),
+            ),
+            ControlRequest::Pause => playback.pause(),
+            ControlRequest::Next => playback.next(),
+            ControlRequest::Previous => playback.previous(),
+            ControlRequest::Stop => playback.stop(),
+            ControlRequest::Scan => self.scan.lock().expect("couldn't get scanner").scan(),
+            ControlRequest::PrivateSession { enabled } => {
+                info!(
+                    "Private session {}",
+                    if enabled { "started" } else { "ended" }
+                );
+                self.scrobble_filter.set_private_session(enabled);
+            }
+            ControlRequest::Open { paths, mode } => {
+                let items = paths
+                    .into_iter()
+                    .map(|path| QueueItemData::new(path, None, None))
+                    .collect();
+
+                match mode {
+                    OpenMode::Enqueue => playback.queue_list(items),
+                    OpenMode::Next => playback.insert_next(items),
+                    OpenMode::Replace => playback.replace_queue(items),
+                }
+            }
+            ControlRequest::Status => {
+                let mut status = playback.status();
+                status.queue_length = self.queue.read().expect("couldn't get queue").len();
+                status.scan = *self.scan_state.lock().expect("couldn't get scan state");
+
+                return ControlResponse::Status(Box::new(status));
+            }
+        }
+
+        ControlResponse::Ok
+    }
+}
+
+/// Runs Muzak without a window. Playback, scanning and the MMBS services run as usual, and are
+/// controlled through the control socket (see `muzak --help`).
+pub async fn run(files: Vec<PathBuf>) {
+    // the control socket is bound first, so that a second instance quits before it scans the
+    // library or binds any ports. Requests are answered once everything has started.
+    let (requests_tx, requests_rx) = channel::unbounded::<PendingRequest>();
+
+    let result = start_server(move |request| {
+        let (response_tx, response_rx) = mpsc::channel();
+
+        if requests_tx.try_send((request, response_tx)).is_err() {
+            return ControlResponse::Error {
+                message: "the daemon is shutting down".to_string(),
+            };
+        }
+
+        response_rx.recv().unwrap_or(ControlResponse::Error {
+            message: "the request was not answered".to_string(),
+        })
+    });
+
+    match result {
+        Ok(_) => (),
+        Err(ControlError::AlreadyRunning) => {
+            error!("Another instance of Muzak is already running");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            error!("Unable to start the control server: {}", e);
+            panic!("fatal: unable to start the control server");
+        }
+    }
+
+    let directory = get_dirs().data_dir().to_path_buf();
+    if !directory.exists() {
+        fs::create_dir_all(&directory)
+            .unwrap_or_else(|e| panic!("couldn't create data directory, {:?}, {:?}", directory, e));
+    }
+
+    let pool = match create_pool(directory.join("library.db")).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            error!("unable to create database pool: {}", e);
+            panic!("fatal: unable to create database pool");
+        }
+    };
+    let settings_path = directory.join("settings.json");
+    let settings = create_settings(&settings_path);
+
+    let mut scan = ScanThread::start(pool.clone(), settings.scanning.clone());
+    let scan_state = Arc::new(Mutex::new(ScanEvent::ScanCompleteIdle));
+
+    if let Some(events_rx) = scan.take_events() {
+        let scan_state = scan_state.clone();
+
+        thread::Builder::new()
+            .name("headless-scan-events".to_string())
+            .spawn(move || {
+                while let Ok(event) = events_rx.recv() {
+                    *scan_state.lock().expect("couldn't get scan state") = event;
+                }
+            })
+            .expect("could not start headless scan event thread");
+    }
+
+    scan.scan();
+
+    let scan = Arc::new(Mutex::new(scan));
+    watch_settings(settings_path, scan.clone());
+
+    let queue: Arc<RwLock<Vec<QueueItemData>>> = Arc::new(RwLock::new(Vec::new()));
+    let mut playback: HeadlessPlaybackInterface =
+        PlaybackThread::start(queue.clone(), Arc::new(Mutex::new(PlayRecorder::default())));
+    start_play_history(pool.clone(), playback.subscribe());
+    start_resume_positions(
+        pool.clone(),
+        playback.subscribe(),
+        playback.command_sender(),
+        Arc::new(AtomicBool::new(settings.playback.resume_long_tracks)),
+    );
+    playback.set_pitch_preservation(settings.playback.preserve_pitch);
+    let service_settings = settings.clone();
+    let scrobble_filter = ScrobbleFilter::new(&settings.scrobbling);
+    let service_filter = scrobble_filter.clone();
+    playback.start_broadcast(
+        PlayerHandle::new(playback.command_sender(), queue.clone()),
+        move || create_services(&service_settings, service_filter),
+    );
+
+    if settings.remote.enabled {
+        start_remote_server(
+            &settings.remote,
+            pool.clone(),
+            playback.command_sender(),
+            playback.subscribe(),
+            queue.clone(),
+        );
+    }
+
+    if settings.mpd.enabled {
+        start_mpd_server(
+            &settings.mpd,
+            settings.scanning.root_paths(),
+            pool,
+            playback.command_sender(),
+            playback.subscribe(),
+            queue.clone(),
+        );
+    }
+
+    if !files.is_empty() {
+        info!("Queueing files found in arguments: {:?}", files);
+
+        playback.queue_list(
+            files
+                .into_iter()
+                .map(|path| QueueItemData::new(path, None, None))
+                .collect(),
+        );
+    }
+
+    let daemon = Daemon {
+        playback: Mutex::new(playback),
+        scan,
+        scan_state,
+        queue,
+        scrobble_filter,
+    };
+
+    info!("Running headless, waiting for commands");
+
+    while let Ok((request, response_tx)) = requests_rx.recv().await {
+        // the client may have disconnected in the meantime
+        let _ = response_tx.send(daemon.handle(request));
+    }
+}
@@ -1,3 +1,5 @@
 pub mod db;
+pub mod import;
 pub mod scan;
+pub mod search;
 pub mod types;
@@ -1,16 +1,28 @@
-use std::{path::Path, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use async_std::task;
+use chrono::{DateTime, Utc};
 use gpui::App;
+use serde::Deserialize;
 use sqlx::{
     sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteSynchronous},
     SqlitePool,
 };
 use tracing::debug;
 
-use crate::ui::app::Pool;
+use crate::{playback::history::Play, ui::app::Pool};
 
-use super::types::{Album, Artist, Track};
+use super::{
+    search::SearchQuery,
+    types::{
+        Album, AlbumPlays, Artist, ArtistPlays, ArtistRelease, ArtistSummary, Bookmark,
+        DailyListening, GenreSummary, RecentPlay, SearchEntry, Track, TrackListing, TrackPlays,
+        TrackRow, UnmatchedPlay,
+    },
+};
 
 pub async fn create_pool(path: impl AsRef<Path>) -> Result<SqlitePool, sqlx::Error> {
     debug!("Creating database pool at {:?}", path.as_ref());
@@ -32,7 +44,28 @@ pub async fn create_pool(path: impl AsRef<Path>) -> Result<SqlitePool, sqlx::Err
     Ok(pool)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// Creates a pool for a new, empty in-memory database.
+#[cfg(test)]
+pub async fn create_memory_pool() -> SqlitePool {
+    // every connection to an in-memory database gets its own database, so only one is kept
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await
+        .expect("couldn't create in-memory database");
+
+    sqlx::migrate!("./migrations")
+        .run(&pool)
+        .await
+        .expect("couldn't run migrations");
+
+    pool
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum AlbumSortMethod {
     TitleAsc,
     TitleDesc,
@@ -44,6 +77,45 @@ pub enum AlbumSortMethod {
     LabelDesc,
     CatalogAsc,
     CatalogDesc,
+    MostPlayed,
+    RecentlyAdded,
+}
+
+/// The order of the track list. Every method sorts in ascending order, falling back to the order
+/// of tracks on their albums.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrackSortMethod {
+    Title,
+    Artist,
+    Album,
+    Duration,
+    /// The order of tracks on their albums, by album artist, album, disc and track number.
+    AlbumOrder,
+    Genre,
+    Added,
+    Plays,
+    Bitrate,
+    Format,
+    Path,
+}
+
+impl TrackSortMethod {
+    /// The name of the sort in `find_track_rows.sql`.
+    fn key(&self) -> &'static str {
+        match self {
+            TrackSortMethod::Title => "title",
+            TrackSortMethod::Artist => "artist",
+            TrackSortMethod::Album => "album",
+            TrackSortMethod::Duration => "duration",
+            TrackSortMethod::AlbumOrder => "album_order",
+            TrackSortMethod::Genre => "genre",
+            TrackSortMethod::Added => "added",
+            TrackSortMethod::Plays => "plays",
+            TrackSortMethod::Bitrate => "bitrate",
+            TrackSortMethod::Format => "format",
+            TrackSortMethod::Path => "path",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -87,6 +159,12 @@ pub async fn list_albums(
         AlbumSortMethod::CatalogDesc => {
             include_str!("../../queries/library/find_albums_catnum_desc.sql")
         }
+        AlbumSortMethod::MostPlayed => {
+            include_str!("../../queries/library/find_albums_most_played.sql")
+        }
+        AlbumSortMethod::RecentlyAdded => {
+            include_str!("../../queries/library/find_albums_recently_added.sql")
+        }
     };
 
     let albums = sqlx::query_as::<_, (u32, String)>(query)
@@ -137,6 +215,33 @@ pub async fn get_album_by_id(
     Ok(album)
 }
 
+/// Finds the albums, like `get_album_by_id`. Albums that aren't in the library are left out, and
+/// the rest are returned in no particular order.
+pub async fn list_albums_by_id(
+    pool: &SqlitePool,
+    album_ids: &[i64],
+    method: AlbumMethod,
+) -> Result<Vec<Arc<Album>>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_albums_by_id.sql");
+
+    let albums = sqlx::query_as::<_, Album>(query)
+        .bind(serde_json::to_string(album_ids).expect("ids can be serialized"))
+        .fetch_all(pool)
+        .await?;
+
+    Ok(albums
+        .into_iter()
+        .map(|mut album| {
+            match method {
+                AlbumMethod::FullQuality => album.thumb = None,
+                AlbumMethod::Thumbnail => album.image = None,
+            }
+
+            Arc::new(album)
+        })
+        .collect())
+}
+
 pub async fn get_artist_name_by_id(
     pool: &SqlitePool,
     artist_id: i64,
@@ -177,16 +282,602 @@ pub async fn get_track_by_id(pool: &SqlitePool, track_id: i64) -> Result<Arc<Tra
     Ok(track)
 }
 
-pub async fn list_albums_search(pool: &SqlitePool) -> Result<Vec<(u32, String)>, sqlx::Error> {
-    let query = include_str!("../../queries/library/find_albums_search.sql");
+pub async fn list_search_entries(pool: &SqlitePool) -> Result<Vec<SearchEntry>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_search_entries.sql");
+
+    let entries = sqlx::query_as::<_, SearchEntry>(query)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(entries)
+}
+
+pub async fn list_bookmarks(
+    pool: &SqlitePool,
+    track_id: i64,
+) -> Result<Arc<Vec<Bookmark>>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_bookmarks_for_track.sql");
+
+    let bookmarks = Arc::new(
+        sqlx::query_as::<_, Bookmark>(query)
+            .bind(track_id)
+            .fetch_all(pool)
+            .await?,
+    );
+
+    Ok(bookmarks)
+}
+
+pub async fn create_bookmark(
+    pool: &SqlitePool,
+    track_id: i64,
+    name: &str,
+    position: f64,
+) -> Result<i64, sqlx::Error> {
+    let query = include_str!("../../queries/library/create_bookmark.sql");
+
+    let id: i64 = sqlx::query_scalar(query)
+        .bind(track_id)
+        .bind(name)
+        .bind(position)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(id)
+}
+
+pub async fn delete_bookmark(pool: &SqlitePool, bookmark_id: i64) -> Result<(), sqlx::Error> {
+    let query = include_str!("../../queries/library/delete_bookmark.sql");
+
+    sqlx::query(query).bind(bookmark_id).execute(pool).await?;
+
+    Ok(())
+}
+
+pub async fn get_resume_position(
+    pool: &SqlitePool,
+    track_id: i64,
+) -> Result<Option<f64>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_resume_position.sql");
+
+    let position: Option<f64> = sqlx::query_scalar(query)
+        .bind(track_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(position)
+}
+
+pub async fn set_resume_position(
+    pool: &SqlitePool,
+    track_id: i64,
+    position: f64,
+) -> Result<(), sqlx::Error> {
+    let query = include_str!("../../queries/library/set_resume_position.sql");
+
+    sqlx::query(query)
+        .bind(track_id)
+        .bind(position)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn clear_resume_position(pool: &SqlitePool, track_id: i64) -> Result<(), sqlx::Error> {
+    let query = include_str!("../../queries/library/delete_resume_position.sql");
+
+    sqlx::query(query).bind(track_id).execute(pool).await?;
+
+    Ok(())
+}
+
+/// Finds the ID of the track at the location, if it is in the library.
+pub async fn get_track_id(pool: &SqlitePool, location: &Path) -> Result<Option<i64>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_track_id_by_location.sql");
+
+    let id: Option<i64> = sqlx::query_scalar(query)
+        .bind(location.to_str())
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(id)
+}
+
+pub async fn list_artists(pool: &SqlitePool) -> Result<Vec<(u32, String)>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_artists.sql");
+
+    let artists = sqlx::query_as::<_, (u32, String)>(query)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(artists)
+}
+
+/// Lists every album the artist is credited on, as an album artist or on any of its tracks.
+pub async fn list_albums_by_artist(
+    pool: &SqlitePool,
+    artist_id: i64,
+) -> Result<Vec<(u32, String)>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_albums_by_artist.sql");
 
     let albums = sqlx::query_as::<_, (u32, String)>(query)
+        .bind(artist_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(albums)
+}
+
+pub async fn list_artist_summaries(pool: &SqlitePool) -> Result<Vec<ArtistSummary>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_artist_summaries.sql");
+
+    let artists = sqlx::query_as::<_, ArtistSummary>(query)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(artists)
+}
+
+pub async fn list_genre_summaries(pool: &SqlitePool) -> Result<Vec<GenreSummary>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_genre_summaries.sql");
+
+    let genres = sqlx::query_as::<_, GenreSummary>(query)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(genres)
+}
+
+pub async fn get_genre_name_by_id(
+    pool: &SqlitePool,
+    genre_id: i64,
+) -> Result<Arc<String>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_genre_name_by_id.sql");
+
+    let name: String = sqlx::query_scalar(query)
+        .bind(genre_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(Arc::new(name))
+}
+
+/// Lists the albums with a track of the genre, by album artist.
+pub async fn list_genre_albums(
+    pool: &SqlitePool,
+    genre_id: i64,
+) -> Result<Vec<ArtistRelease>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_genre_albums.sql");
+
+    let albums = sqlx::query_as::<_, ArtistRelease>(query)
+        .bind(genre_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(albums)
+}
+
+/// Lists the ids of the albums with a track of the genre, for filtering the album table.
+pub async fn list_genre_album_ids(
+    pool: &SqlitePool,
+    genre_id: i64,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_genre_album_ids.sql");
+
+    let albums = sqlx::query_scalar(query)
+        .bind(genre_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(albums)
+}
+
+pub async fn list_genre_tracks(
+    pool: &SqlitePool,
+    genre_id: i64,
+) -> Result<Vec<TrackListing>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_genre_tracks.sql");
+
+    let tracks = sqlx::query_as::<_, TrackListing>(query)
+        .bind(genre_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(tracks)
+}
+
+/// Runs a full-text search query. `matching` is used for queries that match text, and is
+/// ordered by the best matches. `excluding` is used for queries that only exclude text or filter
+/// by year; it takes the expression of the excluded text, if there is any.
+async fn run_search<T>(
+    pool: &SqlitePool,
+    query: &SearchQuery,
+    limit: u32,
+    matching: &'static str,
+    excluding: &'static str,
+) -> Result<Vec<T>, sqlx::Error>
+where
+    T: for<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> + Send + Unpin,
+{
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let (sql, expression) = match (query.matching.as_ref(), query.excluding.as_ref()) {
+        (Some(matching_text), Some(excluding_text)) => (
+            matching,
+            Some(format!("({}) NOT ({})", matching_text, excluding_text)),
+        ),
+        (Some(matching_text), None) => (matching, Some(matching_text.clone())),
+        (None, excluding_text) => (excluding, excluding_text.cloned()),
+    };
+
+    sqlx::query_as::<_, T>(sql)
+        .bind(expression)
+        .bind(query.year_from)
+        .bind(query.year_to)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
+
+/// Lists the tracks matching a full-text search query, best matches first. Queries that only
+/// exclude tracks or filter by year are ordered by album instead.
+pub async fn search_tracks(
+    pool: &SqlitePool,
+    query: &SearchQuery,
+    limit: u32,
+) -> Result<Vec<TrackListing>, sqlx::Error> {
+    run_search(
+        pool,
+        query,
+        limit,
+        include_str!("../../queries/library/find_search_tracks.sql"),
+        include_str!("../../queries/library/find_search_tracks_excluding.sql"),
+    )
+    .await
+}
+
+/// Lists the albums with tracks matching a full-text search query, by their best matching track.
+/// Queries that only exclude tracks or filter by year are ordered by artist instead.
+pub async fn search_albums(
+    pool: &SqlitePool,
+    query: &SearchQuery,
+    limit: u32,
+) -> Result<Vec<SearchEntry>, sqlx::Error> {
+    run_search(
+        pool,
+        query,
+        limit,
+        include_str!("../../queries/library/find_search_albums.sql"),
+        include_str!("../../queries/library/find_search_albums_excluding.sql"),
+    )
+    .await
+}
+
+/// Lists the artists of the tracks matching a full-text search query, by their best matching
+/// track. Artists are found through their albums, and the tracks they are a primary or featured
+/// artist on. Queries that only exclude tracks or filter by year are ordered by name instead.
+pub async fn search_artists(
+    pool: &SqlitePool,
+    query: &SearchQuery,
+    limit: u32,
+) -> Result<Vec<SearchEntry>, sqlx::Error> {
+    run_search(
+        pool,
+        query,
+        limit,
+        include_str!("../../queries/library/find_search_artists.sql"),
+        include_str!("../../queries/library/find_search_artists_excluding.sql"),
+    )
+    .await
+}
+
+/// Lists the albums the artist is an album artist of, newest first.
+pub async fn list_artist_releases(
+    pool: &SqlitePool,
+    artist_id: i64,
+) -> Result<Vec<ArtistRelease>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_artist_releases.sql");
+
+    let albums = sqlx::query_as::<_, ArtistRelease>(query)
+        .bind(artist_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(albums)
+}
+
+/// Lists the albums by other artists that the artist is credited on a track of, newest first.
+pub async fn list_artist_appearances(
+    pool: &SqlitePool,
+    artist_id: i64,
+) -> Result<Vec<ArtistRelease>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_artist_appearances.sql");
+
+    let albums = sqlx::query_as::<_, ArtistRelease>(query)
+        .bind(artist_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(albums)
+}
+
+/// Lists the most played tracks the artist is credited on, across all time.
+pub async fn list_artist_top_tracks(
+    pool: &SqlitePool,
+    artist_id: i64,
+    limit: u32,
+) -> Result<Vec<TrackPlays>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_artist_top_tracks.sql");
+
+    let tracks = sqlx::query_as::<_, TrackPlays>(query)
+        .bind(artist_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(tracks)
+}
+
+/// Finds the first primary artist of the track at the location.
+pub async fn get_track_artist_id(
+    pool: &SqlitePool,
+    location: &Path,
+) -> Result<Option<i64>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_track_artist_id.sql");
+
+    let artist: Option<(i64,)> = sqlx::query_as(query)
+        .bind(location.to_str())
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(artist.map(|v| v.0))
+}
+
+pub async fn list_track_listings(pool: &SqlitePool) -> Result<Vec<TrackListing>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_track_listings.sql");
+
+    let tracks = sqlx::query_as::<_, TrackListing>(query)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(tracks)
+}
+
+pub async fn list_track_rows(
+    pool: &SqlitePool,
+    sort_method: TrackSortMethod,
+) -> Result<Vec<(i64, PathBuf, Option<i64>)>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_track_rows.sql");
+
+    let tracks = sqlx::query_as::<_, (i64, String, Option<i64>)>(query)
+        .bind(sort_method.key())
+        .fetch_all(pool)
+        .await?;
+
+    Ok(tracks
+        .into_iter()
+        .map(|(id, location, album_id)| (id, PathBuf::from(location), album_id))
+        .collect())
+}
+
+/// Finds the rows of the tracks for the track list. Tracks that aren't in the library are left
+/// out, and the rest are returned in no particular order.
+pub async fn list_track_rows_by_id(
+    pool: &SqlitePool,
+    track_ids: &[i64],
+) -> Result<Vec<Arc<TrackRow>>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_track_rows_by_id.sql");
+
+    let tracks = sqlx::query_as::<_, TrackRow>(query)
+        .bind(serde_json::to_string(track_ids).expect("ids can be serialized"))
+        .fetch_all(pool)
+        .await?;
+
+    Ok(tracks.into_iter().map(Arc::new).collect())
+}
+
+pub async fn get_track_listing_by_location(
+    pool: &SqlitePool,
+    location: &Path,
+) -> Result<Option<TrackListing>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_track_listing_by_location.sql");
+
+    let track = sqlx::query_as::<_, TrackListing>(query)
+        .bind(location.to_str())
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(track)
+}
+
+/// Finds the tracks at the locations. Locations that aren't in the library are left out.
+pub async fn list_track_listings_by_location(
+    pool: &SqlitePool,
+    locations: &[PathBuf],
+) -> Result<Vec<TrackListing>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_track_listings_by_locations.sql");
+    let locations: Vec<_> = locations.iter().map(|v| v.to_string_lossy()).collect();
+
+    let tracks = sqlx::query_as::<_, TrackListing>(query)
+        .bind(serde_json::to_string(&locations).expect("paths can be serialized"))
+        .fetch_all(pool)
+        .await?;
+
+    Ok(tracks)
+}
+
+pub async fn record_play(pool: &SqlitePool, play: &Play) -> Result<(), sqlx::Error> {
+    let query = include_str!("../../queries/library/create_play.sql");
+
+    sqlx::query(query)
+        .bind(play.location.to_str())
+        .bind(play.started_at)
+        .bind(play.listened as i64)
+        .bind(play.skipped)
+        .bind(play.counted)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Plays of the same track that start within this many seconds of each other are considered to be
+/// the same play when importing, since other services round the time or record when the track
+/// ended instead of when it started.
+const IMPORT_DUPLICATE_WINDOW: i64 = 30;
+
+/// Records a play imported from another service or player. Returns false if the play was already
+/// in the history, either from an earlier import or because it was recorded by Muzak.
+pub async fn create_imported_play(
+    pool: &SqlitePool,
+    location: &Path,
+    started_at: DateTime<Utc>,
+    listened: u64,
+    source: &str,
+) -> Result<bool, sqlx::Error> {
+    let query = include_str!("../../queries/library/create_imported_play.sql");
+    let window = chrono::Duration::seconds(IMPORT_DUPLICATE_WINDOW);
+
+    let result = sqlx::query(query)
+        .bind(location.to_str())
+        .bind(started_at)
+        .bind(listened as i64)
+        .bind(source)
+        .bind(started_at - window)
+        .bind(started_at + window)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn create_unmatched_play(
+    pool: &SqlitePool,
+    source: &str,
+    artist: &str,
+    title: &str,
+    album: Option<&str>,
+    played_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    let query = include_str!("../../queries/library/create_unmatched_play.sql");
+
+    sqlx::query(query)
+        .bind(source)
+        .bind(artist)
+        .bind(title)
+        .bind(album)
+        .bind(played_at)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn list_unmatched_plays(pool: &SqlitePool) -> Result<Vec<UnmatchedPlay>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_unmatched_plays.sql");
+
+    let plays = sqlx::query_as::<_, UnmatchedPlay>(query)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(plays)
+}
+
+pub async fn delete_unmatched_play(pool: &SqlitePool, id: i64) -> Result<(), sqlx::Error> {
+    let query = include_str!("../../queries/library/delete_unmatched_play.sql");
+
+    sqlx::query(query).bind(id).execute(pool).await?;
+
+    Ok(())
+}
+
+pub async fn list_top_artists(
+    pool: &SqlitePool,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    limit: u32,
+) -> Result<Vec<ArtistPlays>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_top_artists.sql");
+
+    let artists = sqlx::query_as::<_, ArtistPlays>(query)
+        .bind(start)
+        .bind(end)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(artists)
+}
+
+pub async fn list_top_albums(
+    pool: &SqlitePool,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    limit: u32,
+) -> Result<Vec<AlbumPlays>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_top_albums.sql");
+
+    let albums = sqlx::query_as::<_, AlbumPlays>(query)
+        .bind(start)
+        .bind(end)
+        .bind(limit)
         .fetch_all(pool)
         .await?;
 
     Ok(albums)
 }
 
+pub async fn list_top_tracks(
+    pool: &SqlitePool,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    limit: u32,
+) -> Result<Vec<TrackPlays>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_top_tracks.sql");
+
+    let tracks = sqlx::query_as::<_, TrackPlays>(query)
+        .bind(start)
+        .bind(end)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(tracks)
+}
+
+pub async fn list_listening_time_by_day(
+    pool: &SqlitePool,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<DailyListening>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_listening_time_by_day.sql");
+
+    let days = sqlx::query_as::<_, DailyListening>(query)
+        .bind(start)
+        .bind(end)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(days)
+}
+
+pub async fn list_recent_plays(
+    pool: &SqlitePool,
+    limit: u32,
+) -> Result<Vec<RecentPlay>, sqlx::Error> {
+    let query = include_str!("../../queries/library/find_recent_plays.sql");
+
+    let plays = sqlx::query_as::<_, RecentPlay>(query)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(plays)
+}
+
 pub trait LibraryAccess {
     fn list_albums(&self, sort_method: AlbumSortMethod) -> Result<Vec<(u32, String)>, sqlx::Error>;
     fn list_tracks_in_album(&self, album_id: i64) -> Result<Arc<Vec<Track>>, sqlx::Error>;
@@ -195,22 +886,108 @@ pub trait LibraryAccess {
         album_id: i64,
         method: AlbumMethod,
     ) -> Result<Arc<Album>, sqlx::Error>;
+    fn list_albums_by_id(
+        &self,
+        album_ids: &[i64],
+        method: AlbumMethod,
+    ) -> Result<Vec<Arc<Album>>, sqlx::Error>;
     fn get_artist_name_by_id(&self, artist_id: i64) -> Result<Arc<String>, sqlx::Error>;
     fn get_artist_by_id(&self, artist_id: i64) -> Result<Arc<Artist>, sqlx::Error>;
     fn get_track_by_id(&self, track_id: i64) -> Result<Arc<Track>, sqlx::Error>;
-    fn list_albums_search(&self) -> Result<Vec<(u32, String)>, sqlx::Error>;
+    fn list_search_entries(&self) -> Result<Vec<SearchEntry>, sqlx::Error>;
+    fn get_track_id(&self, location: &Path) -> Result<Option<i64>, sqlx::Error>;
+    fn list_bookmarks(&self, track_id: i64) -> Result<Arc<Vec<Bookmark>>, sqlx::Error>;
+    fn create_bookmark(&self, track_id: i64, name: &str, position: f64)
+        -> Result<i64, sqlx::Error>;
+    fn delete_bookmark(&self, bookmark_id: i64) -> Result<(), sqlx::Error>;
+    fn list_artist_summaries(&self) -> Result<Vec<ArtistSummary>, sqlx::Error>;
+    fn list_genre_summaries(&self) -> Result<Vec<GenreSummary>, sqlx::Error>;
+    fn get_genre_name_by_id(&self, genre_id: i64) -> Result<Arc<String>, sqlx::Error>;
+    fn list_genre_albums(&self, genre_id: i64) -> Result<Vec<ArtistRelease>, sqlx::Error>;
+    fn list_genre_album_ids(&self, genre_id: i64) -> Result<Vec<i64>, sqlx::Error>;
+    fn list_genre_tracks(&self, genre_id: i64) -> Result<Vec<TrackListing>, sqlx::Error>;
+    fn search_tracks(
+        &self,
+        query: &SearchQuery,
+        limit: u32,
+    ) -> Result<Vec<TrackListing>, sqlx::Error>;
+    fn search_albums(
+        &self,
+        query: &SearchQuery,
+        limit: u32,
+    ) -> Result<Vec<SearchEntry>, sqlx::Error>;
+    fn search_artists(
+        &self,
+        query: &SearchQuery,
+        limit: u32,
+    ) -> Result<Vec<SearchEntry>, sqlx::Error>;
+    fn list_artist_releases(&self, artist_id: i64) -> Result<Vec<ArtistRelease>, sqlx::Error>;
+    fn list_artist_appearances(&self, artist_id: i64) -> Result<Vec<ArtistRelease>, sqlx::Error>;
+    fn list_artist_top_tracks(
+        &self,
+        artist_id: i64,
+        limit: u32,
+    ) -> Result<Vec<TrackPlays>, sqlx::Error>;
+    fn get_track_artist_id(&self, location: &Path) -> Result<Option<i64>, sqlx::Error>;
+    /// Lists the id, location and album id of every track.
+    fn list_track_rows(
+        &self,
+        sort_method: TrackSortMethod,
+    ) -> Result<Vec<(i64, PathBuf, Option<i64>)>, sqlx::Error>;
+    fn list_track_rows_by_id(&self, track_ids: &[i64]) -> Result<Vec<Arc<TrackRow>>, sqlx::Error>;
+    fn list_top_artists(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<ArtistPlays>, sqlx::Error>;
+    fn list_top_albums(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<AlbumPlays>, sqlx::Error>;
+    fn list_top_tracks(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<TrackPlays>, sqlx::Error>;
+    fn list_listening_time_by_day(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<DailyListening>, sqlx::Error>;
+    fn list_recent_plays(&self, limit: u32) -> Result<Vec<RecentPlay>, sqlx::Error>;
+}
+
+/// Where the library database is reached from. `LibraryAccess` is implemented for all of these,
+/// so that code outside of GPUI, like the remote control server, can use the same queries.
+pub trait LibrarySource {
+    fn library_pool(&self) -> &SqlitePool;
+}
+
+impl LibrarySource for App {
+    fn library_pool(&self) -> &SqlitePool {
+        let pool: &Pool = self.global();
+        &pool.0
+    }
+}
+
+impl LibrarySource for SqlitePool {
+    fn library_pool(&self) -> &SqlitePool {
+        self
+    }
 }
 
 // TODO: profile this with a large library
-impl LibraryAccess for App {
+impl<T: LibrarySource> LibraryAccess for T {
     fn list_albums(&self, sort_method: AlbumSortMethod) -> Result<Vec<(u32, String)>, sqlx::Error> {
-        let pool: &Pool = self.global();
-        task::block_on(list_albums(&pool.0, sort_method))
+        task::block_on(list_albums(self.library_pool(), sort_method))
     }
 
     fn list_tracks_in_album(&self, album_id: i64) -> Result<Arc<Vec<Track>>, sqlx::Error> {
-        let pool: &Pool = self.global();
-        task::block_on(list_tracks_in_album(&pool.0, album_id))
+        task::block_on(list_tracks_in_album(self.library_pool(), album_id))
     }
 
     fn get_album_by_id(
@@ -218,27 +995,400 @@ impl LibraryAccess for App {
         album_id: i64,
         method: AlbumMethod,
     ) -> Result<Arc<Album>, sqlx::Error> {
-        let pool: &Pool = self.global();
-        task::block_on(get_album_by_id(&pool.0, album_id, method))
+        task::block_on(get_album_by_id(self.library_pool(), album_id, method))
+    }
+
+    fn list_albums_by_id(
+        &self,
+        album_ids: &[i64],
+        method: AlbumMethod,
+    ) -> Result<Vec<Arc<Album>>, sqlx::Error> {
+        task::block_on(list_albums_by_id(self.library_pool(), album_ids, method))
     }
 
     fn get_artist_name_by_id(&self, artist_id: i64) -> Result<Arc<String>, sqlx::Error> {
-        let pool: &Pool = self.global();
-        task::block_on(get_artist_name_by_id(&pool.0, artist_id))
+        task::block_on(get_artist_name_by_id(self.library_pool(), artist_id))
     }
 
     fn get_artist_by_id(&self, artist_id: i64) -> Result<Arc<Artist>, sqlx::Error> {
-        let pool: &Pool = self.global();
-        task::block_on(get_artist_by_id(&pool.0, artist_id))
+        task::block_on(get_artist_by_id(self.library_pool(), artist_id))
     }
 
     fn get_track_by_id(&self, track_id: i64) -> Result<Arc<Track>, sqlx::Error> {
-        let pool: &Pool = self.global();
-        task::block_on(get_track_by_id(&pool.0, track_id))
+        task::block_on(get_track_by_id(self.library_pool(), track_id))
     }
 
-    fn list_albums_search(&self) -> Result<Vec<(u32, String)>, sqlx::Error> {
-        let pool: &Pool = self.global();
-        task::block_on(list_albums_search(&pool.0))
+    fn list_search_entries(&self) -> Result<Vec<SearchEntry>, sqlx::Error> {
+        task::block_on(list_search_entries(self.library_pool()))
+    }
+
+    fn get_track_id(&self, location: &Path) -> Result<Option<i64>, sqlx::Error> {
+        task::block_on(get_track_id(self.library_pool(), location))
+    }
+
+    fn list_bookmarks(&self, track_id: i64) -> Result<Arc<Vec<Bookmark>>, sqlx::Error> {
+        task::block_on(list_bookmarks(self.library_pool(), track_id))
+    }
+
+    fn create_bookmark(
+        &self,
+        track_id: i64,
+        name: &str,
+        position: f64,
+    ) -> Result<i64, sqlx::Error> {
+        task::block_on(create_bookmark(
+            self.library_pool(),
+            track_id,
+            name,
+            position,
+        ))
+    }
+
+    fn delete_bookmark(&self, bookmark_id: i64) -> Result<(), sqlx::Error> {
+        task::block_on(delete_bookmark(self.library_pool(), bookmark_id))
+    }
+
+    fn list_artist_summaries(&self) -> Result<Vec<ArtistSummary>, sqlx::Error> {
+        task::block_on(list_artist_summaries(self.library_pool()))
+    }
+
+    fn list_genre_summaries(&self) -> Result<Vec<GenreSummary>, sqlx::Error> {
+        task::block_on(list_genre_summaries(self.library_pool()))
+    }
+
+    fn get_genre_name_by_id(&self, genre_id: i64) -> Result<Arc<String>, sqlx::Error> {
+        task::block_on(get_genre_name_by_id(self.library_pool(), genre_id))
+    }
+
+    fn list_genre_albums(&self, genre_id: i64) -> Result<Vec<ArtistRelease>, sqlx::Error> {
+        task::block_on(list_genre_albums(self.library_pool(), genre_id))
+    }
+
+    fn list_genre_album_ids(&self, genre_id: i64) -> Result<Vec<i64>, sqlx::Error> {
+        task::block_on(list_genre_album_ids(self.library_pool(), genre_id))
+    }
+
+    fn list_genre_tracks(&self, genre_id: i64) -> Result<Vec<TrackListing>, sqlx::Error> {
+        task::block_on(list_genre_tracks(self.library_pool(), genre_id))
+    }
+
+    fn search_tracks(
+        &self,
+        query: &SearchQuery,
+        limit: u32,
+    ) -> Result<Vec<TrackListing>, sqlx::Error> {
+        task::block_on(search_tracks(self.library_pool(), query, limit))
+    }
+
+    fn search_albums(
+        &self,
+        query: &SearchQuery,
+        limit: u32,
+    ) -> Result<Vec<SearchEntry>, sqlx::Error> {
+        task::block_on(search_albums(self.library_pool(), query, limit))
+    }
+
+    fn search_artists(
+        &self,
+        query: &SearchQuery,
+        limit: u32,
+    ) -> Result<Vec<SearchEntry>, sqlx::Error> {
+        task::block_on(search_artists(self.library_pool(), query, limit))
+    }
+
+    fn list_artist_releases(&self, artist_id: i64) -> Result<Vec<ArtistRelease>, sqlx::Error> {
+        task::block_on(list_artist_releases(self.library_pool(), artist_id))
+    }
+
+    fn list_artist_appearances(&self, artist_id: i64) -> Result<Vec<ArtistRelease>, sqlx::Error> {
+        task::block_on(list_artist_appearances(self.library_pool(), artist_id))
+    }
+
+    fn list_artist_top_tracks(
+        &self,
+        artist_id: i64,
+        limit: u32,
+    ) -> Result<Vec<TrackPlays>, sqlx::Error> {
+        task::block_on(list_artist_top_tracks(
+            self.library_pool(),
+            artist_id,
+            limit,
+        ))
+    }
+
+    fn get_track_artist_id(&self, location: &Path) -> Result<Option<i64>, sqlx::Error> {
+        task::block_on(get_track_artist_id(self.library_pool(), location))
+    }
+
+    fn list_track_rows(
+        &self,
+        sort_method: TrackSortMethod,
+    ) -> Result<Vec<(i64, PathBuf, Option<i64>)>, sqlx::Error> {
+        task::block_on(list_track_rows(self.library_pool(), sort_method))
+    }
+
+    fn list_track_rows_by_id(&self, track_ids: &[i64]) -> Result<Vec<Arc<TrackRow>>, sqlx::Error> {
+        task::block_on(list_track_rows_by_id(self.library_pool(), track_ids))
+    }
+
+    fn list_top_artists(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<ArtistPlays>, sqlx::Error> {
+        task::block_on(list_top_artists(self.library_pool(), start, end, limit))
+    }
+
+    fn list_top_albums(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<AlbumPlays>, sqlx::Error> {
+        task::block_on(list_top_albums(self.library_pool(), start, end, limit))
+    }
+
+    fn list_top_tracks(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<TrackPlays>, sqlx::Error> {
+        task::block_on(list_top_tracks(self.library_pool(), start, end, limit))
+    }
+
+    fn list_listening_time_by_day(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<DailyListening>, sqlx::Error> {
+        task::block_on(list_listening_time_by_day(self.library_pool(), start, end))
+    }
+
+    fn list_recent_plays(&self, limit: u32) -> Result<Vec<RecentPlay>, sqlx::Error> {
+        task::block_on(list_recent_plays(self.library_pool(), limit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    const LIBRARY: &str = "
+        INSERT INTO track (id, title, title_sortable, duration, location)
+        VALUES
+            (1, 'One', 'One', 100, '/music/01.flac'),
+            (2, 'Two', 'Two', 100, '/music/02.flac');
+    ";
+
+    fn play(location: &str, skipped: bool, counted: bool) -> Play {
+        Play {
+            location: PathBuf::from(location),
+            started_at: Utc::now(),
+            listened: 60,
+            skipped,
+            counted,
+        }
+    }
+
+    async fn top_tracks(pool: &SqlitePool) -> Vec<(i64, i64)> {
+        let now = Utc::now();
+
+        list_top_tracks(pool, now - Duration::hours(1), now + Duration::hours(1), 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|v| (v.id, v.plays))
+            .collect()
+    }
+
+    #[async_std::test]
+    async fn plays_follow_moved_tracks() {
+        let pool = create_memory_pool().await;
+        sqlx::raw_sql(LIBRARY).execute(&pool).await.unwrap();
+
+        record_play(&pool, &play("/music/01.flac", false, true))
+            .await
+            .unwrap();
+        sqlx::query("UPDATE track SET location = '/music/moved/01.flac' WHERE id = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+        record_play(&pool, &play("/music/moved/01.flac", false, true))
+            .await
+            .unwrap();
+
+        assert_eq!(top_tracks(&pool).await, vec![(1, 2)]);
+
+        let recent = list_recent_plays(&pool, 10).await.unwrap();
+        assert!(recent
+            .iter()
+            .all(|v| v.location == Path::new("/music/moved/01.flac")));
+    }
+
+    #[async_std::test]
+    async fn plays_are_kept_for_removed_tracks() {
+        let pool = create_memory_pool().await;
+        sqlx::raw_sql(LIBRARY).execute(&pool).await.unwrap();
+
+        record_play(&pool, &play("/music/01.flac", false, true))
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM track WHERE id = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let recent = list_recent_plays(&pool, 10).await.unwrap();
+        assert_eq!(recent.len(), 1);
+        assert!(recent[0].title.is_none());
+
+        sqlx::query(
+            "INSERT INTO track (id, title, title_sortable, duration, location)
+            VALUES (3, 'One', 'One', 100, '/music/01.flac')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(top_tracks(&pool).await, vec![(3, 1)]);
+    }
+
+    #[async_std::test]
+    async fn early_skips_are_not_counted() {
+        let pool = create_memory_pool().await;
+        sqlx::raw_sql(LIBRARY).execute(&pool).await.unwrap();
+
+        record_play(&pool, &play("/music/01.flac", true, false))
+            .await
+            .unwrap();
+        record_play(&pool, &play("/music/02.flac", true, true))
+            .await
+            .unwrap();
+
+        assert_eq!(top_tracks(&pool).await, vec![(2, 1)]);
+        assert_eq!(list_recent_plays(&pool, 10).await.unwrap().len(), 2);
+    }
+
+    const SEARCH_LIBRARY: &str = "
+        INSERT INTO artist (id, name, name_sortable)
+        VALUES
+            (1, 'Miles Davis', 'Davis, Miles'),
+            (2, 'John Coltrane', 'Coltrane, John'),
+            (3, 'Cannonball Adderley', 'Adderley, Cannonball');
+        INSERT INTO album (id, title, title_sortable, artist_id, release_date)
+        VALUES
+            (1, 'Kind of Blue', 'Kind of Blue', 1, '1959-08-17 00:00:00+00:00'),
+            (2, 'Giant Steps', 'Giant Steps', 2, '1960-01-27 00:00:00+00:00'),
+            (3, 'Live at the Lighthouse', 'Live at the Lighthouse', 3, '1967-01-01 00:00:00+00:00');
+        INSERT INTO track (id, title, title_sortable, album_id, duration, location)
+        VALUES
+            (1, 'So What', 'So What', 1, 545, '/music/1/01.flac'),
+            (2, 'Blue in Green', 'Blue in Green', 1, 337, '/music/1/03.flac'),
+            (3, 'Giant Steps', 'Giant Steps', 2, 286, '/music/2/01.flac'),
+            (4, 'Mercy, Mercy, Mercy', 'Mercy, Mercy, Mercy', 3, 310, '/music/3/01.flac');
+        INSERT INTO track_artist (track_id, artist_id, role, position)
+        VALUES
+            (1, 1, 'primary', 0),
+            (1, 2, 'featured', 1),
+            (1, 3, 'featured', 2);
+    ";
+
+    fn titles(entries: Vec<SearchEntry>) -> Vec<String> {
+        entries.into_iter().map(|v| v.title).collect()
+    }
+
+    #[async_std::test]
+    async fn albums_and_artists_are_found_through_their_tracks() {
+        let pool = create_memory_pool().await;
+        sqlx::raw_sql(SEARCH_LIBRARY).execute(&pool).await.unwrap();
+
+        let query = SearchQuery::parse("title:\"so what\"").unwrap();
+        assert_eq!(
+            titles(search_albums(&pool, &query, 10).await.unwrap()),
+            ["Kind of Blue"]
+        );
+
+        let mut artists = titles(search_artists(&pool, &query, 10).await.unwrap());
+        artists.sort();
+        assert_eq!(
+            artists,
+            ["Cannonball Adderley", "John Coltrane", "Miles Davis"]
+        );
+
+        let query = SearchQuery::parse("giant").unwrap();
+        assert_eq!(
+            titles(search_artists(&pool, &query, 10).await.unwrap()),
+            ["John Coltrane"]
+        );
+    }
+
+    #[async_std::test]
+    async fn searches_can_only_exclude_or_filter_by_year() {
+        let pool = create_memory_pool().await;
+        sqlx::raw_sql(SEARCH_LIBRARY).execute(&pool).await.unwrap();
+
+        let query = SearchQuery::parse("-live").unwrap();
+        assert_eq!(
+            titles(search_albums(&pool, &query, 10).await.unwrap()),
+            ["Giant Steps", "Kind of Blue"]
+        );
+
+        let query = SearchQuery::parse("year:1960..").unwrap();
+        assert_eq!(
+            titles(search_artists(&pool, &query, 10).await.unwrap()),
+            ["Cannonball Adderley", "John Coltrane"]
+        );
+        assert_eq!(search_tracks(&pool, &query, 10).await.unwrap().len(), 2);
+
+        let query = SearchQuery::parse("blue year:..1959").unwrap();
+        assert_eq!(
+            titles(search_albums(&pool, &query, 10).await.unwrap()),
+            ["Kind of Blue"]
+        );
+        assert_eq!(search_tracks(&pool, &query, 10).await.unwrap().len(), 2);
+    }
+
+    #[async_std::test]
+    async fn lyrics_and_comments_are_searched() {
+        let pool = create_memory_pool().await;
+        sqlx::raw_sql(SEARCH_LIBRARY).execute(&pool).await.unwrap();
+        sqlx::query(
+            "UPDATE track SET lyrics = 'Steps of a giant', comment = 'Remastered' WHERE id = 2",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let ids = |tracks: Vec<TrackListing>| tracks.into_iter().map(|v| v.id).collect::<Vec<_>>();
+
+        let query = SearchQuery::parse("lyrics:giant").unwrap();
+        assert_eq!(ids(search_tracks(&pool, &query, 10).await.unwrap()), [2]);
+
+        let query = SearchQuery::parse("remaster").unwrap();
+        assert_eq!(ids(search_tracks(&pool, &query, 10).await.unwrap()), [2]);
+
+        let query = SearchQuery::parse("comment:remaster -lyrics:steps").unwrap();
+        assert!(search_tracks(&pool, &query, 10).await.unwrap().is_empty());
+    }
+
+    #[async_std::test]
+    async fn track_rows_are_found_in_batches() {
+        let pool = create_memory_pool().await;
+        sqlx::raw_sql(LIBRARY).execute(&pool).await.unwrap();
+
+        let mut rows: Vec<_> = list_track_rows_by_id(&pool, &[2, 3, 1])
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|v| (v.id, v.title.0.to_string()))
+            .collect();
+        rows.sort();
+
+        assert_eq!(rows, [(1, "One".to_string()), (2, "Two".to_string())]);
+        assert!(list_track_rows_by_id(&pool, &[]).await.unwrap().is_empty());
     }
 }
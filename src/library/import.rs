@@ -0,0 +1,136 @@
+use chrono::{DateTime, Utc};
+use errors::ImportError;
+use matcher::TrackMatcher;
+use sqlx::SqlitePool;
+use tracing::debug;
+
+use super::db::{
+    create_imported_play, create_unmatched_play, delete_unmatched_play, list_track_listings,
+    list_unmatched_plays,
+};
+
+pub mod errors;
+pub mod foobar2000;
+pub mod lastfm;
+pub mod listenbrainz;
+pub mod matcher;
+
+/// Where imported plays came from. This is stored with the plays, so that they can be told apart
+/// from the plays recorded by Muzak.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaySource {
+    LastFM,
+    ListenBrainz,
+    Foobar2000,
+}
+
+impl PlaySource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlaySource::LastFM => "lastfm",
+            PlaySource::ListenBrainz => "listenbrainz",
+            PlaySource::Foobar2000 => "foobar2000",
+        }
+    }
+}
+
+/// A play read from another service or player, before it has been matched to a track in the
+/// library.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedPlay {
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    pub played_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ImportSummary {
+    /// The plays that were added to the play history.
+    pub imported: usize,
+    /// The plays that were already in the play history.
+    pub duplicates: usize,
+    /// The plays that couldn't be matched to a track in the library.
+    pub unmatched: usize,
+}
+
+/// Matches the plays to tracks in the library and adds them to the play history. Other services
+/// don't record how long a track was listened to, so imported plays count as full plays. Plays
+/// that can't be matched are kept, so that `rematch_plays` can try again later.
+pub async fn import_plays(
+    pool: &SqlitePool,
+    source: PlaySource,
+    plays: Vec<ImportedPlay>,
+) -> Result<ImportSummary, ImportError> {
+    let mut matcher = TrackMatcher::new(list_track_listings(pool).await?);
+    let mut summary = ImportSummary::default();
+
+    for play in plays {
+        match matcher.find(&play.artist, &play.title, play.album.as_deref()) {
+            Some(track) => {
+                let created = create_imported_play(
+                    pool,
+                    &track.location,
+                    play.played_at,
+                    track.duration,
+                    source.as_str(),
+                )
+                .await?;
+
+                if created {
+                    summary.imported += 1;
+                } else {
+                    summary.duplicates += 1;
+                }
+            }
+            None => {
+                debug!("no match for {} - {}", play.artist, play.title);
+                create_unmatched_play(
+                    pool,
+                    source.as_str(),
+                    &play.artist,
+                    &play.title,
+                    play.album.as_deref(),
+                    play.played_at,
+                )
+                .await?;
+                summary.unmatched += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Tries to match the plays that couldn't be matched when they were imported, for example after
+/// new music has been added to the library.
+pub async fn rematch_plays(pool: &SqlitePool) -> Result<ImportSummary, ImportError> {
+    let mut matcher = TrackMatcher::new(list_track_listings(pool).await?);
+    let mut summary = ImportSummary::default();
+
+    for play in list_unmatched_plays(pool).await? {
+        let Some(track) = matcher.find(&play.artist, &play.title, play.album.as_deref()) else {
+            summary.unmatched += 1;
+            continue;
+        };
+
+        let created = create_imported_play(
+            pool,
+            &track.location,
+            play.played_at,
+            track.duration,
+            &play.source,
+        )
+        .await?;
+
+        if created {
+            summary.imported += 1;
+        } else {
+            summary.duplicates += 1;
+        }
+
+        delete_unmatched_play(pool, play.id).await?;
+    }
+
+    Ok(summary)
+}
@@ -0,0 +1,15 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("Could not read the export: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse the export: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("The last.fm request failed: {0}")]
+    LastFM(anyhow::Error),
+    #[error("Muzak was not compiled with last.fm support")]
+    LastFMUnavailable,
+}
@@ -0,0 +1,192 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Deserialize;
+use tracing::warn;
+
+use super::{errors::ImportError, ImportedPlay};
+
+/// Plays in the statistics of a track with only one known play time are placed this far apart
+/// before it, so that they aren't mistaken for the same play.
+const UNKNOWN_PLAY_SPACING: TimeDelta = TimeDelta::hours(1);
+
+/// Play counts are read from the export as they are written, so larger counts are assumed to be
+/// corrupt and capped, rather than creating a play for each.
+const MAX_PLAY_COUNT: u64 = 100_000;
+
+/// Numbers and dates are written as strings or numbers, depending on the component that exported
+/// the statistics.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Value {
+    Number(i64),
+    Text(String),
+}
+
+impl Value {
+    fn count(&self) -> Option<u64> {
+        match self {
+            Value::Number(v) => u64::try_from(*v).ok(),
+            Value::Text(v) => v.trim().parse().ok(),
+        }
+    }
+
+    fn time(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Value::Number(v) => DateTime::from_timestamp(*v, 0),
+            // times without a timezone are in local time, which is what foobar2000 writes
+            Value::Text(v) => dateparser::parse(v.trim()).ok(),
+        }
+    }
+}
+
+/// A track in a playback statistics export. foo_playcount and foo_enhanced_playcount name the
+/// fields differently, so both sets of names are accepted, as are the title formatting fields.
+#[derive(Deserialize)]
+struct StatisticsEntry {
+    #[serde(alias = "Artist", alias = "%artist%")]
+    artist: Option<String>,
+    #[serde(alias = "Title", alias = "%title%")]
+    title: Option<String>,
+    #[serde(alias = "Album", alias = "%album%")]
+    album: Option<String>,
+    #[serde(
+        alias = "count",
+        alias = "Count",
+        alias = "play_count",
+        alias = "%play_count%"
+    )]
+    playcount: Option<Value>,
+    #[serde(alias = "firstPlayed", alias = "FirstPlayed", alias = "%first_played%")]
+    first_played: Option<Value>,
+    #[serde(alias = "lastPlayed", alias = "LastPlayed", alias = "%last_played%")]
+    last_played: Option<Value>,
+}
+
+/// Spreads the plays evenly between the first and last play, since the statistics only record
+/// those two times.
+fn play_times(
+    count: u64,
+    first: Option<DateTime<Utc>>,
+    last: Option<DateTime<Utc>>,
+) -> Vec<DateTime<Utc>> {
+    let Ok(count) = i32::try_from(count.min(MAX_PLAY_COUNT)) else {
+        return Vec::new();
+    };
+
+    match (first, last) {
+        (Some(first), Some(last)) if count > 1 && last > first => {
+            let step = (last - first).num_seconds() / i64::from(count - 1);
+
+            (0..count)
+                .map(|i| first + TimeDelta::seconds(step * i64::from(i)))
+                .collect()
+        }
+        (_, Some(time)) | (Some(time), None) => (0..count)
+            .map(|i| time - UNKNOWN_PLAY_SPACING * i)
+            .collect(),
+        (None, None) => Vec::new(),
+    }
+}
+
+impl StatisticsEntry {
+    fn into_plays(self) -> Vec<ImportedPlay> {
+        let (Some(artist), Some(title)) = (self.artist, self.title) else {
+            return Vec::new();
+        };
+
+        let first = self.first_played.as_ref().and_then(Value::time);
+        let last = self.last_played.as_ref().and_then(Value::time);
+        let count = self
+            .playcount
+            .as_ref()
+            .and_then(Value::count)
+            .unwrap_or(if last.is_some() { 1 } else { 0 });
+
+        if count > MAX_PLAY_COUNT {
+            warn!(
+                "Only importing {} of the {} plays of {} - {}",
+                MAX_PLAY_COUNT, count, artist, title
+            );
+        }
+
+        let album = self.album.filter(|v| !v.is_empty());
+
+        play_times(count, first, last)
+            .into_iter()
+            .map(|played_at| ImportedPlay {
+                artist: artist.clone(),
+                title: title.clone(),
+                album: album.clone(),
+                played_at,
+            })
+            .collect()
+    }
+}
+
+/// Reads a foobar2000 playback statistics export, a JSON array with an entry for every track.
+/// Only the number of plays and the times of the first and last play are recorded, so the times
+/// of the other plays are estimated.
+pub fn read_statistics(contents: &str) -> Result<Vec<ImportedPlay>, ImportError> {
+    let entries: Vec<StatisticsEntry> = serde_json::from_str(contents)?;
+
+    Ok(entries
+        .into_iter()
+        .flat_map(StatisticsEntry::into_plays)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn statistics_are_read_with_every_field_name() {
+        let plays = read_statistics(include_str!(
+            "../../../tests/fixtures/import/foobar2000.json"
+        ))
+        .unwrap();
+
+        let times: Vec<_> = plays
+            .iter()
+            .map(|v| (v.title.as_str(), v.played_at))
+            .collect();
+        assert_eq!(
+            times,
+            [
+                ("Turquoise Hexagon Sun", time("2024-06-01T10:00:00Z")),
+                ("Turquoise Hexagon Sun", time("2024-06-01T11:00:00Z")),
+                ("Turquoise Hexagon Sun", time("2024-06-01T12:00:00Z")),
+                ("Xtal", time("2024-06-01T12:00:00Z")),
+                ("Xtal", time("2024-06-01T11:00:00Z")),
+                ("Bike", time("2024-06-01T10:00:00Z")),
+            ]
+        );
+
+        assert_eq!(plays[3].artist, "Aphex Twin");
+        assert_eq!(
+            plays[3].album.as_deref(),
+            Some("Selected Ambient Works 85-92")
+        );
+        assert_eq!(plays[5].album, None);
+    }
+
+    #[test]
+    fn play_counts_are_capped() {
+        let last = Some(time("2024-06-01T12:00:00Z"));
+
+        assert_eq!(
+            play_times(u64::MAX, None, last).len(),
+            MAX_PLAY_COUNT as usize
+        );
+        assert!(play_times(3, None, None).is_empty());
+    }
+
+    #[test]
+    fn malformed_statistics_are_rejected() {
+        assert!(read_statistics(r#"{"artist": "Artist"}"#).is_err());
+        assert!(read_statistics("[{").is_err());
+    }
+}
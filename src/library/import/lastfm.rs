@@ -0,0 +1,205 @@
+use std::{path::PathBuf, time::Duration};
+
+use async_trait::async_trait;
+use chrono::DateTime;
+use tracing::{info, warn};
+
+use crate::services::mmb::lastfm::{
+    client::LastFMClient,
+    is_retryable,
+    types::{GetRecentTracks, RecentTrack, RecentTracks},
+};
+
+use super::{errors::ImportError, ImportedPlay};
+
+/// Provides the pages of a user's `user.getRecentTracks` history.
+#[async_trait]
+pub trait RecentTracksSource {
+    /// Returns the page with the given number, starting at 1.
+    async fn page(&mut self, page: u64) -> Result<RecentTracks, ImportError>;
+}
+
+/// How long to wait before requesting a page again after a request was rate limited or failed,
+/// doubled after every failure.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// How many times a page is requested before the import gives up.
+const MAX_ATTEMPTS: u32 = 6;
+
+/// Reads the history from the last.fm API. Requests that were rate limited or failed because of a
+/// network or server problem are retried, so that a long history can be read in one go.
+pub struct LastFMHistory {
+    client: LastFMClient,
+    user: String,
+    retry_delay: Duration,
+}
+
+impl LastFMHistory {
+    pub fn new(client: LastFMClient, user: String) -> Self {
+        LastFMHistory {
+            client,
+            user,
+            retry_delay: RETRY_DELAY,
+        }
+    }
+}
+
+#[async_trait]
+impl RecentTracksSource for LastFMHistory {
+    async fn page(&mut self, page: u64) -> Result<RecentTracks, ImportError> {
+        let mut delay = self.retry_delay;
+        let mut attempt = 1;
+
+        loop {
+            match self.client.get_recent_tracks(self.user.clone(), page).await {
+                Ok(tracks) => return Ok(tracks),
+                Err(e) if attempt < MAX_ATTEMPTS && is_retryable(&e) => {
+                    warn!(
+                        "Could not read page {} of the last.fm history, retrying in {:?}: {}",
+                        page, delay, e
+                    );
+                    async_std::task::sleep(delay).await;
+
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(e) => return Err(ImportError::LastFM(e)),
+            }
+        }
+    }
+}
+
+/// Reads the history from responses that were recorded earlier, named `page-1.json`,
+/// `page-2.json` and so on. This allows an import to be repeated without an API key, and the
+/// import to be checked against known responses.
+pub struct RecordedHistory {
+    directory: PathBuf,
+}
+
+impl RecordedHistory {
+    pub fn new(directory: PathBuf) -> Self {
+        RecordedHistory { directory }
+    }
+}
+
+#[async_trait]
+impl RecentTracksSource for RecordedHistory {
+    async fn page(&mut self, page: u64) -> Result<RecentTracks, ImportError> {
+        let path = self.directory.join(format!("page-{}.json", page));
+        let contents = async_std::fs::read_to_string(path).await?;
+
+        Ok(serde_json::from_str::<GetRecentTracks>(&contents)?.recent_tracks)
+    }
+}
+
+fn to_play(track: RecentTrack) -> Option<ImportedPlay> {
+    // the track that is playing right now hasn't been scrobbled yet
+    if track.now_playing() {
+        return None;
+    }
+
+    let played_at = DateTime::from_timestamp(track.date?.uts? as i64, 0)?;
+
+    Some(ImportedPlay {
+        artist: track.artist.text,
+        title: track.name,
+        album: track.album.map(|v| v.text).filter(|v| !v.is_empty()),
+        played_at,
+    })
+}
+
+/// Reads every page of the history.
+pub async fn read_history(
+    source: &mut dyn RecentTracksSource,
+) -> Result<Vec<ImportedPlay>, ImportError> {
+    let mut plays = Vec::new();
+    let mut page = 1;
+
+    loop {
+        let tracks = source.page(page).await?;
+        let total_pages = tracks.attr.total_pages.unwrap_or(1);
+
+        info!(
+            "Read page {} of {} of the last.fm history",
+            page, total_pages
+        );
+        plays.extend(tracks.track.into_iter().filter_map(to_play));
+
+        if page >= total_pages {
+            break;
+        }
+
+        page += 1;
+    }
+
+    Ok(plays)
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::task;
+
+    use super::*;
+    use crate::services::mmb::scrobbling::mock::MockServer;
+
+    fn fixtures() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/import/lastfm")
+    }
+
+    fn history(server: &MockServer) -> LastFMHistory {
+        let mut client = LastFMClient::new("key".to_string(), "secret");
+        client.set_api_url(server.url.clone());
+
+        let mut history = LastFMHistory::new(client, "example".to_string());
+        history.retry_delay = Duration::ZERO;
+        history
+    }
+
+    #[test]
+    fn recorded_history_is_read_page_by_page() {
+        let mut source = RecordedHistory::new(fixtures());
+        let plays = task::block_on(read_history(&mut source)).unwrap();
+
+        let titles: Vec<_> = plays.iter().map(|v| v.title.as_str()).collect();
+        assert_eq!(titles, ["Turquoise Hexagon Sun", "Xtal", "Bike"]);
+
+        assert_eq!(plays[0].artist, "Boards of Canada");
+        assert_eq!(
+            plays[0].album.as_deref(),
+            Some("Music Has the Right to Children")
+        );
+        assert_eq!(plays[0].played_at.timestamp(), 1717243200);
+        assert_eq!(plays[2].album, None);
+    }
+
+    #[test]
+    fn missing_pages_fail_the_import() {
+        let mut source = RecordedHistory::new(fixtures().join("missing"));
+
+        assert!(matches!(
+            task::block_on(read_history(&mut source)),
+            Err(ImportError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn rate_limited_requests_are_retried() {
+        let server = MockServer::start(429, r#"{"error": 29, "message": "Rate Limit Exceeded"}"#);
+        let mut history = history(&server);
+
+        assert!(matches!(
+            task::block_on(history.page(1)),
+            Err(ImportError::LastFM(_))
+        ));
+        assert_eq!(server.requests(), MAX_ATTEMPTS as usize);
+    }
+
+    #[test]
+    fn other_errors_are_not_retried() {
+        let server = MockServer::start(200, r#"{"error": 6, "message": "User not found"}"#);
+        let mut history = history(&server);
+
+        assert!(task::block_on(history.page(1)).is_err());
+        assert_eq!(server.requests(), 1);
+    }
+}
@@ -0,0 +1,98 @@
+use chrono::DateTime;
+use serde::Deserialize;
+
+use super::{errors::ImportError, ImportedPlay};
+
+#[derive(Deserialize)]
+struct ExportedListen {
+    listened_at: i64,
+    track_metadata: ExportedMetadata,
+}
+
+#[derive(Deserialize)]
+struct ExportedMetadata {
+    artist_name: String,
+    track_name: String,
+    release_name: Option<String>,
+}
+
+impl ExportedListen {
+    fn into_play(self) -> Option<ImportedPlay> {
+        Some(ImportedPlay {
+            artist: self.track_metadata.artist_name,
+            title: self.track_metadata.track_name,
+            album: self.track_metadata.release_name.filter(|v| !v.is_empty()),
+            played_at: DateTime::from_timestamp(self.listened_at, 0)?,
+        })
+    }
+}
+
+/// Reads a ListenBrainz listen export. Older exports are a single JSON array, newer ones contain
+/// one listen per line.
+pub fn read_export(contents: &str) -> Result<Vec<ImportedPlay>, ImportError> {
+    let listens: Vec<ExportedListen> = if contents.trim_start().starts_with('[') {
+        serde_json::from_str(contents)?
+    } else {
+        contents
+            .lines()
+            .filter(|v| !v.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?
+    };
+
+    Ok(listens
+        .into_iter()
+        .filter_map(ExportedListen::into_play)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(plays: &[ImportedPlay]) -> Vec<(&str, &str, Option<&str>, i64)> {
+        plays
+            .iter()
+            .map(|v| {
+                (
+                    v.artist.as_str(),
+                    v.title.as_str(),
+                    v.album.as_deref(),
+                    v.played_at.timestamp(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn json_and_json_lines_exports_are_read() {
+        let expected = [
+            (
+                "Boards of Canada",
+                "Turquoise Hexagon Sun",
+                Some("Music Has the Right to Children"),
+                1717243200,
+            ),
+            ("Autechre", "Bike", None, 1717236000),
+        ];
+
+        let plays = read_export(include_str!(
+            "../../../tests/fixtures/import/listenbrainz.json"
+        ))
+        .unwrap();
+        assert_eq!(summary(&plays), expected);
+
+        let plays = read_export(include_str!(
+            "../../../tests/fixtures/import/listenbrainz.jsonl"
+        ))
+        .unwrap();
+        assert_eq!(summary(&plays), expected);
+    }
+
+    #[test]
+    fn malformed_exports_are_rejected() {
+        assert!(read_export(r#"{"listened_at": 1717236000}"#).is_err());
+        assert!(read_export("[{").is_err());
+        assert_eq!(read_export("").unwrap(), Vec::new());
+    }
+}
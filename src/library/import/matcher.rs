@@ -0,0 +1,180 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use nucleo::{
+    pattern::{AtomKind, CaseMatching, Normalization, Pattern},
+    Config, Matcher,
+};
+
+use crate::library::types::TrackListing;
+
+/// Fuzzy matches are only accepted if the shorter name is at least this fraction of the length of
+/// the longer one, so that a short title doesn't match every title that contains its letters.
+const MIN_LENGTH_RATIO: f32 = 0.75;
+
+fn words(value: &str, strip_brackets: bool) -> Vec<String> {
+    let mut cleaned = String::new();
+    let mut depth = 0usize;
+
+    for c in value.chars() {
+        match c {
+            '(' | '[' if strip_brackets => depth += 1,
+            ')' | ']' if strip_brackets => depth = depth.saturating_sub(1),
+            _ if depth > 0 => {}
+            '&' => cleaned.push_str(" and "),
+            c if c.is_alphanumeric() => cleaned.push(c),
+            _ => cleaned.push(' '),
+        }
+    }
+
+    cleaned.split_whitespace().map(str::to_string).collect()
+}
+
+/// Reduces a name to a form that is the same across services: lowercase, without punctuation,
+/// featured artists, or suffixes such as "(Live)" or "- 2011 Remaster".
+fn normalize(value: &str) -> String {
+    let lower = value.to_lowercase();
+    let value = match lower.rsplit_once(" - ") {
+        Some((head, tail)) if tail.contains("remaster") => head,
+        _ => &lower,
+    };
+
+    let mut words = words(value, true);
+    if words.is_empty() {
+        // the whole name is in brackets
+        words = self::words(value, false);
+    }
+
+    let end = words
+        .iter()
+        .position(|v| matches!(v.as_str(), "feat" | "ft" | "featuring"))
+        .unwrap_or(words.len());
+
+    words[..end].join(" ")
+}
+
+fn similar_length(a: &str, b: &str) -> bool {
+    let (a, b) = (a.chars().count(), b.chars().count());
+    a.min(b) as f32 >= a.max(b) as f32 * MIN_LENGTH_RATIO
+}
+
+/// Returns the name in `names` that matches `needle` best, if any is close enough.
+fn best_match<'a>(
+    matcher: &mut Matcher,
+    needle: &str,
+    names: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    if needle.is_empty() {
+        return None;
+    }
+
+    let pattern = Pattern::new(
+        needle,
+        CaseMatching::Ignore,
+        Normalization::Smart,
+        AtomKind::Fuzzy,
+    );
+    let names = names.into_iter().filter(|v| similar_length(needle, v));
+
+    pattern
+        .match_list(names, matcher)
+        .into_iter()
+        .next()
+        .map(|(v, _)| v)
+}
+
+/// A track in the library that a play was matched to.
+pub struct LibraryTrack {
+    pub location: PathBuf,
+    /// The duration of the track, in seconds.
+    pub duration: u64,
+    title: String,
+    album: Option<String>,
+}
+
+/// Matches plays from other services to tracks in the library by their artist and title. Names
+/// are compared after normalization, and fuzzily if there is no exact match. The album is used to
+/// choose between tracks with the same title, such as a song on a studio album and a compilation.
+pub struct TrackMatcher {
+    tracks: Vec<LibraryTrack>,
+    /// The tracks of every artist, by both the track artist and the album artist.
+    by_artist: HashMap<String, Vec<usize>>,
+    matcher: Matcher,
+}
+
+impl TrackMatcher {
+    pub fn new(listings: Vec<TrackListing>) -> Self {
+        let mut tracks = Vec::with_capacity(listings.len());
+        let mut by_artist: HashMap<String, Vec<usize>> = HashMap::new();
+
+        for listing in listings {
+            let index = tracks.len();
+            let mut artists: Vec<String> = [&listing.artist_names, &listing.album_artist]
+                .into_iter()
+                .flatten()
+                .map(|v| normalize(&v.0))
+                .filter(|v| !v.is_empty())
+                .collect();
+            artists.dedup();
+
+            for artist in artists {
+                by_artist.entry(artist).or_default().push(index);
+            }
+
+            tracks.push(LibraryTrack {
+                location: listing.location,
+                duration: listing.duration.max(0) as u64,
+                title: normalize(&listing.title.0),
+                album: listing.album_title.map(|v| normalize(&v.0)),
+            });
+        }
+
+        TrackMatcher {
+            tracks,
+            by_artist,
+            matcher: Matcher::new(Config::DEFAULT),
+        }
+    }
+
+    pub fn find(
+        &mut self,
+        artist: &str,
+        title: &str,
+        album: Option<&str>,
+    ) -> Option<&LibraryTrack> {
+        let artist = normalize(artist);
+        let title = normalize(title);
+        let album = album.map(normalize);
+
+        let candidates = match self.by_artist.get(&artist) {
+            Some(candidates) => candidates,
+            None => {
+                let name = best_match(
+                    &mut self.matcher,
+                    &artist,
+                    self.by_artist.keys().map(String::as_str),
+                )?;
+                &self.by_artist[name]
+            }
+        };
+
+        let title = if candidates.iter().any(|v| self.tracks[*v].title == title) {
+            title
+        } else {
+            let titles = candidates.iter().map(|v| self.tracks[*v].title.as_str());
+            best_match(&mut self.matcher, &title, titles)?.to_string()
+        };
+
+        let matching: Vec<usize> = candidates
+            .iter()
+            .copied()
+            .filter(|v| self.tracks[*v].title == title)
+            .collect();
+
+        let index = matching
+            .iter()
+            .find(|v| album.is_some() && self.tracks[**v].album == album)
+            .or(matching.first())?;
+
+        Some(&self.tracks[*index])
+    }
+}
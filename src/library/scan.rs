@@ -1,20 +1,31 @@
 use std::{
+    collections::HashSet,
     fs::{self, File},
-    io::{BufReader, Cursor, Write},
-    path::{Path, PathBuf},
-    sync::mpsc,
-    time::{Duration, SystemTime},
+    io::{BufReader, Write},
+    path::{Path, PathBuf, MAIN_SEPARATOR_STR},
+    sync::mpsc::{self, RecvTimeoutError},
+    time::{Duration, Instant, SystemTime},
 };
 
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
 use async_std::task;
-use globwalk::GlobWalkerBuilder;
+use chrono::{DateTime, Utc};
+use credits::{album_credits, track_credits, Credit};
+use genres::track_genres;
 use gpui::{App, Global};
-use image::{codecs::jpeg::JpegEncoder, imageops::thumbnail, EncodableLayout};
-use sqlx::SqlitePool;
+use indexmap::IndexSet;
+use notify::{
+    event::{ModifyKind, RenameMode},
+    Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
+use roots::{find_root, is_removed, RootRules};
+use serde::{Deserialize, Serialize};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
 use tracing::{debug, error, info, warn};
+use workers::{AlbumArt, ScannedFile, WorkerPool, WorkerResult};
 
 use crate::{
+    library::types::ArtistRole,
     media::{
         builtin::symphonia::SymphoniaProvider,
         metadata::Metadata,
@@ -24,7 +35,16 @@ use crate::{
     ui::models::Models,
 };
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+mod credits;
+pub mod genres;
+mod roots;
+mod workers;
+
+/// The number of scanned files written to the database in one transaction.
+const WRITE_BATCH_SIZE: usize = 250;
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ScanEvent {
     Cleaning,
     DiscoverProgress(u64),
@@ -33,10 +53,11 @@ pub enum ScanEvent {
     ScanCompleteIdle,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, Clone)]
 enum ScanCommand {
     Scan,
     Stop,
+    UpdateSettings(ScanSettings),
 }
 
 pub struct ScanInterface {
@@ -67,6 +88,20 @@ impl ScanInterface {
             .expect("could not send tx");
     }
 
+    /// Replaces the scan settings. If the library folders or their rules changed, the library is
+    /// scanned again, which removes the tracks that are no longer in any of them.
+    pub fn update_settings(&self, settings: ScanSettings) {
+        self.command_tx
+            .send(ScanCommand::UpdateSettings(settings))
+            .expect("could not send tx");
+    }
+
+    /// Takes the event receiver, for consumers that do not use `start_broadcast`. Returns None if
+    /// the receiver has already been taken.
+    pub fn take_events(&mut self) -> Option<mpsc::Receiver<ScanEvent>> {
+        self.events_rx.take()
+    }
+
     pub fn start_broadcast(&mut self, cx: &mut App) {
         let mut events_rx = None;
         std::mem::swap(&mut self.events_rx, &mut events_rx);
@@ -104,20 +139,73 @@ pub enum ScanState {
     Scanning,
 }
 
+/// Changes to the library folders are applied once nothing has changed for this long, so that
+/// copying an album produces one update instead of one per file.
+const WATCH_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// A folder to look for files in while discovering.
+struct Folder {
+    /// The canonical path of the folder.
+    path: PathBuf,
+    /// The index of the root the folder was found in.
+    root: usize,
+    /// The path of the folder relative to its root. This is not the same as the canonical path
+    /// when the folder was reached through a symlink.
+    relative: PathBuf,
+}
+
+/// A change to the library folders reported by the file watcher.
+#[derive(Debug, PartialEq, Clone)]
+enum WatchChange {
+    /// A file or directory was created or modified.
+    Changed(PathBuf),
+    Removed(PathBuf),
+    /// A file or directory was moved. Whether it is a directory is recorded when the event
+    /// arrives, since it may have been moved or deleted again by the time the change is applied.
+    Renamed {
+        from: PathBuf,
+        to: PathBuf,
+        directory: bool,
+    },
+}
+
 pub struct ScanThread {
     event_tx: mpsc::Sender<ScanEvent>,
     command_rx: mpsc::Receiver<ScanCommand>,
     pool: SqlitePool,
     scan_settings: ScanSettings,
+    roots: Vec<RootRules>,
     visited: Vec<PathBuf>,
-    discovered: Vec<PathBuf>,
-    to_process: Vec<PathBuf>,
+    discovered: Vec<Folder>,
+    /// The files found during a full scan, whether or not they changed. Once discovery is done,
+    /// the tracks that weren't found are removed, which cleans up the tracks in folders that are
+    /// no longer part of the library or are now excluded.
+    seen: Option<AHashSet<PathBuf>>,
+    /// Settings received during a scan, which are applied once it's done.
+    pending_settings: Option<ScanSettings>,
+    /// The files to scan, with their modification times.
+    to_process: Vec<(PathBuf, u64)>,
     scan_state: ScanState,
-    provider_table: Vec<(&'static [&'static str], Box<dyn MediaProvider>)>,
+    /// The extensions supported by each provider. The files are read by the workers, which have
+    /// their own providers.
+    extensions: Vec<&'static [&'static str]>,
+    workers: Option<WorkerPool>,
+    /// Files that have been read but not yet written to the database.
+    batch: Vec<ScannedFile>,
+    /// The modification time of every file in the database when it was scanned. Files are only
+    /// added once they have been written to the database, so that a cancelled scan picks up where
+    /// it left off.
     scan_record: AHashMap<PathBuf, u64>,
     scan_record_path: Option<PathBuf>,
     scanned: u64,
     discovered_total: u64,
+    watcher: Option<RecommendedWatcher>,
+    watch_tx: mpsc::Sender<notify::Result<Event>>,
+    watch_rx: mpsc::Receiver<notify::Result<Event>>,
+    pending_changes: Vec<WatchChange>,
+    last_change: Option<Instant>,
+    /// The path and tracker of the last rename reported without its destination.
+    rename_from: Option<(Option<usize>, PathBuf)>,
 }
 
 fn build_provider_table() -> Vec<(&'static [&'static str], Box<dyn MediaProvider>)> {
@@ -128,18 +216,6 @@ fn build_provider_table() -> Vec<(&'static [&'static str], Box<dyn MediaProvider
     )]
 }
 
-fn retrieve_base_paths() -> Vec<PathBuf> {
-    // TODO: user-defined base paths
-    // TODO: we should also probably check if these directories exist
-    let system_music = directories::UserDirs::new()
-        .unwrap()
-        .audio_dir()
-        .unwrap()
-        .to_path_buf();
-
-    vec![system_music]
-}
-
 fn file_is_scannable_with_provider(path: &Path, exts: &&[&str]) -> bool {
     for extension in exts.iter() {
         if let Some(ext) = path.extension() {
@@ -152,45 +228,6 @@ fn file_is_scannable_with_provider(path: &Path, exts: &&[&str]) -> bool {
     false
 }
 
-type FileInformation = (Metadata, u64, Option<Box<[u8]>>);
-
-// We don't care about the error message. If the file can't be scanned, we just ignore it.
-// TODO: it might be worth logging why the file couldn't be scanned (for plugin development)
-fn scan_file_with_provider(
-    path: &PathBuf,
-    provider: &mut Box<dyn MediaProvider>,
-) -> Result<FileInformation, ()> {
-    let src = std::fs::File::open(path).map_err(|_| ())?;
-    provider.open(src, None).map_err(|_| ())?;
-    provider.start_playback().map_err(|_| ())?;
-    let metadata = provider.read_metadata().cloned().map_err(|_| ())?;
-    let image = provider.read_image().map_err(|_| ())?;
-    let len = provider.duration_secs().map_err(|_| ())?;
-    provider.close().map_err(|_| ())?;
-    Ok((metadata, len, image))
-}
-
-// Returns the first image (cover/front/folder.jpeg/png/jpeg) in the track's containing folder
-// Album art can be named anything, but this pattern is convention and the least likely to return a false positive
-fn scan_path_for_album_art(path: &Path) -> Option<Box<[u8]>> {
-    let glob = GlobWalkerBuilder::from_patterns(
-        path.parent().unwrap(),
-        &["{folder,cover,front}.{jpg,jpeg,png}"],
-    )
-    .case_insensitive(true)
-    .max_depth(1)
-    .build()
-    .expect("Failed to build album art glob")
-    .filter_map(|e| e.ok());
-
-    for entry in glob {
-        if let Ok(bytes) = fs::read(entry.path()) {
-            return Some(bytes.into_boxed_slice());
-        }
-    }
-    None
-}
-
 impl ScanThread {
     pub fn start(pool: SqlitePool, settings: ScanSettings) -> ScanInterface {
         let (commands_tx, commands_rx) = std::sync::mpsc::channel();
@@ -199,22 +236,7 @@ impl ScanThread {
         std::thread::Builder::new()
             .name("scanner".to_string())
             .spawn(move || {
-                let mut thread = ScanThread {
-                    event_tx: events_tx,
-                    command_rx: commands_rx,
-                    pool,
-                    visited: Vec::new(),
-                    discovered: Vec::new(),
-                    to_process: Vec::new(),
-                    scan_state: ScanState::Idle,
-                    provider_table: build_provider_table(),
-                    scan_settings: settings,
-                    scan_record: AHashMap::new(),
-                    scan_record_path: None,
-                    scanned: 0,
-                    discovered_total: 0,
-                };
-
+                let mut thread = ScanThread::new(pool, settings, events_tx, commands_rx);
                 thread.run();
             })
             .expect("could not start playback thread");
@@ -222,6 +244,42 @@ impl ScanThread {
         ScanInterface::new(Some(events_rx), commands_tx)
     }
 
+    fn new(
+        pool: SqlitePool,
+        settings: ScanSettings,
+        event_tx: mpsc::Sender<ScanEvent>,
+        command_rx: mpsc::Receiver<ScanCommand>,
+    ) -> Self {
+        let (watch_tx, watch_rx) = std::sync::mpsc::channel();
+
+        ScanThread {
+            event_tx,
+            command_rx,
+            pool,
+            roots: settings.roots.iter().map(RootRules::new).collect(),
+            visited: Vec::new(),
+            discovered: Vec::new(),
+            seen: None,
+            pending_settings: None,
+            to_process: Vec::new(),
+            scan_state: ScanState::Idle,
+            extensions: build_provider_table().into_iter().map(|v| v.0).collect(),
+            workers: None,
+            batch: Vec::new(),
+            scan_settings: settings,
+            scan_record: AHashMap::new(),
+            scan_record_path: None,
+            scanned: 0,
+            discovered_total: 0,
+            watcher: None,
+            watch_tx,
+            watch_rx,
+            pending_changes: Vec::new(),
+            last_change: None,
+            rename_from: None,
+        }
+    }
+
     fn run(&mut self) {
         let dirs = directories::ProjectDirs::from("me", "william341", "muzak")
             .expect("couldn't find project dirs");
@@ -255,12 +313,15 @@ impl ScanThread {
         loop {
             self.read_commands();
 
-            // TODO: clear out old files if they've been deleted or moved
-            // TODO: connect to user interface to display progress
-            // TODO: start file watcher to update db automatically when files are added or removed
             match self.scan_state {
                 ScanState::Idle => {
-                    std::thread::sleep(std::time::Duration::from_millis(100));
+                    self.read_watch_events();
+
+                    if self.changes_settled() {
+                        self.apply_changes();
+                    } else {
+                        std::thread::sleep(std::time::Duration::from_millis(100));
+                    }
                 }
                 ScanState::Cleanup => {
                     self.cleanup();
@@ -280,20 +341,20 @@ impl ScanThread {
             match command {
                 ScanCommand::Scan => {
                     if self.scan_state == ScanState::Idle {
-                        self.discovered = self.scan_settings.paths.clone();
-                        self.scan_state = ScanState::Cleanup;
-                        self.scanned = 0;
-                        self.discovered_total = 0;
-                        self.event_tx
-                            .send(ScanEvent::Cleaning)
-                            .expect("could not send scan started event");
+                        self.start_scan();
                     }
                 }
+                ScanCommand::UpdateSettings(settings) => self.update_settings(settings),
                 ScanCommand::Stop => {
-                    self.scan_state = ScanState::Idle;
-                    self.visited.clear();
-                    self.discovered.clear();
-                    self.to_process.clear();
+                    if let Some(workers) = &self.workers {
+                        // the files that are being read are still written, see `scan`
+                        workers.cancel();
+                    } else if self.scan_state != ScanState::Idle {
+                        self.discovered.clear();
+                        self.seen = None;
+                        self.to_process.clear();
+                        self.finish_scan();
+                    }
                 }
             }
         }
@@ -307,58 +368,141 @@ impl ScanThread {
         }
     }
 
-    fn file_is_scannable(&mut self, path: &PathBuf) -> bool {
-        let timestamp = match fs::metadata(path) {
-            Ok(metadata) => metadata
-                .modified()
-                .unwrap()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            Err(_) => return false,
-        };
+    /// Starts a full scan of every root.
+    fn start_scan(&mut self) {
+        self.discovered = self
+            .roots
+            .iter()
+            .enumerate()
+            .map(|(idx, root)| Folder {
+                path: root.path.clone(),
+                root: idx,
+                relative: PathBuf::new(),
+            })
+            .collect();
+        self.seen = Some(AHashSet::new());
+        self.scan_state = ScanState::Cleanup;
+        self.scanned = 0;
+        self.discovered_total = 0;
+        self.event_tx
+            .send(ScanEvent::Cleaning)
+            .expect("could not send scan started event");
+    }
 
-        for (exts, _) in self.provider_table.iter() {
-            let x = file_is_scannable_with_provider(path, exts);
+    fn update_settings(&mut self, settings: ScanSettings) {
+        // discovery refers to the roots by index, so they can't change during a scan
+        if self.scan_state != ScanState::Idle {
+            self.pending_settings = Some(settings);
+            return;
+        }
 
-            if !x {
-                continue;
-            }
-            if let Some(last_scan) = self.scan_record.get(path) {
-                if *last_scan == timestamp {
-                    return false;
-                }
-            }
+        if settings.roots == self.scan_settings.roots {
+            return;
+        }
 
-            self.scan_record.insert(path.clone(), timestamp);
-            return true;
+        info!("Library folders changed, scanning again");
+        self.roots = settings.roots.iter().map(RootRules::new).collect();
+        self.scan_settings = settings;
+        // the watcher is started again for the new roots once the scan is done
+        self.watcher = None;
+        self.pending_changes.clear();
+        self.last_change = None;
+        self.start_scan();
+    }
+
+    fn is_supported(&self, path: &Path) -> bool {
+        self.extensions
+            .iter()
+            .any(|exts| file_is_scannable_with_provider(path, exts))
+    }
+
+    /// Returns the modification time of the file if it can be scanned and has changed since it
+    /// was last scanned.
+    fn file_is_scannable(&self, path: &Path) -> Option<u64> {
+        let timestamp = fs::metadata(path)
+            .ok()?
+            .modified()
+            .ok()?
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()?
+            .as_secs();
+
+        if !self.is_supported(path) {
+            return None;
+        }
+
+        if self.scan_record.get(path) == Some(&timestamp) {
+            return None;
         }
 
-        false
+        Some(timestamp)
     }
 
     fn discover(&mut self) {
         if self.discovered.is_empty() {
+            if let Some(seen) = self.seen.take() {
+                self.remove_unseen(&seen);
+            }
+
             self.scan_state = ScanState::Scanning;
             return;
         }
 
-        let path = self.discovered.pop().unwrap();
+        let folder = self.discovered.pop().unwrap();
 
-        if self.visited.contains(&path) {
+        if self.visited.contains(&folder.path) {
             return;
         }
 
-        let paths = fs::read_dir(&path).unwrap();
+        let entries = match fs::read_dir(&folder.path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                // the directory may have been removed since it was discovered
+                warn!("Could not read directory {:?}: {:?}", folder.path, e);
+                return;
+            }
+        };
+
+        let root = &self.roots[folder.root];
+
+        for entry in entries {
+            let Ok(entry) = entry else {
+                continue;
+            };
+
+            if !root.follow_symlinks && entry.file_type().is_ok_and(|v| v.is_symlink()) {
+                continue;
+            }
 
-        for paths in paths {
-            // TODO: handle errors
             // this might be slower than just reading the path directly but this prevents loops
-            let path = paths.unwrap().path().canonicalize().unwrap();
+            let Ok(path) = entry.path().canonicalize() else {
+                continue;
+            };
+            let relative = folder.relative.join(entry.file_name());
+
             if path.is_dir() {
-                self.discovered.push(path);
-            } else if self.file_is_scannable(&path) {
-                self.to_process.push(path);
+                if root.allows_dir(&relative) {
+                    self.discovered.push(Folder {
+                        path,
+                        root: folder.root,
+                        relative,
+                    });
+                }
+                continue;
+            }
+
+            if !self.is_supported(&path)
+                || !root.allows_file(&relative, fs::metadata(&path).map_or(0, |v| v.len()))
+            {
+                continue;
+            }
+
+            if let Some(seen) = self.seen.as_mut() {
+                seen.insert(path.clone());
+            }
+
+            if let Some(timestamp) = self.file_is_scannable(&path) {
+                self.to_process.push((path, timestamp));
 
                 self.discovered_total += 1;
 
@@ -370,292 +514,476 @@ impl ScanThread {
             }
         }
 
-        self.visited.push(path.clone());
+        self.visited.push(folder.path);
     }
 
-    async fn insert_artist(&self, metadata: &Metadata) -> Option<i64> {
-        let artist = metadata.album_artist.clone().or(metadata.artist.clone());
+    /// Removes the tracks that weren't found by a full scan. Every track in the database is
+    /// checked, rather than just the ones in the scan record, so that the tracks in folders that
+    /// were removed from the library or are now excluded are always cleaned up.
+    fn remove_unseen(&mut self, seen: &AHashSet<PathBuf>) {
+        let locations = match task::block_on(list_track_locations(&self.pool)) {
+            Ok(locations) => locations,
+            Err(e) => {
+                error!("Database error while retrieving tracks: {:?}", e);
+                return;
+            }
+        };
 
-        let artist = artist?;
-        let result: Result<(i64,), sqlx::Error> =
-            sqlx::query_as(include_str!("../../queries/scan/create_artist.sql"))
-                .bind(&artist)
-                .bind(metadata.artist_sort.as_ref().unwrap_or(&artist))
-                .fetch_one(&self.pool)
-                .await;
+        let unseen: Vec<PathBuf> = locations
+            .into_iter()
+            .filter(|path| !seen.contains(path) && is_removed(&self.roots, path))
+            .collect();
 
-        match result {
-            Ok(v) => Some(v.0),
-            Err(sqlx::Error::RowNotFound) => {
-                let result: Result<(i64,), sqlx::Error> =
-                    sqlx::query_as(include_str!("../../queries/scan/get_artist_id.sql"))
-                        .bind(&artist)
-                        .fetch_one(&self.pool)
-                        .await;
+        if !unseen.is_empty() {
+            info!(
+                "Removing {} tracks outside of the library folders",
+                unseen.len()
+            );
+        }
 
-                match result {
-                    Ok(v) => Some(v.0),
-                    Err(e) => {
-                        error!("Database error while retriving artist: {:?}", e);
-                        None
+        for path in unseen {
+            task::block_on(self.delete_track(&path));
+        }
+    }
+
+    fn write_scan_record(&self) {
+        if let Some(path) = self.scan_record_path.as_ref() {
+            let mut file = File::create(path).unwrap();
+            let data = serde_json::to_string(&self.scan_record).unwrap();
+            if let Err(err) = file.write_all(data.as_bytes()) {
+                error!("Could not write scan record: {:?}", err);
+                error!("Scan record will not be saved, this may cause rescans on restart");
+            } else {
+                info!("Scan record written to {:?}", path);
+            }
+        } else {
+            error!("No scan record path set, scan record will not be saved");
+        }
+    }
+
+    /// Writes the files that have been read to the database, and adds the ones that were written
+    /// to the scan record. The others are read again in the next scan.
+    fn write_batch(&mut self) {
+        if self.batch.is_empty() {
+            return;
+        }
+
+        let batch = std::mem::take(&mut self.batch);
+
+        match task::block_on(write_files(&self.pool, &batch)) {
+            Ok(written) => {
+                for (file, written) in batch.into_iter().zip(written) {
+                    if written {
+                        self.scan_record.insert(file.path, file.timestamp);
                     }
                 }
             }
-            Err(e) => {
-                error!("Database error while creating artist: {:?}", e);
-                None
-            }
+            Err(e) => error!(
+                "Database error while writing {} files: {:?}",
+                batch.len(),
+                e
+            ),
         }
     }
 
-    async fn insert_album(
-        &self,
-        metadata: &Metadata,
-        artist_id: Option<i64>,
-        image: &Option<Box<[u8]>>,
-    ) -> Option<i64> {
-        let Some(album) = &metadata.album else {
-            return None;
-        };
-        let result: Result<(i64,), sqlx::Error> =
-            sqlx::query_as(include_str!("../../queries/scan/get_album_id.sql"))
-                .bind(album)
-                .fetch_one(&self.pool)
-                .await;
+    /// Writes the remaining files and the scan record, and starts watching the library.
+    fn finish_scan(&mut self) {
+        self.write_batch();
+        self.workers = None;
+        self.write_scan_record();
+        self.scan_state = ScanState::Idle;
+        self.visited.clear();
 
-        match result {
-            Ok(v) => Some(v.0),
-            Err(sqlx::Error::RowNotFound) => {
-                let thumb = match image {
-                    Some(image) => {
-                        let decoded = image::ImageReader::new(Cursor::new(&image))
-                            .with_guessed_format()
-                            .ok()?
-                            .decode()
-                            .ok()?
-                            .into_rgba8();
+        let event = if self.start_watching() {
+            ScanEvent::ScanCompleteWatching
+        } else {
+            ScanEvent::ScanCompleteIdle
+        };
+        self.event_tx.send(event).unwrap();
 
-                        let thumb = thumbnail(&decoded, 70, 70);
+        if let Some(settings) = self.pending_settings.take() {
+            self.update_settings(settings);
+        }
+    }
 
-                        let mut buf: Cursor<Vec<u8>> = Cursor::new(Vec::new());
+    fn scan(&mut self) {
+        let Some(workers) = &self.workers else {
+            let albums = match task::block_on(list_album_keys(&self.pool)) {
+                Ok(albums) => albums,
+                Err(e) => {
+                    error!("Database error while retrieving albums: {:?}", e);
+                    HashSet::new()
+                }
+            };
 
-                        thumb
-                            .write_to(&mut buf, image::ImageFormat::Bmp)
-                            .expect("i don't know how Cursor could fail");
-                        buf.flush().expect("could not flush buffer");
+            self.workers = Some(WorkerPool::start(
+                std::mem::take(&mut self.to_process),
+                albums,
+            ));
+            return;
+        };
 
-                        Some(buf.get_mut().clone())
+        match workers.recv_timeout(Duration::from_millis(100)) {
+            Ok(result) => {
+                match result {
+                    WorkerResult::Scanned(file) => self.batch.push(*file),
+                    WorkerResult::Failed(path) => {
+                        warn!("Could not read metadata for file: {:?}", path)
                     }
-                    None => None,
-                };
+                }
 
-                let resized_image = match image {
-                    Some(image) => {
-                        let mut decoded = image::ImageReader::new(Cursor::new(&image))
-                            .with_guessed_format()
-                            .ok()?
-                            .decode()
-                            .ok()?
-                            .into_rgb8();
-
-                        if decoded.dimensions().0 <= 1024 || decoded.dimensions().1 <= 1024 {
-                            Some(image.clone().to_vec())
-                        } else {
-                            decoded = image::imageops::resize(
-                                &decoded,
-                                1024,
-                                1024,
-                                image::imageops::FilterType::Lanczos3,
-                            );
-                            let mut buf: Cursor<Vec<u8>> = Cursor::new(Vec::new());
-                            let mut encoder = JpegEncoder::new_with_quality(&mut buf, 70);
-
-                            encoder
-                                .encode(
-                                    decoded.as_bytes(),
-                                    decoded.width(),
-                                    decoded.height(),
-                                    image::ExtendedColorType::Rgb8,
-                                )
-                                .expect("could not encode image");
-                            buf.flush().expect("could not flush buffer");
-
-                            Some(buf.get_mut().clone())
-                        }
-                    }
-                    None => None,
-                };
+                self.scanned += 1;
 
-                let result: Result<(i64,), sqlx::Error> =
-                    sqlx::query_as(include_str!("../../queries/scan/create_album.sql"))
-                        .bind(album)
-                        .bind(metadata.sort_album.as_ref().unwrap_or(album))
-                        .bind(artist_id)
-                        .bind(resized_image)
-                        .bind(thumb)
-                        .bind(metadata.date)
-                        .bind(&metadata.label)
-                        .bind(&metadata.catalog)
-                        .bind(&metadata.isrc)
-                        .fetch_one(&self.pool)
-                        .await;
+                if self.scanned % 5 == 0 {
+                    self.event_tx
+                        .send(ScanEvent::ScanProgress {
+                            current: self.scanned,
+                            total: self.discovered_total,
+                        })
+                        .unwrap();
+                }
 
-                match result {
-                    Ok(v) => Some(v.0),
-                    Err(e) => {
-                        error!("Database error while creating album: {:?}", e);
-                        None
-                    }
+                if self.batch.len() >= WRITE_BATCH_SIZE {
+                    self.write_batch();
                 }
             }
-            Err(e) => {
-                error!("Database error while retriving album: {:?}", e);
-                None
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => {
+                info!("Scan complete, writing scan record and stopping");
+                self.finish_scan();
             }
         }
     }
 
-    async fn insert_track(
-        &self,
-        metadata: &Metadata,
-        album_id: Option<i64>,
-        path: &Path,
-        length: u64,
-    ) {
-        // literally i do not know how this could possibly fail
-        let name = metadata
-            .name
-            .clone()
-            .or_else(|| {
-                path.file_name()
-                    .and_then(|x| x.to_str())
-                    .map(|x| x.to_string())
-            })
-            .expect("weird file recieved in update metadata");
+    async fn delete_track(&mut self, path: &PathBuf) {
+        debug!("track deleted or moved: {:?}", path);
+        let result = sqlx::query(include_str!("../../queries/scan/delete_track.sql"))
+            .bind(path.to_str())
+            .execute(&self.pool)
+            .await;
 
-        let result: Result<(i64,), sqlx::Error> =
-            sqlx::query_as(include_str!("../../queries/scan/create_track.sql"))
-                .bind(&name)
-                .bind(&name)
-                .bind(album_id)
-                .bind(metadata.track_current.map(|x| x as i32))
-                .bind(metadata.disc_current.map(|x| x as i32))
-                .bind(length as i32)
-                .bind(path.to_str())
-                .bind(&metadata.genre)
-                .bind(&metadata.artist)
-                .fetch_one(&self.pool)
-                .await;
+        if let Err(e) = result {
+            error!("Database error while deleting track: {:?}", e);
+        } else {
+            self.scan_record.remove(path);
+        }
+    }
 
-        match result {
-            Ok(_) => (),
-            Err(sqlx::Error::RowNotFound) => (),
-            Err(e) => {
-                error!("Database error while creating track: {:?}", e);
+    /// Removes the track at the path, or every track in it if it was a directory.
+    async fn delete_path(&mut self, path: &Path) {
+        debug!("path deleted: {:?}", path);
+        let directory = format!("{}{}", path.to_string_lossy(), MAIN_SEPARATOR_STR);
+
+        for (query, location) in [
+            (
+                include_str!("../../queries/scan/delete_track.sql"),
+                path.to_str(),
+            ),
+            (
+                include_str!("../../queries/scan/delete_directory.sql"),
+                Some(directory.as_str()),
+            ),
+        ] {
+            if let Err(e) = sqlx::query(query).bind(location).execute(&self.pool).await {
+                error!("Database error while deleting {:?}: {:?}", path, e);
+                return;
             }
         }
+
+        self.scan_record.retain(|v, _| !v.starts_with(path));
     }
 
-    async fn update_metadata(
-        &mut self,
-        metadata: (Metadata, u64, Option<Box<[u8]>>),
-        path: &Path,
-    ) -> anyhow::Result<()> {
-        debug!(
-            "Adding/updating record for {:?} - {:?}",
-            metadata.0.artist, metadata.0.name
-        );
+    /// Moves the track at `from`, or every track in it if it is a directory, to `to`. The tracks
+    /// keep their IDs, and their plays, bookmarks and resume positions move with them.
+    async fn move_path(&mut self, from: &Path, to: &Path, directory: bool) {
+        debug!("path moved: {:?} -> {:?}", from, to);
 
-        let artist_id = self.insert_artist(&metadata.0).await;
-        let album_id = self.insert_album(&metadata.0, artist_id, &metadata.2).await;
-        self.insert_track(&metadata.0, album_id, path, metadata.1)
-            .await;
+        if from == to {
+            return;
+        }
+
+        if let Err(e) = move_tracks(&self.pool, from, to, directory).await {
+            error!("Database error while moving {:?}: {:?}", from, e);
+            return;
+        }
+
+        self.scan_record.retain(|v, _| !v.starts_with(to));
+
+        let moved: Vec<PathBuf> = self
+            .scan_record
+            .keys()
+            .filter(|v| v.starts_with(from))
+            .cloned()
+            .collect();
 
-        Ok(())
+        for path in moved {
+            let Some(timestamp) = self.scan_record.remove(&path) else {
+                continue;
+            };
+
+            let new_path = match path.strip_prefix(from) {
+                Ok(rest) if !rest.as_os_str().is_empty() => to.join(rest),
+                _ => to.to_path_buf(),
+            };
+            self.scan_record.insert(new_path, timestamp);
+        }
     }
 
-    fn read_metadata_for_path(&mut self, path: &PathBuf) -> Option<FileInformation> {
-        for (exts, provider) in &mut self.provider_table {
-            if file_is_scannable_with_provider(path, exts) {
-                if let Ok(mut metadata) = scan_file_with_provider(path, provider) {
-                    if metadata.2.is_none() {
-                        metadata.2 = scan_path_for_album_art(path);
-                    }
+    /// Watches the library folders for changes, if they aren't watched already. Returns whether
+    /// the folders are being watched.
+    fn start_watching(&mut self) -> bool {
+        if self.watcher.is_some() {
+            return true;
+        }
 
-                    return Some(metadata);
-                }
+        let mut watcher = match notify::recommended_watcher(self.watch_tx.clone()) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                error!("Could not start watching the library: {:?}", e);
+                return false;
             }
-        }
+        };
 
-        None
-    }
+        let mut watching = false;
 
-    fn write_scan_record(&self) {
-        if let Some(path) = self.scan_record_path.as_ref() {
-            let mut file = File::create(path).unwrap();
-            let data = serde_json::to_string(&self.scan_record).unwrap();
-            if let Err(err) = file.write_all(data.as_bytes()) {
-                error!("Could not write scan record: {:?}", err);
-                error!("Scan record will not be saved, this may cause rescans on restart");
-            } else {
-                info!("Scan record written to {:?}", path);
+        // events are reported relative to the watched path, and the paths of the roots are
+        // canonical, like the scanned paths
+        for path in self.roots.iter().map(|v| &v.path) {
+            match watcher.watch(path, RecursiveMode::Recursive) {
+                Ok(_) => watching = true,
+                Err(e) => warn!("Could not watch {:?} for changes: {:?}", path, e),
             }
-        } else {
-            error!("No scan record path set, scan record will not be saved");
         }
-    }
 
-    fn scan(&mut self) {
-        if self.to_process.is_empty() {
-            info!("Scan complete, writing scan record and stopping");
-            self.write_scan_record();
-            self.scan_state = ScanState::Idle;
-            self.event_tx.send(ScanEvent::ScanCompleteIdle).unwrap();
-            return;
+        if watching {
+            info!("Watching the library for changes");
+            self.watcher = Some(watcher);
         }
 
-        let path = self.to_process.pop().unwrap();
-        let metadata = self.read_metadata_for_path(&path);
+        watching
+    }
+
+    fn read_watch_events(&mut self) {
+        while let Ok(event) = self.watch_rx.try_recv() {
+            match event {
+                Ok(event) => self.handle_watch_event(event),
+                Err(e) => warn!("Error while watching the library: {:?}", e),
+            }
+        }
+    }
 
-        if let Some(metadata) = metadata {
-            task::block_on(self.update_metadata(metadata, &path)).unwrap();
+    fn handle_watch_event(&mut self, event: Event) {
+        let changes = match event.kind {
+            EventKind::Create(_) => event.paths.into_iter().map(WatchChange::Changed).collect(),
+            EventKind::Remove(_) => event.paths.into_iter().map(WatchChange::Removed).collect(),
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                let change = WatchChange::Renamed {
+                    from: event.paths[0].clone(),
+                    to: event.paths[1].clone(),
+                    directory: event.paths[1].is_dir(),
+                };
 
-            self.scanned += 1;
+                // on Linux, the two halves of the rename are reported before the rename itself
+                if self.pending_changes.last() == Some(&change) {
+                    return;
+                }
 
-            if self.scanned % 5 == 0 {
-                self.event_tx
-                    .send(ScanEvent::ScanProgress {
-                        current: self.scanned,
-                        total: self.discovered_total,
+                vec![change]
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                // if the destination is outside of the library, this is all that is reported
+                self.rename_from = event.paths.last().map(|v| (event.tracker(), v.clone()));
+                event.paths.into_iter().map(WatchChange::Removed).collect()
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                // the source of the rename is the removal reported just before this
+                let from = self
+                    .rename_from
+                    .take()
+                    .filter(|(tracker, path)| {
+                        *tracker == event.tracker()
+                            && self.pending_changes.last()
+                                == Some(&WatchChange::Removed(path.clone()))
                     })
-                    .unwrap();
+                    .map(|v| v.1);
+
+                match from {
+                    Some(from) if event.paths.len() == 1 => {
+                        self.pending_changes.pop();
+                        vec![WatchChange::Renamed {
+                            from,
+                            to: event.paths[0].clone(),
+                            directory: event.paths[0].is_dir(),
+                        }]
+                    }
+                    _ => event.paths.into_iter().map(WatchChange::Changed).collect(),
+                }
             }
-        } else {
-            warn!("Could not read metadata for file: {:?}", path);
+            // some platforms don't report which side of the rename the path is on
+            EventKind::Modify(ModifyKind::Name(_)) => event
+                .paths
+                .into_iter()
+                .map(|v| {
+                    if v.exists() {
+                        WatchChange::Changed(v)
+                    } else {
+                        WatchChange::Removed(v)
+                    }
+                })
+                .collect(),
+            EventKind::Modify(_) => event.paths.into_iter().map(WatchChange::Changed).collect(),
+            _ => Vec::new(),
+        };
+
+        if !changes.is_empty() {
+            self.pending_changes.extend(changes);
+            self.last_change = Some(Instant::now());
         }
     }
 
-    async fn delete_track(&mut self, path: &PathBuf) {
-        debug!("track deleted or moved: {:?}", path);
-        let result = sqlx::query(include_str!("../../queries/scan/delete_track.sql"))
-            .bind(path.to_str())
-            .execute(&self.pool)
-            .await;
+    /// Whether the library folders have changed, and nothing has changed since for long enough.
+    fn changes_settled(&self) -> bool {
+        self.last_change
+            .is_some_and(|v| v.elapsed() >= WATCH_DEBOUNCE)
+    }
 
-        if let Err(e) = result {
-            error!("Database error while deleting track: {:?}", e);
-        } else {
-            self.scan_record.remove(path);
+    /// Whether a full scan would pick up the path: it has to be in a library folder, and neither
+    /// it nor the folders it is in can be excluded.
+    fn is_allowed(&self, path: &Path, directory: bool) -> bool {
+        let Some((root, relative)) = find_root(&self.roots, path) else {
+            return false;
+        };
+        let rules = &self.roots[root];
+
+        let folders_allowed = relative
+            .ancestors()
+            .skip(if directory { 0 } else { 1 })
+            .filter(|v| !v.as_os_str().is_empty())
+            .all(|v| rules.allows_dir(v));
+
+        folders_allowed
+            && (directory || rules.allows_file(relative, fs::metadata(path).map_or(0, |v| v.len())))
+    }
+
+    /// Removes the tracks in the folder that a full scan wouldn't pick up, after the folder was
+    /// moved.
+    fn remove_excluded(&mut self, folder: &Path) {
+        let locations = match task::block_on(list_track_locations(&self.pool)) {
+            Ok(locations) => locations,
+            Err(e) => {
+                error!("Database error while checking moved tracks: {:?}", e);
+                return;
+            }
+        };
+
+        for path in locations {
+            if path.starts_with(folder) && !self.is_allowed(&path, false) {
+                task::block_on(self.delete_track(&path));
+            }
         }
     }
 
-    // This is done in one shot because it's required for data integrity
-    // Cleanup cannot be cancelled
-    fn cleanup(&mut self) {
-        self.scan_record
-            .clone()
-            .iter()
-            .filter(|v| !v.0.exists())
+    /// Applies the changes reported by the file watcher. Removals and renames are applied
+    /// directly, and new or modified files are scanned like in a full scan, without cleanup.
+    fn apply_changes(&mut self) {
+        let changes = std::mem::take(&mut self.pending_changes);
+        self.last_change = None;
+
+        debug!("applying {} changes to the library", changes.len());
+
+        let mut changed: IndexSet<PathBuf> = IndexSet::new();
+
+        for change in changes {
+            match change {
+                WatchChange::Changed(path) => {
+                    changed.insert(path);
+                }
+                WatchChange::Removed(path) => {
+                    changed.retain(|v| !v.starts_with(&path));
+                    task::block_on(self.delete_path(&path));
+                }
+                WatchChange::Renamed {
+                    from,
+                    to,
+                    directory,
+                } => {
+                    changed.retain(|v| !v.starts_with(&from));
+
+                    // moved to an excluded name or folder, or out of the library
+                    if !self.is_allowed(&to, directory) {
+                        task::block_on(self.delete_path(&from));
+                        continue;
+                    }
+
+                    task::block_on(self.move_path(&from, &to, directory));
+
+                    if directory {
+                        self.remove_excluded(&to);
+                    }
+
+                    // the file may also have been replaced, which is checked like any change
+                    changed.insert(to);
+                }
+            }
+        }
+
+        self.scanned = 0;
+        self.discovered_total = 0;
+
+        for path in changed {
+            if !path.exists() {
+                task::block_on(self.delete_path(&path));
+                continue;
+            }
+
+            let Some((root, relative)) = find_root(&self.roots, &path) else {
+                continue;
+            };
+            let directory = path.is_dir();
+
+            if !self.is_allowed(&path, directory) {
+                continue;
+            }
+
+            if directory {
+                self.discovered.push(Folder {
+                    path: path.clone(),
+                    root,
+                    relative: relative.to_path_buf(),
+                });
+            } else if let Some(timestamp) = self.file_is_scannable(&path) {
+                self.to_process.push((path, timestamp));
+                self.discovered_total += 1;
+            }
+        }
+
+        // scanning sends the completion event and writes the scan record once it's done
+        if self.discovered.is_empty() && self.to_process.is_empty() {
+            self.write_scan_record();
+            self.event_tx
+                .send(ScanEvent::ScanCompleteWatching)
+                .expect("could not send scan complete event");
+        } else {
+            self.scan_state = ScanState::Discovering;
+        }
+    }
+
+    // This is done in one shot because it's required for data integrity
+    // Cleanup cannot be cancelled
+    fn cleanup(&mut self) {
+        match task::block_on(take_rescan_tracks(&self.pool)) {
+            Ok(tracks) => {
+                for track in tracks {
+                    self.scan_record.remove(&track);
+                }
+            }
+            Err(e) => error!("Database error while retrieving tracks to rescan: {:?}", e),
+        }
+
+        self.scan_record
+            .clone()
+            .iter()
+            .filter(|v| !v.0.exists())
             .map(|v| v.0)
             .for_each(|v| {
                 task::block_on(self.delete_track(v));
@@ -664,3 +992,940 @@ impl ScanThread {
         self.scan_state = ScanState::Discovering;
     }
 }
+
+/// Identifies an album while scanning, following the rules in `get_album_id.sql`: albums are
+/// identified by their MusicBrainz release ID, or by their artist, title and release date.
+fn album_key(
+    title: &str,
+    artist: Option<&str>,
+    date: Option<DateTime<Utc>>,
+    mbid: Option<&str>,
+) -> String {
+    match mbid {
+        Some(mbid) => format!("mbid:{}", mbid),
+        None => format!(
+            "{}\0{}\0{}",
+            artist.unwrap_or_default(),
+            title,
+            date.map(|v| v.to_rfc3339()).unwrap_or_default()
+        ),
+    }
+}
+
+/// The key of the album the track belongs to, if it has one.
+fn metadata_album_key(metadata: &Metadata) -> Option<String> {
+    Some(album_key(
+        metadata.album.as_ref()?,
+        metadata
+            .album_artist
+            .as_deref()
+            .or(metadata.artist.as_deref()),
+        metadata.date,
+        metadata.mbid_album.as_deref(),
+    ))
+}
+
+/// The title, album artist, release date and release ID of an album.
+type AlbumIdentity = (
+    String,
+    Option<String>,
+    Option<DateTime<Utc>>,
+    Option<String>,
+);
+
+async fn list_album_keys(pool: &SqlitePool) -> Result<HashSet<String>, sqlx::Error> {
+    let albums: Vec<AlbumIdentity> =
+        sqlx::query_as(include_str!("../../queries/scan/get_album_keys.sql"))
+            .fetch_all(pool)
+            .await?;
+
+    let mut keys = HashSet::new();
+
+    for (title, artist, date, mbid) in albums {
+        // tracks without a release ID are also added to albums with one
+        keys.insert(album_key(&title, artist.as_deref(), date, None));
+
+        if mbid.is_some() {
+            keys.insert(album_key(&title, artist.as_deref(), date, mbid.as_deref()));
+        }
+    }
+
+    Ok(keys)
+}
+
+/// Moves the tracks at `from` to `to` in one transaction. The tracks that were at `to` are removed
+/// first: a file that is moved over another one replaces it, and a folder can only be moved to a
+/// path that is empty, so they no longer exist.
+async fn move_tracks(
+    pool: &SqlitePool,
+    from: &Path,
+    to: &Path,
+    directory: bool,
+) -> Result<(), sqlx::Error> {
+    let (delete, update, source, destination) = if directory {
+        let separator = |v: &Path| format!("{}{}", v.to_string_lossy(), MAIN_SEPARATOR_STR);
+
+        (
+            include_str!("../../queries/scan/delete_directory.sql"),
+            include_str!("../../queries/scan/move_directory.sql"),
+            Some(separator(from)),
+            Some(separator(to)),
+        )
+    } else {
+        (
+            include_str!("../../queries/scan/delete_track.sql"),
+            include_str!("../../queries/scan/move_track.sql"),
+            from.to_str().map(str::to_string),
+            to.to_str().map(str::to_string),
+        )
+    };
+
+    let mut tx = pool.begin().await?;
+
+    let replaced = sqlx::query(delete)
+        .bind(&destination)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    if replaced > 0 {
+        info!(
+            "{} tracks were replaced by moving {:?} to {:?}",
+            replaced, from, to
+        );
+    }
+
+    sqlx::query(update)
+        .bind(&source)
+        .bind(&destination)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await
+}
+
+/// Lists the location of every track in the database.
+async fn list_track_locations(pool: &SqlitePool) -> Result<Vec<PathBuf>, sqlx::Error> {
+    let tracks: Vec<(String,)> =
+        sqlx::query_as(include_str!("../../queries/scan/get_track_locations.sql"))
+            .fetch_all(pool)
+            .await?;
+
+    Ok(tracks.into_iter().map(|v| PathBuf::from(v.0)).collect())
+}
+
+async fn take_rescan_tracks(pool: &SqlitePool) -> Result<Vec<PathBuf>, sqlx::Error> {
+    let tracks: Vec<(String,)> =
+        sqlx::query_as(include_str!("../../queries/scan/get_rescan_tracks.sql"))
+            .fetch_all(pool)
+            .await?;
+
+    sqlx::query(include_str!("../../queries/scan/delete_rescan_tracks.sql"))
+        .execute(pool)
+        .await?;
+
+    Ok(tracks.into_iter().map(|v| PathBuf::from(v.0)).collect())
+}
+
+async fn insert_artist(conn: &mut SqliteConnection, metadata: &Metadata) -> Option<i64> {
+    let artist = metadata
+        .album_artist
+        .as_ref()
+        .or(metadata.artist.as_ref())?;
+
+    insert_artist_named(
+        conn,
+        artist,
+        metadata.artist_sort.as_ref().unwrap_or(artist),
+    )
+    .await
+}
+
+async fn insert_artist_named(
+    conn: &mut SqliteConnection,
+    artist: &str,
+    artist_sort: &str,
+) -> Option<i64> {
+    let result: Result<(i64,), sqlx::Error> =
+        sqlx::query_as(include_str!("../../queries/scan/create_artist.sql"))
+            .bind(artist)
+            .bind(artist_sort)
+            .fetch_one(&mut *conn)
+            .await;
+
+    match result {
+        Ok(v) => Some(v.0),
+        Err(sqlx::Error::RowNotFound) => {
+            let result: Result<(i64,), sqlx::Error> =
+                sqlx::query_as(include_str!("../../queries/scan/get_artist_id.sql"))
+                    .bind(artist)
+                    .fetch_one(&mut *conn)
+                    .await;
+
+            match result {
+                Ok(v) => Some(v.0),
+                Err(e) => {
+                    error!("Database error while retriving artist: {:?}", e);
+                    None
+                }
+            }
+        }
+        Err(e) => {
+            error!("Database error while creating artist: {:?}", e);
+            None
+        }
+    }
+}
+
+/// Tracks marked as part of a compilation without a release type are treated as compilations.
+fn release_type(metadata: &Metadata) -> Option<&str> {
+    metadata
+        .release_type
+        .as_deref()
+        .or(metadata.compilation.then_some("compilation"))
+}
+
+/// Updates the release type of an album that is already in the library, which could have been
+/// added before release types were read.
+async fn set_release_type(conn: &mut SqliteConnection, album_id: i64, metadata: &Metadata) {
+    let Some(release_type) = release_type(metadata) else {
+        return;
+    };
+
+    let result = sqlx::query(include_str!(
+        "../../queries/scan/set_album_release_type.sql"
+    ))
+    .bind(album_id)
+    .bind(release_type)
+    .execute(&mut *conn)
+    .await;
+
+    if let Err(e) = result {
+        error!("Database error while updating album: {:?}", e);
+    }
+}
+
+async fn insert_album(
+    conn: &mut SqliteConnection,
+    metadata: &Metadata,
+    artist_id: Option<i64>,
+    art: Option<&AlbumArt>,
+) -> Option<i64> {
+    let Some(album) = &metadata.album else {
+        return None;
+    };
+    let result: Result<(i64, Option<String>), sqlx::Error> =
+        sqlx::query_as(include_str!("../../queries/scan/get_album_id.sql"))
+            .bind(album)
+            .bind(artist_id)
+            .bind(metadata.date)
+            .bind(&metadata.mbid_album)
+            .fetch_one(&mut *conn)
+            .await;
+
+    match result {
+        Ok((id, mbid)) => {
+            if mbid.is_none() && metadata.mbid_album.is_some() {
+                // the album was added from tracks without a release ID
+                let result = sqlx::query(include_str!("../../queries/scan/set_album_mbid.sql"))
+                    .bind(id)
+                    .bind(&metadata.mbid_album)
+                    .execute(&mut *conn)
+                    .await;
+
+                if let Err(e) = result {
+                    error!("Database error while updating album: {:?}", e);
+                }
+            }
+
+            set_release_type(conn, id, metadata).await;
+            Some(id)
+        }
+        Err(sqlx::Error::RowNotFound) => {
+            let result: Result<(i64,), sqlx::Error> =
+                sqlx::query_as(include_str!("../../queries/scan/create_album.sql"))
+                    .bind(album)
+                    .bind(metadata.sort_album.as_ref().unwrap_or(album))
+                    .bind(artist_id)
+                    .bind(art.and_then(|v| v.full.as_deref()))
+                    .bind(art.and_then(|v| v.thumbnail.as_deref()))
+                    .bind(metadata.date)
+                    .bind(&metadata.label)
+                    .bind(&metadata.catalog)
+                    .bind(&metadata.isrc)
+                    .bind(&metadata.mbid_album)
+                    .bind(release_type(metadata))
+                    .fetch_one(&mut *conn)
+                    .await;
+
+            match result {
+                Ok(v) => Some(v.0),
+                Err(e) => {
+                    error!("Database error while creating album: {:?}", e);
+                    None
+                }
+            }
+        }
+        Err(e) => {
+            error!("Database error while retriving album: {:?}", e);
+            None
+        }
+    }
+}
+
+async fn insert_track(
+    conn: &mut SqliteConnection,
+    file: &ScannedFile,
+    album_id: Option<i64>,
+) -> Option<i64> {
+    let metadata = &file.metadata;
+    let path = file.path.as_path();
+
+    // literally i do not know how this could possibly fail
+    let name = metadata
+        .name
+        .clone()
+        .or_else(|| {
+            path.file_name()
+                .and_then(|x| x.to_str())
+                .map(|x| x.to_string())
+        })
+        .expect("weird file recieved in update metadata");
+
+    let result: Result<(i64,), sqlx::Error> =
+        sqlx::query_as(include_str!("../../queries/scan/create_track.sql"))
+            .bind(&name)
+            .bind(&name)
+            .bind(album_id)
+            .bind(metadata.track_current.map(|x| x as i32))
+            .bind(metadata.disc_current.map(|x| x as i32))
+            .bind(file.length as i32)
+            .bind(path.to_str())
+            .bind(&metadata.genre)
+            .bind(&metadata.artist)
+            .bind(file.bitrate.map(|x| x as i64))
+            .bind(
+                path.extension()
+                    .and_then(|x| x.to_str())
+                    .map(|x| x.to_lowercase()),
+            )
+            .bind(&metadata.lyrics)
+            .bind(&metadata.comment)
+            .fetch_one(&mut *conn)
+            .await;
+
+    match result {
+        Ok(v) => Some(v.0),
+        Err(sqlx::Error::RowNotFound) => None,
+        Err(e) => {
+            error!("Database error while creating track: {:?}", e);
+            None
+        }
+    }
+}
+
+/// Writes the artists credited on a track, and returns the artists and roles that were written.
+async fn write_credits(
+    conn: &mut SqliteConnection,
+    query: &'static str,
+    id: i64,
+    credits: &[Credit],
+) -> Vec<(i64, ArtistRole)> {
+    let mut written = Vec::with_capacity(credits.len());
+
+    for (position, credit) in credits.iter().enumerate() {
+        let Some(artist_id) = insert_artist_named(conn, &credit.name, &credit.name).await else {
+            continue;
+        };
+
+        let result = sqlx::query(query)
+            .bind(id)
+            .bind(artist_id)
+            .bind(credit.role)
+            .bind(position as i64)
+            .execute(&mut *conn)
+            .await;
+
+        match result {
+            Ok(_) => written.push((artist_id, credit.role)),
+            Err(e) => error!("Database error while creating artist credit: {:?}", e),
+        }
+    }
+
+    written
+}
+
+/// Pushes a condition that is true for the credits that aren't in `kept`.
+fn push_stale_credits(builder: &mut QueryBuilder<'_, Sqlite>, kept: &[(i64, ArtistRole)]) {
+    if kept.is_empty() {
+        return;
+    }
+
+    builder.push(" AND (artist_id, role) NOT IN (VALUES ");
+
+    for (index, (artist_id, role)) in kept.iter().enumerate() {
+        if index > 0 {
+            builder.push(", ");
+        }
+
+        builder
+            .push("(")
+            .push_bind(*artist_id)
+            .push(", ")
+            .push_bind(*role)
+            .push(")");
+    }
+
+    builder.push(")");
+}
+
+/// Replaces the artists credited on a track. Credits that are still there are updated in place,
+/// and the ones that aren't are removed afterwards, so that artists who are still credited are
+/// never deleted along with their last credit.
+async fn insert_track_credits(conn: &mut SqliteConnection, track_id: i64, credits: &[Credit]) {
+    let query = include_str!("../../queries/scan/create_track_artist.sql");
+    let kept = write_credits(conn, query, track_id, credits).await;
+
+    let mut builder = QueryBuilder::new("DELETE FROM track_artist WHERE track_id = ");
+    builder.push_bind(track_id);
+    push_stale_credits(&mut builder, &kept);
+
+    if let Err(e) = builder.build().execute(&mut *conn).await {
+        error!("Database error while removing track artists: {:?}", e);
+    }
+}
+
+/// Replaces the genres of a track.
+async fn insert_track_genres(conn: &mut SqliteConnection, track_id: i64, genres: &[String]) {
+    // removed first, since removing the last track of a genre deletes the genre
+    let result = sqlx::query(include_str!("../../queries/scan/delete_track_genres.sql"))
+        .bind(track_id)
+        .execute(&mut *conn)
+        .await;
+
+    if let Err(e) = result {
+        error!("Database error while removing track genres: {:?}", e);
+        return;
+    }
+
+    for (position, genre) in genres.iter().enumerate() {
+        let result: Result<(i64,), sqlx::Error> =
+            sqlx::query_as(include_str!("../../queries/scan/create_genre.sql"))
+                .bind(genre)
+                .fetch_one(&mut *conn)
+                .await;
+
+        let genre_id = match result {
+            Ok(v) => v.0,
+            Err(sqlx::Error::RowNotFound) => {
+                let result: Result<(i64,), sqlx::Error> =
+                    sqlx::query_as(include_str!("../../queries/scan/get_genre_id.sql"))
+                        .bind(genre)
+                        .fetch_one(&mut *conn)
+                        .await;
+
+                match result {
+                    Ok(v) => v.0,
+                    Err(e) => {
+                        error!("Database error while retriving genre: {:?}", e);
+                        continue;
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Database error while creating genre: {:?}", e);
+                continue;
+            }
+        };
+
+        let result = sqlx::query(include_str!("../../queries/scan/create_track_genre.sql"))
+            .bind(track_id)
+            .bind(genre_id)
+            .bind(position as i64)
+            .execute(&mut *conn)
+            .await;
+
+        if let Err(e) = result {
+            error!("Database error while creating track genre: {:?}", e);
+        }
+    }
+}
+
+/// Replaces the artists credited on an album. Every track of an album has the same album artists,
+/// so the credits of the last track written are kept.
+async fn insert_album_credits(conn: &mut SqliteConnection, album_id: i64, credits: &[Credit]) {
+    let query = include_str!("../../queries/scan/create_album_artist.sql");
+    let kept = write_credits(conn, query, album_id, credits).await;
+
+    let mut builder = QueryBuilder::new("DELETE FROM album_artist WHERE album_id = ");
+    builder.push_bind(album_id);
+    push_stale_credits(&mut builder, &kept);
+
+    if let Err(e) = builder.build().execute(&mut *conn).await {
+        error!("Database error while removing album artists: {:?}", e);
+    }
+}
+
+/// Writes the files to the database in one transaction, returning whether each file was written.
+/// Errors for individual files are logged, and don't prevent the other files from being written.
+async fn write_files(pool: &SqlitePool, files: &[ScannedFile]) -> Result<Vec<bool>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut written = Vec::with_capacity(files.len());
+
+    for file in files {
+        debug!(
+            "Adding/updating record for {:?} - {:?}",
+            file.metadata.artist, file.metadata.name
+        );
+
+        let artist_id = insert_artist(&mut tx, &file.metadata).await;
+        let album_id = insert_album(&mut tx, &file.metadata, artist_id, file.art.as_ref()).await;
+        let track_id = insert_track(&mut tx, file, album_id).await;
+
+        if let Some(album_id) = album_id {
+            insert_album_credits(&mut tx, album_id, &album_credits(&file.metadata)).await;
+        }
+
+        if let Some(track_id) = track_id {
+            insert_track_credits(&mut tx, track_id, &track_credits(&file.metadata)).await;
+            insert_track_genres(&mut tx, track_id, &track_genres(&file.metadata)).await;
+        }
+
+        written.push(track_id.is_some());
+    }
+
+    tx.commit().await?;
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use notify::event::CreateKind;
+
+    use super::*;
+    use crate::{library::db::create_memory_pool, settings::scan::LibraryRoot};
+
+    fn file(path: &str, title: &str, album: &str, album_artist: &str) -> ScannedFile {
+        ScannedFile {
+            path: PathBuf::from(path),
+            timestamp: 0,
+            metadata: Metadata {
+                name: Some(title.to_string()),
+                artist: Some(album_artist.to_string()),
+                artists: vec![album_artist.to_string()],
+                album_artist: Some(album_artist.to_string()),
+                album_artists: vec![album_artist.to_string()],
+                album: Some(album.to_string()),
+                ..Default::default()
+            },
+            length: 100,
+            bitrate: None,
+            art: None,
+        }
+    }
+
+    async fn album_id(pool: &SqlitePool, path: &str) -> Option<i64> {
+        sqlx::query_scalar("SELECT album_id FROM track WHERE location = $1")
+            .bind(path)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn album_count(pool: &SqlitePool) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM album")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[async_std::test]
+    async fn albums_with_the_same_title_are_kept_apart_by_artist() {
+        let pool = create_memory_pool().await;
+        let files = [
+            file("/music/A/Hits/01.flac", "One", "Greatest Hits", "A"),
+            file("/music/B/Hits/01.flac", "One", "Greatest Hits", "B"),
+            file("/music/B/Hits/02.flac", "Two", "Greatest Hits", "B"),
+        ];
+
+        assert_eq!(
+            write_files(&pool, &files).await.unwrap(),
+            [true, true, true]
+        );
+
+        let a = album_id(&pool, "/music/A/Hits/01.flac").await;
+        let b = album_id(&pool, "/music/B/Hits/01.flac").await;
+        assert!(a.is_some() && b.is_some());
+        assert_ne!(a, b);
+        assert_eq!(album_id(&pool, "/music/B/Hits/02.flac").await, b);
+        assert_eq!(album_count(&pool).await, 2);
+    }
+
+    #[async_std::test]
+    async fn retagged_tracks_keep_their_album() {
+        let pool = create_memory_pool().await;
+        let path = "/music/A/Album/01.flac";
+
+        write_files(&pool, &[file(path, "One", "Album", "A")])
+            .await
+            .unwrap();
+        let id = album_id(&pool, path).await;
+
+        let mut retagged = file(path, "One (Remastered)", "Album", "A");
+        retagged.metadata.genre = Some("Rock".to_string());
+        retagged.metadata.mbid_album = Some("5b1e2d3c-0000-4000-8000-000000000001".to_string());
+        write_files(&pool, &[retagged]).await.unwrap();
+
+        assert_eq!(album_id(&pool, path).await, id);
+
+        // tracks without the release ID still belong to the album
+        write_files(
+            &pool,
+            &[file("/music/A/Album/02.flac", "Two", "Album", "A")],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(album_id(&pool, "/music/A/Album/02.flac").await, id);
+        assert_eq!(album_count(&pool).await, 1);
+    }
+
+    async fn credits(pool: &SqlitePool, query: &str, id: i64) -> Vec<(String, String)> {
+        sqlx::query_as(query)
+            .bind(id)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn track_credits(pool: &SqlitePool, path: &str) -> Vec<(String, String)> {
+        let query = "SELECT artist.name, track_artist.role
+            FROM track_artist
+            JOIN artist ON artist.id = track_artist.artist_id
+            JOIN track ON track.id = track_artist.track_id
+            WHERE track.location = $1
+            ORDER BY track_artist.position";
+
+        sqlx::query_as(query)
+            .bind(path)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    fn credit(name: &str, role: &str) -> (String, String) {
+        (name.to_string(), role.to_string())
+    }
+
+    #[async_std::test]
+    async fn retagged_tracks_replace_their_credits() {
+        let pool = create_memory_pool().await;
+        let path = "/music/A/Album/01.flac";
+
+        let mut tagged = file(path, "One (feat. B)", "Album", "A");
+        tagged.metadata.composers = vec!["C".to_string()];
+        write_files(&pool, &[tagged]).await.unwrap();
+
+        assert_eq!(
+            track_credits(&pool, path).await,
+            [
+                credit("A", "primary"),
+                credit("B", "featured"),
+                credit("C", "composer")
+            ]
+        );
+
+        let mut retagged = file(path, "One", "Album", "A");
+        retagged.metadata.composers = vec!["D".to_string(), "C".to_string()];
+        write_files(&pool, &[retagged]).await.unwrap();
+
+        assert_eq!(
+            track_credits(&pool, path).await,
+            [
+                credit("A", "primary"),
+                credit("D", "composer"),
+                credit("C", "composer")
+            ]
+        );
+
+        // artists that aren't credited anywhere are removed
+        let artists: Vec<String> = sqlx::query_scalar("SELECT name FROM artist ORDER BY name")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(artists, ["A", "C", "D"]);
+    }
+
+    #[async_std::test]
+    async fn stale_album_credits_are_removed() {
+        let pool = create_memory_pool().await;
+        let query = "SELECT artist.name, album_artist.role
+            FROM album_artist
+            JOIN artist ON artist.id = album_artist.artist_id
+            WHERE album_artist.album_id = $1
+            ORDER BY album_artist.position";
+
+        let mut first = file("/music/A/Album/01.flac", "One", "Album", "A");
+        first.metadata.album_artists = vec!["A".to_string(), "B".to_string()];
+        let mut second = file("/music/A/Album/02.flac", "Two", "Album", "A");
+        second.metadata.album_artists = vec!["A".to_string(), "B".to_string()];
+        write_files(&pool, &[first, second]).await.unwrap();
+
+        let id = album_id(&pool, "/music/A/Album/01.flac").await.unwrap();
+        assert_eq!(
+            credits(&pool, query, id).await,
+            [credit("A", "primary"), credit("B", "primary")]
+        );
+
+        let retagged = [
+            file("/music/A/Album/01.flac", "One", "Album", "A"),
+            file("/music/A/Album/02.flac", "Two", "Album", "A"),
+        ];
+        write_files(&pool, &retagged).await.unwrap();
+
+        assert_eq!(album_id(&pool, "/music/A/Album/01.flac").await, Some(id));
+        assert_eq!(credits(&pool, query, id).await, [credit("A", "primary")]);
+    }
+
+    #[async_std::test]
+    async fn albums_with_the_same_identity_are_rejected() {
+        let pool = create_memory_pool().await;
+        let insert = "INSERT INTO album (title, title_sortable) VALUES ('Album', 'Album')";
+
+        sqlx::query(insert).execute(&pool).await.unwrap();
+        assert!(sqlx::query(insert).execute(&pool).await.is_err());
+    }
+
+    fn genres(genre: Option<&str>, tags: &[&str]) -> Vec<String> {
+        track_genres(&Metadata {
+            genre: genre.map(str::to_string),
+            genres: tags.iter().map(|v| v.to_string()).collect(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn genre_tags_are_split_on_separators() {
+        assert_eq!(
+            genres(Some("Rock; Pop/Jazz, Blues|Folk"), &[]),
+            ["Rock", "Pop", "Jazz", "Blues", "Folk"]
+        );
+        assert_eq!(genres(Some(" Rock ;; / Pop "), &[]), ["Rock", "Pop"]);
+        assert!(genres(Some(" ; "), &[]).is_empty());
+        assert!(genres(None, &[]).is_empty());
+    }
+
+    #[test]
+    fn genres_differing_in_case_are_kept_once() {
+        assert_eq!(
+            genres(Some("Hip-Hop; hip-hop; HIP-HOP; Rap"), &[]),
+            ["Hip-Hop", "Rap"]
+        );
+    }
+
+    #[test]
+    fn genre_lists_are_preferred_to_the_single_tag() {
+        assert_eq!(
+            genres(Some("Rock; Pop"), &["Jazz", "jazz; Blues"]),
+            ["Jazz", "Blues"]
+        );
+    }
+
+    async fn track_genre_names(pool: &SqlitePool, path: &str) -> Vec<String> {
+        sqlx::query_scalar(
+            "SELECT genre.name FROM track_genre
+                JOIN genre ON genre.id = track_genre.genre_id
+                JOIN track ON track.id = track_genre.track_id
+                WHERE track.location = $1
+                ORDER BY track_genre.position",
+        )
+        .bind(path)
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[async_std::test]
+    async fn tracks_share_genres_differing_in_case() {
+        let pool = create_memory_pool().await;
+        let mut first = file("/music/A/01.flac", "One", "Album", "A");
+        first.metadata.genre = Some("Rock; Pop".to_string());
+        let mut second = file("/music/A/02.flac", "Two", "Album", "A");
+        second.metadata.genre = Some("rock".to_string());
+
+        write_files(&pool, &[first, second]).await.unwrap();
+
+        assert_eq!(
+            track_genre_names(&pool, "/music/A/01.flac").await,
+            ["Rock", "Pop"]
+        );
+        assert_eq!(track_genre_names(&pool, "/music/A/02.flac").await, ["Rock"]);
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM genre")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+
+    /// A scanner for a new, empty library folder in the temporary directory.
+    fn watched_library(name: &str, exclude: &[&str]) -> (ScanThread, PathBuf) {
+        let path =
+            std::env::temp_dir().join(format!("muzak-watch-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        let path = path.canonicalize().unwrap();
+
+        let mut root = LibraryRoot::new(&path);
+        root.exclude = exclude.iter().map(|v| v.to_string()).collect();
+
+        let (events_tx, _) = mpsc::channel();
+        let (_, commands_rx) = mpsc::channel();
+        let pool = task::block_on(create_memory_pool());
+        let settings = ScanSettings { roots: vec![root] };
+
+        (
+            ScanThread::new(pool, settings, events_tx, commands_rx),
+            path,
+        )
+    }
+
+    fn track_id(thread: &ScanThread, path: &Path) -> Option<i64> {
+        task::block_on(
+            sqlx::query_scalar("SELECT id FROM track WHERE location = $1")
+                .bind(path.to_str())
+                .fetch_optional(&thread.pool),
+        )
+        .unwrap()
+    }
+
+    /// Creates an empty file, and adds a track for it to the library.
+    fn add_track(thread: &ScanThread, path: &Path) -> i64 {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, b"").unwrap();
+
+        let file = file(path.to_str().unwrap(), "One", "Album", "A");
+        task::block_on(write_files(&thread.pool, &[file])).unwrap();
+
+        track_id(thread, path).unwrap()
+    }
+
+    /// Renames the path, and reports the rename like the watcher does.
+    fn rename(thread: &mut ScanThread, from: &Path, to: &Path) {
+        fs::create_dir_all(to.parent().unwrap()).unwrap();
+        fs::rename(from, to).unwrap();
+
+        thread.handle_watch_event(
+            Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+                .add_path(from.to_path_buf())
+                .add_path(to.to_path_buf()),
+        );
+    }
+
+    #[test]
+    fn renamed_tracks_keep_their_ids() {
+        let (mut thread, library) = watched_library("rename", &[]);
+        let one = add_track(&thread, &library.join("A/01.flac"));
+        let two = add_track(&thread, &library.join("A/02.flac"));
+
+        rename(
+            &mut thread,
+            &library.join("A/01.flac"),
+            &library.join("B/01.flac"),
+        );
+        thread.apply_changes();
+
+        assert_eq!(track_id(&thread, &library.join("A/01.flac")), None);
+        assert_eq!(track_id(&thread, &library.join("B/01.flac")), Some(one));
+
+        rename(&mut thread, &library.join("A"), &library.join("C"));
+        thread.apply_changes();
+
+        assert_eq!(track_id(&thread, &library.join("A/02.flac")), None);
+        assert_eq!(track_id(&thread, &library.join("C/02.flac")), Some(two));
+
+        // a file moved over another one replaces it
+        rename(
+            &mut thread,
+            &library.join("B/01.flac"),
+            &library.join("C/02.flac"),
+        );
+        thread.apply_changes();
+
+        assert_eq!(track_id(&thread, &library.join("B/01.flac")), None);
+        assert_eq!(track_id(&thread, &library.join("C/02.flac")), Some(one));
+
+        fs::remove_dir_all(library).unwrap();
+    }
+
+    #[test]
+    fn tracks_moved_to_excluded_paths_are_removed() {
+        let (mut thread, library) = watched_library("exclude", &["Samples/**", "B/**/*.flac"]);
+        add_track(&thread, &library.join("01.flac"));
+        add_track(&thread, &library.join("A/02.flac"));
+        let three = add_track(&thread, &library.join("C/03.flac"));
+
+        rename(
+            &mut thread,
+            &library.join("01.flac"),
+            &library.join("Samples/01.flac"),
+        );
+        // the folder itself is allowed, but not the files in it
+        rename(&mut thread, &library.join("A"), &library.join("B/A"));
+        rename(&mut thread, &library.join("C"), &library.join("D"));
+        thread.apply_changes();
+
+        let tracks: i64 = task::block_on(
+            sqlx::query_scalar("SELECT COUNT(*) FROM track").fetch_one(&thread.pool),
+        )
+        .unwrap();
+
+        assert_eq!(tracks, 1);
+        assert_eq!(track_id(&thread, &library.join("D/03.flac")), Some(three));
+
+        fs::remove_dir_all(library).unwrap();
+    }
+
+    #[test]
+    fn watched_changes_are_applied_together() {
+        let (mut thread, library) = watched_library("debounce", &[]);
+        let one = add_track(&thread, &library.join("01.flac"));
+
+        for name in ["02.flac", "03.flac"] {
+            fs::write(library.join(name), b"").unwrap();
+            thread.handle_watch_event(
+                Event::new(EventKind::Create(CreateKind::File)).add_path(library.join(name)),
+            );
+        }
+
+        // on Linux, the two halves of a rename are reported separately
+        fs::rename(library.join("01.flac"), library.join("04.flac")).unwrap();
+        thread.handle_watch_event(
+            Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::From)))
+                .add_path(library.join("01.flac"))
+                .set_tracker(1),
+        );
+        thread.handle_watch_event(
+            Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::To)))
+                .add_path(library.join("04.flac"))
+                .set_tracker(1),
+        );
+
+        assert_eq!(thread.pending_changes.len(), 3);
+        assert!(!thread.changes_settled());
+
+        thread.last_change = Some(Instant::now() - WATCH_DEBOUNCE);
+        assert!(thread.changes_settled());
+        thread.apply_changes();
+
+        assert!(thread.pending_changes.is_empty());
+        assert!(!thread.changes_settled());
+        assert_eq!(track_id(&thread, &library.join("04.flac")), Some(one));
+        // the new files are scanned together, along with the renamed one in case it changed
+        assert_eq!(thread.to_process.len(), 3);
+        assert_eq!(thread.scan_state, ScanState::Discovering);
+
+        fs::remove_dir_all(library).unwrap();
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{library::types::ArtistRole, media::metadata::Metadata};
+
+/// The ways a featured artist is introduced in an artist credit or track title.
+const FEATURED_MARKERS: [&str; 10] = [
+    "(feat. ",
+    "(ft. ",
+    "(featuring ",
+    "[feat. ",
+    "[ft. ",
+    "[featuring ",
+    " feat. ",
+    " ft. ",
+    " featuring ",
+    " feat ",
+];
+
+/// Splits an artist credit like "Artist feat. Other Artist" into the primary and featured parts.
+fn split_featured(credit: &str) -> (&str, Option<&str>) {
+    // ASCII lowercasing keeps the byte offsets the same as in the original string
+    let lower = credit.to_ascii_lowercase();
+
+    let found = FEATURED_MARKERS
+        .iter()
+        .filter_map(|marker| lower.find(marker).map(|position| (position, *marker)))
+        .min_by_key(|(position, _)| *position);
+
+    let Some((position, marker)) = found else {
+        return (credit.trim(), None);
+    };
+
+    let rest = &credit[position + marker.len()..];
+    // bracketed credits end at the closing bracket, for titles like "Title (feat. Artist) [Live]"
+    let featured = match marker.chars().next() {
+        Some('(') => rest.split(')').next().unwrap_or_default(),
+        Some('[') => rest.split(']').next().unwrap_or_default(),
+        _ => rest,
+    }
+    .trim();
+
+    (
+        credit[..position].trim(),
+        (!featured.is_empty()).then_some(featured),
+    )
+}
+
+/// An artist credited on a track or album. Credits are listed in the order the tags list them.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Credit {
+    pub name: String,
+    pub role: ArtistRole,
+}
+
+fn push_credit(credits: &mut Vec<Credit>, name: &str, role: ArtistRole) {
+    let name = name.trim();
+
+    if name.is_empty() || credits.iter().any(|v| v.role == role && v.name == name) {
+        return;
+    }
+
+    credits.push(Credit {
+        name: name.to_string(),
+        role,
+    });
+}
+
+/// Works out the primary and featured artists from a list of artists and the displayed credit.
+/// Artists named after a featuring marker in the credit are featured. If there is only one artist,
+/// it is the whole credit, and is split in two.
+fn push_artists(credits: &mut Vec<Credit>, artists: &[String], display: Option<&str>) {
+    let featured = display.and_then(|v| split_featured(v).1);
+
+    match artists {
+        [artist] if display.is_none_or(|v| v == artist.as_str()) => {
+            let (primary, featured) = split_featured(artist);
+            push_credit(credits, primary, ArtistRole::Primary);
+
+            if let Some(featured) = featured {
+                push_credit(credits, featured, ArtistRole::Featured);
+            }
+        }
+        artists => {
+            for artist in artists {
+                let is_featured = featured.is_some_and(|v| v.contains(artist.as_str()));
+                let role = if is_featured {
+                    ArtistRole::Featured
+                } else {
+                    ArtistRole::Primary
+                };
+
+                push_credit(credits, artist, role);
+            }
+        }
+    }
+}
+
+/// The artists credited on a track, from its artist, composer, remixer and performer tags and
+/// any featured artists in its title.
+pub fn track_credits(metadata: &Metadata) -> Vec<Credit> {
+    let mut credits = Vec::new();
+
+    push_artists(&mut credits, &metadata.artists, metadata.artist.as_deref());
+
+    if let Some(featured) = metadata.name.as_deref().and_then(|v| split_featured(v).1) {
+        if !credits.iter().any(|v| v.name == featured) {
+            push_credit(&mut credits, featured, ArtistRole::Featured);
+        }
+    }
+
+    for composer in &metadata.composers {
+        push_credit(&mut credits, composer, ArtistRole::Composer);
+    }
+
+    for remixer in &metadata.remixers {
+        push_credit(&mut credits, remixer, ArtistRole::Remixer);
+    }
+
+    for performer in &metadata.performers {
+        push_credit(&mut credits, performer, ArtistRole::Performer);
+    }
+
+    credits
+}
+
+/// The artists credited on the album a track belongs to. Tracks without album artists are
+/// credited to their own primary and featured artists, like the album's artist in `insert_album`.
+pub fn album_credits(metadata: &Metadata) -> Vec<Credit> {
+    let mut credits = Vec::new();
+
+    if metadata.album_artists.is_empty() {
+        push_artists(&mut credits, &metadata.artists, metadata.artist.as_deref());
+    } else {
+        push_artists(
+            &mut credits,
+            &metadata.album_artists,
+            metadata.album_artist.as_deref(),
+        );
+    }
+
+    credits
+}
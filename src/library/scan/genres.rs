@@ -0,0 +1,26 @@
+use crate::media::metadata::Metadata;
+
+/// The characters that separate genres written in a single tag, like "Rock; Pop" or "Rock/Pop".
+const GENRE_SEPARATORS: [char; 4] = [';', '/', ',', '|'];
+
+/// The genres of a track, from every genre tag, split where a tag lists more than one genre.
+/// Genres that only differ in case are kept once, as they are first written.
+pub fn track_genres(metadata: &Metadata) -> Vec<String> {
+    let tags = if metadata.genres.is_empty() {
+        metadata.genre.as_slice()
+    } else {
+        metadata.genres.as_slice()
+    };
+
+    let mut genres: Vec<String> = Vec::new();
+
+    for genre in tags.iter().flat_map(|v| v.split(GENRE_SEPARATORS)) {
+        let genre = genre.trim();
+
+        if !genre.is_empty() && !genres.iter().any(|v| v.eq_ignore_ascii_case(genre)) {
+            genres.push(genre.to_string());
+        }
+    }
+
+    genres
+}
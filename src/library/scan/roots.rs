@@ -0,0 +1,118 @@
+use std::path::{Component, Path, PathBuf};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use tracing::{error, warn};
+
+use crate::settings::scan::LibraryRoot;
+
+/// The compiled form of a library root's settings.
+pub struct RootRules {
+    /// The canonical path of the root, which scanned paths are compared with.
+    pub path: PathBuf,
+    exclude: GlobSet,
+    min_file_size: u64,
+    pub follow_symlinks: bool,
+    max_depth: Option<usize>,
+    skip_hidden: bool,
+}
+
+impl RootRules {
+    pub fn new(root: &LibraryRoot) -> Self {
+        let mut builder = GlobSetBuilder::new();
+
+        for pattern in &root.exclude {
+            match Glob::new(pattern) {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(e) => warn!("Ignoring invalid scan exclusion {:?}: {}", pattern, e),
+            }
+        }
+
+        RootRules {
+            path: root
+                .path
+                .canonicalize()
+                .unwrap_or_else(|_| root.path.clone()),
+            exclude: builder.build().unwrap_or_else(|e| {
+                error!(
+                    "Could not build the scan exclusions for {:?}: {}",
+                    root.path, e
+                );
+                GlobSet::empty()
+            }),
+            min_file_size: root.min_file_size,
+            follow_symlinks: root.follow_symlinks,
+            max_depth: root.max_depth,
+            skip_hidden: root.skip_hidden,
+        }
+    }
+
+    fn excludes(&self, relative: &Path) -> bool {
+        (self.skip_hidden && relative.components().any(is_hidden))
+            || self.exclude.is_match(relative)
+    }
+
+    /// Whether the folder, relative to the root, should be looked in.
+    pub fn allows_dir(&self, relative: &Path) -> bool {
+        let depth = relative.components().count();
+
+        self.max_depth.is_none_or(|v| depth <= v) && !self.excludes(relative)
+    }
+
+    /// Whether the file, relative to the root, should be scanned.
+    pub fn allows_file(&self, relative: &Path, size: u64) -> bool {
+        // files directly in the root are at depth 0
+        let depth = relative.components().count().saturating_sub(1);
+
+        size >= self.min_file_size
+            && self.max_depth.is_none_or(|v| depth <= v)
+            && !self.excludes(relative)
+    }
+}
+
+fn is_hidden(component: Component) -> bool {
+    match component {
+        Component::Normal(name) => name.to_string_lossy().starts_with('.'),
+        _ => false,
+    }
+}
+
+/// Finds the root a path is in, and returns its index along with the path relative to it. Paths
+/// in more than one root belong to the innermost one.
+pub fn find_root<'a>(roots: &[RootRules], path: &'a Path) -> Option<(usize, &'a Path)> {
+    roots
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, root)| Some((idx, path.strip_prefix(&root.path).ok()?)))
+        .min_by_key(|(_, relative)| relative.components().count())
+}
+
+/// Whether a track that wasn't found by a full scan should be removed. Tracks outside of every
+/// root are removed, but tracks in a root that doesn't exist are kept, since it may be on a drive
+/// that isn't connected.
+pub fn is_removed(roots: &[RootRules], path: &Path) -> bool {
+    find_root(roots, path).is_none_or(|(idx, _)| roots[idx].path.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_in_missing_roots_are_kept() {
+        let existing = std::env::temp_dir().canonicalize().unwrap();
+        let missing = existing.join("muzak-missing-library-root");
+        let roots = [
+            RootRules::new(&LibraryRoot::new(&existing)),
+            RootRules::new(&LibraryRoot::new(&missing)),
+        ];
+
+        assert!(is_removed(&roots, &existing.join("Artist/01.flac")));
+        assert!(!is_removed(&roots, &missing.join("Artist/01.flac")));
+        assert!(is_removed(
+            &roots,
+            Path::new("/removed-root/Artist/01.flac")
+        ));
+    }
+}
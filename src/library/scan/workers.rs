@@ -0,0 +1,275 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use globwalk::GlobWalkerBuilder;
+use image::{codecs::jpeg::JpegEncoder, imageops::thumbnail, EncodableLayout};
+use tracing::debug;
+
+use crate::media::{metadata::Metadata, traits::MediaProvider};
+
+use super::{build_provider_table, file_is_scannable_with_provider, metadata_album_key};
+
+/// Album art, resized for the album page and as a thumbnail for lists.
+pub struct AlbumArt {
+    pub full: Option<Vec<u8>>,
+    pub thumbnail: Option<Vec<u8>>,
+}
+
+/// A file read by a worker, ready to be written to the database.
+pub struct ScannedFile {
+    pub path: PathBuf,
+    /// The modification time of the file when it was discovered, for the scan record.
+    pub timestamp: u64,
+    pub metadata: Metadata,
+    /// The duration of the file, in seconds.
+    pub length: u64,
+    /// The average bitrate of the file in kbps, worked out from its size and duration.
+    pub bitrate: Option<u32>,
+    /// Only processed for the first track of an album that isn't in the library yet, since the
+    /// art of existing albums isn't replaced.
+    pub art: Option<AlbumArt>,
+}
+
+pub enum WorkerResult {
+    Scanned(Box<ScannedFile>),
+    Failed(PathBuf),
+}
+
+type FileInformation = (Metadata, u64, Option<Box<[u8]>>);
+
+// We don't care about the error message. If the file can't be scanned, we just ignore it.
+// TODO: it might be worth logging why the file couldn't be scanned (for plugin development)
+fn scan_file_with_provider(
+    path: &PathBuf,
+    provider: &mut Box<dyn MediaProvider>,
+) -> Result<FileInformation, ()> {
+    let src = std::fs::File::open(path).map_err(|_| ())?;
+    provider.open(src, None).map_err(|_| ())?;
+    provider.start_playback().map_err(|_| ())?;
+    let metadata = provider.read_metadata().cloned().map_err(|_| ())?;
+    let image = provider.read_image().map_err(|_| ())?;
+    let len = provider.duration_secs().map_err(|_| ())?;
+    provider.close().map_err(|_| ())?;
+    Ok((metadata, len, image))
+}
+
+// Returns the first image (cover/front/folder.jpeg/png/jpeg) in the track's containing folder
+// Album art can be named anything, but this pattern is convention and the least likely to return a false positive
+fn scan_path_for_album_art(path: &Path) -> Option<Box<[u8]>> {
+    let glob = GlobWalkerBuilder::from_patterns(
+        path.parent().unwrap(),
+        &["{folder,cover,front}.{jpg,jpeg,png}"],
+    )
+    .case_insensitive(true)
+    .max_depth(1)
+    .build()
+    .expect("Failed to build album art glob")
+    .filter_map(|e| e.ok());
+
+    for entry in glob {
+        if let Ok(bytes) = fs::read(entry.path()) {
+            return Some(bytes.into_boxed_slice());
+        }
+    }
+    None
+}
+
+fn create_thumbnail(image: &[u8]) -> Option<Vec<u8>> {
+    let decoded = image::ImageReader::new(Cursor::new(image))
+        .with_guessed_format()
+        .ok()?
+        .decode()
+        .ok()?
+        .into_rgba8();
+
+    let thumb = thumbnail(&decoded, 70, 70);
+
+    let mut buf: Cursor<Vec<u8>> = Cursor::new(Vec::new());
+
+    thumb
+        .write_to(&mut buf, image::ImageFormat::Bmp)
+        .expect("i don't know how Cursor could fail");
+    buf.flush().expect("could not flush buffer");
+
+    Some(buf.into_inner())
+}
+
+fn resize_image(image: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = image::ImageReader::new(Cursor::new(image))
+        .with_guessed_format()
+        .ok()?
+        .decode()
+        .ok()?
+        .into_rgb8();
+
+    if decoded.dimensions().0 <= 1024 || decoded.dimensions().1 <= 1024 {
+        return Some(image.to_vec());
+    }
+
+    decoded = image::imageops::resize(&decoded, 1024, 1024, image::imageops::FilterType::Lanczos3);
+    let mut buf: Cursor<Vec<u8>> = Cursor::new(Vec::new());
+    let mut encoder = JpegEncoder::new_with_quality(&mut buf, 70);
+
+    encoder
+        .encode(
+            decoded.as_bytes(),
+            decoded.width(),
+            decoded.height(),
+            image::ExtendedColorType::Rgb8,
+        )
+        .expect("could not encode image");
+    buf.flush().expect("could not flush buffer");
+
+    Some(buf.into_inner())
+}
+
+/// State shared between the workers of one scan.
+struct Shared {
+    files: Mutex<Vec<(PathBuf, u64)>>,
+    /// The albums whose art has been processed, or that were already in the library, by the keys
+    /// from `album_key`.
+    claimed_albums: Mutex<HashSet<String>>,
+    cancelled: AtomicBool,
+}
+
+impl Shared {
+    fn is_claimed(&self, album: &str) -> bool {
+        self.claimed_albums
+            .lock()
+            .expect("poisoned lock")
+            .contains(album)
+    }
+
+    /// Returns true if the caller is the first to claim the album, and should process its art.
+    fn claim_album(&self, album: &str) -> bool {
+        self.claimed_albums
+            .lock()
+            .expect("poisoned lock")
+            .insert(album.to_string())
+    }
+}
+
+type ProviderTable = Vec<(&'static [&'static str], Box<dyn MediaProvider>)>;
+
+fn read_file(
+    shared: &Shared,
+    providers: &mut ProviderTable,
+    path: PathBuf,
+    timestamp: u64,
+) -> WorkerResult {
+    for (exts, provider) in providers.iter_mut() {
+        if !file_is_scannable_with_provider(&path, exts) {
+            continue;
+        }
+
+        let Ok((metadata, length, image)) = scan_file_with_provider(&path, provider) else {
+            continue;
+        };
+
+        let art = metadata_album_key(&metadata)
+            .and_then(|album| {
+                if shared.is_claimed(&album) {
+                    return None;
+                }
+
+                let image = image.or_else(|| scan_path_for_album_art(&path))?;
+                shared.claim_album(&album).then_some(image)
+            })
+            .map(|image| AlbumArt {
+                full: resize_image(&image),
+                thumbnail: create_thumbnail(&image),
+            });
+
+        let bitrate = fs::metadata(&path)
+            .ok()
+            .filter(|_| length > 0)
+            .map(|v| (v.len() * 8 / length / 1000) as u32);
+
+        return WorkerResult::Scanned(Box::new(ScannedFile {
+            path,
+            timestamp,
+            metadata,
+            length,
+            bitrate,
+            art,
+        }));
+    }
+
+    WorkerResult::Failed(path)
+}
+
+/// Reads the metadata and album art of files on a pool of threads, each with its own providers.
+/// Results are sent as soon as a file has been read, and the receiver is disconnected once every
+/// file has been read or the pool has been cancelled.
+pub struct WorkerPool {
+    shared: Arc<Shared>,
+    results_rx: mpsc::Receiver<WorkerResult>,
+}
+
+impl WorkerPool {
+    /// Starts reading the files, given as paths with their modification times. The art of the
+    /// albums in `existing_albums` isn't processed.
+    pub fn start(files: Vec<(PathBuf, u64)>, existing_albums: HashSet<String>) -> Self {
+        let threads = thread::available_parallelism()
+            .map(|v| v.get())
+            .unwrap_or(4)
+            .min(files.len().max(1));
+
+        debug!("scanning {} files on {} threads", files.len(), threads);
+
+        let shared = Arc::new(Shared {
+            files: Mutex::new(files),
+            claimed_albums: Mutex::new(existing_albums),
+            cancelled: AtomicBool::new(false),
+        });
+        let (results_tx, results_rx) = mpsc::channel();
+
+        for i in 0..threads {
+            let shared = shared.clone();
+            let results_tx = results_tx.clone();
+
+            thread::Builder::new()
+                .name(format!("scan-worker-{}", i))
+                .spawn(move || {
+                    let mut providers = build_provider_table();
+
+                    while !shared.cancelled.load(Ordering::Relaxed) {
+                        let next = shared.files.lock().expect("poisoned lock").pop();
+                        let Some((path, timestamp)) = next else {
+                            break;
+                        };
+
+                        let result = read_file(&shared, &mut providers, path, timestamp);
+
+                        if results_tx.send(result).is_err() {
+                            break;
+                        }
+                    }
+                })
+                .expect("could not start scan worker");
+        }
+
+        WorkerPool { shared, results_rx }
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<WorkerResult, RecvTimeoutError> {
+        self.results_rx.recv_timeout(timeout)
+    }
+
+    /// Stops the workers once they have finished the files they are reading. The results for
+    /// those files are still sent.
+    pub fn cancel(&self) {
+        self.shared.cancelled.store(true, Ordering::Relaxed);
+    }
+}
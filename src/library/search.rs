@@ -0,0 +1,355 @@
+//! The query language of the full-text search, which is turned into a query on the `track_search`
+//! index.
+//!
+//! A query is made of terms separated by spaces. A term is a word, or a phrase in double quotes,
+//! that can be limited to one field with a prefix such as `artist:`, and excluded with a leading
+//! `-`. Terms without quotes also match words they're the start of. The fields are `title`,
+//! `artist`, `album`, `albumartist`, `label`, `genre`, `lyrics` and `comment`. `year:` takes a
+//! year or a range of years, such as `1955..1960`, `1955..` or `..1960`. For example:
+//!
+//! ```text
+//! artist:"Miles Davis" year:1955..1960 genre:jazz -live
+//! ```
+
+use errors::QueryError;
+
+pub mod errors;
+
+/// The fields of the search index that terms can be limited to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchField {
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    Label,
+    Genre,
+    Lyrics,
+    Comment,
+}
+
+impl SearchField {
+    fn from_name(name: &str) -> Option<SearchField> {
+        match name.to_lowercase().as_str() {
+            "title" => Some(SearchField::Title),
+            "artist" => Some(SearchField::Artist),
+            "album" => Some(SearchField::Album),
+            "albumartist" => Some(SearchField::AlbumArtist),
+            "label" => Some(SearchField::Label),
+            "genre" => Some(SearchField::Genre),
+            "lyrics" => Some(SearchField::Lyrics),
+            "comment" => Some(SearchField::Comment),
+            _ => None,
+        }
+    }
+
+    /// The name of the field's column in `track_search`.
+    fn column(&self) -> &'static str {
+        match self {
+            SearchField::Title => "title",
+            SearchField::Artist => "artist",
+            SearchField::Album => "album",
+            SearchField::AlbumArtist => "album_artist",
+            SearchField::Label => "label",
+            SearchField::Genre => "genre",
+            SearchField::Lyrics => "lyrics",
+            SearchField::Comment => "comment",
+        }
+    }
+}
+
+/// A parsed search query.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    /// An FTS5 expression the tracks must match.
+    pub matching: Option<String>,
+    /// An FTS5 expression the tracks must not match.
+    pub excluding: Option<String>,
+    /// The earliest release year, inclusive.
+    pub year_from: Option<i32>,
+    /// The latest release year, inclusive.
+    pub year_to: Option<i32>,
+    /// Whether the query uses any fields or exclusions, rather than just being text.
+    pub structured: bool,
+}
+
+/// A term of the query, before it's turned into FTS5 syntax.
+struct Term<'a> {
+    field: Option<&'a str>,
+    value: &'a str,
+    quoted: bool,
+    excluded: bool,
+}
+
+/// Splits the query into its terms. Quotes that are never closed run to the end of the query.
+fn terms(query: &str) -> Vec<Term<'_>> {
+    let mut terms = Vec::new();
+    let mut rest = query.trim_start();
+
+    while !rest.is_empty() {
+        let excluded = rest.len() > 1 && rest.starts_with('-');
+        if excluded {
+            rest = &rest[1..];
+        }
+
+        // a field name is only recognized directly before a colon, and never inside quotes
+        let mut field = None;
+        if !rest.starts_with('"') {
+            let word_end = rest
+                .find(|c: char| c.is_whitespace() || c == '"' || c == ':')
+                .unwrap_or(rest.len());
+
+            if rest[word_end..].starts_with(':') && word_end > 0 {
+                field = Some(&rest[..word_end]);
+                rest = &rest[word_end + 1..];
+            }
+        }
+
+        let (value, quoted) = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            let value = &quoted[..end];
+            rest = quoted.get(end + 1..).unwrap_or("");
+            (value, true)
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let value = &rest[..end];
+            rest = &rest[end..];
+            (value, false)
+        };
+
+        terms.push(Term {
+            field,
+            value,
+            quoted,
+            excluded,
+        });
+
+        rest = rest.trim_start();
+    }
+
+    terms
+}
+
+/// Parses a year, or a range of years such as `1955..1960`, either end of which can be left out.
+fn parse_years(value: &str) -> Result<(Option<i32>, Option<i32>), QueryError> {
+    let year = |v: &str| -> Result<Option<i32>, QueryError> {
+        if v.is_empty() {
+            Ok(None)
+        } else {
+            v.parse()
+                .map(Some)
+                .map_err(|_| QueryError::InvalidYear(value.to_string()))
+        }
+    };
+
+    match value.split_once("..") {
+        Some((from, to)) => Ok((year(from)?, year(to)?)),
+        None => {
+            let year = year(value)?;
+            Ok((year, year))
+        }
+    }
+}
+
+impl SearchQuery {
+    /// Parses a query. Terms that are still being typed, such as a field without a value, are
+    /// ignored rather than treated as errors.
+    pub fn parse(query: &str) -> Result<SearchQuery, QueryError> {
+        let mut search = SearchQuery::default();
+        let mut matching = Vec::new();
+        let mut excluding = Vec::new();
+
+        for term in terms(query) {
+            if term.field.is_some() || term.excluded {
+                search.structured = true;
+            }
+
+            if term.field.is_some_and(|v| v.eq_ignore_ascii_case("year")) {
+                if term.excluded {
+                    return Err(QueryError::ExcludedYear);
+                }
+                if term.value.is_empty() {
+                    continue;
+                }
+
+                let (from, to) = parse_years(term.value)?;
+                if let Some(from) = from {
+                    search.year_from = Some(search.year_from.map_or(from, |v| v.max(from)));
+                }
+                if let Some(to) = to {
+                    search.year_to = Some(search.year_to.map_or(to, |v| v.min(to)));
+                }
+                continue;
+            }
+
+            let field = match term.field {
+                Some(name) => Some(
+                    SearchField::from_name(name)
+                        .ok_or_else(|| QueryError::UnknownField(name.to_string()))?,
+                ),
+                None => None,
+            };
+
+            // FTS5 can't search for something without any words in it
+            if !term.value.chars().any(char::is_alphanumeric) {
+                continue;
+            }
+
+            let mut expression = format!("\"{}\"", term.value.replace('"', "\"\""));
+            if !term.quoted {
+                expression.push_str(" *");
+            }
+            if let Some(field) = field {
+                expression = format!("{} : {}", field.column(), expression);
+            }
+
+            if term.excluded {
+                excluding.push(expression);
+            } else {
+                matching.push(expression);
+            }
+        }
+
+        if !matching.is_empty() {
+            search.matching = Some(matching.join(" AND "));
+        }
+        if !excluding.is_empty() {
+            search.excluding = Some(excluding.join(" OR "));
+        }
+
+        Ok(search)
+    }
+
+    /// Whether the query doesn't filter anything, and so shouldn't be run.
+    pub fn is_empty(&self) -> bool {
+        self.matching.is_none()
+            && self.excluding.is_none()
+            && self.year_from.is_none()
+            && self.year_to.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matching(query: &str) -> Option<String> {
+        SearchQuery::parse(query).unwrap().matching
+    }
+
+    #[test]
+    fn words_match_the_start_of_words() {
+        let query = SearchQuery::parse("kind of blue").unwrap();
+
+        assert_eq!(
+            query.matching.as_deref(),
+            Some("\"kind\" * AND \"of\" * AND \"blue\" *")
+        );
+        assert!(!query.structured);
+    }
+
+    #[test]
+    fn fields_limit_terms_to_a_column() {
+        let query = SearchQuery::parse("artist:davis AlbumArtist:miles").unwrap();
+
+        assert_eq!(
+            query.matching.as_deref(),
+            Some("artist : \"davis\" * AND album_artist : \"miles\" *")
+        );
+        assert!(query.structured);
+
+        assert_eq!(
+            matching("lyrics:\"so what\" comment:remaster").as_deref(),
+            Some("lyrics : \"so what\" AND comment : \"remaster\" *")
+        );
+    }
+
+    #[test]
+    fn quoted_phrases_are_matched_exactly() {
+        assert_eq!(
+            matching("artist:\"Miles Davis\"").as_deref(),
+            Some("artist : \"Miles Davis\"")
+        );
+        // colons inside quotes aren't fields
+        assert_eq!(
+            matching("\"title: subtitle\"").as_deref(),
+            Some("\"title: subtitle\"")
+        );
+        // unclosed quotes run to the end of the query
+        assert_eq!(matching("\"so what").as_deref(), Some("\"so what\""));
+    }
+
+    #[test]
+    fn quotes_in_terms_are_escaped() {
+        assert_eq!(matching("12\"").as_deref(), Some("\"12\"\"\" *"));
+        assert_eq!(
+            matching("rock'n'roll").as_deref(),
+            Some("\"rock'n'roll\" *")
+        );
+    }
+
+    #[test]
+    fn excluded_terms_are_kept_apart() {
+        let query = SearchQuery::parse("jazz -live -genre:\"smooth jazz\"").unwrap();
+
+        assert_eq!(query.matching.as_deref(), Some("\"jazz\" *"));
+        assert_eq!(
+            query.excluding.as_deref(),
+            Some("\"live\" * OR genre : \"smooth jazz\"")
+        );
+        assert!(query.structured);
+
+        // a dash on its own doesn't exclude anything
+        let query = SearchQuery::parse("a - b").unwrap();
+        assert!(query.excluding.is_none());
+    }
+
+    #[test]
+    fn years_and_ranges() {
+        let query = SearchQuery::parse("year:1959").unwrap();
+        assert_eq!((query.year_from, query.year_to), (Some(1959), Some(1959)));
+        assert!(query.matching.is_none());
+
+        let query = SearchQuery::parse("year:1955..1960").unwrap();
+        assert_eq!((query.year_from, query.year_to), (Some(1955), Some(1960)));
+
+        let query = SearchQuery::parse("year:1955..").unwrap();
+        assert_eq!((query.year_from, query.year_to), (Some(1955), None));
+
+        let query = SearchQuery::parse("YEAR:..1960").unwrap();
+        assert_eq!((query.year_from, query.year_to), (None, Some(1960)));
+
+        // ranges narrow each other
+        let query = SearchQuery::parse("year:1950..1970 year:1960..1980").unwrap();
+        assert_eq!((query.year_from, query.year_to), (Some(1960), Some(1970)));
+    }
+
+    #[test]
+    fn unfinished_terms_are_ignored() {
+        let query = SearchQuery::parse("artist: year: \"\" - ...").unwrap();
+
+        assert!(query.is_empty());
+        assert!(SearchQuery::parse("").unwrap().is_empty());
+        assert!(SearchQuery::parse("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn malformed_queries_are_errors() {
+        assert_eq!(
+            SearchQuery::parse("composer:bach"),
+            Err(QueryError::UnknownField("composer".to_string()))
+        );
+        assert_eq!(
+            SearchQuery::parse("year:soon"),
+            Err(QueryError::InvalidYear("soon".to_string()))
+        );
+        assert_eq!(
+            SearchQuery::parse("year:1960..19x0"),
+            Err(QueryError::InvalidYear("1960..19x0".to_string()))
+        );
+        assert_eq!(
+            SearchQuery::parse("-year:1960"),
+            Err(QueryError::ExcludedYear)
+        );
+    }
+}
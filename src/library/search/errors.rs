@@ -0,0 +1,11 @@
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum QueryError {
+    #[error("Unknown search field \"{0}\"")]
+    UnknownField(String),
+    #[error("\"{0}\" is not a year or range of years")]
+    InvalidYear(String),
+    #[error("Years can't be excluded")]
+    ExcludedYear,
+}
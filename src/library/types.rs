@@ -27,6 +27,18 @@ pub struct Artist {
     pub tags: Option<Vec<String>>,
 }
 
+/// How an artist is credited on a track or album, stored in the `role` column of the
+/// `track_artist` and `album_artist` tables.
+#[derive(sqlx::Type, Clone, Copy, PartialEq, Eq, Debug)]
+#[sqlx(rename_all = "lowercase")]
+pub enum ArtistRole {
+    Primary,
+    Featured,
+    Composer,
+    Remixer,
+    Performer,
+}
+
 #[derive(Clone)]
 pub struct Thumbnail(pub Arc<RenderImage>);
 
@@ -203,6 +215,9 @@ pub struct Album {
     pub catalog_number: Option<DBString>,
     #[sqlx(default)]
     pub isrc: Option<DBString>,
+    /// The number of plays of the album's tracks in the play history.
+    #[sqlx(default)]
+    pub play_count: i64,
 }
 
 #[derive(sqlx::FromRow, Clone)]
@@ -226,3 +241,191 @@ pub struct Track {
     pub location: PathBuf,
     pub artist_names: Option<DBString>,
 }
+
+/// A track along with the title of its album and the name of the album's artist, for places that
+/// list tracks from many albums at once.
+#[derive(sqlx::FromRow, Clone)]
+pub struct TrackListing {
+    pub id: i64,
+    pub title: DBString,
+    #[sqlx(default)]
+    pub album_id: Option<i64>,
+    #[sqlx(default)]
+    pub track_number: Option<i32>,
+    #[sqlx(default)]
+    pub disc_number: Option<i32>,
+    pub duration: i64,
+    #[sqlx(try_from = "String")]
+    pub location: PathBuf,
+    pub artist_names: Option<DBString>,
+    pub album_title: Option<DBString>,
+    pub release_date: Option<DateTime<Utc>>,
+    pub album_artist: Option<DBString>,
+}
+
+/// A row of the track list, with the play count and file information shown in its columns.
+#[derive(sqlx::FromRow, Clone)]
+pub struct TrackRow {
+    pub id: i64,
+    pub title: DBString,
+    pub album_id: Option<i64>,
+    pub artist_name: Option<DBString>,
+    pub album_title: Option<DBString>,
+    pub duration: i64,
+    pub track_number: Option<i32>,
+    pub disc_number: Option<i32>,
+    pub genres: Option<DBString>,
+    pub created_at: DateTime<Utc>,
+    pub plays: i64,
+    /// The average bitrate of the file, in kbps.
+    pub bitrate: Option<i64>,
+    /// The file extension, in lowercase.
+    pub format: Option<DBString>,
+    #[sqlx(try_from = "String")]
+    pub location: PathBuf,
+}
+
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct Bookmark {
+    pub id: i64,
+    pub track_id: i64,
+    pub name: DBString,
+    /// The position of the bookmark in the track, in seconds.
+    pub position: f64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An artist, along with how much of the library they are credited on, for the artist list.
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct ArtistSummary {
+    pub id: i64,
+    pub name: DBString,
+    pub name_sortable: String,
+    /// The number of albums the artist is an album artist of.
+    pub albums: i64,
+    /// The number of tracks the artist is credited on, in any role.
+    pub tracks: i64,
+    /// The number of plays of the tracks the artist is credited on.
+    pub plays: i64,
+}
+
+/// A genre, along with how much of the library has it, for the genre list.
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct GenreSummary {
+    pub id: i64,
+    pub name: DBString,
+    /// The number of albums with a track of the genre.
+    pub albums: i64,
+    pub tracks: i64,
+}
+
+/// What a search result refers to, stored in the `kind` column of the search query.
+#[derive(sqlx::Type, Clone, Copy, PartialEq, Eq, Debug)]
+#[sqlx(rename_all = "lowercase")]
+pub enum SearchKind {
+    Artist,
+    Album,
+    Track,
+}
+
+/// An artist, album or track in the search index, along with the text it can be found by.
+#[derive(sqlx::FromRow, Clone, PartialEq, Debug)]
+pub struct SearchEntry {
+    pub kind: SearchKind,
+    pub id: i64,
+    /// The artist's name, or the album or track title.
+    pub title: String,
+    /// The album's artist, or the track's artists.
+    pub artist_name: Option<String>,
+    /// The title of the track's album.
+    pub album_title: Option<String>,
+    /// The album's record label.
+    pub label: Option<String>,
+}
+
+/// An album on an artist page, either in the artist's discography or one they appear on, or on a
+/// genre page.
+#[derive(sqlx::FromRow, Clone)]
+pub struct ArtistRelease {
+    pub id: i64,
+    pub title: DBString,
+    #[sqlx(default)]
+    pub release_date: Option<DateTime<Utc>>,
+    /// The MusicBrainz release type, see `Metadata::release_type`.
+    #[sqlx(default)]
+    pub release_type: Option<String>,
+    #[sqlx(default)]
+    pub thumb: Option<Thumbnail>,
+}
+
+/// An artist, along with how often their music was played in a date range.
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct ArtistPlays {
+    pub id: i64,
+    pub name: Option<DBString>,
+    pub plays: i64,
+    /// The total time spent listening, in seconds.
+    pub listened: i64,
+}
+
+/// An album, along with how often it was played in a date range.
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct AlbumPlays {
+    pub id: i64,
+    pub title: DBString,
+    pub artist_name: Option<DBString>,
+    pub plays: i64,
+    /// The total time spent listening, in seconds.
+    pub listened: i64,
+}
+
+/// A track, along with how often it was played in a date range.
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct TrackPlays {
+    pub id: i64,
+    pub title: DBString,
+    pub album_id: Option<i64>,
+    pub artist_name: Option<DBString>,
+    pub plays: i64,
+    /// The total time spent listening, in seconds.
+    pub listened: i64,
+}
+
+/// The time spent listening on a single day, in local time.
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct DailyListening {
+    /// The day, formatted as YYYY-MM-DD.
+    pub day: String,
+    /// The total time spent listening, in seconds.
+    pub listened: i64,
+}
+
+/// An entry in the play history. The track information is missing if the track is no longer in
+/// the library.
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct RecentPlay {
+    pub id: i64,
+    #[sqlx(try_from = "String")]
+    pub location: PathBuf,
+    pub started_at: DateTime<Utc>,
+    /// The time spent listening, in seconds.
+    pub listened: i64,
+    pub skipped: bool,
+    /// False for tracks that were skipped before they passed the play threshold.
+    pub counted: bool,
+    pub title: Option<DBString>,
+    pub album_id: Option<i64>,
+    pub artist_name: Option<DBString>,
+}
+
+/// An imported play that couldn't be matched to a track in the library. These are kept so that
+/// they can be matched again after the library changes.
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct UnmatchedPlay {
+    pub id: i64,
+    pub source: String,
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    pub played_at: DateTime<Utc>,
+}
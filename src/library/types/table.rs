@@ -1,22 +1,26 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
+use ahash::AHashMap;
 use fnv::FnvBuildHasher;
 use gpui::{App, RenderImage, SharedString};
 use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
 
-use super::Album;
+use super::{Album, TrackRow};
 use crate::{
-    library::db::{AlbumMethod, AlbumSortMethod, LibraryAccess},
+    library::db::{AlbumMethod, AlbumSortMethod, LibraryAccess, TrackSortMethod},
     ui::components::table::table_data::{Column, TableData, TableSort},
 };
 
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AlbumColumn {
     Title,
     Artist,
     Date,
     Label,
     CatalogNumber,
+    Plays,
+    Added,
 }
 
 impl Column for AlbumColumn {
@@ -27,8 +31,22 @@ impl Column for AlbumColumn {
             AlbumColumn::Date => "Date",
             AlbumColumn::Label => "Label",
             AlbumColumn::CatalogNumber => "Catalog Number",
+            AlbumColumn::Plays => "Plays",
+            AlbumColumn::Added => "Added",
         }
     }
+
+    fn all() -> &'static [Self] {
+        &[
+            AlbumColumn::Title,
+            AlbumColumn::Artist,
+            AlbumColumn::Date,
+            AlbumColumn::Label,
+            AlbumColumn::CatalogNumber,
+            AlbumColumn::Plays,
+            AlbumColumn::Added,
+        ]
+    }
 }
 
 impl TableData<AlbumColumn> for Album {
@@ -83,14 +101,43 @@ impl TableData<AlbumColumn> for Album {
                 column: AlbumColumn::CatalogNumber,
                 ascending: false,
             }) => AlbumSortMethod::CatalogDesc,
+            Some(TableSort {
+                column: AlbumColumn::Plays,
+                ..
+            }) => AlbumSortMethod::MostPlayed,
+            Some(TableSort {
+                column: AlbumColumn::Added,
+                ..
+            }) => AlbumSortMethod::RecentlyAdded,
             _ => AlbumSortMethod::ArtistAsc,
         };
 
-        Ok(cx.list_albums(sort_method)?)
+        let mut rows = cx.list_albums(sort_method)?;
+
+        // MostPlayed and RecentlyAdded are descending, reverse them for ascending order
+        if let Some(TableSort {
+            column: AlbumColumn::Plays | AlbumColumn::Added,
+            ascending: true,
+        }) = sort
+        {
+            rows.reverse();
+        }
+
+        Ok(rows)
     }
 
-    fn get_row(cx: &mut gpui::App, id: Self::Identifier) -> anyhow::Result<Option<Arc<Self>>> {
-        Ok(cx.get_album_by_id(id.0 as i64, AlbumMethod::Thumbnail).ok())
+    fn get_rows_by_id(
+        cx: &mut gpui::App,
+        ids: &[Self::Identifier],
+    ) -> anyhow::Result<Vec<Option<Arc<Self>>>> {
+        let album_ids: Vec<i64> = ids.iter().map(|id| id.0 as i64).collect();
+        let albums: AHashMap<i64, Arc<Album>> = cx
+            .list_albums_by_id(&album_ids, AlbumMethod::Thumbnail)?
+            .into_iter()
+            .map(|album| (album.id, album))
+            .collect();
+
+        Ok(album_ids.iter().map(|id| albums.get(id).cloned()).collect())
     }
 
     fn get_column(&self, cx: &mut App, column: AlbumColumn) -> Option<SharedString> {
@@ -105,6 +152,8 @@ impl TableData<AlbumColumn> for Album {
                 .map(|date| date.format("%x").to_string().into()),
             AlbumColumn::Label => self.label.as_ref().map(|v| v.0.clone()),
             AlbumColumn::CatalogNumber => self.catalog_number.as_ref().map(|v| v.0.clone()),
+            AlbumColumn::Plays => Some(self.play_count.to_string().into()),
+            AlbumColumn::Added => Some(self.created_at.format("%x").to_string().into()),
         }
     }
 
@@ -122,7 +171,7 @@ impl TableData<AlbumColumn> for Album {
             | AlbumColumn::Artist
             | AlbumColumn::Label
             | AlbumColumn::CatalogNumber => false,
-            AlbumColumn::Date => true,
+            AlbumColumn::Date | AlbumColumn::Plays | AlbumColumn::Added => true,
         }
     }
 
@@ -142,6 +191,184 @@ impl TableData<AlbumColumn> for Album {
         columns.insert(AlbumColumn::Date, 100.0);
         columns.insert(AlbumColumn::Label, 150.0);
         columns.insert(AlbumColumn::CatalogNumber, 200.0);
+        columns.insert(AlbumColumn::Plays, 80.0);
+        columns.insert(AlbumColumn::Added, 100.0);
         columns
     }
 }
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrackColumn {
+    Title,
+    Artist,
+    Album,
+    Duration,
+    TrackNumber,
+    DiscNumber,
+    Genre,
+    Added,
+    Plays,
+    Bitrate,
+    Format,
+    Path,
+}
+
+impl Column for TrackColumn {
+    fn get_column_name(&self) -> &'static str {
+        match self {
+            TrackColumn::Title => "Title",
+            TrackColumn::Artist => "Artist",
+            TrackColumn::Album => "Album",
+            TrackColumn::Duration => "Duration",
+            TrackColumn::TrackNumber => "Track",
+            TrackColumn::DiscNumber => "Disc",
+            TrackColumn::Genre => "Genre",
+            TrackColumn::Added => "Added",
+            TrackColumn::Plays => "Plays",
+            TrackColumn::Bitrate => "Bitrate",
+            TrackColumn::Format => "Format",
+            TrackColumn::Path => "Path",
+        }
+    }
+
+    fn all() -> &'static [Self] {
+        &[
+            TrackColumn::Title,
+            TrackColumn::Artist,
+            TrackColumn::Album,
+            TrackColumn::Duration,
+            TrackColumn::TrackNumber,
+            TrackColumn::DiscNumber,
+            TrackColumn::Genre,
+            TrackColumn::Added,
+            TrackColumn::Plays,
+            TrackColumn::Bitrate,
+            TrackColumn::Format,
+            TrackColumn::Path,
+        ]
+    }
+}
+
+impl TableData<TrackColumn> for TrackRow {
+    /// The id, location and album id of the track, so rows can be queued without loading them.
+    type Identifier = (i64, PathBuf, Option<i64>);
+
+    fn get_table_name() -> &'static str {
+        "Tracks"
+    }
+
+    fn get_rows(
+        cx: &mut gpui::App,
+        sort: Option<TableSort<TrackColumn>>,
+    ) -> anyhow::Result<Vec<Self::Identifier>> {
+        let sort_method = match sort.map(|v| v.column) {
+            Some(TrackColumn::Title) => TrackSortMethod::Title,
+            Some(TrackColumn::Artist) => TrackSortMethod::Artist,
+            Some(TrackColumn::Album) => TrackSortMethod::Album,
+            Some(TrackColumn::Duration) => TrackSortMethod::Duration,
+            Some(TrackColumn::Genre) => TrackSortMethod::Genre,
+            Some(TrackColumn::Added) => TrackSortMethod::Added,
+            Some(TrackColumn::Plays) => TrackSortMethod::Plays,
+            Some(TrackColumn::Bitrate) => TrackSortMethod::Bitrate,
+            Some(TrackColumn::Format) => TrackSortMethod::Format,
+            Some(TrackColumn::Path) => TrackSortMethod::Path,
+            Some(TrackColumn::TrackNumber | TrackColumn::DiscNumber) | None => {
+                TrackSortMethod::AlbumOrder
+            }
+        };
+
+        let mut rows = cx.list_track_rows(sort_method)?;
+
+        // every sort method is ascending, reverse them for descending order
+        if let Some(TableSort {
+            ascending: false, ..
+        }) = sort
+        {
+            rows.reverse();
+        }
+
+        Ok(rows)
+    }
+
+    fn get_rows_by_id(
+        cx: &mut gpui::App,
+        ids: &[Self::Identifier],
+    ) -> anyhow::Result<Vec<Option<Arc<Self>>>> {
+        let track_ids: Vec<i64> = ids.iter().map(|id| id.0).collect();
+        let tracks: AHashMap<i64, Arc<TrackRow>> = cx
+            .list_track_rows_by_id(&track_ids)?
+            .into_iter()
+            .map(|track| (track.id, track))
+            .collect();
+
+        Ok(track_ids.iter().map(|id| tracks.get(id).cloned()).collect())
+    }
+
+    fn get_column(&self, _: &mut App, column: TrackColumn) -> Option<SharedString> {
+        match column {
+            TrackColumn::Title => Some(self.title.0.clone()),
+            TrackColumn::Artist => self.artist_name.as_ref().map(|v| v.0.clone()),
+            TrackColumn::Album => self.album_title.as_ref().map(|v| v.0.clone()),
+            TrackColumn::Duration => {
+                Some(format!("{}:{:02}", self.duration / 60, self.duration % 60).into())
+            }
+            TrackColumn::TrackNumber => self.track_number.map(|v| v.to_string().into()),
+            TrackColumn::DiscNumber => self.disc_number.map(|v| v.to_string().into()),
+            TrackColumn::Genre => self.genres.as_ref().map(|v| v.0.clone()),
+            TrackColumn::Added => Some(self.created_at.format("%x").to_string().into()),
+            TrackColumn::Plays => Some(self.plays.to_string().into()),
+            TrackColumn::Bitrate => self.bitrate.map(|v| format!("{} kbps", v).into()),
+            TrackColumn::Format => self.format.as_ref().map(|v| v.0.to_uppercase().into()),
+            TrackColumn::Path => Some(self.location.to_string_lossy().to_string().into()),
+        }
+    }
+
+    fn get_image(&self) -> Option<Arc<RenderImage>> {
+        None
+    }
+
+    fn has_images() -> bool {
+        false
+    }
+
+    fn column_monospace(column: TrackColumn) -> bool {
+        match column {
+            TrackColumn::Title
+            | TrackColumn::Artist
+            | TrackColumn::Album
+            | TrackColumn::Genre
+            | TrackColumn::Format
+            | TrackColumn::Path => false,
+            TrackColumn::Duration
+            | TrackColumn::TrackNumber
+            | TrackColumn::DiscNumber
+            | TrackColumn::Added
+            | TrackColumn::Plays
+            | TrackColumn::Bitrate => true,
+        }
+    }
+
+    fn get_element_id(&self) -> impl Into<gpui::ElementId> {
+        ("track", self.id as u64)
+    }
+
+    fn get_table_id(&self) -> Self::Identifier {
+        (self.id, self.location.clone(), self.album_id)
+    }
+
+    fn default_columns() -> IndexMap<TrackColumn, f32, FnvBuildHasher> {
+        let s = FnvBuildHasher::default();
+        let mut columns: IndexMap<TrackColumn, f32, FnvBuildHasher> = IndexMap::with_hasher(s);
+        columns.insert(TrackColumn::TrackNumber, 80.0);
+        columns.insert(TrackColumn::Title, 300.0);
+        columns.insert(TrackColumn::Artist, 200.0);
+        columns.insert(TrackColumn::Album, 200.0);
+        columns.insert(TrackColumn::Duration, 90.0);
+        columns.insert(TrackColumn::Plays, 80.0);
+        columns
+    }
+
+    fn multi_select() -> bool {
+        true
+    }
+}
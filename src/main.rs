@@ -1,9 +1,15 @@
+use clap::Parser;
 use services::mmb::lastfm::{LASTFM_API_KEY, LASTFM_API_SECRET};
 
+mod cli;
+mod control;
 mod devices;
+mod headless;
 mod library;
 mod media;
+mod mpd;
 mod playback;
+mod remote;
 mod services;
 mod settings;
 mod ui;
@@ -11,6 +17,22 @@ mod util;
 
 #[async_std::main]
 async fn main() {
+    let args = cli::Args::parse();
+
+    if let Some(command) = args.command {
+        std::process::exit(cli::run_command(command).await);
+    }
+
+    if args.headless {
+        // the running instance would keep the control socket, leaving this one uncontrollable
+        if control::is_running() {
+            eprintln!("error: Muzak is already running, stop it before starting it headless");
+            std::process::exit(1);
+        }
+    } else if cli::forward_to_running_instance(&args) {
+        return;
+    }
+
     tracing_subscriber::fmt::init();
 
     tracing::info!("Starting application");
@@ -19,5 +41,10 @@ async fn main() {
         tracing::warn!("Binary not compiled with LastFM support, set LASTFM_API_KEY and LASTFM_API_SECRET at compile time to enable");
     }
 
-    crate::ui::app::run().await;
+    if args.headless {
+        crate::headless::run(args.files).await;
+    } else {
+        let mode = args.open_mode();
+        crate::ui::app::run(args.files, mode).await;
+    }
 }
@@ -37,10 +37,37 @@ pub struct SymphoniaProvider {
     current_duration: u64,
     current_length: Option<u64>,
     current_position: u64,
+    current_frame_position: f64,
     current_timebase: Option<TimeBase>,
     decoder: Option<Box<dyn Decoder>>,
     pending_metadata_update: bool,
     last_image: Option<Visual>,
+    /// The values of the ARTIST and ALBUMARTIST tags, which are only used as the list of artists
+    /// if the file doesn't have ARTISTS and ALBUMARTISTS tags.
+    artist_tags: Vec<String>,
+    album_artist_tags: Vec<String>,
+}
+
+/// Adds the values of a tag to a list, skipping duplicates. ID3v2.4 separates multiple values in
+/// one frame with null characters.
+fn push_values(list: &mut Vec<String>, value: &Value) {
+    for value in value.to_string().split('\0') {
+        let value = value.trim();
+
+        if !value.is_empty() && !list.iter().any(|v| v == value) {
+            list.push(value.to_string());
+        }
+    }
+}
+
+/// The name of a tag without a standard key, without the prefixes some formats add (TXXX: for
+/// ID3v2, ----:com.apple.iTunes: for MP4).
+fn custom_key(tag: &Tag) -> String {
+    tag.key
+        .rsplit(':')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase()
 }
 
 impl SymphoniaProvider {
@@ -50,23 +77,29 @@ impl SymphoniaProvider {
                 Some(StandardTagKey::TrackTitle) => {
                     self.current_metadata.name = Some(tag.value.to_string())
                 }
-                Some(StandardTagKey::Artist) => {
-                    self.current_metadata.artist = Some(tag.value.to_string())
-                }
+                Some(StandardTagKey::Artist) => push_values(&mut self.artist_tags, &tag.value),
                 Some(StandardTagKey::AlbumArtist) => {
-                    self.current_metadata.album_artist = Some(tag.value.to_string())
+                    push_values(&mut self.album_artist_tags, &tag.value)
                 }
                 Some(StandardTagKey::OriginalArtist) => {
                     self.current_metadata.original_artist = Some(tag.value.to_string())
                 }
                 Some(StandardTagKey::Composer) => {
-                    self.current_metadata.composer = Some(tag.value.to_string())
+                    self.current_metadata.composer = Some(tag.value.to_string());
+                    push_values(&mut self.current_metadata.composers, &tag.value);
+                }
+                Some(StandardTagKey::Remixer) => {
+                    push_values(&mut self.current_metadata.remixers, &tag.value)
+                }
+                Some(StandardTagKey::Performer) => {
+                    push_values(&mut self.current_metadata.performers, &tag.value)
                 }
                 Some(StandardTagKey::Album) => {
                     self.current_metadata.album = Some(tag.value.to_string())
                 }
                 Some(StandardTagKey::Genre) => {
-                    self.current_metadata.genre = Some(tag.value.to_string())
+                    self.current_metadata.genre = Some(tag.value.to_string());
+                    push_values(&mut self.current_metadata.genres, &tag.value);
                 }
                 Some(StandardTagKey::ContentGroup) => {
                     self.current_metadata.grouping = Some(tag.value.to_string())
@@ -126,12 +159,42 @@ impl SymphoniaProvider {
                 Some(StandardTagKey::IdentIsrc) => {
                     self.current_metadata.isrc = Some(tag.value.to_string())
                 }
+                Some(StandardTagKey::Lyrics) => {
+                    self.current_metadata.lyrics = Some(tag.value.to_string())
+                }
+                Some(StandardTagKey::Comment) => {
+                    self.current_metadata.comment = Some(tag.value.to_string())
+                }
                 Some(StandardTagKey::SortAlbum) => {
                     self.current_metadata.sort_album = Some(tag.value.to_string())
                 }
                 Some(StandardTagKey::SortAlbumArtist) => {
                     self.current_metadata.artist_sort = Some(tag.value.to_string())
                 }
+                Some(StandardTagKey::MusicBrainzRecordingId) => {
+                    self.current_metadata.mbid_recording = Some(tag.value.to_string())
+                }
+                Some(StandardTagKey::MusicBrainzReleaseTrackId) => {
+                    self.current_metadata.mbid_track = Some(tag.value.to_string())
+                }
+                Some(StandardTagKey::MusicBrainzAlbumId) => {
+                    self.current_metadata.mbid_album = Some(tag.value.to_string())
+                }
+                Some(StandardTagKey::MusicBrainzReleaseGroupId) => {
+                    self.current_metadata.mbid_release_group = Some(tag.value.to_string())
+                }
+                Some(StandardTagKey::MusicBrainzReleaseType) => {
+                    self.current_metadata.release_type = Some(tag.value.to_string().to_lowercase())
+                }
+                Some(StandardTagKey::MusicBrainzArtistId) => {
+                    self.current_metadata.mbid_artist = Some(tag.value.to_string())
+                }
+                None if custom_key(tag) == "ARTISTS" => {
+                    push_values(&mut self.current_metadata.artists, &tag.value)
+                }
+                None if custom_key(tag) == "ALBUMARTISTS" => {
+                    push_values(&mut self.current_metadata.album_artists, &tag.value)
+                }
                 _ => (),
             }
         }
@@ -140,6 +203,8 @@ impl SymphoniaProvider {
     fn read_base_metadata(&mut self, probed: &mut ProbeResult) {
         self.current_metadata = Metadata::default();
         self.last_image = None;
+        self.artist_tags.clear();
+        self.album_artist_tags.clear();
 
         if let Some(metadata) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
             self.break_metadata(metadata.tags());
@@ -155,6 +220,22 @@ impl SymphoniaProvider {
             }
         }
 
+        let artists = std::mem::take(&mut self.artist_tags);
+        if !artists.is_empty() {
+            self.current_metadata.artist = Some(artists.join(", "));
+        }
+        if self.current_metadata.artists.is_empty() {
+            self.current_metadata.artists = artists;
+        }
+
+        let album_artists = std::mem::take(&mut self.album_artist_tags);
+        if !album_artists.is_empty() {
+            self.current_metadata.album_artist = Some(album_artists.join(", "));
+        }
+        if self.current_metadata.album_artists.is_empty() {
+            self.current_metadata.album_artists = album_artists;
+        }
+
         self.pending_metadata_update = true;
     }
 }
@@ -183,6 +264,7 @@ impl MediaProvider for SymphoniaProvider {
 
         self.read_base_metadata(&mut probed);
         self.current_position = 0;
+        self.current_frame_position = 0.0;
         self.current_length = None;
         self.current_timebase = None;
         self.format = Some(probed.format);
@@ -268,7 +350,9 @@ impl MediaProvider for SymphoniaProvider {
                     self.current_duration = decoded.capacity() as u64;
 
                     if let Some(tb) = &self.current_timebase {
-                        self.current_position = tb.calc_time(packet.ts()).seconds;
+                        let time = tb.calc_time(packet.ts());
+                        self.current_position = time.seconds;
+                        self.current_frame_position = time.seconds as f64 + time.frac;
                     }
 
                     match decoded {
@@ -498,6 +582,16 @@ impl MediaProvider for SymphoniaProvider {
         }
     }
 
+    fn frame_position_secs(&self) -> Result<f64, TrackDurationError> {
+        if self.decoder.is_none() {
+            Err(TrackDurationError::NothingOpen)
+        } else if self.current_length.is_none() {
+            Err(TrackDurationError::NeverStarted)
+        } else {
+            Ok(self.current_frame_position)
+        }
+    }
+
     fn seek(&mut self, time: f64) -> Result<(), SeekError> {
         let timebase = self.current_timebase;
         let Some(format) = &mut self.format else {
@@ -517,7 +611,9 @@ impl MediaProvider for SymphoniaProvider {
             .map_err(|e| SeekError::Unknown(e.to_string()))?;
 
         if let Some(timebase) = timebase {
-            self.current_position = timebase.calc_time(seek.actual_ts).seconds;
+            let time = timebase.calc_time(seek.actual_ts);
+            self.current_position = time.seconds;
+            self.current_frame_position = time.seconds as f64 + time.frac;
         }
 
         Ok(())
@@ -1,8 +1,10 @@
 use chrono::{DateTime, Utc};
+use serde::Serialize;
 
-#[derive(Debug, Default, PartialEq, Clone)]
+#[derive(Debug, Default, PartialEq, Clone, Serialize)]
 pub struct Metadata {
     pub name: Option<String>,
+    /// The artist credit as it should be displayed, for example "Artist feat. Other Artist".
     pub artist: Option<String>,
     pub album_artist: Option<String>,
     pub artist_sort: Option<String>,
@@ -14,6 +16,8 @@ pub struct Metadata {
     pub grouping: Option<String>,
     pub bpm: Option<u64>,
     pub compilation: bool,
+    /// The MusicBrainz release type, like "album", "single" or "album; live".
+    pub release_type: Option<String>,
     pub date: Option<DateTime<Utc>>,
 
     pub track_current: Option<u64>,
@@ -24,4 +28,23 @@ pub struct Metadata {
     pub label: Option<String>,
     pub catalog: Option<String>,
     pub isrc: Option<String>,
+
+    pub lyrics: Option<String>,
+    pub comment: Option<String>,
+
+    pub mbid_recording: Option<String>,
+    pub mbid_track: Option<String>,
+    pub mbid_album: Option<String>,
+    pub mbid_release_group: Option<String>,
+    pub mbid_artist: Option<String>,
+
+    /// Every artist credited on the track, from the ARTISTS tag or every ARTIST tag.
+    pub artists: Vec<String>,
+    /// Every artist credited on the album, from the ALBUMARTISTS tag or every ALBUMARTIST tag.
+    pub album_artists: Vec<String>,
+    pub composers: Vec<String>,
+    pub remixers: Vec<String>,
+    pub performers: Vec<String>,
+    /// Every genre tag. A tag may still list more than one genre, like "Rock; Pop".
+    pub genres: Vec<String>,
 }
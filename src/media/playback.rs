@@ -35,6 +35,68 @@ impl Samples {
             Samples::Dsd(_) => format == SampleFormat::Dsd,
         }
     }
+
+    /// Returns the number of samples in the first channel.
+    pub fn len(&self) -> usize {
+        match self {
+            Samples::Float64(v) => v.first().map_or(0, |c| c.len()),
+            Samples::Float32(v) => v.first().map_or(0, |c| c.len()),
+            Samples::Signed32(v) => v.first().map_or(0, |c| c.len()),
+            Samples::Unsigned32(v) => v.first().map_or(0, |c| c.len()),
+            Samples::Signed24(v) => v.first().map_or(0, |c| c.len()),
+            Samples::Unsigned24(v) => v.first().map_or(0, |c| c.len()),
+            Samples::Signed16(v) => v.first().map_or(0, |c| c.len()),
+            Samples::Unsigned16(v) => v.first().map_or(0, |c| c.len()),
+            Samples::Signed8(v) => v.first().map_or(0, |c| c.len()),
+            Samples::Unsigned8(v) => v.first().map_or(0, |c| c.len()),
+            Samples::Dsd(v) => v.first().map_or(0, |c| c.len()),
+        }
+    }
+
+    /// Keeps only the samples in the range `start..end` of every channel.
+    pub fn slice(self, start: usize, end: usize) -> Samples {
+        match self {
+            Samples::Float64(v) => Samples::Float64(slice_channels(v, start, end)),
+            Samples::Float32(v) => Samples::Float32(slice_channels(v, start, end)),
+            Samples::Signed32(v) => Samples::Signed32(slice_channels(v, start, end)),
+            Samples::Unsigned32(v) => Samples::Unsigned32(slice_channels(v, start, end)),
+            Samples::Signed24(v) => Samples::Signed24(slice_channels(v, start, end)),
+            Samples::Unsigned24(v) => Samples::Unsigned24(slice_channels(v, start, end)),
+            Samples::Signed16(v) => Samples::Signed16(slice_channels(v, start, end)),
+            Samples::Unsigned16(v) => Samples::Unsigned16(slice_channels(v, start, end)),
+            Samples::Signed8(v) => Samples::Signed8(slice_channels(v, start, end)),
+            Samples::Unsigned8(v) => Samples::Unsigned8(slice_channels(v, start, end)),
+            Samples::Dsd(v) => Samples::Dsd(slice_channels(v, start, end)),
+        }
+    }
+
+    /// Returns true if the first channel contains no samples.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Samples::Float64(v) => v.first().is_none_or(|c| c.is_empty()),
+            Samples::Float32(v) => v.first().is_none_or(|c| c.is_empty()),
+            Samples::Signed32(v) => v.first().is_none_or(|c| c.is_empty()),
+            Samples::Unsigned32(v) => v.first().is_none_or(|c| c.is_empty()),
+            Samples::Signed24(v) => v.first().is_none_or(|c| c.is_empty()),
+            Samples::Unsigned24(v) => v.first().is_none_or(|c| c.is_empty()),
+            Samples::Signed16(v) => v.first().is_none_or(|c| c.is_empty()),
+            Samples::Unsigned16(v) => v.first().is_none_or(|c| c.is_empty()),
+            Samples::Signed8(v) => v.first().is_none_or(|c| c.is_empty()),
+            Samples::Unsigned8(v) => v.first().is_none_or(|c| c.is_empty()),
+            Samples::Dsd(v) => v.first().is_none_or(|c| c.is_empty()),
+        }
+    }
+}
+
+fn slice_channels<T>(channels: Vec<Vec<T>>, start: usize, end: usize) -> Vec<Vec<T>> {
+    channels
+        .into_iter()
+        .map(|mut channel| {
+            channel.truncate(end);
+            channel.drain(..start.min(channel.len()));
+            channel
+        })
+        .collect()
 }
 
 pub trait Mute {
@@ -106,6 +106,12 @@ pub trait MediaProvider {
     /// immediately after playback has started, and should not require reading any samples.
     fn position_secs(&self) -> Result<u64, TrackDurationError>;
 
+    /// Returns the precise position, in seconds, of the first sample of the last PlaybackFrame
+    /// returned by read_samples. After a seek, this should return the position the Provider
+    /// actually landed on, which may be slightly before the requested time. If no file is opened,
+    /// or playback has not started, this function should return an error.
+    fn frame_position_secs(&self) -> Result<f64, TrackDurationError>;
+
     /// Returns the chnanel specification used by the track being decoded. This function should be
     /// available immediately after playback has started, and should not require reading any
     /// samples.
@@ -0,0 +1,1399 @@
+pub mod protocol;
+mod query;
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Write as _,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex, MutexGuard, RwLock,
+    },
+    thread,
+    time::Duration,
+};
+
+use async_std::task;
+use protocol::{parse_arguments, parse_filters, Ack, AckCode, Filter, Tag};
+use query::{find_listings, find_listings_in, find_stats, list_values};
+use sqlx::SqlitePool;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    control::PlaybackStatus,
+    library::{
+        db::{get_track_listing_by_location, list_track_listings_by_location},
+        types::TrackListing,
+    },
+    playback::{
+        events::{PlaybackCommand, PlaybackEvent},
+        queue::QueueItemData,
+        thread::PlaybackState,
+    },
+    settings::mpd::MpdSettings,
+    util::is_loopback,
+};
+
+/// The protocol version reported to clients. Clients use this to decide which commands they can
+/// use, so it should match the newest features that are implemented.
+const PROTOCOL_VERSION: &str = "0.23.0";
+
+/// How often a client that is waiting in `idle` checks for changes.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The tags sent for songs in the library.
+const TAG_TYPES: [Tag; 7] = [
+    Tag::Title,
+    Tag::Artist,
+    Tag::AlbumArtist,
+    Tag::Album,
+    Tag::Track,
+    Tag::Disc,
+    Tag::Date,
+];
+
+/// The subsystems reported by `idle`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Subsystem {
+    Playlist,
+    Player,
+    Mixer,
+    Options,
+}
+
+const SUBSYSTEMS: [Subsystem; 4] = [
+    Subsystem::Playlist,
+    Subsystem::Player,
+    Subsystem::Mixer,
+    Subsystem::Options,
+];
+
+impl Subsystem {
+    fn name(&self) -> &'static str {
+        match self {
+            Subsystem::Playlist => "playlist",
+            Subsystem::Player => "player",
+            Subsystem::Mixer => "mixer",
+            Subsystem::Options => "options",
+        }
+    }
+
+    fn index(&self) -> usize {
+        SUBSYSTEMS.iter().position(|v| v == self).unwrap()
+    }
+
+    /// The subsystem that changes with the event. Changes to the queue are found by comparing it
+    /// with the previous queue instead (see `Shared::sync_queue`).
+    fn for_event(event: &PlaybackEvent) -> Option<Subsystem> {
+        match event {
+            PlaybackEvent::StateChanged(_)
+            | PlaybackEvent::SongChanged(_)
+            | PlaybackEvent::DurationChanged(_)
+            | PlaybackEvent::QueuePositionChanged(_)
+            | PlaybackEvent::MetadataUpdate(_) => Some(Subsystem::Player),
+            PlaybackEvent::VolumeChanged(_) => Some(Subsystem::Mixer),
+            PlaybackEvent::ShuffleToggled(_, _) => Some(Subsystem::Options),
+            _ => None,
+        }
+    }
+}
+
+/// A song in the queue, as seen by MPD clients.
+#[derive(Debug, Clone, PartialEq)]
+struct QueueEntry {
+    /// The ID of the song. It stays the same while the song is in the queue, even if it moves.
+    id: u32,
+    path: PathBuf,
+    /// The playlist version in which the song was added or moved to its current position.
+    version: u32,
+}
+
+/// The maximum number of IDs handed out by `addid` that can wait for their song at once.
+const MAX_RESERVED_IDS: usize = 256;
+
+/// The state shared by all connections, kept up to date by the playback events.
+#[derive(Default)]
+struct Shared {
+    status: PlaybackStatus,
+    /// Incremented every time the queue changes. Clients use this to detect changes to the queue.
+    playlist_version: u32,
+    /// A counter for every subsystem, incremented when something in the subsystem changes.
+    changes: [u64; SUBSYSTEMS.len()],
+    /// The queue as it was last seen, with the IDs of its songs.
+    queue: Vec<QueueEntry>,
+    /// IDs handed out by `addid` for songs that haven't reached the queue yet.
+    reserved: VecDeque<(PathBuf, u32)>,
+    last_id: u32,
+}
+
+impl Shared {
+    fn next_id(&mut self) -> u32 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    /// Hands out the ID the song will have once it is added to the queue.
+    fn reserve_id(&mut self, path: PathBuf) -> u32 {
+        let id = self.next_id();
+
+        if self.reserved.len() == MAX_RESERVED_IDS {
+            self.reserved.pop_front();
+        }
+
+        self.reserved.push_back((path, id));
+        id
+    }
+
+    fn take_reserved_id(&mut self, path: &Path) -> Option<u32> {
+        let index = self.reserved.iter().position(|(v, _)| v == path)?;
+        self.reserved.remove(index).map(|(_, id)| id)
+    }
+
+    /// Brings the copy of the queue up to date. Songs that were already in the queue keep their
+    /// IDs, and only the positions whose song changed get the new playlist version.
+    fn sync_queue(&mut self, queue: &[QueueItemData]) {
+        let unchanged = self.queue.len() == queue.len()
+            && self
+                .queue
+                .iter()
+                .zip(queue)
+                .all(|(entry, item)| &entry.path == item.get_path());
+
+        if unchanged {
+            return;
+        }
+
+        self.playlist_version += 1;
+        self.changes[Subsystem::Playlist.index()] += 1;
+
+        let previous = std::mem::take(&mut self.queue);
+        let mut ids: HashMap<&Path, VecDeque<u32>> = HashMap::new();
+
+        for entry in &previous {
+            ids.entry(&entry.path).or_default().push_back(entry.id);
+        }
+
+        for (position, item) in queue.iter().enumerate() {
+            let path = item.get_path();
+            let id = match ids.get_mut(path.as_path()).and_then(|v| v.pop_front()) {
+                Some(id) => id,
+                None => match self.take_reserved_id(path) {
+                    Some(id) => id,
+                    None => self.next_id(),
+                },
+            };
+            let version = match previous.get(position) {
+                Some(entry) if entry.id == id => entry.version,
+                _ => self.playlist_version,
+            };
+
+            self.queue.push(QueueEntry {
+                id,
+                path: path.clone(),
+                version,
+            });
+        }
+    }
+
+    /// The position of the song with the ID.
+    fn position_of(&self, id: u32) -> Result<usize, Ack> {
+        self.queue
+            .iter()
+            .position(|v| v.id == id)
+            .ok_or_else(|| Ack::new(AckCode::NoExist, "No such song"))
+    }
+}
+
+#[derive(Clone)]
+struct MpdState {
+    pool: SqlitePool,
+    commands: Sender<PlaybackCommand>,
+    queue: Arc<RwLock<Vec<QueueItemData>>>,
+    shared: Arc<Mutex<Shared>>,
+    roots: Arc<Vec<PathBuf>>,
+}
+
+impl MpdState {
+    /// Locks the shared state, after bringing its copy of the queue up to date.
+    fn shared(&self) -> MutexGuard<'_, Shared> {
+        // the queue is always locked first
+        let queue = self.queue.read().expect("couldn't get queue");
+        let mut shared = self.shared.lock().expect("couldn't get mpd state");
+        shared.sync_queue(&queue);
+        shared
+    }
+
+    fn handle_event(&self, event: &PlaybackEvent) {
+        let mut shared = self.shared();
+        shared.status.update(event);
+
+        if let Some(subsystem) = Subsystem::for_event(event) {
+            shared.changes[subsystem.index()] += 1;
+        }
+    }
+
+    fn changes(&self) -> [u64; SUBSYSTEMS.len()] {
+        self.shared().changes
+    }
+
+    fn status(&self) -> PlaybackStatus {
+        self.shared().status.clone()
+    }
+
+    fn send(&self, command: PlaybackCommand) -> Result<(), Ack> {
+        self.commands
+            .send(command)
+            .map_err(|_| Ack::new(AckCode::System, "the playback thread is not running"))
+    }
+
+    /// Runs a database query, reporting errors to the client.
+    fn query<T>(
+        &self,
+        query: impl std::future::Future<Output = Result<T, sqlx::Error>>,
+    ) -> Result<T, Ack> {
+        task::block_on(query)
+            .map_err(|e| Ack::new(AckCode::System, format!("database error: {}", e)))
+    }
+
+    /// Returns the URI of the file, which is its path relative to the library folder it is in.
+    /// Files outside of the library are referred to by their absolute path.
+    fn uri(&self, path: &Path) -> String {
+        self.roots
+            .iter()
+            .find_map(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Resolves a URI sent by a client. Only files and folders inside the library folders can be
+    /// accessed.
+    fn resolve(&self, uri: &str) -> Result<PathBuf, Ack> {
+        let uri = uri.strip_prefix("file://").unwrap_or(uri);
+        let candidates: Vec<PathBuf> = if Path::new(uri).is_absolute() {
+            vec![PathBuf::from(uri)]
+        } else {
+            self.roots.iter().map(|root| root.join(uri)).collect()
+        };
+
+        candidates
+            .into_iter()
+            .filter_map(|path| path.canonicalize().ok())
+            .find(|path| {
+                self.roots.iter().any(|root| {
+                    root.canonicalize()
+                        .map(|root| path.starts_with(root))
+                        .unwrap_or(false)
+                })
+            })
+            .ok_or_else(|| Ack::new(AckCode::NoExist, "no such file or directory"))
+    }
+
+    /// Returns the files to add for the URI: either the file itself, or every track in the library
+    /// inside the folder.
+    fn files_for_uri(&self, uri: &str) -> Result<Vec<PathBuf>, Ack> {
+        let path = self.resolve(uri)?;
+
+        if !path.is_dir() {
+            return Ok(vec![path]);
+        }
+
+        Ok(self
+            .query(find_listings_in(&self.pool, &path, true))?
+            .into_iter()
+            .map(|v| v.location)
+            .collect())
+    }
+}
+
+fn tag_values(listing: &TrackListing, tag: Tag, uri: &str) -> Vec<String> {
+    let artist = listing
+        .artist_names
+        .as_ref()
+        .or(listing.album_artist.as_ref());
+
+    match tag {
+        Tag::Artist => artist.map(|v| v.to_string()).into_iter().collect(),
+        Tag::AlbumArtist => listing
+            .album_artist
+            .as_ref()
+            .map(|v| v.to_string())
+            .into_iter()
+            .collect(),
+        Tag::Album => listing
+            .album_title
+            .as_ref()
+            .map(|v| v.to_string())
+            .into_iter()
+            .collect(),
+        Tag::Title => vec![listing.title.to_string()],
+        Tag::Track => listing
+            .track_number
+            .map(|v| v.to_string())
+            .into_iter()
+            .collect(),
+        Tag::Disc => listing
+            .disc_number
+            .map(|v| v.to_string())
+            .into_iter()
+            .collect(),
+        Tag::Date => listing
+            .release_date
+            .map(|v| v.format("%Y").to_string())
+            .into_iter()
+            .collect(),
+        Tag::File | Tag::Base => vec![uri.to_string()],
+        Tag::Any => [
+            Tag::Artist,
+            Tag::AlbumArtist,
+            Tag::Album,
+            Tag::Title,
+            Tag::File,
+        ]
+        .into_iter()
+        .flat_map(|tag| tag_values(listing, tag, uri))
+        .collect(),
+    }
+}
+
+fn write_song(
+    out: &mut String,
+    uri: &str,
+    listing: Option<&TrackListing>,
+    status: Option<&PlaybackStatus>,
+    entry: Option<(usize, u32)>,
+) {
+    writeln!(out, "file: {}", uri).unwrap();
+
+    if let Some(listing) = listing {
+        for tag in TAG_TYPES {
+            for value in tag_values(listing, tag, uri) {
+                writeln!(out, "{}: {}", tag.name(), value).unwrap();
+            }
+        }
+
+        writeln!(out, "Time: {}", listing.duration).unwrap();
+        writeln!(out, "duration: {}.000", listing.duration).unwrap();
+    } else if let Some(status) = status {
+        // not in the library, use what the playback thread read from the file
+        for (name, value) in [
+            ("Title", &status.title),
+            ("Artist", &status.artist),
+            ("Album", &status.album),
+        ] {
+            if let Some(value) = value {
+                writeln!(out, "{}: {}", name, value).unwrap();
+            }
+        }
+
+        writeln!(out, "Time: {}", status.duration).unwrap();
+        writeln!(out, "duration: {}.000", status.duration).unwrap();
+    }
+
+    if let Some((position, id)) = entry {
+        writeln!(out, "Pos: {}", position).unwrap();
+        writeln!(out, "Id: {}", id).unwrap();
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: Option<&String>) -> Result<T, Ack> {
+    let value = value.ok_or_else(|| Ack::arg("missing argument"))?;
+    value
+        .parse()
+        .map_err(|_| Ack::arg(format!("invalid number: {}", value)))
+}
+
+fn parse_bool(value: Option<&String>) -> Result<bool, Ack> {
+    match value.map(|v| v.as_str()) {
+        Some("1") => Ok(true),
+        Some("0") => Ok(false),
+        _ => Err(Ack::arg("expected 0 or 1")),
+    }
+}
+
+/// Parses a `START:END` range, or a single position. Ranges without an end are open.
+fn parse_range(value: &str) -> Result<(usize, usize), Ack> {
+    let invalid = || Ack::arg(format!("invalid range: {}", value));
+
+    match value.split_once(':') {
+        Some((start, "")) => Ok((start.parse().map_err(|_| invalid())?, usize::MAX)),
+        Some((start, end)) => Ok((
+            start.parse().map_err(|_| invalid())?,
+            end.parse().map_err(|_| invalid())?,
+        )),
+        None => {
+            let position: usize = value.parse().map_err(|_| invalid())?;
+            let end = position.checked_add(1).ok_or_else(invalid)?;
+            Ok((position, end))
+        }
+    }
+}
+
+struct Connection {
+    state: MpdState,
+    /// The change counters the client has been told about by `idle`.
+    seen: [u64; SUBSYSTEMS.len()],
+}
+
+impl Connection {
+    /// Writes the songs in the queue for which `include` returns true, given their position and
+    /// queue entry.
+    fn write_queue(&self, out: &mut String, include: impl Fn(usize, &QueueEntry) -> bool) {
+        let (status, entries) = {
+            let shared = self.state.shared();
+            let entries: Vec<(usize, QueueEntry)> = shared
+                .queue
+                .iter()
+                .enumerate()
+                .filter(|(position, entry)| include(*position, entry))
+                .map(|(position, entry)| (position, entry.clone()))
+                .collect();
+
+            (shared.status.clone(), entries)
+        };
+
+        let locations: Vec<PathBuf> = entries.iter().map(|(_, v)| v.path.clone()).collect();
+        let listings: HashMap<PathBuf, TrackListing> = self
+            .state
+            .query(list_track_listings_by_location(
+                &self.state.pool,
+                &locations,
+            ))
+            .unwrap_or_else(|e| {
+                warn!("Unable to read the queue from the library: {}", e.message);
+                Vec::new()
+            })
+            .into_iter()
+            .map(|v| (v.location.clone(), v))
+            .collect();
+
+        for (position, entry) in entries {
+            let current = status.track.as_ref() == Some(&entry.path);
+
+            write_song(
+                out,
+                &self.state.uri(&entry.path),
+                listings.get(&entry.path),
+                current.then_some(&status),
+                Some((position, entry.id)),
+            );
+        }
+    }
+
+    fn find(
+        &self,
+        out: &mut String,
+        arguments: &[String],
+        fold_case: bool,
+        add: bool,
+    ) -> Result<(), Ack> {
+        let filters = parse_filters(arguments)?;
+        let matches = self.state.query(find_listings(
+            &self.state.pool,
+            &self.state.roots,
+            &filters,
+            fold_case,
+        ))?;
+
+        if add {
+            let items = matches
+                .into_iter()
+                .map(|v| QueueItemData::new(v.location, Some(v.id), v.album_id))
+                .collect();
+            return self.state.send(PlaybackCommand::QueueList(items));
+        }
+
+        for listing in matches {
+            write_song(
+                out,
+                &self.state.uri(&listing.location),
+                Some(&listing),
+                None,
+                None,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn list(&self, out: &mut String, arguments: &[String]) -> Result<(), Ack> {
+        let tag = Tag::parse(arguments.first().ok_or_else(|| Ack::arg("missing tag"))?)?;
+        let mut rest = &arguments[1..];
+
+        // `list album "Artist"` is shorthand for `list album artist "Artist"`
+        let shorthand;
+        if tag == Tag::Album && rest.len() == 1 && !rest[0].starts_with('(') {
+            shorthand = [String::from("artist"), rest[0].clone()];
+            rest = &shorthand;
+        }
+
+        let filters: Vec<Filter> = parse_filters(rest)?;
+        let values = self.state.query(list_values(
+            &self.state.pool,
+            &self.state.roots,
+            tag,
+            &filters,
+        ))?;
+
+        for value in values {
+            writeln!(out, "{}: {}", tag.name(), value).unwrap();
+        }
+
+        Ok(())
+    }
+
+    fn lsinfo(&self, out: &mut String, uri: Option<&String>) -> Result<(), Ack> {
+        let directories: Vec<PathBuf> = match uri.filter(|v| !v.is_empty() && *v != "/") {
+            Some(uri) => vec![self.state.resolve(uri)?],
+            None => self.state.roots.to_vec(),
+        };
+
+        for directory in &directories {
+            let Ok(entries) = std::fs::read_dir(directory) else {
+                continue;
+            };
+
+            let mut folders: Vec<PathBuf> = entries
+                .filter_map(|v| v.ok())
+                .map(|v| v.path())
+                .filter(|v| v.is_dir())
+                .collect();
+            folders.sort();
+
+            for folder in folders {
+                writeln!(out, "directory: {}", self.state.uri(&folder)).unwrap();
+            }
+        }
+
+        for directory in &directories {
+            for listing in self
+                .state
+                .query(find_listings_in(&self.state.pool, directory, false))?
+            {
+                write_song(
+                    out,
+                    &self.state.uri(&listing.location),
+                    Some(&listing),
+                    None,
+                    None,
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_stats(&self, out: &mut String) -> Result<(), Ack> {
+        let (artists, albums, songs, playtime) = self.state.query(find_stats(&self.state.pool))?;
+
+        writeln!(out, "artists: {}", artists).unwrap();
+        writeln!(out, "albums: {}", albums).unwrap();
+        writeln!(out, "songs: {}", songs).unwrap();
+        writeln!(out, "db_playtime: {}", playtime).unwrap();
+
+        Ok(())
+    }
+
+    fn write_status(&self, out: &mut String) {
+        let shared = self.state.shared();
+        let status = &shared.status;
+        let length = shared.queue.len();
+
+        let state = match status.state {
+            PlaybackState::Playing => "play",
+            PlaybackState::Paused => "pause",
+            PlaybackState::Stopped => "stop",
+        };
+
+        writeln!(out, "volume: {}", (status.volume * 100.0).round() as u32).unwrap();
+        writeln!(out, "repeat: 0").unwrap();
+        writeln!(out, "random: {}", status.shuffling as u8).unwrap();
+        writeln!(out, "single: 0").unwrap();
+        writeln!(out, "consume: 0").unwrap();
+        writeln!(out, "playlist: {}", shared.playlist_version).unwrap();
+        writeln!(out, "playlistlength: {}", length).unwrap();
+        writeln!(out, "state: {}", state).unwrap();
+
+        if status.track.is_some() && status.queue_position < length {
+            let position = status.queue_position;
+            writeln!(out, "song: {}", position).unwrap();
+            writeln!(out, "songid: {}", shared.queue[position].id).unwrap();
+            writeln!(out, "time: {}:{}", status.position, status.duration).unwrap();
+            writeln!(out, "elapsed: {}.000", status.position).unwrap();
+            writeln!(out, "duration: {}.000", status.duration).unwrap();
+
+            if let Some(next) = shared.queue.get(position + 1) {
+                writeln!(out, "nextsong: {}", position + 1).unwrap();
+                writeln!(out, "nextsongid: {}", next.id).unwrap();
+            }
+        }
+    }
+
+    /// Checks that there is a song at the position in the queue.
+    fn check_position(&self, position: usize) -> Result<usize, Ack> {
+        if position < self.state.shared().queue.len() {
+            Ok(position)
+        } else {
+            Err(Ack::arg("Bad song index"))
+        }
+    }
+
+    fn seek(&self, position: Option<usize>, time: &str) -> Result<(), Ack> {
+        let status = self.state.status();
+        let time: f64 = match time.strip_prefix(['+', '-']) {
+            Some(offset) => {
+                let offset: f64 = parse_number(Some(&offset.to_string()))?;
+                let current = status.position as f64;
+
+                if time.starts_with('-') {
+                    (current - offset).max(0.0)
+                } else {
+                    current + offset
+                }
+            }
+            None => parse_number(Some(&time.to_string()))?,
+        };
+
+        if !time.is_finite() || time < 0.0 {
+            return Err(Ack::arg(format!("invalid time: {}", time)));
+        }
+
+        let changes_track = match position {
+            Some(position) => position != status.queue_position || status.track.is_none(),
+            None => false,
+        };
+
+        if changes_track {
+            self.state.send(PlaybackCommand::Jump(position.unwrap()))?;
+        } else if status.track.is_none() {
+            return Err(Ack::new(AckCode::PlayerSync, "Not playing"));
+        } else if status.duration > 0 && time > status.duration as f64 {
+            return Err(Ack::arg("seek past the end of the song"));
+        }
+
+        self.state.send(PlaybackCommand::Seek(time))
+    }
+
+    /// Runs a single command, writing the response (without the final OK) to `out`.
+    fn execute(&self, out: &mut String, command: &str, args: &[String]) -> Result<(), Ack> {
+        let state = &self.state;
+
+        match command {
+            "ping" | "password" | "binarylimit" | "decoders" | "urlhandlers" => (),
+            // enabling or disabling tag types is accepted, but all tags are always sent
+            "tagtypes" if !args.is_empty() => (),
+            "tagtypes" => {
+                for tag in TAG_TYPES {
+                    writeln!(out, "tagtype: {}", tag.name()).unwrap();
+                }
+            }
+            "status" => self.write_status(out),
+            "stats" => self.write_stats(out)?,
+            "outputs" => {
+                writeln!(out, "outputid: 0").unwrap();
+                writeln!(out, "outputname: Muzak").unwrap();
+                writeln!(out, "plugin: muzak").unwrap();
+                writeln!(out, "outputenabled: 1").unwrap();
+            }
+            "replay_gain_status" => writeln!(out, "replay_gain_mode: off").unwrap(),
+            "commands" => {
+                for name in COMMANDS {
+                    writeln!(out, "command: {}", name).unwrap();
+                }
+            }
+            "notcommands" => (),
+            "currentsong" => {
+                let (status, id) = {
+                    let shared = state.shared();
+                    let id = shared.queue.get(shared.status.queue_position).map(|v| v.id);
+                    (shared.status.clone(), id)
+                };
+
+                if let Some(track) = &status.track {
+                    let listing = task::block_on(get_track_listing_by_location(&state.pool, track))
+                        .ok()
+                        .flatten();
+
+                    write_song(
+                        out,
+                        &state.uri(track),
+                        listing.as_ref(),
+                        Some(&status),
+                        id.map(|id| (status.queue_position, id)),
+                    );
+                }
+            }
+            "playlistinfo" => {
+                let range = match args.first() {
+                    Some(v) => Some(parse_range(v)?),
+                    None => None,
+                };
+
+                self.write_queue(out, |position, _| {
+                    range.is_none_or(|(start, end)| position >= start && position < end)
+                });
+            }
+            "playlistid" => match args.first() {
+                Some(v) => {
+                    let id: u32 = parse_number(Some(v))?;
+                    state.shared().position_of(id)?;
+                    self.write_queue(out, |_, entry| entry.id == id);
+                }
+                None => self.write_queue(out, |_, _| true),
+            },
+            "plchanges" => {
+                let version: u32 = parse_number(args.first())?;
+                let range = match args.get(1) {
+                    Some(v) => Some(parse_range(v)?),
+                    None => None,
+                };
+
+                self.write_queue(out, |position, entry| {
+                    entry.version > version
+                        && range.is_none_or(|(start, end)| position >= start && position < end)
+                });
+            }
+            "plchangesposid" => {
+                let version: u32 = parse_number(args.first())?;
+
+                for (position, entry) in state.shared().queue.iter().enumerate() {
+                    if entry.version > version {
+                        writeln!(out, "cpos: {}", position).unwrap();
+                        writeln!(out, "Id: {}", entry.id).unwrap();
+                    }
+                }
+            }
+            "add" => {
+                let uri = args.first().ok_or_else(|| Ack::arg("missing URI"))?;
+                let files = state.files_for_uri(uri)?;
+
+                state.send(PlaybackCommand::QueueList(
+                    files
+                        .into_iter()
+                        .map(|path| QueueItemData::new(path, None, None))
+                        .collect(),
+                ))?;
+            }
+            "addid" => {
+                let uri = args.first().ok_or_else(|| Ack::arg("missing URI"))?;
+
+                if args.len() > 1 {
+                    return Err(Ack::arg("adding at a position is not supported"));
+                }
+
+                let path = state.resolve(uri)?;
+
+                if path.is_dir() {
+                    return Err(Ack::arg("addid only adds single songs"));
+                }
+
+                let id = state.shared().reserve_id(path.clone());
+                state.send(PlaybackCommand::Queue(QueueItemData::new(path, None, None)))?;
+
+                writeln!(out, "Id: {}", id).unwrap();
+            }
+            "clear" => state.send(PlaybackCommand::ClearQueue)?,
+            "play" => match args.first() {
+                Some(v) => {
+                    let position = self.check_position(parse_number(Some(v))?)?;
+                    state.send(PlaybackCommand::Jump(position))?;
+                }
+                None => state.send(PlaybackCommand::Play)?,
+            },
+            "playid" => match args.first() {
+                Some(v) => {
+                    let position = state.shared().position_of(parse_number(Some(v))?)?;
+                    state.send(PlaybackCommand::Jump(position))?;
+                }
+                None => state.send(PlaybackCommand::Play)?,
+            },
+            "pause" => {
+                let pause = match args.first() {
+                    Some(_) => parse_bool(args.first())?,
+                    None => state.status().state == PlaybackState::Playing,
+                };
+
+                state.send(if pause {
+                    PlaybackCommand::Pause
+                } else {
+                    PlaybackCommand::Play
+                })?;
+            }
+            "stop" => state.send(PlaybackCommand::Stop)?,
+            "next" => state.send(PlaybackCommand::Next)?,
+            "previous" => state.send(PlaybackCommand::Previous)?,
+            "seek" => {
+                let position = self.check_position(parse_number(args.first())?)?;
+                let time = args.get(1).ok_or_else(|| Ack::arg("missing time"))?;
+                self.seek(Some(position), time)?;
+            }
+            "seekid" => {
+                let position = state.shared().position_of(parse_number(args.first())?)?;
+                let time = args.get(1).ok_or_else(|| Ack::arg("missing time"))?;
+                self.seek(Some(position), time)?;
+            }
+            "seekcur" => {
+                let time = args.first().ok_or_else(|| Ack::arg("missing time"))?;
+                self.seek(None, time)?;
+            }
+            "setvol" => {
+                let volume: u32 = parse_number(args.first())?;
+                state.send(PlaybackCommand::SetVolume(volume.min(100) as f64 / 100.0))?;
+            }
+            "random" => {
+                if parse_bool(args.first())? != state.status().shuffling {
+                    state.send(PlaybackCommand::ToggleShuffle)?;
+                }
+            }
+            "repeat" | "single" | "consume" => {
+                if parse_bool(args.first())? {
+                    return Err(Ack::arg(format!("{} is not supported", command)));
+                }
+            }
+            "list" => self.list(out, args)?,
+            "find" => self.find(out, args, false, false)?,
+            "search" => self.find(out, args, true, false)?,
+            "findadd" => self.find(out, args, false, true)?,
+            "searchadd" => self.find(out, args, true, true)?,
+            "lsinfo" => self.lsinfo(out, args.first())?,
+            _ => {
+                return Err(Ack::new(
+                    AckCode::Unknown,
+                    format!("unknown command \"{}\"", command),
+                ))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs a command line, returning the response including the final OK or ACK.
+    fn run_line(&self, line: &str) -> String {
+        let mut out = String::new();
+
+        match parse_arguments(line) {
+            Ok(arguments) if !arguments.is_empty() => {
+                let command = arguments[0].to_lowercase();
+
+                match self.execute(&mut out, &command, &arguments[1..]) {
+                    Ok(_) => out.push_str("OK\n"),
+                    Err(ack) => return ack.format(0, &command),
+                }
+            }
+            Ok(_) => return Ack::new(AckCode::Unknown, "no command given").format(0, ""),
+            Err(ack) => return ack.format(0, ""),
+        }
+
+        out
+    }
+
+    fn run_list(&self, lines: &[String], list_ok: bool) -> String {
+        let mut out = String::new();
+
+        for (index, line) in lines.iter().enumerate() {
+            let arguments = match parse_arguments(line) {
+                Ok(arguments) if !arguments.is_empty() => arguments,
+                Ok(_) => continue,
+                Err(ack) => {
+                    out.push_str(&ack.format(index, ""));
+                    return out;
+                }
+            };
+            let command = arguments[0].to_lowercase();
+
+            if let Err(ack) = self.execute(&mut out, &command, &arguments[1..]) {
+                out.push_str(&ack.format(index, &command));
+                return out;
+            }
+
+            if list_ok {
+                out.push_str("list_OK\n");
+            }
+        }
+
+        out.push_str("OK\n");
+        out
+    }
+
+    /// Waits until one of the subsystems changes, or the client cancels with `noidle`. Returns
+    /// None if the client disconnected.
+    fn idle(&mut self, args: &[String], lines: &Receiver<String>) -> Option<String> {
+        let wanted: Vec<Subsystem> = args
+            .iter()
+            .filter_map(|name| {
+                SUBSYSTEMS
+                    .iter()
+                    .find(|v| v.name() == name.to_lowercase())
+                    .copied()
+            })
+            .collect();
+
+        loop {
+            let changes = self.state.changes();
+            let mut out = String::new();
+
+            for (index, subsystem) in SUBSYSTEMS.iter().enumerate() {
+                let interested = wanted.is_empty() || wanted.contains(subsystem);
+
+                if interested && changes[index] != self.seen[index] {
+                    writeln!(out, "changed: {}", subsystem.name()).unwrap();
+                    self.seen[index] = changes[index];
+                }
+            }
+
+            if !out.is_empty() {
+                out.push_str("OK\n");
+                return Some(out);
+            }
+
+            match lines.recv_timeout(IDLE_POLL_INTERVAL) {
+                Ok(line) if line.trim() == "noidle" => return Some("OK\n".to_string()),
+                Ok(line) => {
+                    // only noidle is allowed while idle
+                    warn!("MPD client sent {:?} while idle, disconnecting", line);
+                    return None;
+                }
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+    }
+}
+
+const COMMANDS: &[&str] = &[
+    "add",
+    "addid",
+    "clear",
+    "close",
+    "command_list_begin",
+    "command_list_end",
+    "command_list_ok_begin",
+    "commands",
+    "currentsong",
+    "decoders",
+    "find",
+    "findadd",
+    "idle",
+    "list",
+    "lsinfo",
+    "next",
+    "noidle",
+    "notcommands",
+    "outputs",
+    "pause",
+    "ping",
+    "play",
+    "playid",
+    "playlistid",
+    "playlistinfo",
+    "plchanges",
+    "plchangesposid",
+    "previous",
+    "random",
+    "replay_gain_status",
+    "search",
+    "searchadd",
+    "seek",
+    "seekcur",
+    "seekid",
+    "setvol",
+    "stats",
+    "status",
+    "stop",
+    "tagtypes",
+];
+
+fn handle_connection(stream: TcpStream, state: MpdState) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    writer.write_all(format!("OK MPD {}\n", PROTOCOL_VERSION).as_bytes())?;
+
+    // lines are read on their own thread, so that `idle` can wait for changes and for `noidle`
+    // at the same time
+    let (lines_tx, lines_rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+
+            if lines_tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut connection = Connection {
+        seen: state.changes(),
+        state,
+    };
+
+    while let Ok(line) = lines_rx.recv() {
+        debug!("MPD command: {}", line);
+        let trimmed = line.trim();
+
+        let response = if trimmed == "close" {
+            return Ok(());
+        } else if trimmed == "command_list_begin" || trimmed == "command_list_ok_begin" {
+            let mut lines = Vec::new();
+
+            loop {
+                let Ok(line) = lines_rx.recv() else {
+                    return Ok(());
+                };
+
+                if line.trim() == "command_list_end" {
+                    break;
+                }
+
+                lines.push(line);
+            }
+
+            connection.run_list(&lines, trimmed == "command_list_ok_begin")
+        } else if trimmed == "idle" || trimmed.starts_with("idle ") {
+            let args = parse_arguments(trimmed).unwrap_or_default();
+
+            match connection.idle(&args[1..], &lines_rx) {
+                Some(response) => response,
+                None => return Ok(()),
+            }
+        } else if trimmed == "noidle" {
+            // the client wasn't idle, there is nothing to cancel
+            continue;
+        } else {
+            connection.run_line(trimmed)
+        };
+
+        writer.write_all(response.as_bytes())?;
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Starts a server speaking the MPD protocol, so that MPD clients can control playback and browse
+/// the library. Connections are handled on their own threads.
+pub fn start_mpd_server(
+    settings: &MpdSettings,
+    roots: Vec<PathBuf>,
+    pool: SqlitePool,
+    commands: Sender<PlaybackCommand>,
+    events: Receiver<PlaybackEvent>,
+    queue: Arc<RwLock<Vec<QueueItemData>>>,
+) {
+    if !settings.allow_network && !is_loopback(&settings.address) {
+        error!(
+            "The MPD server is set to listen on {}, but mpd.allow_network is not set",
+            settings.address
+        );
+        warn!("The MPD server will not be started.");
+        return;
+    }
+
+    let listener = match TcpListener::bind((settings.address.as_str(), settings.port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!(
+                "Unable to start the MPD server on {}:{}: {:?}",
+                settings.address, settings.port, e
+            );
+            return;
+        }
+    };
+
+    info!(
+        "Starting MPD server on {}:{}",
+        settings.address, settings.port
+    );
+
+    let state = MpdState {
+        pool,
+        commands,
+        queue,
+        shared: Arc::new(Mutex::new(Shared::default())),
+        roots: Arc::new(roots),
+    };
+
+    serve(listener, state, events);
+}
+
+/// Handles the connections to the listener, and keeps the state up to date with the playback
+/// events.
+fn serve(listener: TcpListener, state: MpdState, events: Receiver<PlaybackEvent>) {
+    let event_state = state.clone();
+
+    thread::Builder::new()
+        .name("mpd-events".to_string())
+        .spawn(move || {
+            while let Ok(event) = events.recv() {
+                event_state.handle_event(&event);
+            }
+        })
+        .expect("could not start mpd event thread");
+
+    thread::Builder::new()
+        .name("mpd".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        warn!("Failed to accept MPD connection: {:?}", e);
+                        continue;
+                    }
+                };
+                let state = state.clone();
+
+                thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream, state) {
+                        debug!("MPD connection closed: {:?}", e);
+                    }
+                });
+            }
+        })
+        .expect("could not start mpd thread");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::{TcpListener, TcpStream},
+        path::PathBuf,
+        sync::{
+            mpsc::{channel, Receiver, Sender},
+            Arc, Mutex, RwLock,
+        },
+    };
+
+    use async_std::task;
+
+    use crate::{
+        library::db::create_memory_pool,
+        playback::{
+            events::{PlaybackCommand, PlaybackEvent},
+            queue::QueueItemData,
+        },
+    };
+
+    use super::{serve, MpdState, Shared};
+
+    const LIBRARY: &str = "
+        INSERT INTO artist (id, name, name_sortable) VALUES (1, 'Artist', 'Artist');
+        INSERT INTO album (id, title, title_sortable, artist_id, release_date)
+            VALUES (1, 'Album', 'Album', 1, '2020-05-01 00:00:00+00:00');
+        INSERT INTO track
+            (id, title, title_sortable, album_id, track_number, disc_number, duration, location,
+             artist_names)
+        VALUES
+            (1, 'One', 'One', 1, 1, 1, 100, '/music/Artist/Album/01.flac', NULL),
+            (2, 'Two', 'Two', 1, 2, 1, 200, '/music/Artist/Album/02.flac', NULL),
+            (3, 'Three', 'Three', NULL, NULL, NULL, 300, '/music/Other/03.flac', 'Someone');
+    ";
+
+    /// An MPD client connected to a server with a small library, that plays the part of the
+    /// playback thread by keeping the commands it receives and changing the queue itself.
+    struct Client {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream,
+        commands: Receiver<PlaybackCommand>,
+        queue: Arc<RwLock<Vec<QueueItemData>>>,
+        _events: Sender<PlaybackEvent>,
+    }
+
+    impl Client {
+        fn connect() -> Client {
+            let pool = task::block_on(async {
+                let pool = create_memory_pool().await;
+                sqlx::raw_sql(LIBRARY).execute(&pool).await.unwrap();
+                pool
+            });
+            let (commands_tx, commands) = channel();
+            let (events, events_rx) = channel();
+            let queue = Arc::new(RwLock::new(Vec::new()));
+
+            let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+            let address = listener.local_addr().unwrap();
+            let state = MpdState {
+                pool,
+                commands: commands_tx,
+                queue: queue.clone(),
+                shared: Arc::new(Mutex::new(Shared::default())),
+                roots: Arc::new(vec![PathBuf::from("/music")]),
+            };
+
+            serve(listener, state, events_rx);
+
+            let writer = TcpStream::connect(address).unwrap();
+            let mut client = Client {
+                reader: BufReader::new(writer.try_clone().unwrap()),
+                writer,
+                commands,
+                queue,
+                _events: events,
+            };
+
+            assert_eq!(client.read_line(), "OK MPD 0.23.0");
+            client
+        }
+
+        fn read_line(&mut self) -> String {
+            let mut line = String::new();
+            self.reader.read_line(&mut line).unwrap();
+            line.trim_end().to_string()
+        }
+
+        /// Sends a command, and returns the response without the final OK.
+        fn send(&mut self, command: &str) -> Vec<String> {
+            writeln!(self.writer, "{}", command).unwrap();
+            let mut lines = Vec::new();
+
+            loop {
+                match self.read_line() {
+                    line if line == "OK" => return lines,
+                    line if line.starts_with("ACK ") => panic!("{} failed: {}", command, line),
+                    line => lines.push(line),
+                }
+            }
+        }
+
+        /// Sends a command that should fail, and returns the error.
+        fn send_err(&mut self, command: &str) -> String {
+            writeln!(self.writer, "{}", command).unwrap();
+
+            loop {
+                match self.read_line() {
+                    line if line == "OK" => panic!("{} should have failed", command),
+                    line if line.starts_with("ACK ") => return line,
+                    _ => (),
+                }
+            }
+        }
+
+        /// Returns the values of the field in the response, in order.
+        fn values(&mut self, command: &str, field: &str) -> Vec<String> {
+            let prefix = format!("{}: ", field);
+
+            self.send(command)
+                .into_iter()
+                .filter_map(|line| line.strip_prefix(&prefix).map(str::to_string))
+                .collect()
+        }
+
+        fn set_queue(&self, files: &[&str]) {
+            *self.queue.write().unwrap() = files
+                .iter()
+                .map(|v| QueueItemData::new(PathBuf::from("/music").join(v), None, None))
+                .collect();
+        }
+    }
+
+    #[test]
+    fn finds_and_lists_songs() {
+        let mut client = Client::connect();
+
+        assert_eq!(
+            client.values(r#"find album "Album""#, "file"),
+            ["Artist/Album/01.flac", "Artist/Album/02.flac"]
+        );
+        assert_eq!(
+            client.values(r#"find "(title != 'One')""#, "Title"),
+            ["Three", "Two"]
+        );
+        assert_eq!(
+            client.values(r#"search title "T""#, "Title"),
+            ["Three", "Two"]
+        );
+        assert_eq!(client.values(r#"search any "someone""#, "Title"), ["Three"]);
+        assert_eq!(
+            client.values(r#"find base "Artist""#, "file"),
+            ["Artist/Album/01.flac", "Artist/Album/02.flac"]
+        );
+        assert_eq!(
+            client.values("list artist", "Artist"),
+            ["Artist", "Someone"]
+        );
+        assert_eq!(client.values(r#"list album "Artist""#, "Album"), ["Album"]);
+        assert_eq!(client.values("list date", "Date"), ["2020"]);
+        assert_eq!(
+            client.send("stats"),
+            ["artists: 1", "albums: 1", "songs: 3", "db_playtime: 600"]
+        );
+    }
+
+    #[test]
+    fn song_ids_follow_songs() {
+        let mut client = Client::connect();
+
+        client.set_queue(&["Artist/Album/01.flac", "Artist/Album/02.flac"]);
+        let ids = client.values("playlistinfo", "Id");
+        let version: u32 = client.values("status", "playlist")[0].parse().unwrap();
+
+        // the songs swap places, and a new song is added at the end
+        client.set_queue(&[
+            "Artist/Album/02.flac",
+            "Artist/Album/01.flac",
+            "Other/03.flac",
+        ]);
+
+        let moved = client.values("playlistinfo", "Id");
+        assert_eq!(moved[..2], [ids[1].clone(), ids[0].clone()]);
+        assert!(!ids.contains(&moved[2]));
+
+        // only the songs that moved are reported, at their new positions
+        client.set_queue(&["Artist/Album/02.flac", "Artist/Album/01.flac"]);
+        let changes = client.send(&format!("plchangesposid {}", version));
+        assert_eq!(
+            changes,
+            [
+                "cpos: 0".to_string(),
+                format!("Id: {}", ids[1]),
+                "cpos: 1".to_string(),
+                format!("Id: {}", ids[0]),
+            ]
+        );
+
+        let version: u32 = client.values("status", "playlist")[0].parse().unwrap();
+        assert!(client.send(&format!("plchanges {}", version)).is_empty());
+    }
+
+    #[test]
+    fn commands_use_song_ids() {
+        let mut client = Client::connect();
+
+        client.set_queue(&["Artist/Album/01.flac", "Artist/Album/02.flac"]);
+        let ids = client.values("playlistinfo", "Id");
+
+        client.send(&format!("playid {}", ids[1]));
+        assert!(matches!(
+            client.commands.try_recv(),
+            Ok(PlaybackCommand::Jump(1))
+        ));
+
+        client.send(&format!("seekid {} 10", ids[0]));
+        assert!(matches!(
+            client.commands.try_recv(),
+            Ok(PlaybackCommand::Jump(0))
+        ));
+        assert!(matches!(
+            client.commands.try_recv(),
+            Ok(PlaybackCommand::Seek(time)) if time == 10.0
+        ));
+
+        assert!(client.send_err("playid 1000").starts_with("ACK [50@0]"));
+        assert!(client.send_err("play 2").starts_with("ACK [2@0]"));
+        assert!(client.send_err("seek 0 soon").starts_with("ACK [2@0]"));
+        assert!(client.commands.try_recv().is_err());
+    }
+
+    #[test]
+    fn out_of_range_positions_are_rejected() {
+        let mut client = Client::connect();
+
+        assert!(client
+            .send_err(&format!("playlistinfo {}", usize::MAX))
+            .starts_with("ACK [2@0]"));
+        assert!(client.send_err("playlistinfo x:2").starts_with("ACK [2@0]"));
+    }
+}
@@ -0,0 +1,291 @@
+use std::fmt::Display;
+
+/// The error codes used in ACK responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AckCode {
+    Arg = 2,
+    Unknown = 5,
+    NoExist = 50,
+    System = 52,
+    PlayerSync = 55,
+}
+
+/// An error response. MPD clients expect errors in the form
+/// `ACK [code@index] {command} message`, where the index is the position of the failing command
+/// in a command list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ack {
+    pub code: AckCode,
+    pub message: String,
+}
+
+impl Ack {
+    pub fn new(code: AckCode, message: impl Display) -> Self {
+        Ack {
+            code,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn arg(message: impl Display) -> Self {
+        Ack::new(AckCode::Arg, message)
+    }
+
+    pub fn format(&self, index: usize, command: &str) -> String {
+        format!(
+            "ACK [{}@{}] {{{}}} {}\n",
+            self.code as u8, index, command, self.message
+        )
+    }
+}
+
+/// Splits a command line into its arguments. Arguments are separated by whitespace, and may be
+/// quoted with double quotes, in which case backslashes escape the next character.
+pub fn parse_arguments(line: &str) -> Result<Vec<String>, Ack> {
+    let mut arguments = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut argument = String::new();
+
+        if c == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(c) => argument.push(c),
+                        None => return Err(Ack::arg("unterminated quoted argument")),
+                    },
+                    Some(c) => argument.push(c),
+                    None => return Err(Ack::arg("unterminated quoted argument")),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                argument.push(c);
+                chars.next();
+            }
+        }
+
+        arguments.push(argument);
+    }
+
+    Ok(arguments)
+}
+
+/// A tag that songs can be filtered or listed by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tag {
+    Artist,
+    AlbumArtist,
+    Album,
+    Title,
+    Track,
+    Disc,
+    Date,
+    File,
+    /// Matches if the song is in the given directory.
+    Base,
+    /// Matches any of the tags.
+    Any,
+}
+
+impl Tag {
+    pub fn parse(name: &str) -> Result<Tag, Ack> {
+        match name.to_lowercase().as_str() {
+            "artist" | "artistsort" => Ok(Tag::Artist),
+            "albumartist" | "albumartistsort" => Ok(Tag::AlbumArtist),
+            "album" | "albumsort" => Ok(Tag::Album),
+            "title" | "titlesort" => Ok(Tag::Title),
+            "track" => Ok(Tag::Track),
+            "disc" => Ok(Tag::Disc),
+            "date" | "originaldate" => Ok(Tag::Date),
+            "file" => Ok(Tag::File),
+            "base" => Ok(Tag::Base),
+            "any" => Ok(Tag::Any),
+            _ => Err(Ack::arg(format!("unknown tag type: {}", name))),
+        }
+    }
+
+    /// The name of the tag in responses.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tag::Artist => "Artist",
+            Tag::AlbumArtist => "AlbumArtist",
+            Tag::Album => "Album",
+            Tag::Title => "Title",
+            Tag::Track => "Track",
+            Tag::Disc => "Disc",
+            Tag::Date => "Date",
+            Tag::File => "file",
+            Tag::Base => "base",
+            Tag::Any => "any",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Equals,
+    NotEquals,
+    Contains,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    pub tag: Tag,
+    pub operator: Operator,
+    pub value: String,
+}
+
+/// Parses the filters of `find`, `search`, `list` and friends. Both the old syntax
+/// (`find artist "Name" album "Title"`) and filter expressions
+/// (`find "((artist == 'Name') AND (album == 'Title'))"`) are supported. All filters must match.
+pub fn parse_filters(arguments: &[String]) -> Result<Vec<Filter>, Ack> {
+    let mut filters = Vec::new();
+    let mut iter = arguments.iter();
+
+    while let Some(argument) = iter.next() {
+        if argument.starts_with('(') {
+            parse_expression(argument, &mut filters)?;
+            continue;
+        }
+
+        // the grouping of `list` is not supported, the results are always flat
+        if argument.eq_ignore_ascii_case("group") {
+            iter.next();
+            continue;
+        }
+
+        let value = iter
+            .next()
+            .ok_or_else(|| Ack::arg(format!("missing value for {}", argument)))?;
+
+        filters.push(Filter {
+            tag: Tag::parse(argument)?,
+            operator: Operator::Equals,
+            value: value.clone(),
+        });
+    }
+
+    Ok(filters)
+}
+
+fn parse_expression(expression: &str, filters: &mut Vec<Filter>) -> Result<(), Ack> {
+    let expression = expression.trim();
+    let inner = expression
+        .strip_prefix('(')
+        .and_then(|v| v.strip_suffix(')'))
+        .ok_or_else(|| Ack::arg(format!("invalid filter expression: {}", expression)))?
+        .trim();
+
+    if inner.starts_with('(') {
+        for part in split_groups(inner)? {
+            parse_expression(&part, filters)?;
+        }
+
+        return Ok(());
+    }
+
+    let (tag, rest) = inner
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| Ack::arg(format!("invalid filter expression: {}", expression)))?;
+    let rest = rest.trim_start();
+    let (operator, rest) = if let Some(rest) = rest.strip_prefix("==") {
+        (Operator::Equals, rest)
+    } else if let Some(rest) = rest.strip_prefix("!=") {
+        (Operator::NotEquals, rest)
+    } else if let Some(rest) = rest.strip_prefix("contains") {
+        (Operator::Contains, rest)
+    } else if tag.eq_ignore_ascii_case("base") {
+        (Operator::Equals, rest)
+    } else {
+        return Err(Ack::arg(format!("unsupported filter: {}", expression)));
+    };
+
+    filters.push(Filter {
+        tag: Tag::parse(tag)?,
+        operator,
+        value: parse_quoted(rest.trim())?,
+    });
+
+    Ok(())
+}
+
+/// Splits `(a) AND (b) AND (c)` into its parenthesized groups.
+fn split_groups(expression: &str) -> Result<Vec<String>, Ack> {
+    let mut groups = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    let mut quote = None;
+    let mut escaped = false;
+
+    for c in expression.chars() {
+        if depth > 0 {
+            current.push(c);
+        }
+
+        if escaped {
+            escaped = false;
+            continue;
+        }
+
+        match (c, quote) {
+            ('\\', Some(_)) => escaped = true,
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (_, Some(_)) => (),
+            ('(', None) => {
+                if depth == 0 {
+                    current.push(c);
+                }
+                depth += 1;
+            }
+            (')', None) => {
+                depth -= 1;
+                if depth == 0 {
+                    groups.push(std::mem::take(&mut current));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if depth != 0 || quote.is_some() {
+        return Err(Ack::arg("unbalanced filter expression"));
+    }
+
+    Ok(groups)
+}
+
+fn parse_quoted(value: &str) -> Result<String, Ack> {
+    let mut chars = value.chars();
+    let quote = match chars.next() {
+        Some(c @ ('"' | '\'')) => c,
+        _ => return Err(Ack::arg("filter values must be quoted")),
+    };
+
+    let mut result = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    result.push(c);
+                }
+            }
+            c if c == quote => return Ok(result),
+            c => result.push(c),
+        }
+    }
+
+    Err(Ack::arg("unterminated filter value"))
+}
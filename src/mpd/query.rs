@@ -0,0 +1,277 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf, MAIN_SEPARATOR_STR},
+};
+
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+
+use crate::library::types::TrackListing;
+
+use super::protocol::{Filter, Operator, Tag};
+
+const SELECT_LISTINGS: &str = include_str!("../../queries/mpd/select_track_listings.sql");
+
+const ORDER_LISTINGS: &str = "
+ORDER BY
+    artist.name_sortable COLLATE NOCASE ASC,
+    album.title_sortable COLLATE NOCASE ASC,
+    track.disc_number ASC,
+    track.track_number ASC";
+
+/// The tags searched by `any`.
+const ANY_TAGS: [Tag; 5] = [
+    Tag::Artist,
+    Tag::AlbumArtist,
+    Tag::Album,
+    Tag::Title,
+    Tag::File,
+];
+
+/// The path of the folder with a trailing separator, so that it only matches paths inside it.
+fn folder_prefix(folder: &Path) -> String {
+    let folder = folder.to_string_lossy();
+    format!(
+        "{}{}",
+        folder.trim_end_matches(MAIN_SEPARATOR_STR),
+        MAIN_SEPARATOR_STR
+    )
+}
+
+/// Pushes the URI of the track, which is its location relative to the first library folder that
+/// contains it (see `MpdState::uri`).
+fn push_uri(builder: &mut QueryBuilder<'_, Sqlite>, roots: &[PathBuf]) {
+    if roots.is_empty() {
+        builder.push("track.location");
+        return;
+    }
+
+    builder.push("(CASE");
+
+    for root in roots {
+        let prefix = folder_prefix(root);
+        // substr counts characters rather than bytes
+        let length = prefix.chars().count() as i64;
+
+        builder
+            .push(" WHEN substr(track.location, 1, ")
+            .push_bind(length)
+            .push(") = ")
+            .push_bind(prefix)
+            .push(" THEN substr(track.location, ")
+            .push_bind(length + 1)
+            .push(")");
+    }
+
+    builder.push(" ELSE track.location END)");
+}
+
+/// Pushes the value of the tag. This has to match the values sent for songs (see `tag_values`).
+fn push_tag(builder: &mut QueryBuilder<'_, Sqlite>, tag: Tag, roots: &[PathBuf]) {
+    let column = match tag {
+        Tag::Artist => "COALESCE(track.artist_names, artist.name)",
+        Tag::AlbumArtist => "artist.name",
+        Tag::Album => "album.title",
+        Tag::Title => "track.title",
+        Tag::Track => "CAST(track.track_number AS TEXT)",
+        Tag::Disc => "CAST(track.disc_number AS TEXT)",
+        Tag::Date => "strftime('%Y', album.release_date)",
+        Tag::File | Tag::Base | Tag::Any => return push_uri(builder, roots),
+    };
+
+    builder.push(column);
+}
+
+/// Pushes a condition that is true if the value of the tag matches the filter, ignoring whether
+/// the filter is negated. If `fold_case` is set, values are compared case-insensitively, and `==`
+/// matches substrings (the behaviour of `search`). Only ASCII letters are folded, like SQLite's
+/// `lower`.
+fn push_comparison(
+    builder: &mut QueryBuilder<'_, Sqlite>,
+    tag: Tag,
+    filter: &Filter,
+    fold_case: bool,
+    roots: &[PathBuf],
+) {
+    let value = if fold_case {
+        filter.value.to_ascii_lowercase()
+    } else {
+        filter.value.clone()
+    };
+    let push_value = |builder: &mut QueryBuilder<'_, Sqlite>| {
+        if fold_case {
+            builder.push("lower(");
+            push_tag(builder, tag, roots);
+            builder.push(")");
+        } else {
+            push_tag(builder, tag, roots);
+        }
+    };
+
+    // tracks without the tag don't match
+    builder.push("COALESCE(");
+
+    match filter.operator {
+        Operator::Contains => {
+            builder.push("instr(");
+            push_value(builder);
+            builder.push(", ").push_bind(value).push(") > 0");
+        }
+        _ if tag == Tag::Base => {
+            let prefix = format!("{}/", value.trim_end_matches('/'));
+
+            builder.push("(");
+            push_value(builder);
+            builder.push(" = ").push_bind(value).push(" OR substr(");
+            push_value(builder);
+            builder
+                .push(", 1, ")
+                .push_bind(prefix.chars().count() as i64)
+                .push(") = ")
+                .push_bind(prefix)
+                .push(")");
+        }
+        _ if fold_case => {
+            builder.push("instr(");
+            push_value(builder);
+            builder.push(", ").push_bind(value).push(") > 0");
+        }
+        _ => {
+            push_value(builder);
+            builder.push(" = ").push_bind(value);
+        }
+    }
+
+    builder.push(", 0)");
+}
+
+/// Pushes a WHERE clause that is true for the tracks that match all of the filters.
+fn push_filters(
+    builder: &mut QueryBuilder<'_, Sqlite>,
+    filters: &[Filter],
+    fold_case: bool,
+    roots: &[PathBuf],
+) {
+    builder.push(" WHERE 1");
+
+    for filter in filters {
+        builder.push(" AND ");
+
+        if filter.operator == Operator::NotEquals {
+            builder.push("NOT ");
+        }
+
+        let tags = match filter.tag {
+            Tag::Any => &ANY_TAGS[..],
+            ref tag => std::slice::from_ref(tag),
+        };
+
+        builder.push("(");
+
+        for (index, tag) in tags.iter().enumerate() {
+            if index > 0 {
+                builder.push(" OR ");
+            }
+
+            push_comparison(builder, *tag, filter, fold_case, roots);
+        }
+
+        builder.push(")");
+    }
+}
+
+/// Finds the tracks in the library that match all of the filters.
+pub async fn find_listings(
+    pool: &SqlitePool,
+    roots: &[PathBuf],
+    filters: &[Filter],
+    fold_case: bool,
+) -> Result<Vec<TrackListing>, sqlx::Error> {
+    let mut builder = QueryBuilder::new(SELECT_LISTINGS);
+    push_filters(&mut builder, filters, fold_case, roots);
+    builder.push(ORDER_LISTINGS);
+
+    builder
+        .build_query_as::<TrackListing>()
+        .fetch_all(pool)
+        .await
+}
+
+/// Finds the distinct values of the tag among the tracks that match all of the filters, in
+/// ascending order.
+pub async fn list_values(
+    pool: &SqlitePool,
+    roots: &[PathBuf],
+    tag: Tag,
+    filters: &[Filter],
+) -> Result<BTreeSet<String>, sqlx::Error> {
+    if tag == Tag::Any {
+        let mut values = BTreeSet::new();
+
+        for tag in ANY_TAGS {
+            values.extend(Box::pin(list_values(pool, roots, tag, filters)).await?);
+        }
+
+        return Ok(values);
+    }
+
+    let mut builder = QueryBuilder::new("SELECT DISTINCT ");
+    push_tag(&mut builder, tag, roots);
+    builder.push(
+        " AS value
+FROM
+    track
+    LEFT JOIN album ON track.album_id = album.id
+    LEFT JOIN artist ON album.artist_id = artist.id",
+    );
+    push_filters(&mut builder, filters, false, roots);
+    builder.push(" AND value IS NOT NULL");
+
+    let values = builder
+        .build_query_scalar::<String>()
+        .fetch_all(pool)
+        .await?;
+
+    Ok(values.into_iter().collect())
+}
+
+/// Finds the tracks inside the folder. If `recursive` is false, the tracks in its subfolders are
+/// left out.
+pub async fn find_listings_in(
+    pool: &SqlitePool,
+    folder: &Path,
+    recursive: bool,
+) -> Result<Vec<TrackListing>, sqlx::Error> {
+    let prefix = folder_prefix(folder);
+    let length = prefix.chars().count() as i64;
+
+    let mut builder = QueryBuilder::new(SELECT_LISTINGS);
+    builder
+        .push(" WHERE substr(track.location, 1, ")
+        .push_bind(length)
+        .push(") = ")
+        .push_bind(prefix);
+
+    if !recursive {
+        builder
+            .push(" AND instr(substr(track.location, ")
+            .push_bind(length + 1)
+            .push("), ")
+            .push_bind(MAIN_SEPARATOR_STR)
+            .push(") = 0");
+    }
+
+    builder.push(ORDER_LISTINGS);
+
+    builder
+        .build_query_as::<TrackListing>()
+        .fetch_all(pool)
+        .await
+}
+
+/// Counts the album artists, albums and tracks in the library, and adds up the duration of the
+/// tracks.
+pub async fn find_stats(pool: &SqlitePool) -> Result<(i64, i64, i64, i64), sqlx::Error> {
+    let query = include_str!("../../queries/mpd/find_stats.sql");
+
+    sqlx::query_as(query).fetch_one(pool).await
+}
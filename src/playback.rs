@@ -1,4 +1,7 @@
 pub mod events;
+pub mod headless;
+pub mod history;
 pub mod interface;
 pub mod queue;
+pub mod resume;
 pub mod thread;
@@ -2,7 +2,7 @@
 
 use crate::media::metadata::Metadata;
 
-use super::{queue::QueueItemData, thread::PlaybackState};
+use super::{history::Play, queue::QueueItemData, thread::PlaybackState};
 use std::path::PathBuf;
 
 /// A command to the playback thread. This is used to control the playback thread from other
@@ -23,6 +23,13 @@ pub enum PlaybackCommand {
     /// Requests that the playback thread queue a list of files for playback after the current
     /// file. If there is no current file, the first file in the list will be played immediately.
     QueueList(Vec<QueueItemData>),
+    /// Requests that the playback thread add the specified file to the end of the queue and play
+    /// it immediately.
+    QueueAndPlay(QueueItemData),
+    /// Requests that the playback thread insert a list of files directly after the current file,
+    /// so that they are played next. If there is no current file, the first file in the list will
+    /// be played immediately.
+    InsertNext(Vec<QueueItemData>),
     /// Requests that the playback thread skip to the next file in the queue.
     Next,
     /// Requests that the playback thread skip to the previous file in the queue.
@@ -47,6 +54,22 @@ pub enum PlaybackCommand {
     /// Requests that the playback thread shuffle (or stop shuffling) the next tracks in the
     /// queue. Note that this currently results in duplication of the *entire* queue.
     ToggleShuffle,
+    /// Requests that the playback thread change the playback speed. The speed is clamped between
+    /// 0.5x and 3x.
+    SetSpeed(f64),
+    /// Requests that the playback thread enable or disable pitch preservation when the playback
+    /// speed is changed. When disabled, the pitch changes with the speed.
+    SetPitchPreservation(bool),
+    /// Requests that the playback thread loop between the two specified positions (in seconds)
+    /// in the current file, or stop looping if None is provided. The loop is cleared when a
+    /// different file is opened.
+    SetLoop(Option<(f64, f64)>),
+    /// Requests that the playback thread remember the current position as the start of an A-B
+    /// loop. The loop begins once its end is marked with MarkLoopEnd.
+    MarkLoopStart,
+    /// Requests that the playback thread loop between the marked start and the current position.
+    /// Nothing happens if no start has been marked in the current file.
+    MarkLoopEnd,
 }
 
 /// An event from the playback thread. This is used to communicate information from the playback
@@ -78,4 +101,11 @@ pub enum PlaybackEvent {
     ShuffleToggled(bool, usize),
     /// Indicates that the volume has changed. The f64 is the new volume, from 0.0 to 1.0.
     VolumeChanged(f64),
+    /// Indicates that the playback speed has changed. The f64 is the new speed multiplier.
+    SpeedChanged(f64),
+    /// Indicates that the A-B loop has been set or cleared. The positions are in seconds.
+    LoopChanged(Option<(f64, f64)>),
+    /// Indicates that a track has stopped playing after being listened to for long enough to
+    /// count as played, and should be added to the play history.
+    PlayRecorded(Play),
 }
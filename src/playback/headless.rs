@@ -0,0 +1,212 @@
+use std::{
+    sync::{
+        mpsc::{Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use async_std::task;
+use tracing::warn;
+
+use crate::{
+    control::PlaybackStatus,
+    services::mmb::{MediaMetadataBroadcastService, NowPlayingTracker, PlayerHandle},
+};
+
+use super::{
+    events::{PlaybackCommand, PlaybackEvent},
+    interface::{EventListeners, PlaybackInterface},
+    queue::QueueItemData,
+    thread::PlaybackState,
+};
+
+/// A playback interface that does not depend on GPUI, used when Muzak is running without a
+/// window. Instead of updating GPUI models, events from the playback thread are used to keep a
+/// PlaybackStatus up to date, and are forwarded to the provided MMBS services.
+pub struct HeadlessPlaybackInterface {
+    commands_tx: Sender<PlaybackCommand>,
+    events_rx: Option<Receiver<PlaybackEvent>>,
+    status: Arc<Mutex<PlaybackStatus>>,
+    listeners: EventListeners,
+}
+
+impl PlaybackInterface for HeadlessPlaybackInterface {
+    fn new(commands_tx: Sender<PlaybackCommand>, events_rx: Receiver<PlaybackEvent>) -> Self {
+        Self {
+            commands_tx,
+            events_rx: Some(events_rx),
+            status: Arc::new(Mutex::new(PlaybackStatus::default())),
+            listeners: EventListeners::default(),
+        }
+    }
+}
+
+impl HeadlessPlaybackInterface {
+    /// Returns a receiver that gets a copy of every event from the playback thread.
+    pub fn subscribe(&self) -> Receiver<PlaybackEvent> {
+        self.listeners.subscribe()
+    }
+
+    /// Returns a sender that can be used to send commands to the playback thread from other
+    /// threads.
+    pub fn command_sender(&self) -> Sender<PlaybackCommand> {
+        self.commands_tx.clone()
+    }
+
+    pub fn play(&self) {
+        self.commands_tx
+            .send(PlaybackCommand::Play)
+            .expect("could not send tx");
+    }
+
+    pub fn pause(&self) {
+        self.commands_tx
+            .send(PlaybackCommand::Pause)
+            .expect("could not send tx");
+    }
+
+    pub fn queue_list(&self, items: Vec<QueueItemData>) {
+        self.commands_tx
+            .send(PlaybackCommand::QueueList(items))
+            .expect("could not send tx");
+    }
+
+    pub fn insert_next(&self, items: Vec<QueueItemData>) {
+        self.commands_tx
+            .send(PlaybackCommand::InsertNext(items))
+            .expect("could not send tx");
+    }
+
+    pub fn next(&self) {
+        self.commands_tx
+            .send(PlaybackCommand::Next)
+            .expect("could not send tx");
+    }
+
+    pub fn previous(&self) {
+        self.commands_tx
+            .send(PlaybackCommand::Previous)
+            .expect("could not send tx");
+    }
+
+    pub fn replace_queue(&self, items: Vec<QueueItemData>) {
+        self.commands_tx
+            .send(PlaybackCommand::ReplaceQueue(items))
+            .expect("could not send tx");
+    }
+
+    pub fn stop(&self) {
+        self.commands_tx
+            .send(PlaybackCommand::Stop)
+            .expect("could not send tx");
+    }
+
+    pub fn set_pitch_preservation(&self, preserve: bool) {
+        self.commands_tx
+            .send(PlaybackCommand::SetPitchPreservation(preserve))
+            .expect("could not send tx");
+    }
+
+    /// Returns a copy of the current playback status. The queue length and scan state are not
+    /// known to the interface, and are left at their defaults.
+    pub fn status(&self) -> PlaybackStatus {
+        self.status.lock().expect("couldn't get status").clone()
+    }
+
+    /// Starts reading events from the playback thread on a new thread. The services are created
+    /// on that thread by `create_services`, as MMBS services are not required to be Send, and are
+    /// attached to the handle before they receive any events.
+    pub fn start_broadcast(
+        &mut self,
+        handle: PlayerHandle,
+        create_services: impl FnOnce() -> Vec<Box<dyn MediaMetadataBroadcastService>> + Send + 'static,
+    ) {
+        let Some(events_rx) = self.events_rx.take() else {
+            return;
+        };
+        let status = self.status.clone();
+        let listeners = self.listeners.clone();
+
+        thread::Builder::new()
+            .name("headless-events".to_string())
+            .spawn(move || {
+                let mut services = create_services();
+
+                let mut now_playing = NowPlayingTracker::default();
+
+                for service in services.iter_mut() {
+                    service.attach(handle.clone());
+                }
+
+                while let Ok(event) = events_rx.recv() {
+                    status.lock().expect("couldn't get status").update(&event);
+                    listeners.send(&event);
+
+                    let info = match &event {
+                        PlaybackEvent::QueuePositionChanged(v) => {
+                            handle.set_queue_position(*v);
+                            None
+                        }
+                        PlaybackEvent::SongChanged(v) => {
+                            now_playing.new_track(v.clone());
+                            None
+                        }
+                        PlaybackEvent::DurationChanged(v) => {
+                            now_playing.duration_changed(*v);
+                            None
+                        }
+                        PlaybackEvent::MetadataUpdate(v) => {
+                            now_playing.metadata_recieved(Arc::new(*v.clone()));
+                            None
+                        }
+                        PlaybackEvent::StateChanged(PlaybackState::Stopped) => {
+                            now_playing.stopped();
+                            None
+                        }
+                        PlaybackEvent::AlbumArtUpdate(v) => now_playing
+                            .album_art_changed(album_art(v), &handle)
+                            .map(Arc::new),
+                        _ => None,
+                    };
+
+                    task::block_on(async {
+                        for service in services.iter_mut() {
+                            broadcast(service.as_mut(), &event, &handle).await;
+
+                            if let Some(info) = &info {
+                                service.now_playing(info.clone()).await;
+                            }
+                        }
+                    });
+                }
+
+                warn!("Playback thread closed the event channel");
+            })
+            .expect("could not start headless event thread");
+    }
+}
+
+fn album_art(art: &Option<Box<[u8]>>) -> Option<Arc<[u8]>> {
+    art.as_ref().map(|v| Arc::from(v.as_ref()))
+}
+
+async fn broadcast(
+    service: &mut dyn MediaMetadataBroadcastService,
+    event: &PlaybackEvent,
+    handle: &PlayerHandle,
+) {
+    match event {
+        PlaybackEvent::SongChanged(v) => service.new_track(v.clone()).await,
+        PlaybackEvent::MetadataUpdate(v) => service.metadata_recieved(Arc::new(*v.clone())).await,
+        PlaybackEvent::StateChanged(v) => service.state_changed(*v).await,
+        PlaybackEvent::PositionChanged(v) => service.position_changed(*v).await,
+        PlaybackEvent::DurationChanged(v) => service.duration_changed(*v).await,
+        PlaybackEvent::SpeedChanged(v) => service.speed_changed(*v).await,
+        PlaybackEvent::VolumeChanged(v) => service.volume_changed(*v).await,
+        PlaybackEvent::AlbumArtUpdate(v) => service.album_art_changed(album_art(v)).await,
+        PlaybackEvent::QueuePositionChanged(v) => service.queue_position_changed(*v).await,
+        PlaybackEvent::QueueUpdated => service.queue_changed(handle.queue_len()).await,
+        _ => (),
+    }
+}
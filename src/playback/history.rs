@@ -0,0 +1,223 @@
+use std::{
+    path::PathBuf,
+    sync::{mpsc::Receiver, Mutex},
+    thread,
+};
+
+use async_std::task;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use tracing::{debug, error};
+
+use crate::library::db::record_play;
+
+use super::events::PlaybackEvent;
+
+/// Accumulates the real time spent listening to a track from its position updates. Seeking isn't
+/// counted as listening, and time is adjusted for the playback speed.
+///
+/// A track counts as played once it has been listened to for half of its duration or four
+/// minutes, whichever comes first. Tracks shorter than 30 seconds never count as played. This is
+/// the threshold used by last.fm, and is shared by the scrobbling services and the play history.
+pub struct ListenTime {
+    /// The amount of real time spent listening, in seconds.
+    accumulated: f64,
+    duration: u64,
+    last_position: u64,
+    speed: f64,
+}
+
+impl Default for ListenTime {
+    fn default() -> Self {
+        ListenTime {
+            accumulated: 0.0,
+            duration: 0,
+            last_position: 0,
+            speed: 1.0,
+        }
+    }
+}
+
+impl ListenTime {
+    /// Resets the accumulated time for a new track. The speed is kept.
+    pub fn new_track(&mut self) {
+        self.accumulated = 0.0;
+        self.last_position = 0;
+    }
+
+    pub fn position_changed(&mut self, position: u64) {
+        if position < self.last_position + 2 && position > self.last_position {
+            // position is in track time, convert it to real time
+            self.accumulated += (position - self.last_position) as f64 / self.speed;
+        }
+
+        self.last_position = position;
+    }
+
+    pub fn duration_changed(&mut self, duration: u64) {
+        self.duration = duration;
+    }
+
+    pub fn speed_changed(&mut self, speed: f64) {
+        self.speed = speed;
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.duration
+    }
+
+    /// The real time spent listening so far, in whole seconds.
+    pub fn listened(&self) -> u64 {
+        self.accumulated as u64
+    }
+
+    /// Whether the track has been listened to for long enough to count as played.
+    pub fn passed_threshold(&self) -> bool {
+        self.duration >= 30
+            && (self.accumulated > self.duration as f64 / 2.0 || self.accumulated > 240.0)
+    }
+}
+
+/// A play of a track that passed the play threshold (see `ListenTime`), or a skip of a track that
+/// didn't.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Play {
+    pub location: PathBuf,
+    pub started_at: DateTime<Utc>,
+    /// The real time spent listening to the track, in seconds.
+    pub listened: u64,
+    /// Whether playback moved on before the track finished.
+    pub skipped: bool,
+    /// Whether the track passed the play threshold. Tracks skipped before then are recorded, but
+    /// aren't counted as plays in the statistics.
+    pub counted: bool,
+}
+
+struct CurrentPlay {
+    location: PathBuf,
+    started_at: DateTime<Utc>,
+}
+
+/// Keeps track of the current track in the playback thread, to decide whether it should be
+/// recorded in the play history once it stops playing. The recorder is shared with the main
+/// thread, which records the play in progress when Muzak quits (see `record_current_play`).
+#[derive(Default)]
+pub struct PlayRecorder {
+    current: Option<CurrentPlay>,
+    time: ListenTime,
+}
+
+impl PlayRecorder {
+    pub fn start(&mut self, location: PathBuf, duration: u64) {
+        self.current = Some(CurrentPlay {
+            location,
+            started_at: Utc::now(),
+        });
+        self.time.new_track();
+        self.time.duration_changed(duration);
+    }
+
+    pub fn position_changed(&mut self, position: u64) {
+        self.time.position_changed(position);
+    }
+
+    pub fn speed_changed(&mut self, speed: f64) {
+        self.time.speed_changed(speed);
+    }
+
+    /// Ends the current play, returning it if it should be recorded. Tracks that didn't pass the
+    /// play threshold are only recorded if they were skipped. Calling this again before the next
+    /// track is started does nothing.
+    pub fn finish(&mut self, skipped: bool) -> Option<Play> {
+        let current = self.current.take()?;
+        let counted = self.time.passed_threshold();
+
+        if !counted && !skipped {
+            return None;
+        }
+
+        Some(Play {
+            location: current.location,
+            started_at: current.started_at,
+            listened: self.time.listened(),
+            skipped,
+            counted,
+        })
+    }
+}
+
+/// Records the play in progress if it passed the play threshold, since the playback thread never
+/// gets to finish it when Muzak quits.
+pub async fn record_current_play(pool: &SqlitePool, recorder: &Mutex<PlayRecorder>) {
+    let play = recorder
+        .lock()
+        .expect("couldn't get play recorder")
+        .finish(false);
+
+    if let Some(play) = play {
+        debug!("recording play of {:?} before quitting", play.location);
+
+        if let Err(e) = record_play(pool, &play).await {
+            error!("Could not record play of {:?}: {}", play.location, e);
+        }
+    }
+}
+
+/// Starts a thread that writes the plays reported by the playback thread to the database.
+pub fn start_play_history(pool: SqlitePool, events_rx: Receiver<PlaybackEvent>) {
+    thread::Builder::new()
+        .name("play-history".to_string())
+        .spawn(move || {
+            while let Ok(event) = events_rx.recv() {
+                let PlaybackEvent::PlayRecorded(play) = event else {
+                    continue;
+                };
+
+                debug!("recording play of {:?}", play.location);
+
+                if let Err(e) = task::block_on(record_play(&pool, &play)) {
+                    error!("Could not record play of {:?}: {}", play.location, e);
+                }
+            }
+        })
+        .expect("could not start play history thread");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listen(recorder: &mut PlayRecorder, seconds: u64) {
+        for position in 1..=seconds {
+            recorder.position_changed(position);
+        }
+    }
+
+    #[test]
+    fn plays_are_counted_after_the_threshold() {
+        let mut recorder = PlayRecorder::default();
+        recorder.start(PathBuf::from("/music/a.flac"), 100);
+        listen(&mut recorder, 60);
+
+        let play = recorder.finish(true).unwrap();
+        assert!(play.counted);
+        assert!(play.skipped);
+        assert_eq!(play.listened, 60);
+        assert_eq!(recorder.finish(true), None);
+    }
+
+    #[test]
+    fn early_skips_are_recorded_but_not_counted() {
+        let mut recorder = PlayRecorder::default();
+        recorder.start(PathBuf::from("/music/a.flac"), 100);
+        listen(&mut recorder, 10);
+
+        let play = recorder.finish(true).unwrap();
+        assert!(!play.counted);
+        assert_eq!(play.listened, 10);
+
+        recorder.start(PathBuf::from("/music/b.flac"), 100);
+        listen(&mut recorder, 10);
+        assert_eq!(recorder.finish(false), None);
+    }
+}
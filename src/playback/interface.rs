@@ -3,15 +3,18 @@
 use std::{
     path::PathBuf,
     sync::{
-        mpsc::{Receiver, Sender},
-        Arc,
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
     },
     time::Duration,
 };
 
 use gpui::App;
 
-use crate::ui::models::{CurrentTrack, ImageEvent, MMBSEvent, Models, PlaybackInfo};
+use crate::{
+    services::mmb::NowPlayingTracker,
+    ui::models::{CurrentTrack, ImageEvent, MMBSEvent, Models, PlaybackInfo},
+};
 
 use super::{
     events::{PlaybackCommand, PlaybackEvent},
@@ -25,6 +28,27 @@ pub trait PlaybackInterface {
     fn new(commands_tx: Sender<PlaybackCommand>, events_rx: Receiver<PlaybackEvent>) -> Self;
 }
 
+/// Additional consumers of playback events, such as the remote control server. The interface that
+/// owns the events channel passes a copy of every event to each subscriber, after handling it
+/// itself. Subscribers that have been dropped are removed automatically.
+#[derive(Clone, Default)]
+pub struct EventListeners(Arc<Mutex<Vec<Sender<PlaybackEvent>>>>);
+
+impl EventListeners {
+    pub fn subscribe(&self) -> Receiver<PlaybackEvent> {
+        let (tx, rx) = channel();
+        self.0.lock().expect("couldn't get listeners").push(tx);
+        rx
+    }
+
+    pub fn send(&self, event: &PlaybackEvent) {
+        self.0
+            .lock()
+            .expect("couldn't get listeners")
+            .retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
+
 /// The playback interface struct that will be used to communicate between the playback thread and
 /// the main thread. This implementation takes advantage of the GPUI Global trait to allow any
 /// function (so long as it is running on the main thread) to send commands to the playback thread.
@@ -39,6 +63,7 @@ pub trait PlaybackInterface {
 pub struct GPUIPlaybackInterface {
     commands_tx: Sender<PlaybackCommand>,
     events_rx: Option<Receiver<PlaybackEvent>>,
+    listeners: EventListeners,
 }
 
 impl gpui::Global for GPUIPlaybackInterface {}
@@ -48,11 +73,23 @@ impl PlaybackInterface for GPUIPlaybackInterface {
         Self {
             commands_tx,
             events_rx: Some(events_rx),
+            listeners: EventListeners::default(),
         }
     }
 }
 
 impl GPUIPlaybackInterface {
+    /// Returns a receiver that gets a copy of every event from the playback thread.
+    pub fn subscribe(&self) -> Receiver<PlaybackEvent> {
+        self.listeners.subscribe()
+    }
+
+    /// Returns a sender that can be used to send commands to the playback thread from other
+    /// threads.
+    pub fn command_sender(&self) -> Sender<PlaybackCommand> {
+        self.commands_tx.clone()
+    }
+
     pub fn play(&self) {
         self.commands_tx
             .send(PlaybackCommand::Play)
@@ -83,6 +120,18 @@ impl GPUIPlaybackInterface {
             .expect("could not send tx");
     }
 
+    pub fn queue_and_play(&self, item: QueueItemData) {
+        self.commands_tx
+            .send(PlaybackCommand::QueueAndPlay(item))
+            .expect("could not send tx");
+    }
+
+    pub fn insert_next(&self, items: Vec<QueueItemData>) {
+        self.commands_tx
+            .send(PlaybackCommand::InsertNext(items))
+            .expect("could not send tx");
+    }
+
     pub fn next(&self) {
         self.commands_tx
             .send(PlaybackCommand::Next)
@@ -143,6 +192,36 @@ impl GPUIPlaybackInterface {
             .expect("could not send tx");
     }
 
+    pub fn set_speed(&self, speed: f64) {
+        self.commands_tx
+            .send(PlaybackCommand::SetSpeed(speed))
+            .expect("could not send tx");
+    }
+
+    pub fn set_pitch_preservation(&self, preserve: bool) {
+        self.commands_tx
+            .send(PlaybackCommand::SetPitchPreservation(preserve))
+            .expect("could not send tx");
+    }
+
+    pub fn set_loop(&self, points: Option<(f64, f64)>) {
+        self.commands_tx
+            .send(PlaybackCommand::SetLoop(points))
+            .expect("could not send tx");
+    }
+
+    pub fn mark_loop_start(&self) {
+        self.commands_tx
+            .send(PlaybackCommand::MarkLoopStart)
+            .expect("could not send tx");
+    }
+
+    pub fn mark_loop_end(&self) {
+        self.commands_tx
+            .send(PlaybackCommand::MarkLoopEnd)
+            .expect("could not send tx");
+    }
+
     /// Starts the broadcast loop that will read events from the playback thread and update data
     /// models accordingly. This function should be called once, and will panic if called more than
     /// once.
@@ -158,17 +237,23 @@ impl GPUIPlaybackInterface {
         let mmbs_model = app.global::<Models>().mmbs.clone();
 
         let playback_info = app.global::<PlaybackInfo>().clone();
+        let listeners = self.listeners.clone();
 
         let Some(events_rx) = events_rx else {
             panic!("broadcast thread already started");
         };
 
         app.spawn(async move |cx| {
+            let mut now_playing = NowPlayingTracker::default();
+
             loop {
                 while let Ok(event) = events_rx.try_recv() {
+                    listeners.send(&event);
+
                     match event {
                         PlaybackEvent::MetadataUpdate(v) => {
                             let metadata = Arc::new(*v.clone());
+                            now_playing.metadata_recieved(metadata.clone());
 
                             metadata_model
                                 .update(cx, |m, cx| {
@@ -184,6 +269,21 @@ impl GPUIPlaybackInterface {
                                 .expect("failed to broadcast MMBS event MetadataRecieved");
                         }
                         PlaybackEvent::AlbumArtUpdate(v) => {
+                            let art = v.as_ref().map(|v| Arc::from(v.as_ref()));
+
+                            mmbs_model
+                                .update(cx, |m, cx| {
+                                    let info =
+                                        now_playing.album_art_changed(art.clone(), &m.handle);
+
+                                    cx.emit(MMBSEvent::AlbumArtChanged(art));
+
+                                    if let Some(info) = info {
+                                        cx.emit(MMBSEvent::NowPlaying(Arc::new(info)));
+                                    }
+                                })
+                                .expect("failed to broadcast MMBS event AlbumArtChanged");
+
                             albumart_model
                                 .update(cx, |m, cx| {
                                     if let Some(v) = v {
@@ -205,6 +305,8 @@ impl GPUIPlaybackInterface {
                                 .expect("failed to update playback state");
 
                             if v == PlaybackState::Stopped {
+                                now_playing.stopped();
+
                                 playback_info
                                     .current_track
                                     .update(cx, |m, cx| {
@@ -235,6 +337,8 @@ impl GPUIPlaybackInterface {
                                 .expect("failed to broadcast MMBS event PositionChanged");
                         }
                         PlaybackEvent::DurationChanged(v) => {
+                            now_playing.duration_changed(v);
+
                             playback_info
                                 .duration
                                 .update(cx, |m, cx| {
@@ -249,6 +353,8 @@ impl GPUIPlaybackInterface {
                                 .expect("failed to broadcast MMBS event DurationChanged");
                         }
                         PlaybackEvent::SongChanged(path) => {
+                            now_playing.new_track(path.clone());
+
                             playback_info
                                 .current_track
                                 .update(cx, |m, cx| {
@@ -266,6 +372,11 @@ impl GPUIPlaybackInterface {
                             queue_model
                                 .update(cx, |_, cx| cx.notify())
                                 .expect("failed to update queue");
+                            mmbs_model
+                                .update(cx, |m, cx| {
+                                    cx.emit(MMBSEvent::QueueChanged(m.handle.queue_len()));
+                                })
+                                .expect("failed to broadcast MMBS event QueueChanged");
                         }
                         PlaybackEvent::ShuffleToggled(v, _) => {
                             playback_info
@@ -284,6 +395,11 @@ impl GPUIPlaybackInterface {
                                     cx.notify()
                                 })
                                 .expect("failed to update volume model");
+                            mmbs_model
+                                .update(cx, |_, cx| {
+                                    cx.emit(MMBSEvent::VolumeChanged(v));
+                                })
+                                .expect("failed to broadcast MMBS event VolumeChanged");
 
                             // Note: `prev_volume` should not be to small.
                             // Its value needs to be visible in UI
@@ -299,12 +415,46 @@ impl GPUIPlaybackInterface {
                                     .expect("failed to update volume model");
                             }
                         }
-                        PlaybackEvent::QueuePositionChanged(v) => queue_model
-                            .update(cx, |m, cx| {
-                                m.position = v;
-                                cx.notify();
-                            })
-                            .expect("failed to update queue position"),
+                        PlaybackEvent::SpeedChanged(v) => {
+                            playback_info
+                                .speed
+                                .update(cx, |m, cx| {
+                                    *m = v;
+                                    cx.notify()
+                                })
+                                .expect("failed to update speed model");
+                            mmbs_model
+                                .update(cx, |_, cx| {
+                                    cx.emit(MMBSEvent::SpeedChanged(v));
+                                })
+                                .expect("failed to broadcast MMBS event SpeedChanged");
+                        }
+                        PlaybackEvent::LoopChanged(v) => {
+                            playback_info
+                                .loop_points
+                                .update(cx, |m, cx| {
+                                    *m = v;
+                                    cx.notify()
+                                })
+                                .expect("failed to update loop points");
+                        }
+                        PlaybackEvent::QueuePositionChanged(v) => {
+                            queue_model
+                                .update(cx, |m, cx| {
+                                    m.position = v;
+                                    cx.notify();
+                                })
+                                .expect("failed to update queue position");
+                            mmbs_model
+                                .update(cx, |m, cx| {
+                                    m.handle.set_queue_position(v);
+                                    cx.emit(MMBSEvent::QueuePositionChanged(v));
+                                })
+                                .expect("failed to broadcast MMBS event QueuePositionChanged");
+                        }
+                        PlaybackEvent::PlayRecorded(_) => {
+                            // written to the database by the play history thread
+                        }
                     }
                 }
 
@@ -1,14 +1,18 @@
-use std::{fmt::Display, sync::Arc};
+use std::{
+    fmt::Display,
+    sync::{Arc, OnceLock},
+};
 
 use gpui::{App, AppContext, Entity, RenderImage, SharedString};
 use std::path::PathBuf;
 
 use crate::{library::db::LibraryAccess, ui::data::Decode};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct QueueItemData {
-    /// The UI data associated with the queue item.
-    data: Entity<Option<QueueItemUIData>>,
+    /// The UI data associated with the queue item. This is created the first time the data is
+    /// requested, so that queue items can be created without a GPUI context.
+    data: Arc<OnceLock<Entity<Option<QueueItemUIData>>>>,
     /// The database ID of track the item is from, if it exists.
     db_id: Option<i64>,
     /// The database ID of album the item is from, if it exists.
@@ -17,6 +21,16 @@ pub struct QueueItemData {
     path: PathBuf,
 }
 
+/// Queue items are equal when they refer to the same track. The UI data is a cache and isn't
+/// compared, so two items are equal whether or not their data has been loaded.
+impl PartialEq for QueueItemData {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+            && self.db_id == other.db_id
+            && self.db_album_id == other.db_album_id
+    }
+}
+
 impl Display for QueueItemData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(self.path.to_str().unwrap_or("invalid path"))
@@ -45,19 +59,19 @@ pub enum DataSource {
 
 impl QueueItemData {
     /// Creates a new `QueueItemData` instance with the given information.
-    pub fn new(cx: &mut App, path: PathBuf, db_id: Option<i64>, db_album_id: Option<i64>) -> Self {
+    pub fn new(path: PathBuf, db_id: Option<i64>, db_album_id: Option<i64>) -> Self {
         QueueItemData {
             path,
             db_id,
             db_album_id,
-            data: cx.new(|_| None),
+            data: Arc::new(OnceLock::new()),
         }
     }
 
     /// Returns a copy of the UI data after ensuring that the metadata is loaded (or going to be
     /// loaded).
     pub fn get_data(&self, cx: &mut App) -> Entity<Option<QueueItemUIData>> {
-        let model = self.data.clone();
+        let model = self.data.get_or_init(|| cx.new(|_| None)).clone();
         let track_id = self.db_id;
         let album_id = self.db_album_id;
         let path = self.path.clone();
@@ -109,14 +123,21 @@ impl QueueItemData {
     /// Drop the UI data from the queue item. This means the data must be retrieved again from disk
     /// if the item is used with get_data again.
     pub fn drop_data(&self, cx: &mut App) {
-        self.data.update(cx, |m, cx| {
-            *m = None;
-            cx.notify();
-        });
+        if let Some(data) = self.data.get() {
+            data.update(cx, |m, cx| {
+                *m = None;
+                cx.notify();
+            });
+        }
     }
 
     /// Returns the file path of the queue item.
     pub fn get_path(&self) -> &PathBuf {
         &self.path
     }
+
+    /// Returns the database IDs of the track and album the item is from, if they are known.
+    pub fn get_db_ids(&self) -> (Option<i64>, Option<i64>) {
+        (self.db_id, self.db_album_id)
+    }
 }
@@ -0,0 +1,140 @@
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+
+use async_std::task;
+use sqlx::SqlitePool;
+use tracing::{debug, error};
+
+use crate::library::db::{
+    clear_resume_position, get_resume_position, get_track_id, set_resume_position,
+};
+
+use super::{
+    events::{PlaybackCommand, PlaybackEvent},
+    thread::LONG_TRACK_THRESHOLD,
+};
+
+/// Positions closer than this (in seconds) to the start or end of a track are not worth resuming
+/// from. Reaching the end of a track clears its resume position.
+const RESUME_MARGIN: u64 = 15;
+
+/// How far playback has to move (in seconds) before the resume position is saved again.
+const RESUME_SAVE_INTERVAL: u64 = 10;
+
+#[derive(Default)]
+struct ResumeState {
+    /// The ID of the current track, if it is in the library.
+    track_id: Option<i64>,
+    position: u64,
+    duration: u64,
+    last_saved: u64,
+}
+
+impl ResumeState {
+    fn finished(&self) -> bool {
+        self.duration >= LONG_TRACK_THRESHOLD && self.position + RESUME_MARGIN >= self.duration
+    }
+
+    fn should_save(&self) -> bool {
+        self.duration >= LONG_TRACK_THRESHOLD
+            && self.position >= RESUME_MARGIN
+            && self.position + RESUME_MARGIN < self.duration
+            && self.position.abs_diff(self.last_saved) >= RESUME_SAVE_INTERVAL
+    }
+}
+
+fn track_opened(
+    pool: &SqlitePool,
+    commands_tx: &Sender<PlaybackCommand>,
+    state: &mut ResumeState,
+    path: &Path,
+) {
+    let track_id = match task::block_on(get_track_id(pool, path)) {
+        Ok(Some(track_id)) => track_id,
+        Ok(None) => return,
+        Err(e) => {
+            error!("Unable to find track {:?}: {:?}", path, e);
+            return;
+        }
+    };
+
+    state.track_id = Some(track_id);
+
+    match task::block_on(get_resume_position(pool, track_id)) {
+        Ok(Some(position)) => {
+            debug!("resuming {:?} at {}", path, position);
+            state.last_saved = position as u64;
+
+            if commands_tx.send(PlaybackCommand::Seek(position)).is_err() {
+                error!("Unable to resume {:?}, playback thread is gone", path);
+            }
+        }
+        Ok(None) => (),
+        Err(e) => error!("Unable to read resume position: {:?}", e),
+    }
+}
+
+/// Starts a thread that saves the position of long tracks while they play, and seeks back to it
+/// when they are opened again. Positions are kept by track, so files outside of the library are
+/// never resumed. Nothing is saved or restored while `enabled` is false.
+pub fn start_resume_positions(
+    pool: SqlitePool,
+    events_rx: Receiver<PlaybackEvent>,
+    commands_tx: Sender<PlaybackCommand>,
+    enabled: Arc<AtomicBool>,
+) {
+    thread::Builder::new()
+        .name("resume-positions".to_string())
+        .spawn(move || {
+            let mut state = ResumeState::default();
+
+            while let Ok(event) = events_rx.recv() {
+                match event {
+                    PlaybackEvent::SongChanged(path) => {
+                        let previous = std::mem::take(&mut state);
+
+                        // the previous track was listened to the end, start from the beginning
+                        // next time
+                        if let Some(track_id) = previous.track_id.filter(|_| previous.finished()) {
+                            if let Err(e) = task::block_on(clear_resume_position(&pool, track_id)) {
+                                error!("Unable to clear resume position: {:?}", e);
+                            }
+                        }
+
+                        if enabled.load(Ordering::Relaxed) {
+                            track_opened(&pool, &commands_tx, &mut state, &path);
+                        }
+                    }
+                    PlaybackEvent::DurationChanged(duration) => state.duration = duration,
+                    PlaybackEvent::PositionChanged(position) => {
+                        state.position = position;
+
+                        let Some(track_id) = state.track_id else {
+                            continue;
+                        };
+
+                        if !state.should_save() || !enabled.load(Ordering::Relaxed) {
+                            continue;
+                        }
+
+                        state.last_saved = position;
+
+                        if let Err(e) =
+                            task::block_on(set_resume_position(&pool, track_id, position as f64))
+                        {
+                            error!("Unable to save resume position: {:?}", e);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        })
+        .expect("could not start resume position thread");
+}
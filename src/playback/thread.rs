@@ -4,12 +4,14 @@ use std::{
     path::PathBuf,
     sync::{
         mpsc::{Receiver, Sender},
-        Arc, RwLock,
+        Arc, Mutex, RwLock,
     },
     thread::sleep,
 };
 
+use ahash::AHashMap;
 use rand::{rng, seq::SliceRandom};
+use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info, warn};
 
 use crate::devices::builtin::cpal::CpalProvider;
@@ -22,27 +24,115 @@ use crate::devices::builtin::win_audiograph::AudioGraphProvider;
 use crate::{
     devices::{
         format::{ChannelSpec, FormatInfo},
-        resample::Resampler,
+        resample::{convert_samples, Resampler},
+        stretch::TimeStretcher,
         traits::{Device, DeviceProvider, OutputStream},
     },
     media::{
-        builtin::symphonia::SymphoniaProvider, errors::PlaybackReadError, traits::MediaProvider,
+        builtin::symphonia::SymphoniaProvider,
+        errors::PlaybackReadError,
+        playback::{PlaybackFrame, Samples},
+        traits::MediaProvider,
     },
 };
 
 use super::{
     events::{PlaybackCommand, PlaybackEvent},
+    history::PlayRecorder,
     interface::PlaybackInterface,
     queue::QueueItemData,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PlaybackState {
     Stopped,
     Playing,
     Paused,
 }
 
+pub const MIN_SPEED: f64 = 0.5;
+pub const MAX_SPEED: f64 = 3.0;
+
+/// Tracks at least this long (in seconds) remember their playback speed, so that audiobooks and
+/// lectures resume at the speed they were last listened to. The user interface also uses this to
+/// decide which tracks should resume from their last position.
+pub const LONG_TRACK_THRESHOLD: u64 = 20 * 60;
+
+/// Applies the playback speed to a decoded frame. If a time stretcher is provided, the frame is
+/// stretched to preserve pitch. Otherwise, the frame's sample rate is scaled so that the resampler
+/// speeds up (or slows down) the audio, changing the pitch with it.
+fn apply_speed(
+    frame: PlaybackFrame,
+    speed: f64,
+    stretcher: &mut Option<TimeStretcher>,
+) -> PlaybackFrame {
+    if speed == 1.0 {
+        return frame;
+    }
+
+    if let Some(stretcher) = stretcher {
+        let source: Vec<Vec<f32>> = convert_samples(frame.samples);
+
+        PlaybackFrame {
+            samples: Samples::Float32(stretcher.process(source)),
+            rate: frame.rate,
+        }
+    } else {
+        PlaybackFrame {
+            samples: frame.samples,
+            rate: (frame.rate as f64 * speed).round() as u32,
+        }
+    }
+}
+
+/// Enforces the A-B loop on a freshly decoded frame. Samples at or past the end of the loop are cut
+/// off and the provider is sent back to the start of the loop. On the first frame after jumping
+/// back, samples from before the start of the loop are dropped, as the provider may land slightly
+/// before the requested position.
+fn apply_loop(
+    frame: PlaybackFrame,
+    provider: &mut dyn MediaProvider,
+    loop_points: Option<(f64, f64)>,
+    looped: &mut bool,
+) -> PlaybackFrame {
+    let Some((start, end)) = loop_points else {
+        return frame;
+    };
+    let Ok(timestamp) = provider.frame_position_secs() else {
+        return frame;
+    };
+
+    let rate = frame.rate as f64;
+    let length = frame.samples.len();
+    let frame_end = timestamp + length as f64 / rate;
+
+    let skip = if *looped {
+        *looped = false;
+        ((start - timestamp) * rate)
+            .ceil()
+            .clamp(0.0, length as f64) as usize
+    } else {
+        0
+    };
+
+    let keep = if timestamp < end && frame_end >= end {
+        match provider.seek(start) {
+            Ok(_) => *looped = true,
+            Err(e) => warn!("unable to seek to loop start: {:?}", e),
+        }
+
+        ((end - timestamp) * rate).round().clamp(0.0, length as f64) as usize
+    } else {
+        length
+    };
+
+    PlaybackFrame {
+        samples: frame.samples.slice(skip, keep.max(skip)),
+        rate: frame.rate,
+    }
+}
+
 pub struct PlaybackThread {
     /// The command receiver.
     commands_rx: Receiver<PlaybackCommand>,
@@ -101,11 +191,42 @@ pub struct PlaybackThread {
 
     /// Whether or not the stream should be reset before playback is continued.
     pending_reset: bool,
+
+    /// The current playback speed multiplier.
+    speed: f64,
+
+    /// Whether or not pitch should be preserved when the playback speed is changed.
+    preserve_pitch: bool,
+
+    /// The current time stretcher, if pitch is being preserved at a speed other than 1x. Like the
+    /// resampler, this is recreated when the first samples of a track are read.
+    stretcher: Option<TimeStretcher>,
+
+    /// The path and duration of the currently open track.
+    current_track: Option<(PathBuf, u64)>,
+
+    /// The last playback speed used for long tracks (see `LONG_TRACK_THRESHOLD`).
+    speed_memory: AHashMap<PathBuf, f64>,
+
+    /// The start and end of the current A-B loop, in seconds.
+    loop_points: Option<(f64, f64)>,
+
+    /// The start of an A-B loop whose end hasn't been marked yet, in seconds.
+    loop_start: Option<f64>,
+
+    /// Whether or not the provider has just been sent back to the start of the loop.
+    looped: bool,
+
+    /// Decides whether the current track is added to the play history when it stops playing.
+    history: Arc<Mutex<PlayRecorder>>,
 }
 
 impl PlaybackThread {
     /// Starts the playback thread and returns the created interface.
-    pub fn start<T: PlaybackInterface>(queue: Arc<RwLock<Vec<QueueItemData>>>) -> T {
+    pub fn start<T: PlaybackInterface>(
+        queue: Arc<RwLock<Vec<QueueItemData>>>,
+        history: Arc<Mutex<PlayRecorder>>,
+    ) -> T {
         let (commands_tx, commands_rx) = std::sync::mpsc::channel();
         let (events_tx, events_rx) = std::sync::mpsc::channel();
 
@@ -128,6 +249,15 @@ impl PlaybackThread {
                     queue_next: 0,
                     last_timestamp: u64::MAX,
                     pending_reset: false,
+                    speed: 1.0,
+                    preserve_pitch: true,
+                    stretcher: None,
+                    current_track: None,
+                    speed_memory: AHashMap::new(),
+                    loop_points: None,
+                    loop_start: None,
+                    looped: false,
+                    history,
                 };
 
                 thread.run();
@@ -260,6 +390,8 @@ impl PlaybackThread {
                 PlaybackCommand::Open(path) => self.open(&path),
                 PlaybackCommand::Queue(v) => self.queue(v),
                 PlaybackCommand::QueueList(v) => self.queue_list(v),
+                PlaybackCommand::QueueAndPlay(v) => self.queue_and_play(v),
+                PlaybackCommand::InsertNext(v) => self.insert_next(v),
                 PlaybackCommand::Next => self.next(true),
                 PlaybackCommand::Previous => self.previous(),
                 PlaybackCommand::ClearQueue => self.clear_queue(),
@@ -270,6 +402,11 @@ impl PlaybackThread {
                 PlaybackCommand::ReplaceQueue(v) => self.replace_queue(v),
                 PlaybackCommand::Stop => self.stop(),
                 PlaybackCommand::ToggleShuffle => self.toggle_shuffle(),
+                PlaybackCommand::SetSpeed(v) => self.set_speed(v),
+                PlaybackCommand::SetPitchPreservation(v) => self.set_pitch_preservation(v),
+                PlaybackCommand::SetLoop(v) => self.set_loop(v),
+                PlaybackCommand::MarkLoopStart => self.mark_loop_start(),
+                PlaybackCommand::MarkLoopEnd => self.mark_loop_end(),
             }
         }
     }
@@ -365,6 +502,9 @@ impl PlaybackThread {
     fn open(&mut self, path: &PathBuf) {
         info!("Opening: {:?}", path);
 
+        // the previous track was replaced before it finished, unless it reached the end
+        self.finish_play(true);
+
         let mut recreation_required = false;
 
         if self.state == PlaybackState::Paused {
@@ -422,15 +562,16 @@ impl PlaybackThread {
             .send(PlaybackEvent::SongChanged(path.clone()))
             .expect("unable to send event");
 
-        if let Ok(duration) = provider.duration_secs() {
-            self.events_tx
-                .send(PlaybackEvent::DurationChanged(duration))
-                .expect("unable to send event");
-        } else {
-            self.events_tx
-                .send(PlaybackEvent::DurationChanged(0))
-                .expect("unable to send event");
-        }
+        let duration = provider.duration_secs().unwrap_or(0);
+
+        self.events_tx
+            .send(PlaybackEvent::DurationChanged(duration))
+            .expect("unable to send event");
+
+        self.history
+            .lock()
+            .expect("couldn't get play recorder")
+            .start(path.clone(), duration);
 
         if recreation_required {
             self.recreate_stream(true, Some(channels));
@@ -443,6 +584,16 @@ impl PlaybackThread {
         }
 
         self.state = PlaybackState::Playing;
+        self.current_track = Some((path.clone(), duration));
+
+        if self.loop_points.is_some() {
+            self.set_loop(None);
+        }
+        self.loop_start = None;
+
+        if let Some(speed) = self.speed_memory.get(path).copied() {
+            self.set_speed(speed);
+        }
 
         self.update_ts();
 
@@ -525,6 +676,27 @@ impl PlaybackThread {
             .expect("unable to send event");
     }
 
+    /// Add a QueueItemData to the end of the queue and start playing it. When the queue is
+    /// shuffled, the item is added to the end of both the shuffled and the original queue.
+    fn queue_and_play(&mut self, item: QueueItemData) {
+        info!("Adding file to queue and playing it: {}", item);
+
+        let mut queue = self.queue.write().expect("couldn't get the queue");
+        let index = queue.len();
+        queue.push(item.clone());
+        drop(queue);
+
+        if self.shuffle {
+            self.original_queue.push(item);
+        }
+
+        self.events_tx
+            .send(PlaybackEvent::QueueUpdated)
+            .expect("unable to send event");
+
+        self.jump(index);
+    }
+
     /// Add a list of QueueItemData to the queue. If nothing is playing, start playing the first
     /// track.
     fn queue_list(&mut self, mut paths: Vec<QueueItemData>) {
@@ -564,6 +736,51 @@ impl PlaybackThread {
             .expect("unable to send event");
     }
 
+    /// Insert a list of QueueItemData directly after the current track. If nothing is playing,
+    /// start playing the first inserted track.
+    fn insert_next(&mut self, paths: Vec<QueueItemData>) {
+        info!("Inserting files after the current track: {:?}", paths);
+
+        let Some(first) = paths.first().cloned() else {
+            return;
+        };
+
+        let mut queue = self.queue.write().expect("couldn't get the queue");
+        let index = self.queue_next.min(queue.len());
+
+        if self.shuffle {
+            // keep the same order in the unshuffled queue, after the current track if possible
+            let original_index = if index > 0 {
+                let path = queue[index - 1].get_path();
+                self.original_queue
+                    .iter()
+                    .position(|x| x.get_path() == path)
+                    .map(|v| v + 1)
+                    .unwrap_or(self.original_queue.len())
+            } else {
+                0
+            };
+
+            self.original_queue
+                .splice(original_index..original_index, paths.clone());
+        }
+
+        queue.splice(index..index, paths);
+        drop(queue);
+
+        if self.state == PlaybackState::Stopped {
+            self.open(first.get_path());
+            self.queue_next = index + 1;
+            self.events_tx
+                .send(PlaybackEvent::QueuePositionChanged(index))
+                .expect("unable to send event");
+        }
+
+        self.events_tx
+            .send(PlaybackEvent::QueueUpdated)
+            .expect("unable to send event");
+    }
+
     /// Emit a PositionChanged event if the timestamp has changed.
     fn update_ts(&mut self) {
         if let Some(provider) = &self.media_provider {
@@ -577,15 +794,48 @@ impl PlaybackThread {
                     .expect("unable to send event");
 
                 self.last_timestamp = timestamp;
+                self.history
+                    .lock()
+                    .expect("couldn't get play recorder")
+                    .position_changed(timestamp);
             }
         }
     }
 
-    /// Seek to the specified timestamp (in seconds).
+    /// Ends the play of the current track, and sends it to be recorded in the play history if it
+    /// was listened to for long enough.
+    fn finish_play(&mut self, skipped: bool) {
+        let play = self
+            .history
+            .lock()
+            .expect("couldn't get play recorder")
+            .finish(skipped);
+
+        if let Some(play) = play {
+            self.events_tx
+                .send(PlaybackEvent::PlayRecorded(play))
+                .expect("unable to send event");
+        }
+    }
+
+    /// Seek to the specified timestamp (in seconds). Seeks that the provider can't perform, such
+    /// as seeks past the end of the track, are ignored.
     fn seek(&mut self, timestamp: f64) {
+        if !timestamp.is_finite() || timestamp < 0.0 {
+            warn!("Ignoring seek to invalid timestamp {}", timestamp);
+            return;
+        }
+
         if let Some(provider) = &mut self.media_provider {
-            provider.seek(timestamp).expect("unable to seek");
+            if let Err(e) = provider.seek(timestamp) {
+                warn!("Unable to seek to {}: {:?}", timestamp, e);
+                return;
+            }
+
             self.pending_reset = true;
+            // the stretcher holds audio from before the seek, start from scratch
+            self.resampler = None;
+            self.looped = false;
             self.update_ts();
         }
     }
@@ -613,8 +863,13 @@ impl PlaybackThread {
             return;
         }
 
+        let Some(item) = self.original_queue.get(index) else {
+            warn!("Ignoring jump to {}, past the end of the queue", index);
+            return;
+        };
+
         let queue = self.queue.read().expect("couldn't get the queue");
-        let path = self.original_queue[index].get_path();
+        let path = item.get_path();
         let pos = queue.iter().position(|a| a.get_path() == path);
         drop(queue);
 
@@ -666,6 +921,8 @@ impl PlaybackThread {
 
     /// Stop the current playback.
     fn stop(&mut self) {
+        self.finish_play(true);
+
         if let Some(provider) = &mut self.media_provider {
             provider.stop_playback().expect("unable to stop playback");
             provider.close().expect("unable to close media");
@@ -720,10 +977,20 @@ impl PlaybackThread {
         }
     }
 
-    /// Sets the volume of the playback stream.
+    /// Sets the volume of the playback stream, between 0 and 1.
     fn set_volume(&mut self, volume: f64) {
+        if volume.is_nan() {
+            warn!("Ignoring invalid volume {}", volume);
+            return;
+        }
+
+        let volume = volume.clamp(0.0, 1.0);
+
         if let Some(stream) = self.stream.as_mut() {
-            stream.set_volume(volume).expect("failed to set volume");
+            if let Err(e) = stream.set_volume(volume) {
+                error!("Unable to set volume: {:?}", e);
+                return;
+            }
 
             self.events_tx
                 .send(PlaybackEvent::VolumeChanged(volume))
@@ -731,6 +998,90 @@ impl PlaybackThread {
         }
     }
 
+    /// Sets the playback speed. The resampler (and time stretcher) are recreated with the new speed
+    /// when the next samples are read.
+    fn set_speed(&mut self, speed: f64) {
+        if !speed.is_finite() {
+            warn!("Ignoring invalid playback speed {}", speed);
+            return;
+        }
+
+        let speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+
+        if let Some((path, duration)) = &self.current_track {
+            if *duration >= LONG_TRACK_THRESHOLD {
+                self.speed_memory.insert(path.clone(), speed);
+            }
+        }
+
+        if speed == self.speed {
+            return;
+        }
+
+        info!("Setting playback speed to {}x", speed);
+
+        self.speed = speed;
+        self.resampler = None;
+        self.history
+            .lock()
+            .expect("couldn't get play recorder")
+            .speed_changed(speed);
+
+        self.events_tx
+            .send(PlaybackEvent::SpeedChanged(speed))
+            .expect("unable to send event");
+    }
+
+    /// Enables or disables pitch preservation for playback speeds other than 1x.
+    fn set_pitch_preservation(&mut self, preserve: bool) {
+        if preserve == self.preserve_pitch {
+            return;
+        }
+
+        self.preserve_pitch = preserve;
+        self.resampler = None;
+    }
+
+    /// Sets (or clears) the A-B loop. The points are swapped if they are provided in the wrong
+    /// order, and the loop is cleared if they are equal.
+    fn set_loop(&mut self, points: Option<(f64, f64)>) {
+        let points = points
+            .map(|(a, b)| if a <= b { (a, b) } else { (b, a) })
+            .filter(|(a, b)| a < b);
+
+        self.loop_points = points;
+        self.looped = false;
+
+        self.events_tx
+            .send(PlaybackEvent::LoopChanged(points))
+            .expect("unable to send event");
+    }
+
+    /// Marks the start of an A-B loop at the current position. The position of the last decoded
+    /// frame is used, so the loop isn't limited to whole seconds.
+    fn mark_loop_start(&mut self) {
+        let Some(provider) = &self.media_provider else {
+            return;
+        };
+
+        match provider.frame_position_secs() {
+            Ok(position) => self.loop_start = Some(position),
+            Err(e) => warn!("unable to mark loop start: {:?}", e),
+        }
+    }
+
+    /// Starts looping between the marked start and the current position.
+    fn mark_loop_end(&mut self) {
+        let (Some(start), Some(provider)) = (self.loop_start.take(), &self.media_provider) else {
+            return;
+        };
+
+        match provider.frame_position_secs() {
+            Ok(end) => self.set_loop(Some((start, end))),
+            Err(e) => warn!("unable to mark loop end: {:?}", e),
+        }
+    }
+
     /// Recreates the playback stream with the given channels if any are provided, otherwise uses
     /// the device's default channel layout.
     fn recreate_stream(&mut self, force: bool, channels: Option<ChannelSpec>) {
@@ -806,6 +1157,23 @@ impl PlaybackThread {
         );
     }
 
+    /// Plays the samples that the resampler is still holding on to, at the end of a track.
+    fn flush_resampler(&mut self) {
+        let (Some(resampler), Some(format), Some(stream)) = (
+            self.resampler.as_mut(),
+            self.format.as_ref(),
+            self.stream.as_mut(),
+        ) else {
+            return;
+        };
+
+        if let Some(frame) = resampler.flush(format) {
+            if let Err(e) = stream.submit_frame(frame) {
+                warn!("Unable to submit the end of the track: {:?}", e);
+            }
+        }
+    }
+
     /// Uses the current media provider to decode audio samples and sends them to the current
     /// playback stream.
     fn play_audio(&mut self) {
@@ -829,6 +1197,7 @@ impl PlaybackThread {
                     }
                     PlaybackReadError::Eof => {
                         info!("EOF, moving to next song");
+                        self.finish_play(false);
                         self.next(false);
                         return;
                     }
@@ -845,6 +1214,23 @@ impl PlaybackThread {
                 },
             };
 
+            let first_samples = apply_loop(
+                first_samples,
+                provider.as_mut(),
+                self.loop_points,
+                &mut self.looped,
+            );
+
+            // Set up the time stretcher if pitch needs to be preserved
+            self.stretcher = if self.preserve_pitch && self.speed != 1.0 {
+                let channels = provider.channels().map(|v| v.count()).unwrap_or(2);
+                Some(TimeStretcher::new(channels as usize, self.speed))
+            } else {
+                None
+            };
+
+            let first_samples = apply_speed(first_samples, self.speed, &mut self.stretcher);
+
             // Set up the resampler
             let duration = provider.frame_duration().expect("can't get duration");
             let device_format = stream.get_current_format().unwrap();
@@ -860,6 +1246,11 @@ impl PlaybackThread {
             ));
             self.format = Some(device_format.clone());
 
+            // nothing to play yet: the stretcher needs more input, or the loop cut the whole frame
+            if first_samples.samples.is_empty() {
+                return;
+            }
+
             // Convert the first samples to the device format
             let converted = self
                 .resampler
@@ -903,6 +1294,8 @@ impl PlaybackThread {
                     }
                     PlaybackReadError::Eof => {
                         info!("EOF, moving to next song");
+                        self.flush_resampler();
+                        self.finish_play(false);
                         self.next(false);
                         return;
                     }
@@ -918,6 +1311,20 @@ impl PlaybackThread {
                     }
                 },
             };
+
+            let samples = apply_loop(
+                samples,
+                provider.as_mut(),
+                self.loop_points,
+                &mut self.looped,
+            );
+            let samples = apply_speed(samples, self.speed, &mut self.stretcher);
+
+            // nothing to play yet: the stretcher needs more input, or the loop cut the whole frame
+            if samples.samples.is_empty() {
+                return;
+            }
+
             let converted = self
                 .resampler
                 .as_mut()
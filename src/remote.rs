@@ -0,0 +1,452 @@
+pub mod types;
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        mpsc::{Receiver, Sender},
+        Arc, Mutex, RwLock,
+    },
+    thread,
+};
+
+use async_std::{channel, prelude::*, task};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tide::{Body, Next, Request, Response, StatusCode};
+use tide_websockets::{Message, WebSocket, WebSocketConnection};
+use tracing::{error, info, warn};
+use types::{
+    AlbumInfo, ArtistInfo, ListEntry, QueueEntry, RemoteCommand, RemoteEvent, SearchResults,
+    TrackInfo,
+};
+
+use crate::{
+    control::PlaybackStatus,
+    library::{
+        db::{
+            get_album_by_id, get_artist_by_id, get_artist_name_by_id, get_track_by_id, list_albums,
+            list_albums_by_artist, list_artists, list_tracks_in_album, AlbumMethod,
+            AlbumSortMethod, LibraryAccess,
+        },
+        search::SearchQuery,
+    },
+    playback::{
+        events::{PlaybackCommand, PlaybackEvent},
+        queue::QueueItemData,
+    },
+    settings::remote::RemoteSettings,
+    util::is_loopback,
+};
+
+/// How many events can be waiting to be sent to a WebSocket client. Clients that fall further
+/// behind than this are disconnected.
+const EVENT_BUFFER: usize = 256;
+
+/// The subprotocol that browsers offer alongside the token when connecting to `/api/events`.
+const EVENTS_PROTOCOL: &str = "muzak";
+
+/// The state shared between the request handlers of the remote control server.
+#[derive(Clone)]
+struct RemoteState {
+    pool: SqlitePool,
+    commands: Sender<PlaybackCommand>,
+    queue: Arc<RwLock<Vec<QueueItemData>>>,
+    status: Arc<Mutex<PlaybackStatus>>,
+    album_art: Arc<Mutex<Option<Box<[u8]>>>>,
+    subscribers: Arc<Mutex<Vec<channel::Sender<RemoteEvent>>>>,
+    token: Arc<String>,
+}
+
+impl RemoteState {
+    fn subscribe(&self) -> channel::Receiver<RemoteEvent> {
+        let (tx, rx) = channel::bounded(EVENT_BUFFER);
+        self.subscribers
+            .lock()
+            .expect("couldn't get subscribers")
+            .push(tx);
+        rx
+    }
+
+    fn send(&self, command: RemoteCommand) -> tide::Result<()> {
+        self.commands.send(command.into()).map_err(|_| {
+            tide::Error::from_str(
+                StatusCode::ServiceUnavailable,
+                "the playback thread is not running",
+            )
+        })
+    }
+
+    /// Keeps the status and album art up to date, and forwards the event to the WebSocket
+    /// subscribers.
+    fn handle_event(&self, event: &PlaybackEvent) {
+        self.status
+            .lock()
+            .expect("couldn't get status")
+            .update(event);
+
+        if let PlaybackEvent::AlbumArtUpdate(v) = event {
+            *self.album_art.lock().expect("couldn't get album art") = v.clone();
+        }
+
+        let event = RemoteEvent::from(event);
+
+        // subscribers that have fallen behind are dropped, which disconnects them
+        self.subscribers
+            .lock()
+            .expect("couldn't get subscribers")
+            .retain(|tx| tx.try_send(event.clone()).is_ok());
+    }
+}
+
+/// Compares the tokens in constant time, so that the token can't be guessed from how long the
+/// comparison takes.
+fn tokens_match(provided: &str, expected: &str) -> bool {
+    provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// Whether the request carries the correct token. The token has to be provided as a bearer token
+/// in the Authorization header, or, as browsers can't set headers on WebSocket connections, as one
+/// of the subprotocols offered in Sec-WebSocket-Protocol. Tokens in the URL are not accepted, as
+/// URLs end up in logs and history.
+fn is_authorized(req: &Request<RemoteState>) -> bool {
+    let expected = req.state().token.as_str();
+
+    if let Some(header) = req.header("Authorization") {
+        return header
+            .as_str()
+            .strip_prefix("Bearer ")
+            .is_some_and(|token| tokens_match(token, expected));
+    }
+
+    req.header("Sec-WebSocket-Protocol").is_some_and(|header| {
+        header
+            .as_str()
+            .split(',')
+            .any(|protocol| tokens_match(protocol.trim(), expected))
+    })
+}
+
+/// Rejects requests without the correct token (see `is_authorized`).
+fn authenticate<'a>(
+    req: Request<RemoteState>,
+    next: Next<'a, RemoteState>,
+) -> Pin<Box<dyn Future<Output = tide::Result> + Send + 'a>> {
+    Box::pin(async move {
+        if is_authorized(&req) {
+            Ok(next.run(req).await)
+        } else {
+            Ok(Response::new(StatusCode::Unauthorized))
+        }
+    })
+}
+
+fn json(value: &impl Serialize) -> tide::Result {
+    Ok(Response::builder(StatusCode::Ok)
+        .body(Body::from_json(value)?)
+        .build())
+}
+
+fn image_response(data: Option<Box<[u8]>>) -> tide::Result {
+    let Some(data) = data else {
+        return Ok(Response::new(StatusCode::NotFound));
+    };
+
+    let content_type = image::guess_format(&data)
+        .map(|v| v.to_mime_type())
+        .unwrap_or("application/octet-stream");
+
+    Ok(Response::builder(StatusCode::Ok)
+        .content_type(content_type)
+        .body(Body::from_bytes(data.into_vec()))
+        .build())
+}
+
+fn id_param(req: &Request<RemoteState>) -> tide::Result<i64> {
+    req.param("id")?
+        .parse()
+        .map_err(|e| tide::Error::new(StatusCode::BadRequest, e))
+}
+
+fn database_error(e: sqlx::Error) -> tide::Error {
+    match e {
+        sqlx::Error::RowNotFound => tide::Error::from_str(StatusCode::NotFound, "not found"),
+        e => {
+            error!("Database error while handling remote request: {:?}", e);
+            tide::Error::new(StatusCode::InternalServerError, e)
+        }
+    }
+}
+
+async fn get_status(req: Request<RemoteState>) -> tide::Result {
+    let state = req.state();
+    let mut status = state.status.lock().expect("couldn't get status").clone();
+    status.queue_length = state.queue.read().expect("couldn't get queue").len();
+
+    json(&status)
+}
+
+async fn post_command(mut req: Request<RemoteState>) -> tide::Result {
+    let command: RemoteCommand = req.body_json().await?;
+    req.state().send(command)?;
+
+    Ok(Response::new(StatusCode::NoContent))
+}
+
+async fn get_queue(req: Request<RemoteState>) -> tide::Result {
+    let queue: Vec<QueueEntry> = req
+        .state()
+        .queue
+        .read()
+        .expect("couldn't get queue")
+        .iter()
+        .map(QueueEntry::from)
+        .collect();
+
+    json(&queue)
+}
+
+async fn get_now_playing_art(req: Request<RemoteState>) -> tide::Result {
+    let art = req
+        .state()
+        .album_art
+        .lock()
+        .expect("couldn't get album art")
+        .clone();
+
+    image_response(art)
+}
+
+#[derive(Deserialize)]
+struct AlbumQuery {
+    sort: Option<AlbumSortMethod>,
+}
+
+async fn get_albums(req: Request<RemoteState>) -> tide::Result {
+    let query: AlbumQuery = req.query()?;
+    let sort = query.sort.unwrap_or(AlbumSortMethod::TitleAsc);
+    let albums = list_albums(&req.state().pool, sort)
+        .await
+        .map_err(database_error)?;
+
+    json(&albums.into_iter().map(ListEntry::from).collect::<Vec<_>>())
+}
+
+async fn get_album(req: Request<RemoteState>) -> tide::Result {
+    let pool = &req.state().pool;
+    let id = id_param(&req)?;
+    let album = get_album_by_id(pool, id, AlbumMethod::FullQuality)
+        .await
+        .map_err(database_error)?;
+    let artist = get_artist_name_by_id(pool, album.artist_id)
+        .await
+        .ok()
+        .map(|v| v.to_string());
+    let tracks = list_tracks_in_album(pool, id)
+        .await
+        .map_err(database_error)?;
+
+    json(&AlbumInfo::new(&album, artist, &tracks))
+}
+
+async fn get_album_art(req: Request<RemoteState>) -> tide::Result {
+    let id = id_param(&req)?;
+    let album = get_album_by_id(&req.state().pool, id, AlbumMethod::FullQuality)
+        .await
+        .map_err(database_error)?;
+
+    image_response(album.image.clone())
+}
+
+async fn get_artists(req: Request<RemoteState>) -> tide::Result {
+    let artists = list_artists(&req.state().pool)
+        .await
+        .map_err(database_error)?;
+
+    json(&artists.into_iter().map(ListEntry::from).collect::<Vec<_>>())
+}
+
+async fn get_artist(req: Request<RemoteState>) -> tide::Result {
+    let pool = &req.state().pool;
+    let id = id_param(&req)?;
+    let artist = get_artist_by_id(pool, id).await.map_err(database_error)?;
+    let albums = list_albums_by_artist(pool, id)
+        .await
+        .map_err(database_error)?;
+
+    json(&ArtistInfo {
+        id: artist.id,
+        name: artist.name.as_ref().map(|v| v.to_string()),
+        bio: artist.bio.as_ref().map(|v| v.to_string()),
+        albums: albums.into_iter().map(ListEntry::from).collect(),
+    })
+}
+
+async fn get_track(req: Request<RemoteState>) -> tide::Result {
+    let id = id_param(&req)?;
+    let track = get_track_by_id(&req.state().pool, id)
+        .await
+        .map_err(database_error)?;
+
+    json(&TrackInfo::from(track.as_ref()))
+}
+
+/// The number of tracks returned by a search when the client doesn't ask for a number.
+const SEARCH_LIMIT: u32 = 50;
+
+#[derive(Deserialize)]
+struct SearchParams {
+    q: String,
+    limit: Option<u32>,
+}
+
+async fn get_search(req: Request<RemoteState>) -> tide::Result {
+    let params: SearchParams = req.query()?;
+    let query = SearchQuery::parse(&params.q)
+        .map_err(|e| tide::Error::from_str(StatusCode::BadRequest, e.to_string()))?;
+    let limit = params.limit.unwrap_or(SEARCH_LIMIT);
+    let pool = req.state().pool.clone();
+
+    // LibraryAccess blocks, so the search is kept off of the server's threads
+    let results = task::spawn_blocking(move || -> Result<SearchResults, sqlx::Error> {
+        Ok(SearchResults {
+            artists: pool
+                .search_artists(&query, limit)?
+                .into_iter()
+                .map(ListEntry::from)
+                .collect(),
+            albums: pool
+                .search_albums(&query, limit)?
+                .into_iter()
+                .map(ListEntry::from)
+                .collect(),
+            tracks: pool
+                .search_tracks(&query, limit)?
+                .iter()
+                .map(TrackInfo::from)
+                .collect(),
+        })
+    })
+    .await
+    .map_err(database_error)?;
+
+    json(&results)
+}
+
+/// Sends every playback event to the client as JSON. Messages received from the client are
+/// handled as RemoteCommands. Clients that don't keep up with the events are disconnected.
+async fn events_socket(
+    req: Request<RemoteState>,
+    mut stream: WebSocketConnection,
+) -> tide::Result<()> {
+    let state = req.state().clone();
+    let events = state.subscribe();
+    let sender = stream.clone();
+
+    let forward = task::spawn(async move {
+        while let Ok(event) = events.recv().await {
+            if sender.send_json(&event).await.is_err() {
+                break;
+            }
+        }
+
+        let _ = sender.send(Message::Close(None)).await;
+    });
+
+    while let Some(Ok(message)) = stream.next().await {
+        let Message::Text(text) = message else {
+            continue;
+        };
+
+        match serde_json::from_str::<RemoteCommand>(&text) {
+            Ok(command) => state.send(command)?,
+            Err(e) => warn!("Invalid command from remote client: {}", e),
+        }
+    }
+
+    forward.cancel().await;
+
+    Ok(())
+}
+
+/// Starts the remote control server, if a token has been configured. The server exposes the
+/// playback commands, the queue and the library over HTTP, and pushes playback events to clients
+/// connected to `/api/events`. See docs/remote.md for the available endpoints.
+pub fn start_remote_server(
+    settings: &RemoteSettings,
+    pool: SqlitePool,
+    commands: Sender<PlaybackCommand>,
+    events: Receiver<PlaybackEvent>,
+    queue: Arc<RwLock<Vec<QueueItemData>>>,
+) {
+    let Some(token) = settings.token.clone().filter(|v| !v.is_empty()) else {
+        error!("The remote control server is enabled, but no token is set in remote.token");
+        warn!("The remote control server will not be started.");
+        return;
+    };
+
+    if !settings.allow_network && !is_loopback(&settings.address) {
+        error!(
+            "The remote control server is set to listen on {}, but remote.allow_network is not set",
+            settings.address
+        );
+        warn!("The remote control server will not be started.");
+        return;
+    }
+
+    let state = RemoteState {
+        pool,
+        commands,
+        queue,
+        status: Arc::new(Mutex::new(PlaybackStatus::default())),
+        album_art: Arc::new(Mutex::new(None)),
+        subscribers: Arc::new(Mutex::new(Vec::new())),
+        token: Arc::new(token),
+    };
+
+    let event_state = state.clone();
+
+    thread::Builder::new()
+        .name("remote-events".to_string())
+        .spawn(move || {
+            while let Ok(event) = events.recv() {
+                event_state.handle_event(&event);
+            }
+        })
+        .expect("could not start remote event thread");
+
+    let mut app = tide::with_state(state);
+    app.with(authenticate);
+
+    app.at("/api/status").get(get_status);
+    app.at("/api/command").post(post_command);
+    app.at("/api/queue").get(get_queue);
+    app.at("/api/now-playing/art").get(get_now_playing_art);
+    app.at("/api/albums").get(get_albums);
+    app.at("/api/albums/:id").get(get_album);
+    app.at("/api/albums/:id/art").get(get_album_art);
+    app.at("/api/artists").get(get_artists);
+    app.at("/api/artists/:id").get(get_artist);
+    app.at("/api/tracks/:id").get(get_track);
+    app.at("/api/search").get(get_search);
+    app.at("/api/events")
+        .get(WebSocket::new(events_socket).with_protocols(&[EVENTS_PROTOCOL]));
+
+    let address = (settings.address.clone(), settings.port);
+
+    task::spawn(async move {
+        info!(
+            "Starting remote control server on {}:{}",
+            address.0, address.1
+        );
+
+        if let Err(e) = app.listen(address).await {
+            error!("Remote control server stopped: {:?}", e);
+        }
+    });
+}
@@ -0,0 +1,304 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    library::types::{Album, SearchEntry, Track, TrackListing},
+    media::metadata::Metadata,
+    playback::{
+        events::{PlaybackCommand, PlaybackEvent},
+        queue::QueueItemData,
+        thread::PlaybackState,
+    },
+};
+
+/// A file to be added to the queue. The track and album IDs are optional, but allow the user
+/// interface to use the information from the library instead of reading the file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteQueueItem {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub track_id: Option<i64>,
+    #[serde(default)]
+    pub album_id: Option<i64>,
+}
+
+impl From<RemoteQueueItem> for QueueItemData {
+    fn from(item: RemoteQueueItem) -> Self {
+        QueueItemData::new(item.path, item.track_id, item.album_id)
+    }
+}
+
+fn queue_items(items: Vec<RemoteQueueItem>) -> Vec<QueueItemData> {
+    items.into_iter().map(QueueItemData::from).collect()
+}
+
+/// A command sent by a remote client, either as the body of `POST /api/command` or as a message
+/// on the events WebSocket. Each command corresponds to a PlaybackCommand; see the playback thread
+/// for documentation.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum RemoteCommand {
+    Play,
+    Pause,
+    Open { path: PathBuf },
+    Queue { item: RemoteQueueItem },
+    QueueList { items: Vec<RemoteQueueItem> },
+    InsertNext { items: Vec<RemoteQueueItem> },
+    Next,
+    Previous,
+    ClearQueue,
+    Jump { index: usize },
+    JumpUnshuffled { index: usize },
+    Seek { position: f64 },
+    SetVolume { volume: f64 },
+    ReplaceQueue { items: Vec<RemoteQueueItem> },
+    Stop,
+    ToggleShuffle,
+    SetSpeed { speed: f64 },
+    SetPitchPreservation { preserve: bool },
+    SetLoop { start: f64, end: f64 },
+    ClearLoop,
+}
+
+impl From<RemoteCommand> for PlaybackCommand {
+    fn from(command: RemoteCommand) -> Self {
+        match command {
+            RemoteCommand::Play => PlaybackCommand::Play,
+            RemoteCommand::Pause => PlaybackCommand::Pause,
+            RemoteCommand::Open { path } => PlaybackCommand::Open(path),
+            RemoteCommand::Queue { item } => PlaybackCommand::Queue(item.into()),
+            RemoteCommand::QueueList { items } => PlaybackCommand::QueueList(queue_items(items)),
+            RemoteCommand::InsertNext { items } => PlaybackCommand::InsertNext(queue_items(items)),
+            RemoteCommand::Next => PlaybackCommand::Next,
+            RemoteCommand::Previous => PlaybackCommand::Previous,
+            RemoteCommand::ClearQueue => PlaybackCommand::ClearQueue,
+            RemoteCommand::Jump { index } => PlaybackCommand::Jump(index),
+            RemoteCommand::JumpUnshuffled { index } => PlaybackCommand::JumpUnshuffled(index),
+            RemoteCommand::Seek { position } => PlaybackCommand::Seek(position),
+            RemoteCommand::SetVolume { volume } => PlaybackCommand::SetVolume(volume),
+            RemoteCommand::ReplaceQueue { items } => {
+                PlaybackCommand::ReplaceQueue(queue_items(items))
+            }
+            RemoteCommand::Stop => PlaybackCommand::Stop,
+            RemoteCommand::ToggleShuffle => PlaybackCommand::ToggleShuffle,
+            RemoteCommand::SetSpeed { speed } => PlaybackCommand::SetSpeed(speed),
+            RemoteCommand::SetPitchPreservation { preserve } => {
+                PlaybackCommand::SetPitchPreservation(preserve)
+            }
+            RemoteCommand::SetLoop { start, end } => PlaybackCommand::SetLoop(Some((start, end))),
+            RemoteCommand::ClearLoop => PlaybackCommand::SetLoop(None),
+        }
+    }
+}
+
+/// A PlaybackEvent, as sent to WebSocket subscribers. Album art is not included; clients should
+/// fetch `/api/now-playing/art` when they receive `album_art_changed`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RemoteEvent {
+    StateChanged {
+        state: PlaybackState,
+    },
+    TrackChanged {
+        path: PathBuf,
+    },
+    DurationChanged {
+        duration: u64,
+    },
+    QueueUpdated,
+    QueuePositionChanged {
+        position: usize,
+    },
+    MetadataChanged {
+        metadata: Box<Metadata>,
+    },
+    AlbumArtChanged {
+        available: bool,
+    },
+    PositionChanged {
+        position: u64,
+    },
+    ShuffleToggled {
+        shuffling: bool,
+    },
+    VolumeChanged {
+        volume: f64,
+    },
+    SpeedChanged {
+        speed: f64,
+    },
+    LoopChanged {
+        start: Option<f64>,
+        end: Option<f64>,
+    },
+    PlayRecorded {
+        path: PathBuf,
+        listened: u64,
+        skipped: bool,
+        counted: bool,
+    },
+}
+
+impl From<&PlaybackEvent> for RemoteEvent {
+    fn from(event: &PlaybackEvent) -> Self {
+        match event {
+            PlaybackEvent::StateChanged(v) => RemoteEvent::StateChanged { state: *v },
+            PlaybackEvent::SongChanged(v) => RemoteEvent::TrackChanged { path: v.clone() },
+            PlaybackEvent::DurationChanged(v) => RemoteEvent::DurationChanged { duration: *v },
+            PlaybackEvent::QueueUpdated => RemoteEvent::QueueUpdated,
+            PlaybackEvent::QueuePositionChanged(v) => {
+                RemoteEvent::QueuePositionChanged { position: *v }
+            }
+            PlaybackEvent::MetadataUpdate(v) => RemoteEvent::MetadataChanged {
+                metadata: v.clone(),
+            },
+            PlaybackEvent::AlbumArtUpdate(v) => RemoteEvent::AlbumArtChanged {
+                available: v.is_some(),
+            },
+            PlaybackEvent::PositionChanged(v) => RemoteEvent::PositionChanged { position: *v },
+            PlaybackEvent::ShuffleToggled(v, _) => RemoteEvent::ShuffleToggled { shuffling: *v },
+            PlaybackEvent::VolumeChanged(v) => RemoteEvent::VolumeChanged { volume: *v },
+            PlaybackEvent::SpeedChanged(v) => RemoteEvent::SpeedChanged { speed: *v },
+            PlaybackEvent::LoopChanged(v) => RemoteEvent::LoopChanged {
+                start: v.map(|v| v.0),
+                end: v.map(|v| v.1),
+            },
+            PlaybackEvent::PlayRecorded(v) => RemoteEvent::PlayRecorded {
+                path: v.location.clone(),
+                listened: v.listened,
+                skipped: v.skipped,
+                counted: v.counted,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ListEntry {
+    pub id: u32,
+    pub name: String,
+}
+
+impl From<(u32, String)> for ListEntry {
+    fn from((id, name): (u32, String)) -> Self {
+        ListEntry { id, name }
+    }
+}
+
+impl From<SearchEntry> for ListEntry {
+    fn from(entry: SearchEntry) -> Self {
+        ListEntry {
+            id: entry.id as u32,
+            name: entry.title,
+        }
+    }
+}
+
+/// The results of a full-text search, best matches first.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResults {
+    pub artists: Vec<ListEntry>,
+    pub albums: Vec<ListEntry>,
+    pub tracks: Vec<TrackInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackInfo {
+    pub id: i64,
+    pub title: String,
+    pub album_id: Option<i64>,
+    pub track_number: Option<i32>,
+    pub disc_number: Option<i32>,
+    /// The duration of the track, in seconds.
+    pub duration: i64,
+    pub path: PathBuf,
+    pub artist_names: Option<String>,
+}
+
+impl From<&Track> for TrackInfo {
+    fn from(track: &Track) -> Self {
+        TrackInfo {
+            id: track.id,
+            title: track.title.to_string(),
+            album_id: track.album_id,
+            track_number: track.track_number,
+            disc_number: track.disc_number,
+            duration: track.duration,
+            path: track.location.clone(),
+            artist_names: track.artist_names.as_ref().map(|v| v.to_string()),
+        }
+    }
+}
+
+impl From<&TrackListing> for TrackInfo {
+    fn from(track: &TrackListing) -> Self {
+        TrackInfo {
+            id: track.id,
+            title: track.title.to_string(),
+            album_id: track.album_id,
+            track_number: track.track_number,
+            disc_number: track.disc_number,
+            duration: track.duration,
+            path: track.location.clone(),
+            artist_names: track.artist_names.as_ref().map(|v| v.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AlbumInfo {
+    pub id: i64,
+    pub title: String,
+    pub artist_id: i64,
+    pub artist: Option<String>,
+    pub release_date: Option<DateTime<Utc>>,
+    pub label: Option<String>,
+    pub catalog_number: Option<String>,
+    pub isrc: Option<String>,
+    pub tracks: Vec<TrackInfo>,
+}
+
+impl AlbumInfo {
+    pub fn new(album: &Album, artist: Option<String>, tracks: &[Track]) -> Self {
+        AlbumInfo {
+            id: album.id,
+            title: album.title.to_string(),
+            artist_id: album.artist_id,
+            artist,
+            release_date: album.release_date,
+            label: album.label.as_ref().map(|v| v.to_string()),
+            catalog_number: album.catalog_number.as_ref().map(|v| v.to_string()),
+            isrc: album.isrc.as_ref().map(|v| v.to_string()),
+            tracks: tracks.iter().map(TrackInfo::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtistInfo {
+    pub id: i64,
+    pub name: Option<String>,
+    pub bio: Option<String>,
+    pub albums: Vec<ListEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueEntry {
+    pub path: PathBuf,
+    pub track_id: Option<i64>,
+    pub album_id: Option<i64>,
+}
+
+impl From<&QueueItemData> for QueueEntry {
+    fn from(item: &QueueItemData) -> Self {
+        let (track_id, album_id) = item.get_db_ids();
+
+        QueueEntry {
+            path: item.get_path().clone(),
+            track_id,
+            album_id,
+        }
+    }
+}
@@ -1,10 +1,155 @@
 pub mod lastfm;
+pub mod listenbrainz;
+#[cfg(target_os = "linux")]
+pub mod mpris;
+pub mod scrobbling;
 
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{SendError, Sender},
+        Arc, RwLock,
+    },
+};
 
-use crate::{media::metadata::Metadata, playback::thread::PlaybackState};
+use crate::{
+    media::metadata::Metadata,
+    playback::{events::PlaybackCommand, queue::QueueItemData, thread::PlaybackState},
+};
 use async_trait::async_trait;
 
+/// A handle to the player, given to services when they are registered. Services that provide
+/// controls (such as desktop integration) can use it to send commands to the playback thread and
+/// to read the queue. The handle can be cloned and sent to other threads.
+#[derive(Clone)]
+pub struct PlayerHandle {
+    commands: Sender<PlaybackCommand>,
+    queue: Arc<RwLock<Vec<QueueItemData>>>,
+    queue_position: Arc<AtomicUsize>,
+}
+
+impl PlayerHandle {
+    pub fn new(commands: Sender<PlaybackCommand>, queue: Arc<RwLock<Vec<QueueItemData>>>) -> Self {
+        PlayerHandle {
+            commands,
+            queue,
+            queue_position: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Sends a command to the playback thread. This only fails if the playback thread is no
+    /// longer running.
+    pub fn send(&self, command: PlaybackCommand) -> Result<(), SendError<PlaybackCommand>> {
+        self.commands.send(command)
+    }
+
+    /// Returns a copy of the queue, in playback order.
+    pub fn queue(&self) -> Vec<QueueItemData> {
+        self.queue.read().expect("couldn't get queue").clone()
+    }
+
+    pub fn queue_len(&self) -> usize {
+        self.queue.read().expect("couldn't get queue").len()
+    }
+
+    /// Returns the position of the current track in the queue.
+    pub fn queue_position(&self) -> usize {
+        self.queue_position.load(Ordering::Relaxed)
+    }
+
+    /// Returns the queue item that is currently playing, if any.
+    pub fn current(&self) -> Option<QueueItemData> {
+        self.queue
+            .read()
+            .expect("couldn't get queue")
+            .get(self.queue_position())
+            .cloned()
+    }
+
+    /// Called by the playback interface when the queue position changes.
+    pub fn set_queue_position(&self, position: usize) {
+        self.queue_position.store(position, Ordering::Relaxed);
+    }
+}
+
+/// A snapshot of the currently playing track. The MusicBrainz IDs, if the file is tagged with
+/// them, are part of the metadata.
+#[derive(Debug, Clone)]
+pub struct NowPlaying {
+    pub path: PathBuf,
+    /// The database ID of the track, if it was queued from the library.
+    pub track_id: Option<i64>,
+    /// The database ID of the album, if it was queued from the library.
+    pub album_id: Option<i64>,
+    pub metadata: Arc<Metadata>,
+    pub album_art: Option<Arc<[u8]>>,
+    /// The duration of the track, in seconds.
+    pub duration: u64,
+    pub queue_position: usize,
+    pub queue_length: usize,
+}
+
+/// Collects the events for the current track until a NowPlaying snapshot can be built. The
+/// playback thread sends the album art directly after the metadata, so the snapshot is complete
+/// once the album art has been received.
+#[derive(Default)]
+pub struct NowPlayingTracker {
+    path: Option<PathBuf>,
+    duration: u64,
+    metadata: Option<Arc<Metadata>>,
+}
+
+impl NowPlayingTracker {
+    pub fn new_track(&mut self, path: PathBuf) {
+        self.path = Some(path);
+        self.duration = 0;
+        self.metadata = None;
+    }
+
+    pub fn stopped(&mut self) {
+        *self = NowPlayingTracker::default();
+    }
+
+    pub fn duration_changed(&mut self, duration: u64) {
+        self.duration = duration;
+    }
+
+    pub fn metadata_recieved(&mut self, metadata: Arc<Metadata>) {
+        self.metadata = Some(metadata);
+    }
+
+    /// Returns the snapshot for the current track, if a track is playing and its metadata has
+    /// been read.
+    pub fn album_art_changed(
+        &mut self,
+        album_art: Option<Arc<[u8]>>,
+        handle: &PlayerHandle,
+    ) -> Option<NowPlaying> {
+        let path = self.path.clone()?;
+        let metadata = self.metadata.clone()?;
+
+        let queue = handle.queue.read().expect("couldn't get queue");
+        let queue_position = handle.queue_position();
+        let (track_id, album_id) = queue
+            .get(queue_position)
+            .filter(|item| *item.get_path() == path)
+            .map(|item| item.get_db_ids())
+            .unwrap_or_default();
+
+        Some(NowPlaying {
+            path,
+            track_id,
+            album_id,
+            metadata,
+            album_art,
+            duration: self.duration,
+            queue_position,
+            queue_length: queue.len(),
+        })
+    }
+}
+
 /// MediaMetadataBroadcastService is a trait that can be implemented by services that wish to
 /// display information about the currently playing track. When the currently playing track
 /// changes, the service will be provided with the track's metadata, duration, and current
@@ -18,8 +163,13 @@ use async_trait::async_trait;
 /// Note that MMBS operations can be performed on the UI thread, and thus services should not
 /// perform substantial blocking operations in their MMBS implementations. If, for example, a
 /// network request is needed, use an async function to perform the request.
+///
+/// Services that need to control playback can implement `attach` to receive a PlayerHandle.
 #[async_trait]
-pub trait MediaMetadataBroadcastService {
+pub trait MediaMetadataBroadcastService: Send {
+    /// Called once when the service is registered, before any other method. Services that only
+    /// display information can ignore the handle.
+    fn attach(&mut self, _handle: PlayerHandle) {}
     /// Called when a new track is played.
     async fn new_track(&mut self, file_path: PathBuf);
     /// Called when new metadata is recieved from the codec.
@@ -32,4 +182,19 @@ pub trait MediaMetadataBroadcastService {
     /// Called when the duration of the currently playing track changes, or when a new track is
     /// played. Time is in seconds.
     async fn duration_changed(&mut self, duration: u64);
+    /// Called when the playback speed changes. Positions are always reported in track time, so
+    /// services that measure how long the user has been listening should divide by the speed.
+    async fn speed_changed(&mut self, _speed: f64) {}
+    /// Called when the volume changes. The volume is between 0 and 1.
+    async fn volume_changed(&mut self, _volume: f64) {}
+    /// Called when the album art of the currently playing track is read, or removed.
+    async fn album_art_changed(&mut self, _art: Option<Arc<[u8]>>) {}
+    /// Called with a snapshot of everything known about the current track, once its metadata and
+    /// album art have been read. This happens once per track, and again if the file's metadata
+    /// changes during playback.
+    async fn now_playing(&mut self, _info: Arc<NowPlaying>) {}
+    /// Called when the position of the current track in the queue changes.
+    async fn queue_position_changed(&mut self, _position: usize) {}
+    /// Called when tracks are added to or removed from the queue, with the new length.
+    async fn queue_changed(&mut self, _length: usize) {}
 }
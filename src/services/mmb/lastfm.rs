@@ -1,16 +1,24 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::PathBuf,
+    sync::{mpsc::Sender, Arc},
+};
 
-use async_std::task;
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
-use client::LastFMClient;
-use tracing::{debug, warn};
+use chrono::{Duration, Utc};
+use client::{LastFMClient, MAX_SCROBBLE_BATCH};
+use errors::LastFMError;
+use tracing::{debug, error, warn};
+use types::Scrobble;
 
 use crate::{media::metadata::Metadata, playback::thread::PlaybackState};
 
-use super::MediaMetadataBroadcastService;
+use super::{
+    scrobbling::{Backoff, OfflineCache, PlayTracker, ScrobbleFilter},
+    MediaMetadataBroadcastService,
+};
 
 pub mod client;
+pub mod errors;
 mod requests;
 pub mod types;
 mod util;
@@ -18,115 +26,327 @@ mod util;
 pub const LASTFM_API_KEY: Option<&'static str> = option_env!("LASTFM_API_KEY");
 pub const LASTFM_API_SECRET: Option<&'static str> = option_env!("LASTFM_API_SECRET");
 
+/// last.fm ignores scrobbles that are older than two weeks.
+const MAX_SCROBBLE_AGE_DAYS: i64 = 14;
+
+/// Whether a failed request may succeed if it is retried later, see `LastFMError::is_retryable`.
+pub fn is_retryable(error: &anyhow::Error) -> bool {
+    // anything other than an API error is a network or server problem
+    error
+        .downcast_ref::<LastFMError>()
+        .map(LastFMError::is_retryable)
+        .unwrap_or(true)
+}
+
+fn is_auth_error(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<LastFMError>()
+        .is_some_and(LastFMError::is_auth_error)
+}
+
+/// Sent by the service to keep the user interface up to date.
+pub enum LastFMStatus {
+    /// The number of scrobbles waiting to be submitted.
+    Pending(usize),
+    /// last.fm rejected the session. Scrobbles are kept, but not submitted, until the user signs
+    /// in again.
+    SessionRejected,
+}
+
 pub struct LastFM {
     client: LastFMClient,
-    start_timestamp: Option<DateTime<Utc>>,
-    accumulated_time: u64,
-    duration: u64,
+    tracker: PlayTracker,
     metadata: Option<Arc<Metadata>>,
-    last_postion: u64,
-    should_scrobble: bool,
+    cache: OfflineCache<Scrobble>,
+    backoff: Backoff,
+    session_rejected: bool,
+    status_tx: Option<Sender<LastFMStatus>>,
 }
 
 impl LastFM {
-    pub fn new(client: LastFMClient) -> Self {
+    /// Creates the service. Scrobbles that can't be submitted are kept in the file at
+    /// `cache_path` until they can be. Tracks rejected by `filter` are not scrobbled.
+    pub fn new(client: LastFMClient, cache_path: PathBuf, filter: ScrobbleFilter) -> Self {
+        let mut cache = OfflineCache::load(cache_path);
+        let cutoff = Utc::now() - Duration::days(MAX_SCROBBLE_AGE_DAYS);
+        let expired = cache.retain(|v: &Scrobble| v.timestamp > cutoff);
+
+        if expired > 0 {
+            warn!(
+                "Dropping {} scrobbles older than {} days",
+                expired, MAX_SCROBBLE_AGE_DAYS
+            );
+        }
+
         LastFM {
             client,
-            start_timestamp: None,
-            accumulated_time: 0,
+            tracker: PlayTracker::new(filter),
             metadata: None,
-            duration: 0,
-            last_postion: 0,
-            should_scrobble: false,
+            cache,
+            backoff: Backoff::default(),
+            session_rejected: false,
+            status_tx: None,
         }
     }
 
-    pub async fn scrobble(&mut self) {
+    /// Sets a channel that receives the number of scrobbles waiting to be submitted whenever it
+    /// changes, and is told when the session is rejected. The current number is sent
+    /// immediately.
+    pub fn set_status_sender(&mut self, tx: Sender<LastFMStatus>) {
+        let _ = tx.send(LastFMStatus::Pending(self.cache.len()));
+        self.status_tx = Some(tx);
+    }
+
+    fn notify(&self, status: LastFMStatus) {
+        if let Some(tx) = &self.status_tx {
+            let _ = tx.send(status);
+        }
+    }
+
+    fn notify_pending(&self) {
+        self.notify(LastFMStatus::Pending(self.cache.len()));
+    }
+
+    /// Adds the current track to the scrobble cache.
+    fn record(&mut self) {
         let Some(info) = &self.metadata else {
             return;
         };
-        let (Some(artist), Some(track)) = (info.artist.clone(), info.name.clone()) else {
+        let (Some(artist), Some(track), Some(timestamp)) = (
+            info.artist.clone(),
+            info.name.clone(),
+            self.tracker.start_timestamp(),
+        ) else {
             return;
         };
-        if let Err(e) = self
-            .client
-            .scrobble(
-                artist,
-                track,
-                self.start_timestamp.unwrap(),
-                info.album.clone(),
-                None,
-            )
-            .await
-        {
-            warn!("Could not scrobble: {}", e)
+
+        self.cache.push(Scrobble {
+            artist,
+            track,
+            album: info.album.clone(),
+            album_artist: info.album_artist.clone(),
+            timestamp,
+            duration: Some(self.tracker.duration()),
+            track_number: info.track_current,
+            mbid: info.mbid_recording.clone(),
+        });
+        self.notify_pending();
+    }
+
+    pub async fn scrobble(&mut self) {
+        self.record();
+        self.submit().await;
+    }
+
+    /// Submits the cached scrobbles in batches. If a batch fails for a reason that may go away,
+    /// such as the network being down, the remaining scrobbles are kept and submission is retried
+    /// after a delay. If the session is rejected, nothing is submitted until the user signs in
+    /// again, which replaces the service.
+    pub async fn submit(&mut self) {
+        if self.session_rejected || !self.backoff.ready() {
+            return;
+        }
+
+        while !self.cache.is_empty() {
+            let batch = self.cache.batch(MAX_SCROBBLE_BATCH).to_vec();
+
+            match self.client.scrobble(&batch).await {
+                Ok(_) => {
+                    debug!("Submitted {} scrobbles", batch.len());
+                    self.cache.remove(batch.len());
+                    self.backoff.succeeded();
+                }
+                Err(e) if is_retryable(&e) => {
+                    let delay = self.backoff.failed();
+
+                    warn!(
+                        "Could not scrobble, {} scrobbles will be retried in {}s: {}",
+                        self.cache.len(),
+                        delay.as_secs(),
+                        e
+                    );
+                    break;
+                }
+                Err(e) if is_auth_error(&e) => {
+                    error!(
+                        "Last.fm rejected the session, sign in again to submit {} scrobbles: {}",
+                        self.cache.len(),
+                        e
+                    );
+                    self.session_rejected = true;
+                    self.notify(LastFMStatus::SessionRejected);
+                    break;
+                }
+                Err(e) => {
+                    error!("Last.fm rejected {} scrobbles: {}", batch.len(), e);
+                    self.cache.remove(batch.len());
+                }
+            }
         }
+
+        self.notify_pending();
     }
 }
 
 #[async_trait]
 impl MediaMetadataBroadcastService for LastFM {
-    async fn new_track(&mut self, _: PathBuf) {
-        if self.should_scrobble {
+    async fn new_track(&mut self, path: PathBuf) {
+        if self.tracker.take() {
             debug!("attempting scrobble");
             self.scrobble().await;
         }
 
-        self.start_timestamp = Some(chrono::offset::Utc::now());
-        self.accumulated_time = 0;
-        self.last_postion = 0;
-        self.should_scrobble = false;
+        self.tracker.new_track(path);
     }
 
     async fn metadata_recieved(&mut self, info: Arc<Metadata>) {
+        self.tracker.metadata_recieved(info.clone());
+        self.metadata = Some(info.clone());
+
+        if !self.tracker.allowed() {
+            debug!("track is excluded from scrobbling, not setting now playing");
+            return;
+        }
+
         let (Some(artist), Some(track)) = (info.artist.clone(), info.name.clone()) else {
             return;
         };
         if let Err(e) = self
             .client
-            .now_playing(artist, track, info.album.clone(), None)
+            .now_playing(
+                artist,
+                track,
+                info.album.clone(),
+                info.album_artist.clone(),
+                info.track_current,
+                Some(self.tracker.duration()).filter(|v| *v > 0),
+            )
             .await
         {
             warn!("Could not set now playing: {}", e)
         }
-
-        self.metadata = Some(info);
     }
 
     async fn state_changed(&mut self, state: PlaybackState) {
-        if self.should_scrobble && state != PlaybackState::Playing {
+        if state != PlaybackState::Playing && self.tracker.take() {
             debug!("attempting scrobble");
             self.scrobble().await;
-            self.should_scrobble = false;
         }
     }
 
     async fn position_changed(&mut self, position: u64) {
-        if position < self.last_postion + 2 && position > self.last_postion {
-            self.accumulated_time += position - self.last_postion;
-        }
-
-        self.last_postion = position;
+        self.tracker.position_changed(position);
 
-        if self.duration >= 30
-            && (self.accumulated_time > self.duration / 2 || self.accumulated_time > 240)
-            && !self.should_scrobble
-            && self.metadata.is_some()
-        {
-            self.should_scrobble = true;
+        if !self.cache.is_empty() {
+            self.submit().await;
         }
     }
 
     async fn duration_changed(&mut self, duration: u64) {
-        self.duration = duration;
+        self.tracker.duration_changed(duration);
+    }
+
+    async fn speed_changed(&mut self, speed: f64) {
+        self.tracker.speed_changed(speed);
     }
 }
 
 impl Drop for LastFM {
     fn drop(&mut self) {
-        if self.should_scrobble {
-            debug!("attempting scrobble before dropping LastFM, this will block");
-            task::block_on(self.scrobble());
+        if self.tracker.take() {
+            // the scrobble is submitted the next time Muzak is started
+            debug!("caching scrobble before dropping LastFM");
+            self.record();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use async_std::task;
+    use chrono::Utc;
+
+    use super::*;
+    use crate::{
+        services::mmb::scrobbling::mock::{cache_path, MockServer},
+        settings::scrobbling::ScrobblingSettings,
+    };
+
+    fn service(server: &MockServer, name: &str, scrobbles: usize) -> LastFM {
+        let mut client = LastFMClient::new("key".to_string(), "secret");
+        client.set_api_url(server.url.clone());
+        client.set_session("session".to_string());
+
+        let filter = ScrobbleFilter::new(&ScrobblingSettings::default());
+        let mut lastfm = LastFM::new(client, cache_path(name), filter);
+
+        for i in 0..scrobbles {
+            lastfm.cache.push(Scrobble {
+                artist: "Artist".to_string(),
+                track: format!("Track {}", i),
+                album: None,
+                album_artist: None,
+                timestamp: Utc::now(),
+                duration: None,
+                track_number: None,
+                mbid: None,
+            });
         }
+
+        lastfm
+    }
+
+    #[test]
+    fn submitted_scrobbles_leave_the_cache() {
+        let server = MockServer::start(200, r#"{"scrobbles": {}}"#);
+        let mut lastfm = service(&server, "lastfm-submitted", MAX_SCROBBLE_BATCH + 1);
+
+        task::block_on(lastfm.submit());
+
+        assert_eq!(server.requests(), 2);
+        assert!(lastfm.cache.is_empty());
+    }
+
+    #[test]
+    fn temporary_errors_are_retried_later() {
+        let server = MockServer::start(200, r#"{"error": 11, "message": "Service Offline"}"#);
+        let mut lastfm = service(&server, "lastfm-offline", 3);
+
+        task::block_on(lastfm.submit());
+        task::block_on(lastfm.submit());
+
+        // the second attempt waits for the backoff
+        assert_eq!(server.requests(), 1);
+        assert_eq!(lastfm.cache.len(), 3);
+    }
+
+    #[test]
+    fn rejected_scrobbles_are_dropped() {
+        let server = MockServer::start(200, r#"{"error": 6, "message": "Invalid parameters"}"#);
+        let mut lastfm = service(&server, "lastfm-rejected", 3);
+
+        task::block_on(lastfm.submit());
+
+        assert!(lastfm.cache.is_empty());
+    }
+
+    #[test]
+    fn rejected_sessions_keep_scrobbles_and_stop_submitting() {
+        let server = MockServer::start(200, r#"{"error": 9, "message": "Invalid session key"}"#);
+        let mut lastfm = service(&server, "lastfm-session", 3);
+
+        let (tx, rx) = mpsc::channel();
+        lastfm.set_status_sender(tx);
+
+        task::block_on(lastfm.submit());
+        task::block_on(lastfm.submit());
+
+        assert_eq!(server.requests(), 1);
+        assert_eq!(lastfm.cache.len(), 3);
+        assert!(rx
+            .try_iter()
+            .any(|v| matches!(v, LastFMStatus::SessionRejected)));
     }
 }
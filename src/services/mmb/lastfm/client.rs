@@ -1,14 +1,24 @@
-use chrono::{DateTime, Utc};
-
 use super::{
     requests::LFMRequestBuilder,
-    types::{GetSession, GetToken, Session},
+    types::{
+        GetRecentTracks, GetSession, GetToken, GetTrackInfo, GetUserInfo, RecentTracks, Scrobble,
+        Session, TrackInfo, UserInfo,
+    },
 };
 
+pub const DEFAULT_API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+
+/// The maximum number of scrobbles that can be submitted in one request.
+pub const MAX_SCROBBLE_BATCH: usize = 50;
+
+/// The maximum number of scrobbles that can be fetched in one request.
+pub const MAX_RECENT_TRACKS_PAGE: u32 = 200;
+
 pub struct LastFMClient {
     api_key: String,
     api_secret: &'static str,
     auth_session: Option<String>,
+    api_url: String,
     ua: &'static str,
 }
 
@@ -18,6 +28,7 @@ impl LastFMClient {
             api_key: key,
             api_secret: secret,
             auth_session: None,
+            api_url: DEFAULT_API_URL.to_string(),
             ua: "Muzak/0.1, lastfm-mmb/0.1",
         }
     }
@@ -26,8 +37,20 @@ impl LastFMClient {
         self.auth_session = Some(session);
     }
 
+    /// Sets the URL requests are sent to, for services that implement the last.fm API or for
+    /// testing against a mock server.
+    pub fn set_api_url(&mut self, url: String) {
+        self.api_url = url;
+    }
+
+    fn request(&self) -> LFMRequestBuilder {
+        LFMRequestBuilder::new(self.api_key.clone())
+            .set_endpoint(format!("{}?format=json", self.api_url))
+    }
+
     pub async fn get_token(&mut self) -> anyhow::Result<String> {
-        let token = LFMRequestBuilder::new(self.api_key.clone())
+        let token = self
+            .request()
             .add_param("method", "auth.gettoken".to_string())
             .read()
             .sign(self.api_secret)
@@ -38,7 +61,8 @@ impl LastFMClient {
     }
 
     pub async fn get_session(&mut self, token: String) -> anyhow::Result<Session> {
-        let session = LFMRequestBuilder::new(self.api_key.clone())
+        let session = self
+            .request()
             .add_param("method", "auth.getsession".to_string())
             .add_param("token", token)
             .write()
@@ -49,24 +73,41 @@ impl LastFMClient {
         Ok(session.session)
     }
 
-    pub async fn scrobble(
-        &mut self,
-        artist: String,
-        track: String,
-        timestamp: DateTime<Utc>,
-        album: Option<String>,
-        duration: Option<u64>,
-    ) -> anyhow::Result<()> {
+    /// Submits up to MAX_SCROBBLE_BATCH scrobbles in one request.
+    pub async fn scrobble(&mut self, scrobbles: &[Scrobble]) -> anyhow::Result<()> {
         let Some(session) = self.auth_session.clone() else {
             return Err(anyhow::Error::msg("not logged in"));
         };
-        LFMRequestBuilder::new(self.api_key.clone())
-            .add_param("method", "track.scrobble".to_string())
-            .add_param("artist[0]", artist)
-            .add_param("track[0]", track)
-            .add_param("timestamp[0]", timestamp.timestamp().to_string())
-            .add_optional_param("album[0]", album)
-            .add_optional_param("duration[0]", duration.map(|a| u64::to_string(&a)))
+        if scrobbles.len() > MAX_SCROBBLE_BATCH {
+            return Err(anyhow::Error::msg("too many scrobbles in one request"));
+        }
+
+        let mut request = self
+            .request()
+            .add_param("method", "track.scrobble".to_string());
+
+        for (i, scrobble) in scrobbles.iter().enumerate() {
+            request = request
+                .add_param(format!("artist[{}]", i), scrobble.artist.clone())
+                .add_param(format!("track[{}]", i), scrobble.track.clone())
+                .add_param(
+                    format!("timestamp[{}]", i),
+                    scrobble.timestamp.timestamp().to_string(),
+                )
+                .add_optional_param(format!("album[{}]", i), scrobble.album.clone())
+                .add_optional_param(format!("albumArtist[{}]", i), scrobble.album_artist.clone())
+                .add_optional_param(
+                    format!("duration[{}]", i),
+                    scrobble.duration.map(|a| u64::to_string(&a)),
+                )
+                .add_optional_param(
+                    format!("trackNumber[{}]", i),
+                    scrobble.track_number.map(|a| u64::to_string(&a)),
+                )
+                .add_optional_param(format!("mbid[{}]", i), scrobble.mbid.clone());
+        }
+
+        request
             .add_param("sk", session)
             .write()
             .sign(self.api_secret)
@@ -81,16 +122,20 @@ impl LastFMClient {
         artist: String,
         track: String,
         album: Option<String>,
+        album_artist: Option<String>,
+        track_number: Option<u64>,
         duration: Option<u64>,
     ) -> anyhow::Result<()> {
         let Some(session) = self.auth_session.clone() else {
             return Err(anyhow::Error::msg("not logged in"));
         };
-        LFMRequestBuilder::new(self.api_key.clone())
+        self.request()
             .add_param("method", "track.updateNowPlaying".to_string())
             .add_param("artist", artist)
             .add_param("track", track)
             .add_optional_param("album", album)
+            .add_optional_param("albumArtist", album_artist)
+            .add_optional_param("trackNumber", track_number.map(|a| u64::to_string(&a)))
             .add_optional_param("duration", duration.map(|a| u64::to_string(&a)))
             .add_param("sk", session)
             .write()
@@ -100,4 +145,85 @@ impl LastFMClient {
 
         Ok(())
     }
+
+    /// Marks the track as loved (or removes the mark) on the user's profile.
+    pub async fn set_loved(
+        &mut self,
+        artist: String,
+        track: String,
+        loved: bool,
+    ) -> anyhow::Result<()> {
+        let Some(session) = self.auth_session.clone() else {
+            return Err(anyhow::Error::msg("not logged in"));
+        };
+        let method = if loved { "track.love" } else { "track.unlove" };
+
+        self.request()
+            .add_param("method", method.to_string())
+            .add_param("artist", artist)
+            .add_param("track", track)
+            .add_param("sk", session)
+            .write()
+            .sign(self.api_secret)
+            .send_write_request_ns()
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_user_info(&mut self, user: String) -> anyhow::Result<UserInfo> {
+        let info = self
+            .request()
+            .add_param("method", "user.getInfo".to_string())
+            .add_param("user", user)
+            .read()
+            .sign(self.api_secret)
+            .send_request::<GetUserInfo>()
+            .await?;
+
+        Ok(info.user)
+    }
+
+    /// Gets information about the track. If a user is given, their play count and whether they
+    /// have loved the track are included.
+    pub async fn get_track_info(
+        &mut self,
+        artist: String,
+        track: String,
+        user: Option<String>,
+    ) -> anyhow::Result<TrackInfo> {
+        let info = self
+            .request()
+            .add_param("method", "track.getInfo".to_string())
+            .add_param("artist", artist)
+            .add_param("track", track)
+            .add_param("autocorrect", "1".to_string())
+            .add_optional_param("username", user)
+            .read()
+            .sign(self.api_secret)
+            .send_request::<GetTrackInfo>()
+            .await?;
+
+        Ok(info.track)
+    }
+
+    /// Gets one page of the user's scrobbles, newest first. Pages start at 1.
+    pub async fn get_recent_tracks(
+        &mut self,
+        user: String,
+        page: u64,
+    ) -> anyhow::Result<RecentTracks> {
+        let tracks = self
+            .request()
+            .add_param("method", "user.getRecentTracks".to_string())
+            .add_param("user", user)
+            .add_param("page", page.to_string())
+            .add_param("limit", MAX_RECENT_TRACKS_PAGE.to_string())
+            .read()
+            .sign(self.api_secret)
+            .send_request::<GetRecentTracks>()
+            .await?;
+
+        Ok(tracks.recent_tracks)
+    }
 }
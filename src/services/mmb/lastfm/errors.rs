@@ -0,0 +1,26 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum LastFMError {
+    #[error("Last.fm returned error {code}: {message}")]
+    Api { code: u32, message: String },
+}
+
+impl LastFMError {
+    /// Whether the request may succeed if it is retried later. This is the case when the service
+    /// is unavailable or the request was rate limited. Other errors are caused by the request
+    /// itself, or by the session (see `is_auth_error`).
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            LastFMError::Api { code, .. } => matches!(code, 8 | 11 | 16 | 29),
+        }
+    }
+
+    /// Whether the session was rejected. Requests will keep failing until the user signs in
+    /// again.
+    pub fn is_auth_error(&self) -> bool {
+        match self {
+            LastFMError::Api { code, .. } => matches!(code, 4 | 9),
+        }
+    }
+}
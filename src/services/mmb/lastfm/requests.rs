@@ -1,10 +1,14 @@
+use std::borrow::Cow;
+
 use isahc::prelude::*;
 use serde::Deserialize;
 use smallvec::SmallVec;
 
+use super::{errors::LastFMError, types::ApiError};
+
 pub struct LFMRequestBuilder {
     api_key: String,
-    params: SmallVec<[(&'static str, String); 5]>,
+    params: SmallVec<[(Cow<'static, str>, String); 5]>,
     endpoint: String,
     signature: Option<String>,
     read: bool,
@@ -26,9 +30,9 @@ impl LFMRequestBuilder {
         self
     }
 
-    pub fn add_param(mut self, key: &'static str, value: String) -> Self {
+    pub fn add_param(mut self, key: impl Into<Cow<'static, str>>, value: String) -> Self {
         if self.signature.is_none() {
-            self.params.push((key, value));
+            self.params.push((key.into(), value));
         } else {
             panic!("cannot add params after signing");
         }
@@ -36,10 +40,14 @@ impl LFMRequestBuilder {
         self
     }
 
-    pub fn add_optional_param(mut self, key: &'static str, value: Option<String>) -> Self {
+    pub fn add_optional_param(
+        mut self,
+        key: impl Into<Cow<'static, str>>,
+        value: Option<String>,
+    ) -> Self {
         if self.signature.is_none() {
             if let Some(value) = value {
-                self.params.push((key, value));
+                self.params.push((key.into(), value));
             }
         } else {
             panic!("cannot add params after signing");
@@ -49,9 +57,10 @@ impl LFMRequestBuilder {
     }
 
     pub fn sign(mut self, secret: &str) -> Self {
-        self.params.insert(0, ("api_key", self.api_key.clone()));
+        self.params
+            .insert(0, (Cow::Borrowed("api_key"), self.api_key.clone()));
 
-        self.params.sort_by(|a, b| a.0.cmp(b.0));
+        self.params.sort_by(|a, b| a.0.cmp(&b.0));
 
         let params = self.params.clone();
         let mut sig = String::new();
@@ -83,14 +92,15 @@ impl LFMRequestBuilder {
         self
     }
 
-    async fn send_read_request<T: for<'de> Deserialize<'de>>(self) -> anyhow::Result<T> {
+    /// The URL of a read request, with the parameters and signature in the query string.
+    fn read_url(&self) -> anyhow::Result<String> {
         let mut url = self.endpoint.clone();
         url.push('&');
 
         for (k, v) in self.params.iter() {
             url.push_str(k);
             url.push('=');
-            url.push_str(v);
+            url.push_str(&urlencoding::encode(v));
             url.push('&');
         }
 
@@ -101,8 +111,14 @@ impl LFMRequestBuilder {
                 .ok_or(anyhow::Error::msg("couldn't unwrap signature"))?,
         );
 
-        let mut response = isahc::get_async(url).await?;
+        Ok(url)
+    }
+
+    async fn send_read_request<T: for<'de> Deserialize<'de>>(self) -> anyhow::Result<T> {
+        let mut response = isahc::get_async(self.read_url()?).await?;
         let body = response.text().await?;
+
+        check_error(&body)?;
         serde_json::from_str(&body).map_err(anyhow::Error::from)
     }
 
@@ -127,6 +143,7 @@ impl LFMRequestBuilder {
         let mut response = isahc::post_async(self.endpoint, body).await?;
         let body = response.text().await?;
 
+        check_error(&body)?;
         Ok(body)
     }
 
@@ -135,3 +152,63 @@ impl LFMRequestBuilder {
         serde_json::from_str(&body).map_err(anyhow::Error::from)
     }
 }
+
+/// Returns the error in the body of a response, if the request failed. last.fm doesn't always set
+/// the HTTP status of failed requests, so the body has to be checked.
+fn check_error(body: &str) -> anyhow::Result<()> {
+    match serde_json::from_str::<ApiError>(body) {
+        Ok(error) => Err(LastFMError::Api {
+            code: error.error,
+            message: error.message,
+        }
+        .into()),
+        Err(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_url_encodes_params() {
+        let url = LFMRequestBuilder::new("key".to_string())
+            .add_param("artist", "AC/DC & Co".to_string())
+            .add_param("track", "back in black".to_string())
+            .read()
+            .sign("secret")
+            .read_url()
+            .unwrap();
+
+        assert_eq!(
+            url,
+            "https://ws.audioscrobbler.com/2.0/?format=json&api_key=key&artist=AC%2FDC%20%26%20Co\
+             &track=back%20in%20black&api_sig=e9384215498b478c655f689b6abbcc97"
+        );
+    }
+
+    #[test]
+    fn read_url_requires_signature() {
+        let request = LFMRequestBuilder::new("key".to_string()).read();
+
+        assert!(request.read_url().is_err());
+    }
+
+    #[test]
+    fn api_errors_are_detected() {
+        let error = check_error(r#"{"error": 6, "message": "Track not found"}"#).unwrap_err();
+
+        match error.downcast_ref::<LastFMError>() {
+            Some(LastFMError::Api { code, message }) => {
+                assert_eq!(*code, 6);
+                assert_eq!(message, "Track not found");
+            }
+            None => panic!("expected an API error, got {:?}", error),
+        }
+    }
+
+    #[test]
+    fn successful_responses_pass() {
+        assert!(check_error(r#"{"track": {"name": "Back in Black"}}"#).is_ok());
+    }
+}
@@ -1,5 +1,15 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::util::{deserialize_bool, deserialize_list, deserialize_number};
+
+/// The body of a failed request.
+#[derive(Deserialize)]
+pub struct ApiError {
+    pub error: u32,
+    pub message: String,
+}
+
 #[derive(Deserialize)]
 pub struct GetToken {
     pub token: String,
@@ -16,3 +26,115 @@ pub struct Session {
     pub key: String,
     pub subscriber: i8,
 }
+
+/// A play that should be submitted to last.fm.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scrobble {
+    pub artist: String,
+    pub track: String,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    /// The time the track started playing.
+    pub timestamp: DateTime<Utc>,
+    /// The duration of the track, in seconds.
+    pub duration: Option<u64>,
+    #[serde(default)]
+    pub track_number: Option<u64>,
+    /// The MusicBrainz recording ID of the track.
+    #[serde(default)]
+    pub mbid: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct GetUserInfo {
+    pub user: UserInfo,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct UserInfo {
+    pub name: String,
+    /// The total number of scrobbles.
+    #[serde(default, deserialize_with = "deserialize_number")]
+    pub playcount: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct GetTrackInfo {
+    pub track: TrackInfo,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct TrackInfo {
+    pub name: String,
+    #[serde(default, deserialize_with = "deserialize_number")]
+    pub playcount: Option<u64>,
+    /// Only present if a user was specified.
+    #[serde(default, deserialize_with = "deserialize_number")]
+    pub userplaycount: Option<u64>,
+    /// Only present if a user was specified.
+    #[serde(default, deserialize_with = "deserialize_bool")]
+    pub userloved: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct GetRecentTracks {
+    #[serde(rename = "recenttracks")]
+    pub recent_tracks: RecentTracks,
+}
+
+/// One page of a user's scrobbles, newest first.
+#[derive(Deserialize)]
+pub struct RecentTracks {
+    #[serde(default, deserialize_with = "deserialize_list")]
+    pub track: Vec<RecentTrack>,
+    #[serde(rename = "@attr")]
+    pub attr: RecentTracksAttr,
+}
+
+#[derive(Deserialize)]
+pub struct RecentTracksAttr {
+    #[serde(
+        rename = "totalPages",
+        default,
+        deserialize_with = "deserialize_number"
+    )]
+    pub total_pages: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct RecentTrack {
+    pub name: String,
+    pub artist: TextValue,
+    pub album: Option<TextValue>,
+    /// Missing for the track that is currently playing.
+    pub date: Option<RecentTrackDate>,
+    #[serde(rename = "@attr")]
+    pub attr: Option<RecentTrackAttr>,
+}
+
+impl RecentTrack {
+    pub fn now_playing(&self) -> bool {
+        self.attr
+            .as_ref()
+            .is_some_and(|v| v.nowplaying.as_deref() == Some("true"))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RecentTrackAttr {
+    pub nowplaying: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RecentTrackDate {
+    /// The time the track was scrobbled, as a Unix timestamp.
+    #[serde(default, deserialize_with = "deserialize_number")]
+    pub uts: Option<u64>,
+}
+
+/// A value that last.fm wraps in an object, along with its MusicBrainz ID.
+#[derive(Deserialize)]
+pub struct TextValue {
+    #[serde(rename = "#text")]
+    pub text: String,
+}
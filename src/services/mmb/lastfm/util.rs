@@ -1 +1,38 @@
+use serde::{Deserialize, Deserializer};
 
+/// The last.fm JSON API returns numbers as strings.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrNumber {
+    String(String),
+    Number(u64),
+}
+
+pub fn deserialize_number<'de, D: Deserializer<'de>>(d: D) -> Result<Option<u64>, D::Error> {
+    Ok(match Option::<StringOrNumber>::deserialize(d)? {
+        Some(StringOrNumber::String(v)) => v.parse().ok(),
+        Some(StringOrNumber::Number(v)) => Some(v),
+        None => None,
+    })
+}
+
+pub fn deserialize_bool<'de, D: Deserializer<'de>>(d: D) -> Result<Option<bool>, D::Error> {
+    Ok(deserialize_number(d)?.map(|v| v != 0))
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    Many(Vec<T>),
+    One(T),
+}
+
+/// Lists with a single item are returned as the item itself.
+pub fn deserialize_list<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+    d: D,
+) -> Result<Vec<T>, D::Error> {
+    Ok(match OneOrMany::deserialize(d)? {
+        OneOrMany::Many(v) => v,
+        OneOrMany::One(v) => vec![v],
+    })
+}
@@ -0,0 +1,303 @@
+use std::{path::PathBuf, sync::Arc};
+
+use async_trait::async_trait;
+use client::{ListenBrainzClient, MAX_IMPORT_BATCH};
+use errors::ListenBrainzError;
+use tracing::{debug, error, warn};
+use types::{Listen, ListenType};
+
+use crate::{media::metadata::Metadata, playback::thread::PlaybackState};
+
+use super::{
+    scrobbling::{Backoff, OfflineCache, PlayTracker, ScrobbleFilter},
+    MediaMetadataBroadcastService,
+};
+
+pub mod client;
+pub mod errors;
+pub mod types;
+
+fn is_retryable(error: &anyhow::Error) -> bool {
+    // anything other than an API error is a network or server problem
+    error
+        .downcast_ref::<ListenBrainzError>()
+        .map(ListenBrainzError::is_retryable)
+        .unwrap_or(true)
+}
+
+fn is_auth_error(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<ListenBrainzError>()
+        .is_some_and(ListenBrainzError::is_auth_error)
+}
+
+/// Submits listens to ListenBrainz, using the same rules as the last.fm service. Listens that
+/// can't be submitted are cached, and later submitted as an import.
+pub struct ListenBrainz {
+    client: ListenBrainzClient,
+    tracker: PlayTracker,
+    metadata: Option<Arc<Metadata>>,
+    cache: OfflineCache<Listen>,
+    backoff: Backoff,
+    /// Set when the token is rejected. Listens are cached until Muzak is restarted with a
+    /// corrected token.
+    token_rejected: bool,
+}
+
+impl ListenBrainz {
+    /// Creates the service. Listens that can't be submitted are kept in the file at `cache_path`
+    /// until they can be. Tracks rejected by `filter` are not submitted.
+    pub fn new(client: ListenBrainzClient, cache_path: PathBuf, filter: ScrobbleFilter) -> Self {
+        ListenBrainz {
+            client,
+            tracker: PlayTracker::new(filter),
+            metadata: None,
+            cache: OfflineCache::load(cache_path),
+            backoff: Backoff::default(),
+            token_rejected: false,
+        }
+    }
+
+    fn listen(&self) -> Option<Listen> {
+        Listen::new(
+            self.metadata.as_ref()?,
+            Some(self.tracker.start_timestamp()?),
+            Some(self.tracker.duration()),
+        )
+    }
+
+    pub async fn scrobble(&mut self) {
+        let Some(listen) = self.listen() else {
+            return;
+        };
+
+        // submit the cached listens first, so that they stay in order
+        self.submit_cached().await;
+
+        if self.token_rejected || !self.cache.is_empty() || !self.backoff.ready() {
+            self.cache.push(listen);
+            return;
+        }
+
+        match self
+            .client
+            .submit(ListenType::Single, std::slice::from_ref(&listen))
+            .await
+        {
+            Ok(_) => debug!("Submitted listen"),
+            Err(e) if is_retryable(&e) => {
+                let delay = self.backoff.failed();
+                warn!(
+                    "Could not submit listen, it will be retried in {}s: {}",
+                    delay.as_secs(),
+                    e
+                );
+                self.cache.push(listen);
+            }
+            Err(e) if is_auth_error(&e) => {
+                self.reject_token(&e);
+                self.cache.push(listen);
+            }
+            Err(e) => error!("ListenBrainz rejected the listen: {}", e),
+        }
+    }
+
+    /// Submits the cached listens in batches, unless the last attempt failed recently.
+    pub async fn submit_cached(&mut self) {
+        if self.token_rejected || !self.backoff.ready() {
+            return;
+        }
+
+        while !self.cache.is_empty() {
+            let batch = self.cache.batch(MAX_IMPORT_BATCH).to_vec();
+
+            match self.client.submit(ListenType::Import, &batch).await {
+                Ok(_) => {
+                    debug!("Submitted {} cached listens", batch.len());
+                    self.cache.remove(batch.len());
+                    self.backoff.succeeded();
+                }
+                Err(e) if is_retryable(&e) => {
+                    let delay = self.backoff.failed();
+                    warn!(
+                        "Could not submit listens, {} listens will be retried in {}s: {}",
+                        self.cache.len(),
+                        delay.as_secs(),
+                        e
+                    );
+                    break;
+                }
+                Err(e) if is_auth_error(&e) => {
+                    self.reject_token(&e);
+                    break;
+                }
+                Err(e) => {
+                    error!("ListenBrainz rejected {} listens: {}", batch.len(), e);
+                    self.cache.remove(batch.len());
+                }
+            }
+        }
+    }
+
+    fn reject_token(&mut self, error: &anyhow::Error) {
+        error!(
+            "ListenBrainz rejected the token, listens will be kept until it is corrected in the \
+             settings and Muzak is restarted: {}",
+            error
+        );
+        self.token_rejected = true;
+    }
+}
+
+#[async_trait]
+impl MediaMetadataBroadcastService for ListenBrainz {
+    async fn new_track(&mut self, path: PathBuf) {
+        if self.tracker.take() {
+            debug!("attempting listen submission");
+            self.scrobble().await;
+        }
+
+        self.tracker.new_track(path);
+    }
+
+    async fn metadata_recieved(&mut self, info: Arc<Metadata>) {
+        self.tracker.metadata_recieved(info.clone());
+        self.metadata = Some(info.clone());
+
+        if !self.tracker.allowed() {
+            debug!("track is excluded from scrobbling, not setting playing now");
+            return;
+        }
+
+        if let Some(listen) = Listen::new(&info, None, Some(self.tracker.duration())) {
+            if let Err(e) = self.client.playing_now(listen).await {
+                warn!("Could not set playing now: {}", e)
+            }
+        }
+    }
+
+    async fn state_changed(&mut self, state: PlaybackState) {
+        if state != PlaybackState::Playing && self.tracker.take() {
+            debug!("attempting listen submission");
+            self.scrobble().await;
+        }
+    }
+
+    async fn position_changed(&mut self, position: u64) {
+        self.tracker.position_changed(position);
+
+        if !self.cache.is_empty() {
+            self.submit_cached().await;
+        }
+    }
+
+    async fn duration_changed(&mut self, duration: u64) {
+        self.tracker.duration_changed(duration);
+    }
+
+    async fn speed_changed(&mut self, speed: f64) {
+        self.tracker.speed_changed(speed);
+    }
+}
+
+impl Drop for ListenBrainz {
+    fn drop(&mut self) {
+        if self.tracker.take() {
+            // the listen is submitted the next time Muzak is started
+            debug!("caching listen before dropping ListenBrainz");
+            if let Some(listen) = self.listen() {
+                self.cache.push(listen);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::task;
+    use chrono::Utc;
+
+    use super::*;
+    use crate::{
+        services::mmb::scrobbling::mock::{cache_path, MockServer},
+        settings::scrobbling::ScrobblingSettings,
+    };
+
+    fn listen(name: &str) -> Listen {
+        let metadata = Metadata {
+            artist: Some("Artist".to_string()),
+            name: Some(name.to_string()),
+            ..Default::default()
+        };
+
+        Listen::new(&metadata, Some(Utc::now()), None).unwrap()
+    }
+
+    fn service(server: &MockServer, name: &str, listens: usize) -> ListenBrainz {
+        let mut client = ListenBrainzClient::new("token".to_string());
+        client.set_api_url(server.url.clone());
+
+        let filter = ScrobbleFilter::new(&ScrobblingSettings::default());
+        let mut service = ListenBrainz::new(client, cache_path(name), filter);
+
+        for i in 0..listens {
+            service.cache.push(listen(&format!("Track {}", i)));
+        }
+
+        service
+    }
+
+    #[test]
+    fn cached_listens_are_imported_in_batches() {
+        let server = MockServer::start(200, r#"{"status": "ok"}"#);
+        let mut service = service(&server, "listenbrainz-imported", MAX_IMPORT_BATCH + 1);
+
+        task::block_on(service.submit_cached());
+
+        assert_eq!(server.requests(), 2);
+        assert!(service.cache.is_empty());
+    }
+
+    #[test]
+    fn rate_limited_listens_are_retried_later() {
+        let server = MockServer::start(429, r#"{"error": "Too many requests"}"#);
+        let mut service = service(&server, "listenbrainz-limited", 2);
+
+        task::block_on(service.submit_cached());
+        task::block_on(service.submit_cached());
+
+        assert_eq!(server.requests(), 1);
+        assert_eq!(service.cache.len(), 2);
+    }
+
+    #[test]
+    fn rejected_listens_are_dropped() {
+        let server = MockServer::start(400, r#"{"error": "Invalid listen"}"#);
+        let mut service = service(&server, "listenbrainz-rejected", 2);
+
+        task::block_on(service.submit_cached());
+
+        assert!(service.cache.is_empty());
+    }
+
+    #[test]
+    fn rejected_tokens_keep_listens_and_stop_submitting() {
+        let server = MockServer::start(401, r#"{"error": "Invalid authorization token"}"#);
+        let mut service = service(&server, "listenbrainz-token", 2);
+
+        task::block_on(service.submit_cached());
+
+        service.metadata = Some(Arc::new(Metadata {
+            artist: Some("Artist".to_string()),
+            name: Some("Another track".to_string()),
+            ..Default::default()
+        }));
+        service
+            .tracker
+            .new_track(PathBuf::from("/music/another.flac"));
+        task::block_on(service.scrobble());
+
+        assert_eq!(server.requests(), 1);
+        assert_eq!(service.cache.len(), 3);
+    }
+}
@@ -0,0 +1,62 @@
+use isahc::{prelude::*, Request};
+
+use super::{
+    errors::ListenBrainzError,
+    types::{Listen, ListenType, Submission},
+};
+
+pub const DEFAULT_API_URL: &str = "https://api.listenbrainz.org";
+
+/// The maximum number of listens submitted in one import request. The API allows more, but
+/// requests are also limited in size.
+pub const MAX_IMPORT_BATCH: usize = 100;
+
+pub struct ListenBrainzClient {
+    token: String,
+    api_url: String,
+}
+
+impl ListenBrainzClient {
+    pub fn new(token: String) -> Self {
+        ListenBrainzClient {
+            token,
+            api_url: DEFAULT_API_URL.to_string(),
+        }
+    }
+
+    /// Sets the URL of the API, for self-hosted instances.
+    pub fn set_api_url(&mut self, url: String) {
+        self.api_url = url.trim_end_matches('/').to_string();
+    }
+
+    pub async fn submit(&self, listen_type: ListenType, listens: &[Listen]) -> anyhow::Result<()> {
+        let body = serde_json::to_vec(&Submission {
+            listen_type,
+            payload: listens,
+        })?;
+
+        let request = Request::post(format!("{}/1/submit-listens", self.api_url))
+            .header("Authorization", format!("Token {}", self.token))
+            .header("Content-Type", "application/json")
+            .body(body)?;
+
+        let mut response = isahc::send_async(request).await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+
+            return Err(ListenBrainzError::Api {
+                status: status.as_u16(),
+                message,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    pub async fn playing_now(&self, listen: Listen) -> anyhow::Result<()> {
+        self.submit(ListenType::PlayingNow, &[listen]).await
+    }
+}
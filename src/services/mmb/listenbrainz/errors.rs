@@ -0,0 +1,26 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ListenBrainzError {
+    #[error("ListenBrainz returned status {status}: {message}")]
+    Api { status: u16, message: String },
+}
+
+impl ListenBrainzError {
+    /// Whether the request may succeed if it is retried later. This is the case when the server is
+    /// unavailable or the request was rate limited. Other errors are caused by the request itself,
+    /// or by the token (see `is_auth_error`).
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ListenBrainzError::Api { status, .. } => *status == 429 || *status >= 500,
+        }
+    }
+
+    /// Whether the token was rejected. Requests will keep failing until it is corrected in the
+    /// settings.
+    pub fn is_auth_error(&self) -> bool {
+        match self {
+            ListenBrainzError::Api { status, .. } => *status == 401,
+        }
+    }
+}
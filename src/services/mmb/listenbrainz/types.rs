@@ -0,0 +1,90 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::media::metadata::Metadata;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListenType {
+    Single,
+    PlayingNow,
+    Import,
+}
+
+/// The body of a `submit-listens` request.
+#[derive(Debug, Serialize)]
+pub struct Submission<'a> {
+    pub listen_type: ListenType,
+    pub payload: &'a [Listen],
+}
+
+/// A listen, in the format expected by the ListenBrainz API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Listen {
+    /// The time the track started playing. Not set for playing now submissions.
+    #[serde(
+        with = "chrono::serde::ts_seconds_option",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub listened_at: Option<DateTime<Utc>>,
+    pub track_metadata: TrackMetadata,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackMetadata {
+    pub artist_name: String,
+    pub track_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_name: Option<String>,
+    pub additional_info: AdditionalInfo,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdditionalInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recording_mbid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_mbid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_group_mbid: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub artist_mbids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracknumber: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    pub media_player: String,
+    pub submission_client: String,
+    pub submission_client_version: String,
+}
+
+impl Listen {
+    /// Creates a listen from the track's metadata. Returns None if the artist or title is
+    /// missing, since ListenBrainz requires both.
+    pub fn new(
+        info: &Metadata,
+        listened_at: Option<DateTime<Utc>>,
+        duration: Option<u64>,
+    ) -> Option<Self> {
+        Some(Listen {
+            listened_at,
+            track_metadata: TrackMetadata {
+                artist_name: info.artist.clone()?,
+                track_name: info.name.clone()?,
+                release_name: info.album.clone(),
+                additional_info: AdditionalInfo {
+                    recording_mbid: info.mbid_recording.clone(),
+                    release_mbid: info.mbid_album.clone(),
+                    release_group_mbid: info.mbid_release_group.clone(),
+                    artist_mbids: info.mbid_artist.iter().cloned().collect(),
+                    tracknumber: info.track_current,
+                    duration_ms: duration.filter(|v| *v > 0).map(|v| v * 1000),
+                    media_player: "Muzak".to_string(),
+                    submission_client: "Muzak".to_string(),
+                    submission_client_version: env!("CARGO_PKG_VERSION").to_string(),
+                },
+            },
+        })
+    }
+}
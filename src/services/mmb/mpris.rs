@@ -0,0 +1,642 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use tracing::{debug, warn};
+use zbus::{
+    connection, fdo, interface,
+    object_server::{InterfaceRef, SignalEmitter},
+    zvariant::{ObjectPath, OwnedValue, Value},
+    Connection,
+};
+
+use crate::{
+    media::metadata::Metadata,
+    playback::{
+        events::PlaybackCommand,
+        thread::{PlaybackState, MAX_SPEED, MIN_SPEED},
+    },
+    ui::app::get_dirs,
+};
+
+use super::{MediaMetadataBroadcastService, PlayerHandle};
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.muzak";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+/// Converts an absolute path to a `file://` URI. Every byte of the path other than the separators
+/// and unreserved characters is percent-encoded, so paths containing spaces, `#`, `?` or invalid
+/// UTF-8 survive the round trip through `file_path`.
+fn file_uri(path: &Path) -> String {
+    let segments: Vec<_> = path
+        .as_os_str()
+        .as_bytes()
+        .split(|b| *b == b'/')
+        .map(|segment| urlencoding::encode_binary(segment).into_owned())
+        .collect();
+
+    format!("file://{}", segments.join("/"))
+}
+
+/// Converts a `file://` URI back to a path. URIs naming another host are not supported.
+fn file_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let path = path.strip_prefix("localhost").unwrap_or(path);
+
+    if !path.starts_with('/') {
+        return None;
+    }
+
+    let bytes = urlencoding::decode_binary(path.as_bytes()).into_owned();
+
+    Some(PathBuf::from(OsString::from_vec(bytes)))
+}
+
+/// The state published over D-Bus. It is shared between the MMBS, which keeps it up to date, and
+/// the player interface, which answers property requests from clients.
+struct PlayerState {
+    /// Set when the service is attached to the player. Commands received before then fail.
+    handle: Option<PlayerHandle>,
+    /// Incremented for every new track, and used to build the track ID.
+    track: u64,
+    path: Option<PathBuf>,
+    metadata: Option<Arc<Metadata>>,
+    art_url: Option<String>,
+    state: PlaybackState,
+    position: u64,
+    duration: u64,
+    volume: f64,
+    speed: f64,
+}
+
+impl Default for PlayerState {
+    fn default() -> Self {
+        PlayerState {
+            handle: None,
+            track: 0,
+            path: None,
+            metadata: None,
+            art_url: None,
+            state: PlaybackState::Stopped,
+            position: 0,
+            duration: 0,
+            volume: 1.0,
+            speed: 1.0,
+        }
+    }
+}
+
+fn owned<'a>(value: impl Into<Value<'a>>) -> OwnedValue {
+    value
+        .into()
+        .try_to_owned()
+        .expect("metadata values never contain file descriptors")
+}
+
+fn seconds_to_micros(seconds: u64) -> i64 {
+    seconds as i64 * 1_000_000
+}
+
+impl PlayerState {
+    fn track_id(&self) -> ObjectPath<'static> {
+        let path = if self.path.is_some() {
+            format!("/me/william341/muzak/track/{}", self.track)
+        } else {
+            NO_TRACK.to_string()
+        };
+
+        ObjectPath::try_from(path).expect("track IDs are valid object paths")
+    }
+
+    fn playback_status(&self) -> &'static str {
+        match self.state {
+            PlaybackState::Playing => "Playing",
+            PlaybackState::Paused => "Paused",
+            PlaybackState::Stopped => "Stopped",
+        }
+    }
+
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let mut map = HashMap::new();
+        map.insert("mpris:trackid".to_string(), owned(self.track_id()));
+
+        let Some(path) = &self.path else {
+            return map;
+        };
+
+        map.insert(
+            "mpris:length".to_string(),
+            owned(seconds_to_micros(self.duration)),
+        );
+        map.insert("xesam:url".to_string(), owned(file_uri(path)));
+
+        if let Some(url) = &self.art_url {
+            map.insert("mpris:artUrl".to_string(), owned(url.clone()));
+        }
+
+        let Some(metadata) = &self.metadata else {
+            return map;
+        };
+
+        if let Some(name) = &metadata.name {
+            map.insert("xesam:title".to_string(), owned(name.clone()));
+        }
+        if let Some(album) = &metadata.album {
+            map.insert("xesam:album".to_string(), owned(album.clone()));
+        }
+        if let Some(artist) = &metadata.artist {
+            map.insert("xesam:artist".to_string(), owned(vec![artist.clone()]));
+        }
+        if let Some(artist) = &metadata.album_artist {
+            map.insert("xesam:albumArtist".to_string(), owned(vec![artist.clone()]));
+        }
+        if let Some(genre) = &metadata.genre {
+            map.insert("xesam:genre".to_string(), owned(vec![genre.clone()]));
+        }
+        if let Some(track) = metadata.track_current {
+            map.insert("xesam:trackNumber".to_string(), owned(track as i32));
+        }
+        if let Some(disc) = metadata.disc_current {
+            map.insert("xesam:discNumber".to_string(), owned(disc as i32));
+        }
+
+        map
+    }
+}
+
+struct RootInterface;
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl RootInterface {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> String {
+        "Muzak".to_string()
+    }
+
+    #[zbus(property)]
+    fn desktop_entry(&self) -> String {
+        "muzak".to_string()
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        vec!["file".to_string()]
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        ["audio/flac", "audio/mpeg", "audio/ogg", "audio/wav"]
+            .iter()
+            .map(|v| v.to_string())
+            .collect()
+    }
+}
+
+struct PlayerInterface {
+    state: Arc<Mutex<PlayerState>>,
+}
+
+impl PlayerInterface {
+    fn send(&self, command: PlaybackCommand) -> fdo::Result<()> {
+        self.read(|v| v.handle.clone())
+            .ok_or_else(|| fdo::Error::Failed("the player is not ready yet".to_string()))?
+            .send(command)
+            .map_err(|_| fdo::Error::Failed("the playback thread is not running".to_string()))
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&PlayerState) -> T) -> T {
+        f(&self.state.lock().expect("couldn't get MPRIS state"))
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl PlayerInterface {
+    fn next(&self) -> fdo::Result<()> {
+        self.send(PlaybackCommand::Next)
+    }
+
+    fn previous(&self) -> fdo::Result<()> {
+        self.send(PlaybackCommand::Previous)
+    }
+
+    fn pause(&self) -> fdo::Result<()> {
+        self.send(PlaybackCommand::Pause)
+    }
+
+    fn play_pause(&self) -> fdo::Result<()> {
+        match self.read(|v| v.state) {
+            PlaybackState::Playing => self.send(PlaybackCommand::Pause),
+            _ => self.send(PlaybackCommand::Play),
+        }
+    }
+
+    fn stop(&self) -> fdo::Result<()> {
+        self.send(PlaybackCommand::Stop)
+    }
+
+    fn play(&self) -> fdo::Result<()> {
+        self.send(PlaybackCommand::Play)
+    }
+
+    /// Seeks relative to the current position. Seeking past the end of the track skips to the
+    /// next track, as required by the specification.
+    fn seek(&self, offset: i64) -> fdo::Result<()> {
+        let (position, duration) = self.read(|v| (v.position, v.duration));
+        let target = seconds_to_micros(position) + offset;
+
+        if target > seconds_to_micros(duration) {
+            self.send(PlaybackCommand::Next)
+        } else {
+            self.send(PlaybackCommand::Seek(target.max(0) as f64 / 1_000_000.0))
+        }
+    }
+
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) -> fdo::Result<()> {
+        let (current, duration) = self.read(|v| (v.track_id(), v.duration));
+
+        // requests for stale tracks and out of range positions are ignored
+        if track_id != current || position < 0 || position > seconds_to_micros(duration) {
+            return Ok(());
+        }
+
+        self.send(PlaybackCommand::Seek(position as f64 / 1_000_000.0))
+    }
+
+    fn open_uri(&self, uri: String) -> fdo::Result<()> {
+        let path = file_path(&uri)
+            .ok_or_else(|| fdo::Error::NotSupported(format!("unsupported URI: {}", uri)))?;
+
+        self.send(PlaybackCommand::Open(path))
+    }
+
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        self.read(|v| v.playback_status().to_string())
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        self.read(|v| v.speed)
+    }
+
+    #[zbus(property)]
+    fn set_rate(&mut self, rate: f64) -> fdo::Result<()> {
+        if !rate.is_finite() {
+            return Err(fdo::Error::InvalidArgs(format!("invalid rate: {}", rate)));
+        }
+
+        self.send(PlaybackCommand::SetSpeed(rate))
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        MIN_SPEED
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        MAX_SPEED
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        self.read(PlayerState::metadata)
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.read(|v| v.volume)
+    }
+
+    #[zbus(property)]
+    fn set_volume(&mut self, volume: f64) -> fdo::Result<()> {
+        self.send(PlaybackCommand::SetVolume(volume.clamp(0.0, 1.0)))
+    }
+
+    /// Clients are expected to poll the position, so changes are not signalled.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        self.read(|v| seconds_to_micros(v.position))
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+/// The properties that are signalled to clients when they change.
+#[derive(Clone, Copy)]
+enum Property {
+    PlaybackStatus,
+    Metadata,
+    Volume,
+    Rate,
+}
+
+/// Publishes the current track on the session bus using the MPRIS D-Bus interface, which is used
+/// by desktop environments to show media controls. Commands from these controls are sent to the
+/// playback thread through the PlayerHandle.
+pub struct Mpris {
+    connection: Connection,
+    state: Arc<Mutex<PlayerState>>,
+    art_path: Option<PathBuf>,
+}
+
+impl Mpris {
+    /// Connects to the session bus and registers the player.
+    pub async fn new() -> zbus::Result<Self> {
+        let state = Arc::new(Mutex::new(PlayerState::default()));
+        let player = PlayerInterface {
+            state: state.clone(),
+        };
+
+        let connection = connection::Builder::session()?
+            .name(BUS_NAME)?
+            .serve_at(OBJECT_PATH, RootInterface)?
+            .serve_at(OBJECT_PATH, player)?
+            .build()
+            .await?;
+
+        debug!("Registered MPRIS player as {}", BUS_NAME);
+
+        Ok(Mpris {
+            connection,
+            state,
+            art_path: None,
+        })
+    }
+
+    fn update(&self, f: impl FnOnce(&mut PlayerState)) {
+        f(&mut self.state.lock().expect("couldn't get MPRIS state"))
+    }
+
+    async fn player(&self) -> zbus::Result<InterfaceRef<PlayerInterface>> {
+        self.connection
+            .object_server()
+            .interface::<_, PlayerInterface>(OBJECT_PATH)
+            .await
+    }
+
+    async fn properties_changed(&self, properties: &[Property]) {
+        let result: zbus::Result<()> = async {
+            let player = self.player().await?;
+            let emitter = player.signal_emitter();
+            let iface = player.get().await;
+
+            for property in properties {
+                match property {
+                    Property::PlaybackStatus => iface.playback_status_changed(emitter).await?,
+                    Property::Metadata => iface.metadata_changed(emitter).await?,
+                    Property::Volume => iface.volume_changed(emitter).await?,
+                    Property::Rate => iface.rate_changed(emitter).await?,
+                }
+            }
+
+            Ok(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            warn!("Could not update MPRIS properties: {}", e);
+        }
+    }
+
+    async fn seeked(&self, position: u64) {
+        let result = match self.player().await {
+            Ok(player) => {
+                PlayerInterface::seeked(player.signal_emitter(), seconds_to_micros(position)).await
+            }
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = result {
+            warn!("Could not send MPRIS Seeked signal: {}", e);
+        }
+    }
+
+    /// Writes the album art to the cache directory, since MPRIS clients expect a URL. Each track
+    /// gets a new file name, so that clients don't show a cached image from the previous track.
+    async fn write_album_art(&mut self, art: Arc<[u8]>) -> Option<String> {
+        let extension = image::guess_format(&art)
+            .ok()
+            .and_then(|v| v.extensions_str().first().copied())
+            .unwrap_or("img");
+        let track = self.state.lock().expect("couldn't get MPRIS state").track;
+
+        let directory = get_dirs().cache_dir().join("mpris");
+        let path = directory.join(format!("art-{}.{}", track, extension));
+
+        if let Err(e) = async_std::fs::create_dir_all(&directory).await {
+            warn!("Could not create MPRIS album art directory: {}", e);
+            return None;
+        }
+
+        if let Err(e) = async_std::fs::write(&path, &art).await {
+            warn!("Could not write MPRIS album art: {}", e);
+            return None;
+        }
+
+        self.art_path = Some(path.clone());
+        Some(file_uri(&path))
+    }
+
+    async fn remove_album_art(&mut self) {
+        if let Some(path) = self.art_path.take() {
+            let _ = async_std::fs::remove_file(path).await;
+        }
+    }
+}
+
+#[async_trait]
+impl MediaMetadataBroadcastService for Mpris {
+    fn attach(&mut self, handle: PlayerHandle) {
+        self.update(|v| v.handle = Some(handle));
+    }
+
+    async fn new_track(&mut self, file_path: PathBuf) {
+        self.update(|v| {
+            v.track += 1;
+            v.path = Some(file_path);
+            v.metadata = None;
+            v.art_url = None;
+            v.position = 0;
+        });
+
+        self.properties_changed(&[Property::Metadata]).await;
+    }
+
+    async fn metadata_recieved(&mut self, info: Arc<Metadata>) {
+        self.update(|v| v.metadata = Some(info));
+        self.properties_changed(&[Property::Metadata]).await;
+    }
+
+    async fn state_changed(&mut self, state: PlaybackState) {
+        self.update(|v| {
+            v.state = state;
+
+            if state == PlaybackState::Stopped {
+                v.path = None;
+                v.metadata = None;
+                v.art_url = None;
+                v.position = 0;
+            }
+        });
+
+        if state == PlaybackState::Stopped {
+            self.remove_album_art().await;
+        }
+
+        self.properties_changed(&[Property::PlaybackStatus, Property::Metadata])
+            .await;
+    }
+
+    async fn position_changed(&mut self, position: u64) {
+        let previous = {
+            let mut state = self.state.lock().expect("couldn't get MPRIS state");
+            std::mem::replace(&mut state.position, position)
+        };
+
+        // the position normally advances by up to a few seconds per update (depending on the
+        // speed), anything else is a seek
+        if position < previous || position > previous + MAX_SPEED as u64 {
+            self.seeked(position).await;
+        }
+    }
+
+    async fn duration_changed(&mut self, duration: u64) {
+        self.update(|v| v.duration = duration);
+        self.properties_changed(&[Property::Metadata]).await;
+    }
+
+    async fn speed_changed(&mut self, speed: f64) {
+        self.update(|v| v.speed = speed);
+        self.properties_changed(&[Property::Rate]).await;
+    }
+
+    async fn volume_changed(&mut self, volume: f64) {
+        self.update(|v| v.volume = volume);
+        self.properties_changed(&[Property::Volume]).await;
+    }
+
+    async fn album_art_changed(&mut self, art: Option<Arc<[u8]>>) {
+        self.remove_album_art().await;
+
+        let url = match art {
+            Some(art) => self.write_album_art(art).await,
+            None => None,
+        };
+
+        self.update(|v| v.art_url = url);
+        self.properties_changed(&[Property::Metadata]).await;
+    }
+}
+
+impl Drop for Mpris {
+    fn drop(&mut self) {
+        if let Some(path) = self.art_path.take() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_uris_are_percent_encoded() {
+        let path = Path::new("/music/AC/DC #1/100% ?.flac");
+
+        assert_eq!(
+            file_uri(path),
+            "file:///music/AC/DC%20%231/100%25%20%3F.flac"
+        );
+    }
+
+    #[test]
+    fn file_uris_round_trip() {
+        let path = PathBuf::from(OsString::from_vec(
+            b"/music/caf\xe9 & co/track.mp3".to_vec(),
+        ));
+
+        assert_eq!(file_path(&file_uri(&path)), Some(path));
+    }
+
+    #[test]
+    fn other_hosts_are_rejected() {
+        assert_eq!(
+            file_path("file://localhost/music/a%20b.flac"),
+            Some(PathBuf::from("/music/a b.flac"))
+        );
+        assert_eq!(file_path("file://server/music/a.flac"), None);
+        assert_eq!(file_path("http://example.com/a.flac"), None);
+    }
+
+    #[test]
+    fn invalid_rates_are_rejected() {
+        let mut player = PlayerInterface {
+            state: Arc::new(Mutex::new(PlayerState::default())),
+        };
+
+        for rate in [f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                player.set_rate(rate),
+                Err(fdo::Error::InvalidArgs(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,514 @@
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::{error, warn};
+
+use crate::{
+    library::scan::genres::track_genres, media::metadata::Metadata, playback::history::ListenTime,
+    settings::scrobbling::ScrobblingSettings,
+};
+
+/// The compiled form of the exclusion rules in ScrobblingSettings.
+struct ScrobbleRules {
+    paths: GlobSet,
+    genres: Vec<String>,
+    albums: Vec<String>,
+    min_duration: u64,
+}
+
+impl ScrobbleRules {
+    fn new(settings: &ScrobblingSettings) -> Self {
+        let mut builder = GlobSetBuilder::new();
+
+        for pattern in &settings.exclude_paths {
+            match Glob::new(pattern) {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(e) => warn!("Ignoring invalid scrobble exclusion {:?}: {}", pattern, e),
+            }
+        }
+
+        ScrobbleRules {
+            paths: builder.build().unwrap_or_else(|e| {
+                error!("Could not build the scrobble exclusions: {}", e);
+                GlobSet::empty()
+            }),
+            genres: settings
+                .exclude_genres
+                .iter()
+                .map(|v| v.to_lowercase())
+                .collect(),
+            albums: settings
+                .exclude_albums
+                .iter()
+                .map(|v| v.to_lowercase())
+                .collect(),
+            min_duration: settings.min_duration,
+        }
+    }
+
+    /// Genres are matched against each of the track's genres, split the same way as they are in
+    /// the library, so that "Rock" matches a track tagged "Rock; Pop".
+    fn excludes(&self, path: Option<&Path>, metadata: Option<&Metadata>, duration: u64) -> bool {
+        let matches = |list: &[String], value: &str| list.contains(&value.to_lowercase());
+
+        path.is_some_and(|v| self.paths.is_match(v))
+            || metadata.is_some_and(|v| {
+                track_genres(v)
+                    .iter()
+                    .any(|genre| matches(&self.genres, genre))
+                    || v.album
+                        .as_deref()
+                        .is_some_and(|album| matches(&self.albums, album))
+            })
+            || (duration > 0 && duration < self.min_duration)
+    }
+}
+
+/// Decides which tracks may be scrobbled, shared between every scrobbling service. Tracks can be
+/// excluded by the rules in the settings, and nothing is scrobbled during a private session.
+#[derive(Clone)]
+pub struct ScrobbleFilter {
+    rules: Arc<RwLock<ScrobbleRules>>,
+    private_session: Arc<AtomicBool>,
+}
+
+impl ScrobbleFilter {
+    pub fn new(settings: &ScrobblingSettings) -> Self {
+        ScrobbleFilter {
+            rules: Arc::new(RwLock::new(ScrobbleRules::new(settings))),
+            private_session: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Replaces the exclusion rules, for example after the settings file has changed.
+    pub fn set_rules(&self, settings: &ScrobblingSettings) {
+        *self.rules.write().expect("poisoned lock") = ScrobbleRules::new(settings);
+    }
+
+    pub fn set_private_session(&self, private: bool) {
+        self.private_session.store(private, Ordering::Relaxed);
+    }
+
+    pub fn private_session(&self) -> bool {
+        self.private_session.load(Ordering::Relaxed)
+    }
+
+    /// Whether a track may be scrobbled. The metadata and duration may be missing if they haven't
+    /// been read yet, in which case only the remaining rules are checked.
+    pub fn allows(&self, path: Option<&Path>, metadata: Option<&Metadata>, duration: u64) -> bool {
+        !self.private_session()
+            && !self
+                .rules
+                .read()
+                .expect("poisoned lock")
+                .excludes(path, metadata, duration)
+    }
+}
+
+/// Tracks how much of the current track has been listened to, to decide whether it should be
+/// scrobbled. A track is scrobbled once it passes the threshold described in `ListenTime`, unless
+/// it is rejected by the ScrobbleFilter, or a private session was active at any point while it
+/// played.
+pub struct PlayTracker {
+    filter: ScrobbleFilter,
+    path: Option<PathBuf>,
+    metadata: Option<Arc<Metadata>>,
+    start_timestamp: Option<DateTime<Utc>>,
+    time: ListenTime,
+    should_scrobble: bool,
+    private: bool,
+}
+
+impl PlayTracker {
+    pub fn new(filter: ScrobbleFilter) -> Self {
+        PlayTracker {
+            filter,
+            path: None,
+            metadata: None,
+            start_timestamp: None,
+            time: ListenTime::default(),
+            should_scrobble: false,
+            private: false,
+        }
+    }
+
+    pub fn new_track(&mut self, path: PathBuf) {
+        self.path = Some(path);
+        self.metadata = None;
+        self.start_timestamp = Some(Utc::now());
+        self.time.new_track();
+        self.should_scrobble = false;
+        self.private = self.filter.private_session();
+    }
+
+    pub fn metadata_recieved(&mut self, metadata: Arc<Metadata>) {
+        self.metadata = Some(metadata);
+    }
+
+    /// Whether the filter allows the current track to be scrobbled. Services should also check
+    /// this before sending now playing notifications.
+    pub fn allowed(&self) -> bool {
+        self.filter.allows(
+            self.path.as_deref(),
+            self.metadata.as_deref(),
+            self.time.duration(),
+        )
+    }
+
+    pub fn position_changed(&mut self, position: u64) {
+        self.time.position_changed(position);
+        self.private |= self.filter.private_session();
+
+        if self.time.passed_threshold() {
+            self.should_scrobble = true;
+        }
+    }
+
+    pub fn duration_changed(&mut self, duration: u64) {
+        self.time.duration_changed(duration);
+    }
+
+    pub fn speed_changed(&mut self, speed: f64) {
+        self.time.speed_changed(speed);
+    }
+
+    /// Returns whether the track should be scrobbled, and resets the flag so that it is only
+    /// scrobbled once.
+    pub fn take(&mut self) -> bool {
+        std::mem::take(&mut self.should_scrobble) && !self.private && self.allowed()
+    }
+
+    /// The time the current track started playing.
+    pub fn start_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.start_timestamp
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.time.duration()
+    }
+}
+
+/// Submissions that have not been sent yet. The cache is written to the data directory every time
+/// it changes, so that plays made while offline are submitted after Muzak is restarted.
+pub struct OfflineCache<T> {
+    path: PathBuf,
+    pending: Vec<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> OfflineCache<T> {
+    pub fn load(path: PathBuf) -> Self {
+        let pending = match File::open(&path) {
+            Ok(file) => {
+                let reader = std::io::BufReader::new(file);
+
+                serde_json::from_reader(reader).unwrap_or_else(|e| {
+                    error!("The cache at {:?} could not be read: {:?}", path, e);
+                    warn!("Pending submissions will be lost.");
+                    Vec::new()
+                })
+            }
+            Err(_) => Vec::new(),
+        };
+
+        OfflineCache { path, pending }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn push(&mut self, item: T) {
+        self.pending.push(item);
+        self.save();
+    }
+
+    /// Returns up to `size` of the oldest items.
+    pub fn batch(&self, size: usize) -> &[T] {
+        &self.pending[..self.pending.len().min(size)]
+    }
+
+    /// Removes the oldest `count` items, after they have been submitted.
+    pub fn remove(&mut self, count: usize) {
+        self.pending.drain(..count.min(self.pending.len()));
+        self.save();
+    }
+
+    /// Keeps only the items for which `f` returns true, and returns the number of removed items.
+    pub fn retain(&mut self, f: impl FnMut(&T) -> bool) -> usize {
+        let before = self.pending.len();
+        self.pending.retain(f);
+
+        let removed = before - self.pending.len();
+        if removed > 0 {
+            self.save();
+        }
+
+        removed
+    }
+
+    fn save(&self) {
+        if self.pending.is_empty() {
+            let _ = fs::remove_file(&self.path);
+            return;
+        }
+
+        let result = serde_json::to_vec(&self.pending)
+            .map_err(std::io::Error::from)
+            .and_then(|v| fs::write(&self.path, v));
+
+        if let Err(e) = result {
+            error!("Could not write the cache at {:?}: {:?}", self.path, e);
+        }
+    }
+}
+
+/// The delay before the first retry of a failed submission. The delay doubles with every failure,
+/// up to MAX_RETRY_DELAY.
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// Exponential backoff for retrying failed submissions.
+#[derive(Default)]
+pub struct Backoff {
+    failures: u32,
+    retry_at: Option<Instant>,
+}
+
+impl Backoff {
+    /// Whether the next attempt may be made.
+    pub fn ready(&self) -> bool {
+        self.retry_at.is_none_or(|v| Instant::now() >= v)
+    }
+
+    /// Records a failed attempt, and returns the delay before the next one.
+    pub fn failed(&mut self) -> Duration {
+        self.failures += 1;
+        let delay = RETRY_DELAY
+            .saturating_mul(2u32.saturating_pow(self.failures - 1))
+            .min(MAX_RETRY_DELAY);
+
+        self.retry_at = Some(Instant::now() + delay);
+        delay
+    }
+
+    pub fn succeeded(&mut self) {
+        self.failures = 0;
+        self.retry_at = None;
+    }
+}
+
+/// A stand-in for a scrobbling service's API, for testing how the services handle its responses.
+#[cfg(test)]
+pub mod mock {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        path::PathBuf,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+    };
+
+    /// An HTTP server that answers every request with the same response.
+    pub struct MockServer {
+        pub url: String,
+        requests: Arc<AtomicUsize>,
+    }
+
+    impl MockServer {
+        pub fn start(status: u16, body: &'static str) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/", listener.local_addr().unwrap());
+            let requests = Arc::new(AtomicUsize::new(0));
+            let count = requests.clone();
+
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = stream else {
+                        continue;
+                    };
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut length = 0;
+
+                    loop {
+                        let mut line = String::new();
+                        if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                            break;
+                        }
+                        if let Some((name, value)) = line.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                length = value.trim().parse().unwrap_or(0);
+                            }
+                        }
+                    }
+
+                    let mut request = vec![0; length];
+                    let _ = reader.read_exact(&mut request);
+                    count.fetch_add(1, Ordering::SeqCst);
+
+                    let _ = write!(
+                        stream,
+                        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\n\
+                         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                }
+            });
+
+            MockServer { url, requests }
+        }
+
+        /// The number of requests received so far.
+        pub fn requests(&self) -> usize {
+            self.requests.load(Ordering::SeqCst)
+        }
+    }
+
+    /// A path for a cache file that doesn't exist yet.
+    pub fn cache_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("muzak-test-{}-{}.json", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mock::cache_path, *};
+
+    #[test]
+    fn cache_is_kept_in_order_across_restarts() {
+        let path = cache_path("cache-order");
+
+        let mut cache = OfflineCache::load(path.clone());
+        for i in 0..5 {
+            cache.push(i);
+        }
+
+        let mut cache = OfflineCache::<i32>::load(path.clone());
+        assert_eq!(cache.batch(3), &[0, 1, 2]);
+
+        cache.remove(3);
+        assert_eq!(cache.batch(3), &[3, 4]);
+
+        let mut cache = OfflineCache::<i32>::load(path.clone());
+        assert_eq!(cache.len(), 2);
+
+        cache.remove(10);
+        assert!(cache.is_empty());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn cache_retain_reports_removed_items() {
+        let path = cache_path("cache-retain");
+
+        let mut cache = OfflineCache::load(path.clone());
+        for i in 0..6 {
+            cache.push(i);
+        }
+
+        assert_eq!(cache.retain(|v| v % 2 == 0), 3);
+        assert_eq!(
+            OfflineCache::<i32>::load(path.clone()).batch(10),
+            &[0, 2, 4]
+        );
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn unreadable_cache_starts_empty() {
+        let path = cache_path("cache-corrupt");
+        fs::write(&path, "not json").unwrap();
+
+        assert!(OfflineCache::<i32>::load(path.clone()).is_empty());
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_limit() {
+        let mut backoff = Backoff::default();
+        assert!(backoff.ready());
+
+        assert_eq!(backoff.failed(), RETRY_DELAY);
+        assert_eq!(backoff.failed(), RETRY_DELAY * 2);
+        assert_eq!(backoff.failed(), RETRY_DELAY * 4);
+        assert!(!backoff.ready());
+
+        for _ in 0..40 {
+            backoff.failed();
+        }
+        assert_eq!(backoff.failed(), MAX_RETRY_DELAY);
+
+        backoff.succeeded();
+        assert!(backoff.ready());
+        assert_eq!(backoff.failed(), RETRY_DELAY);
+    }
+
+    #[test]
+    fn genre_rules_match_any_of_the_genres() {
+        let filter = ScrobbleFilter::new(&ScrobblingSettings {
+            exclude_genres: vec!["rock".to_string()],
+            ..Default::default()
+        });
+        let metadata = |genre: &str| Metadata {
+            genre: Some(genre.to_string()),
+            ..Default::default()
+        };
+
+        assert!(!filter.allows(None, Some(&metadata("Rock; Pop")), 0));
+        assert!(!filter.allows(None, Some(&metadata("Pop/Rock")), 0));
+        assert!(filter.allows(None, Some(&metadata("Pop")), 0));
+        assert!(filter.allows(None, Some(&metadata("Rock and Roll")), 0));
+    }
+
+    fn play(tracker: &mut PlayTracker, from: u64, to: u64) {
+        for position in from..=to {
+            tracker.position_changed(position);
+        }
+    }
+
+    #[test]
+    fn private_sessions_apply_to_the_current_track() {
+        let filter = ScrobbleFilter::new(&ScrobblingSettings::default());
+        let mut tracker = PlayTracker::new(filter.clone());
+
+        tracker.new_track(PathBuf::from("/music/a.flac"));
+        tracker.duration_changed(60);
+        play(&mut tracker, 0, 10);
+        filter.set_private_session(true);
+        play(&mut tracker, 11, 20);
+        filter.set_private_session(false);
+        play(&mut tracker, 21, 60);
+        assert!(!tracker.take());
+
+        tracker.new_track(PathBuf::from("/music/b.flac"));
+        tracker.duration_changed(60);
+        play(&mut tracker, 0, 60);
+        assert!(tracker.take());
+        assert!(!tracker.take());
+    }
+}
@@ -1,4 +1,10 @@
+pub mod lastfm;
+pub mod listenbrainz;
+pub mod mpd;
+pub mod playback;
+pub mod remote;
 pub mod scan;
+pub mod scrobbling;
 pub mod storage;
 
 use std::{fs::File, path::PathBuf, sync::mpsc::channel, time::Duration};
@@ -13,6 +19,18 @@ use tracing::{info, warn};
 pub struct Settings {
     #[serde(default)]
     pub scanning: scan::ScanSettings,
+    #[serde(default)]
+    pub playback: playback::PlaybackSettings,
+    #[serde(default)]
+    pub remote: remote::RemoteSettings,
+    #[serde(default)]
+    pub mpd: mpd::MpdSettings,
+    #[serde(default)]
+    pub lastfm: lastfm::LastFMSettings,
+    #[serde(default)]
+    pub listenbrainz: listenbrainz::ListenBrainzSettings,
+    #[serde(default)]
+    pub scrobbling: scrobbling::ScrobblingSettings,
 }
 
 pub fn create_settings(path: &PathBuf) -> Settings {
@@ -29,9 +47,30 @@ pub fn create_settings(path: &PathBuf) -> Settings {
     }
 }
 
+/// Changes the settings from within the app, and writes them to the settings file.
+pub fn update_settings(cx: &mut App, update: impl FnOnce(&mut Settings)) {
+    let global = cx.global::<SettingsGlobal>();
+    let model = global.model.clone();
+    let path = global.path.clone();
+
+    model.update(cx, |settings, cx| {
+        update(settings);
+
+        let result = File::create(&path)
+            .and_then(|file| serde_json::to_writer_pretty(file, settings).map_err(|e| e.into()));
+        if let Err(e) = result {
+            warn!("Could not write settings to {:?}: {:?}", path, e);
+        }
+
+        cx.notify();
+    });
+}
+
 pub struct SettingsGlobal {
     pub model: Entity<Settings>,
     pub watcher: Option<Box<dyn Watcher>>,
+    /// The settings file, which changes made in the app are written to.
+    pub path: PathBuf,
 }
 
 impl Global for SettingsGlobal {}
@@ -51,11 +90,14 @@ pub fn setup_settings(cx: &mut App, path: PathBuf) {
         let global = SettingsGlobal {
             model: settings,
             watcher: None,
+            path,
         };
 
         cx.set_global(global);
         return;
     };
+    let settings_path = path.clone();
+
     if let Err(e) = watcher.watch(path.parent().unwrap(), RecursiveMode::Recursive) {
         warn!("failed to watch settings file: {:?}", e);
     }
@@ -72,16 +114,18 @@ pub fn setup_settings(cx: &mut App, path: PathBuf) {
                             info!("Settings changed, updating...");
                             let settings = create_settings(&path);
                             settings_model
-                                .update(app, |v, _| {
+                                .update(app, |v, cx| {
                                     *v = settings;
+                                    cx.notify();
                                 })
                                 .expect("settings model could not be updated");
                         }
                         notify::EventKind::Remove(_) => {
                             info!("Settings file removed, using default settings");
                             settings_model
-                                .update(app, |v, _| {
+                                .update(app, |v, cx| {
                                     *v = Settings::default();
+                                    cx.notify();
                                 })
                                 .expect("settings model could not be updated");
                         }
@@ -101,6 +145,7 @@ pub fn setup_settings(cx: &mut App, path: PathBuf) {
     let global = SettingsGlobal {
         model: settings,
         watcher: Some(Box::new(watcher)),
+        path: settings_path,
     };
 
     cx.set_global(global);
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+use crate::services::mmb::lastfm::client::DEFAULT_API_URL;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastFMSettings {
+    /// The URL of the last.fm API. This can be changed to use a compatible service.
+    #[serde(default = "default_api_url")]
+    pub api_url: String,
+}
+
+impl Default for LastFMSettings {
+    fn default() -> Self {
+        Self {
+            api_url: default_api_url(),
+        }
+    }
+}
+
+fn default_api_url() -> String {
+    DEFAULT_API_URL.to_string()
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+use crate::services::mmb::listenbrainz::client::DEFAULT_API_URL;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListenBrainzSettings {
+    /// The user token, found on the ListenBrainz settings page. Listens are only submitted if a
+    /// token is set.
+    #[serde(default)]
+    pub token: Option<String>,
+    /// The URL of the ListenBrainz API, which can be changed for self-hosted instances.
+    #[serde(default = "default_api_url")]
+    pub api_url: String,
+}
+
+impl Default for ListenBrainzSettings {
+    fn default() -> Self {
+        Self {
+            token: None,
+            api_url: default_api_url(),
+        }
+    }
+}
+
+fn default_api_url() -> String {
+    DEFAULT_API_URL.to_string()
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MpdSettings {
+    /// Whether the MPD server should be started. Changes take effect when Muzak is restarted.
+    #[serde(default)]
+    pub enabled: bool,
+    /// The address the server listens on. Addresses other than loopback addresses are only used
+    /// if `allow_network` is set.
+    #[serde(default = "default_address")]
+    pub address: String,
+    /// Whether the server may listen on an address that other devices can reach. The MPD protocol
+    /// has no authentication, so this should only be set on trusted networks.
+    #[serde(default)]
+    pub allow_network: bool,
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+impl Default for MpdSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: default_address(),
+            allow_network: false,
+            port: default_port(),
+        }
+    }
+}
+
+fn default_address() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_port() -> u16 {
+    6600
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackSettings {
+    /// Whether changing the playback speed should keep the pitch of the track intact. If false,
+    /// changing the speed also changes the pitch, like a record played at the wrong speed.
+    #[serde(default = "default_true")]
+    pub preserve_pitch: bool,
+    /// Whether long tracks (such as audiobooks and lectures) should continue from where they were
+    /// last stopped when they are played again.
+    #[serde(default)]
+    pub resume_long_tracks: bool,
+}
+
+impl Default for PlaybackSettings {
+    fn default() -> Self {
+        Self {
+            preserve_pitch: true,
+            resume_long_tracks: false,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
@@ -1,22 +1,106 @@
-use std::{fs::exists, path::PathBuf};
+use std::{
+    fs::exists,
+    path::{Path, PathBuf},
+};
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use tracing::{error, warn};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScanSettings {
-    #[serde(default = "retrieve_default_paths")]
-    pub paths: Vec<PathBuf>,
+    /// The folders the library is scanned from. Older settings files list them as plain paths,
+    /// which are read as folders with the default rules.
+    #[serde(
+        alias = "paths",
+        default = "retrieve_default_roots",
+        deserialize_with = "deserialize_roots"
+    )]
+    pub roots: Vec<LibraryRoot>,
 }
 
 impl Default for ScanSettings {
     fn default() -> Self {
         Self {
-            paths: retrieve_default_paths(),
+            roots: retrieve_default_roots(),
         }
     }
 }
 
+impl ScanSettings {
+    pub fn root_paths(&self) -> Vec<PathBuf> {
+        self.roots.iter().map(|v| v.path.clone()).collect()
+    }
+}
+
+/// A folder the library is scanned from, along with the rules for which of the files in it are
+/// scanned.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LibraryRoot {
+    pub path: PathBuf,
+    /// Glob patterns for files and folders that aren't scanned, such as `**/Samples/**` or
+    /// `*.tmp`. They are matched against paths relative to the root.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Files smaller than this many bytes aren't scanned.
+    #[serde(default)]
+    pub min_file_size: u64,
+    #[serde(default = "default_follow_symlinks")]
+    pub follow_symlinks: bool,
+    /// How many folders deep files are scanned, where 0 only scans the files directly in the
+    /// root. Unlimited if not set.
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    /// Whether folders and files starting with a `.` are skipped.
+    #[serde(default)]
+    pub skip_hidden: bool,
+}
+
+impl LibraryRoot {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            exclude: Vec::new(),
+            min_file_size: 0,
+            follow_symlinks: default_follow_symlinks(),
+            max_depth: None,
+            skip_hidden: false,
+        }
+    }
+}
+
+fn default_follow_symlinks() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RootEntry {
+    Path(PathBuf),
+    Root(LibraryRoot),
+}
+
+fn deserialize_roots<'de, D>(deserializer: D) -> Result<Vec<LibraryRoot>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let entries: Vec<RootEntry> = Vec::deserialize(deserializer)?;
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| match entry {
+            RootEntry::Path(path) => LibraryRoot::new(&path),
+            RootEntry::Root(root) => root,
+        })
+        .collect())
+}
+
+fn retrieve_default_roots() -> Vec<LibraryRoot> {
+    retrieve_default_paths()
+        .iter()
+        .map(|v| LibraryRoot::new(v))
+        .collect()
+}
+
 fn retrieve_default_paths() -> Vec<PathBuf> {
     if let Some(user_directories) = directories::UserDirs::new() {
         if let Some(dir) = user_directories.audio_dir() {
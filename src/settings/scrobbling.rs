@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// Rules for excluding tracks from every scrobbling service.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ScrobblingSettings {
+    /// Glob patterns matched against the full path of the track.
+    #[serde(default)]
+    pub exclude_paths: Vec<String>,
+    /// Genres that are never scrobbled, compared case-insensitively.
+    #[serde(default)]
+    pub exclude_genres: Vec<String>,
+    /// Albums that are never scrobbled, compared case-insensitively.
+    #[serde(default)]
+    pub exclude_albums: Vec<String>,
+    /// Tracks shorter than this many seconds are never scrobbled.
+    #[serde(default)]
+    pub min_duration: u64,
+}
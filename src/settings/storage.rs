@@ -2,12 +2,15 @@ use serde::{Deserialize, Serialize};
 
 use crate::ui::models::CurrentTrack;
 
-use std::{fs, path::PathBuf};
+use std::{collections::BTreeMap, fs, path::PathBuf};
 
 /// Data to store while quitting the app
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct StorageData {
     pub current_track: Option<CurrentTrack>,
+    /// The columns, widths and sort order of each table, by table name.
+    #[serde(default)]
+    pub table_layouts: BTreeMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone)]
@@ -42,6 +45,7 @@ impl Storage {
                         // validate whether path still exists
                         Some(current_track) if !current_track.get_path().exists() => StorageData {
                             current_track: None,
+                            ..data
                         },
                         _ => data,
                     })
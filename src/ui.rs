@@ -1,10 +1,12 @@
 pub mod app;
-mod arguments;
 mod assets;
+mod bookmarks;
 pub mod components;
 mod constants;
+mod control;
 mod controls;
 pub mod data;
+mod folders;
 mod global_actions;
 mod header;
 mod library;
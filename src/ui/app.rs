@@ -1,7 +1,11 @@
 use core::panic;
 use std::{
     fs,
-    sync::{Arc, RwLock},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
 };
 
 use directories::ProjectDirs;
@@ -11,11 +15,21 @@ use sqlx::SqlitePool;
 use tracing::{debug, error};
 
 use crate::{
+    control::OpenMode,
     library::{
         db::create_pool,
         scan::{ScanInterface, ScanThread},
     },
-    playback::{interface::GPUIPlaybackInterface, queue::QueueItemData, thread::PlaybackThread},
+    mpd::start_mpd_server,
+    playback::{
+        history::{record_current_play, start_play_history, PlayRecorder},
+        interface::GPUIPlaybackInterface,
+        queue::QueueItemData,
+        resume::start_resume_positions,
+        thread::PlaybackThread,
+    },
+    remote::start_remote_server,
+    services::mmb::PlayerHandle,
     settings::{
         setup_settings,
         storage::{Storage, StorageData},
@@ -24,16 +38,17 @@ use crate::{
 };
 
 use super::{
-    arguments::parse_args_and_prepare,
     assets::Assets,
+    bookmarks::setup_bookmarks,
     components::{input, modal},
     constants::APP_ROUNDING,
+    control::{open_files, setup_control_server},
     controls::Controls,
     data::create_album_cache,
     global_actions::register_actions,
     header::Header,
     library::Library,
-    models::{self, build_models, PlaybackInfo},
+    models::{self, build_models, Models, PlaybackInfo},
     queue::Queue,
     search::SearchView,
     theme::{setup_theme, Theme},
@@ -267,7 +282,7 @@ pub struct DropImageDummyModel;
 
 impl EventEmitter<Vec<Arc<RenderImage>>> for DropImageDummyModel {}
 
-pub async fn run() {
+pub async fn run(files: Vec<PathBuf>, mode: OpenMode) {
     let dirs = get_dirs();
     let directory = dirs.data_dir().to_path_buf();
     if !directory.exists() {
@@ -281,6 +296,10 @@ pub async fn run() {
     Application::new()
         .with_assets(Assets)
         .run(move |cx: &mut App| {
+            if !setup_control_server(cx, &files, mode) {
+                return;
+            }
+
             let bounds = Bounds::centered(None, size(px(1024.0), px(700.0)), cx);
             find_fonts(cx).expect("unable to load fonts");
 
@@ -290,6 +309,13 @@ pub async fn run() {
             let storage = Storage::new(directory.clone().join("app_data.json"));
             let storage_data = storage.load_or_default();
 
+            // the models read the settings when creating the MMBS services
+            setup_settings(cx, directory.join("settings.json"));
+
+            let current_play = Arc::new(Mutex::new(PlayRecorder::default()));
+            let mut playback_interface: GPUIPlaybackInterface =
+                PlaybackThread::start(queue.clone(), current_play.clone());
+
             build_models(
                 cx,
                 models::Queue {
@@ -297,13 +323,13 @@ pub async fn run() {
                     position: 0,
                 },
                 &storage_data,
+                PlayerHandle::new(playback_interface.command_sender(), queue.clone()),
             );
 
             input::bind_actions(cx);
             modal::bind_actions(cx);
 
             setup_theme(cx, directory.join("theme.json"));
-            setup_settings(cx, directory.join("settings.json"));
 
             create_album_cache(cx);
 
@@ -314,6 +340,41 @@ pub async fn run() {
                 scan_interface.scan();
                 scan_interface.start_broadcast(cx);
 
+                start_play_history(pool.clone(), playback_interface.subscribe());
+
+                cx.on_app_quit({
+                    let pool = pool.clone();
+                    move |cx| {
+                        let pool = pool.clone();
+                        let current_play = current_play.clone();
+                        cx.background_executor().spawn(async move {
+                            record_current_play(&pool, &current_play).await;
+                        })
+                    }
+                })
+                .detach();
+
+                let resume_enabled = Arc::new(AtomicBool::new(
+                    cx.global::<SettingsGlobal>()
+                        .model
+                        .read(cx)
+                        .playback
+                        .resume_long_tracks,
+                ));
+                start_resume_positions(
+                    pool.clone(),
+                    playback_interface.subscribe(),
+                    playback_interface.command_sender(),
+                    resume_enabled.clone(),
+                );
+
+                let settings = cx.global::<SettingsGlobal>().model.clone();
+                cx.observe(&settings, move |settings, cx| {
+                    let enabled = settings.read(cx).playback.resume_long_tracks;
+                    resume_enabled.store(enabled, Ordering::Relaxed);
+                })
+                .detach();
+
                 cx.set_global(scan_interface);
                 cx.set_global(Pool(pool));
             } else {
@@ -330,9 +391,30 @@ pub async fn run() {
             })
             .detach();
 
-            let mut playback_interface: GPUIPlaybackInterface = PlaybackThread::start(queue);
             playback_interface.start_broadcast(cx);
 
+            let settings = cx.global::<SettingsGlobal>().model.read(cx);
+            if settings.remote.enabled {
+                start_remote_server(
+                    &settings.remote,
+                    cx.global::<Pool>().0.clone(),
+                    playback_interface.command_sender(),
+                    playback_interface.subscribe(),
+                    queue.clone(),
+                );
+            }
+
+            if settings.mpd.enabled {
+                start_mpd_server(
+                    &settings.mpd,
+                    settings.scanning.root_paths(),
+                    cx.global::<Pool>().0.clone(),
+                    playback_interface.command_sender(),
+                    playback_interface.subscribe(),
+                    queue,
+                );
+            }
+
             if let Some(track) = storage_data.current_track {
                 // open current track,
                 playback_interface.open(track.get_path().clone());
@@ -340,9 +422,29 @@ pub async fn run() {
                 playback_interface.pause();
             }
 
-            parse_args_and_prepare(cx, &playback_interface);
+            let settings = cx.global::<SettingsGlobal>().model.clone();
+            playback_interface.set_pitch_preservation(settings.read(cx).playback.preserve_pitch);
+
+            cx.observe(&settings, |settings, cx| {
+                let preserve_pitch = settings.read(cx).playback.preserve_pitch;
+                cx.global::<GPUIPlaybackInterface>()
+                    .set_pitch_preservation(preserve_pitch);
+            })
+            .detach();
+
+            // the scanner ignores settings where the library folders haven't changed
+            cx.observe(&settings, |settings, cx| {
+                let scanning = settings.read(cx).scanning.clone();
+                cx.global::<ScanInterface>().update_settings(scanning);
+            })
+            .detach();
+
             cx.set_global(playback_interface);
 
+            open_files(cx, files.clone(), mode);
+
+            setup_bookmarks(cx);
+
             cx.activate(true);
 
             cx.open_window(
@@ -373,11 +475,16 @@ pub async fn run() {
                         // Update `StorageData` and save it to file system while quitting the app
                         cx.on_app_quit({
                             let current_track = cx.global::<PlaybackInfo>().current_track.clone();
+                            let table_layouts = cx.global::<Models>().table_layouts.clone();
                             move |_, cx| {
                                 let current_track = current_track.read(cx).clone();
+                                let table_layouts = table_layouts.read(cx).clone();
                                 let storage = storage.clone();
                                 cx.background_executor().spawn(async move {
-                                    storage.save(&StorageData { current_track });
+                                    storage.save(&StorageData {
+                                        current_track,
+                                        table_layouts,
+                                    });
                                 })
                             }
                         })
@@ -0,0 +1,288 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc, sync::Arc};
+
+use gpui::*;
+use prelude::FluentBuilder;
+use tracing::{error, warn};
+
+use crate::{
+    library::{db::LibraryAccess, types::Bookmark},
+    playback::interface::GPUIPlaybackInterface,
+};
+
+use super::{
+    components::{
+        input::{EnrichedInputAction, TextInput},
+        modal::modal,
+    },
+    constants::FONT_AWESOME,
+    models::{Models, PlaybackInfo},
+    theme::Theme,
+};
+
+/// Replaces the bookmarks in the Models global with the bookmarks for the given track. Files that
+/// aren't in the library have no bookmarks.
+pub fn load_bookmarks(cx: &mut App, path: Option<&PathBuf>) {
+    let track_id = match path.map(|path| cx.get_track_id(path)) {
+        Some(Ok(track_id)) => track_id,
+        Some(Err(e)) => {
+            error!("Unable to find track {:?}: {:?}", path, e);
+            None
+        }
+        None => None,
+    };
+    let bookmarks = match track_id {
+        Some(track_id) => cx.list_bookmarks(track_id).unwrap_or_else(|e| {
+            error!("Unable to load bookmarks for {:?}: {:?}", path, e);
+            Arc::new(Vec::new())
+        }),
+        None => Arc::new(Vec::new()),
+    };
+
+    let model = cx.global::<Models>().bookmarks.clone();
+    model.update(cx, |m, cx| {
+        *m = bookmarks;
+        cx.notify();
+    });
+}
+
+/// Keeps the bookmarks in the Models global in sync with the current track.
+pub fn setup_bookmarks(cx: &mut App) {
+    let playback_info = cx.global::<PlaybackInfo>().clone();
+    let current = Rc::new(RefCell::new(None));
+
+    cx.observe(&playback_info.current_track, move |m, cx| {
+        let path = m.read(cx).as_ref().map(|v| v.get_path().clone());
+
+        if *current.borrow() == path {
+            return;
+        }
+
+        load_bookmarks(cx, path.as_ref());
+        current.replace(path);
+    })
+    .detach();
+}
+
+/// A modal used to add, jump to and remove the bookmarks of the current track.
+pub struct BookmarkView {
+    show: Entity<bool>,
+    input: Entity<TextInput>,
+    name: String,
+    bookmarks: Entity<Arc<Vec<Bookmark>>>,
+    handle: FocusHandle,
+}
+
+impl BookmarkView {
+    pub fn new(cx: &mut App, show: Entity<bool>) -> Entity<Self> {
+        cx.new(|cx| {
+            let handle = cx.focus_handle();
+            let weak = cx.weak_entity();
+            let handler = move |action, _: &mut Window, cx: &mut App| {
+                if let EnrichedInputAction::Accept = action {
+                    weak.update(cx, |this: &mut BookmarkView, cx| this.add_bookmark(cx))
+                        .expect("failed to update bookmark view");
+                }
+            };
+            let input = TextInput::new(
+                cx,
+                handle.clone(),
+                None,
+                Some("Bookmark name".into()),
+                Some(Box::new(handler)),
+            );
+            let bookmarks = cx.global::<Models>().bookmarks.clone();
+
+            cx.subscribe(&input, |this: &mut BookmarkView, _, ev: &String, _| {
+                this.name = ev.clone();
+            })
+            .detach();
+
+            cx.observe(&show, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            cx.observe(&bookmarks, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            BookmarkView {
+                show,
+                input,
+                name: String::new(),
+                bookmarks,
+                handle,
+            }
+        })
+    }
+
+    /// Adds a bookmark at the current position, named after the contents of the input. If the
+    /// input is empty, the bookmark is named after its position.
+    fn add_bookmark(&mut self, cx: &mut Context<Self>) {
+        let info = cx.global::<PlaybackInfo>().clone();
+        let Some(track) = info.current_track.read(cx).clone() else {
+            return;
+        };
+        let position = *info.position.read(cx);
+        let name = match self.name.trim() {
+            "" => format!("{:02}:{:02}", position / 60, position % 60),
+            name => name.to_string(),
+        };
+
+        match cx.get_track_id(track.get_path()) {
+            Ok(Some(track_id)) => {
+                if let Err(e) = cx.create_bookmark(track_id, &name, position as f64) {
+                    error!("Unable to create bookmark: {:?}", e);
+                }
+            }
+            Ok(None) => warn!("Only tracks in the library can be bookmarked"),
+            Err(e) => error!("Unable to find track {:?}: {:?}", track.get_path(), e),
+        }
+
+        load_bookmarks(cx, Some(track.get_path()));
+        self.reset(cx);
+    }
+
+    fn reset(&mut self, cx: &mut Context<Self>) {
+        self.name.clear();
+        cx.update_entity(&self.input, |input, cx| {
+            input.reset();
+            cx.notify();
+        });
+        self.show.update(cx, |m, cx| {
+            *m = false;
+            cx.notify();
+        })
+    }
+}
+
+impl Render for BookmarkView {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if !*self.show.read(cx) {
+            return div().into_any_element();
+        }
+
+        if !self.handle.is_focused(window) {
+            self.handle.focus(window);
+        }
+
+        let theme = cx.global::<Theme>();
+        let bookmarks = self.bookmarks.read(cx).clone();
+        let weak = cx.weak_entity();
+
+        modal()
+            .on_exit(move |_, cx| {
+                weak.update(cx, |this, cx| {
+                    this.reset(cx);
+                })
+                .expect("failed to update bookmark view")
+            })
+            .child(
+                div()
+                    .w(px(400.0))
+                    .max_h(px(350.0))
+                    .overflow_hidden()
+                    .flex_col()
+                    .font_family("Inter")
+                    .font_weight(FontWeight::NORMAL)
+                    .child(
+                        div()
+                            .w_full()
+                            .p(px(12.0))
+                            .line_height(px(14.0))
+                            .h(px(40.0))
+                            .text_sm()
+                            .border_b(px(1.0))
+                            .border_color(theme.border_color)
+                            .child(self.input.clone()),
+                    )
+                    .child(
+                        div()
+                            .id("bookmark-list")
+                            .flex()
+                            .flex_col()
+                            .overflow_y_scroll()
+                            .p(px(4.0))
+                            .when(bookmarks.is_empty(), |this| {
+                                this.child(
+                                    div()
+                                        .p(px(8.0))
+                                        .text_sm()
+                                        .text_color(theme.text_secondary)
+                                        .child("Press enter to bookmark the current position"),
+                                )
+                            })
+                            .children(bookmarks.iter().map(|bookmark| {
+                                let position = bookmark.position;
+                                let id = bookmark.id;
+                                let seconds = position as u64;
+
+                                div()
+                                    .id(("bookmark", id as u64))
+                                    .flex()
+                                    .items_center()
+                                    .px(px(8.0))
+                                    .py(px(6.0))
+                                    .gap(px(8.0))
+                                    .rounded(px(4.0))
+                                    .text_sm()
+                                    .cursor_pointer()
+                                    .hover(|this| this.bg(theme.palette_item_hover))
+                                    .active(|this| this.bg(theme.palette_item_active))
+                                    .on_click(cx.listener(move |this, _, _, cx| {
+                                        cx.global::<GPUIPlaybackInterface>().seek(position);
+                                        this.reset(cx);
+                                    }))
+                                    .child(
+                                        div()
+                                            .font_family("Roboto Mono")
+                                            .text_color(theme.text_secondary)
+                                            .child(format!(
+                                                "{:02}:{:02}",
+                                                seconds / 60,
+                                                seconds % 60
+                                            )),
+                                    )
+                                    .child(
+                                        div()
+                                            .flex_grow()
+                                            .overflow_x_hidden()
+                                            .child(bookmark.name.clone()),
+                                    )
+                                    .child(
+                                        div()
+                                            .id(("bookmark-delete", id as u64))
+                                            .font_family(FONT_AWESOME)
+                                            .text_size(px(11.0))
+                                            .text_color(theme.text_secondary)
+                                            .px(px(4.0))
+                                            .rounded(px(3.0))
+                                            .hover(|this| this.bg(theme.button_danger))
+                                            // icon: `xmark`
+                                            // https://fontawesome.com/icons/xmark?f=classic&s=solid
+                                            .child("\u{f00d}")
+                                            .on_click(move |_, _, cx| {
+                                                cx.stop_propagation();
+
+                                                if let Err(e) = cx.delete_bookmark(id) {
+                                                    error!("Unable to delete bookmark: {:?}", e);
+                                                }
+
+                                                let track = cx
+                                                    .global::<PlaybackInfo>()
+                                                    .current_track
+                                                    .read(cx)
+                                                    .clone();
+                                                load_bookmarks(
+                                                    cx,
+                                                    track.as_ref().map(|v| v.get_path()),
+                                                );
+                                            }),
+                                    )
+                            })),
+                    ),
+            )
+            .into_any_element()
+    }
+}
@@ -1,24 +1,43 @@
 pub mod table_data;
 mod table_item;
 
-use std::{rc::Rc, sync::Arc};
+use std::{collections::BTreeSet, rc::Rc, sync::Arc};
 
 use ahash::AHashMap;
 use fnv::FnvBuildHasher;
 use gpui::{prelude::FluentBuilder, *};
 use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
 use table_data::{Column, TableData, TableSort};
 use table_item::TableItem;
 use tracing::warn;
 
 use crate::ui::{
+    components::{
+        context::context,
+        menu::{menu, menu_item},
+    },
     constants::FONT_AWESOME,
+    models::Models,
     theme::Theme,
     util::{create_or_retrieve_view, prune_views},
 };
 
 type RowMap<T, C> = AHashMap<usize, Entity<TableItem<T, C>>>;
 
+/// Rows that were retrieved before their views were created, by their position in the table.
+type RowCache<T> = AHashMap<usize, Option<Arc<T>>>;
+
+/// The number of rows retrieved at once. Rows are retrieved in batches of consecutive positions,
+/// starting at a multiple of this.
+const ROW_BATCH: usize = 64;
+
+/// The narrowest a column can be resized to.
+const MIN_COLUMN_WIDTH: f32 = 40.0;
+
+/// The width of columns that aren't in the table's default columns when they are shown.
+const DEFAULT_COLUMN_WIDTH: f32 = 150.0;
+
 #[allow(type_alias_bounds)]
 pub type OnSelectHandler<T, C>
 where
@@ -26,6 +45,73 @@ where
     T: TableData<C>,
 = Rc<dyn Fn(&mut App, &T::Identifier) + 'static>;
 
+/// Decides which rows are shown. Rows are filtered after they are retrieved and sorted.
+#[allow(type_alias_bounds)]
+pub type RowFilter<T, C>
+where
+    C: Column,
+    T: TableData<C>,
+= Rc<dyn Fn(&T::Identifier) -> bool + 'static>;
+
+/// The selected rows of a table, by their position in the table.
+#[derive(Default)]
+pub struct TableSelection {
+    rows: BTreeSet<usize>,
+    /// The row that shift-clicking selects from.
+    anchor: Option<usize>,
+}
+
+impl TableSelection {
+    pub fn contains(&self, row: usize) -> bool {
+        self.rows.contains(&row)
+    }
+
+    /// Selects only the row.
+    pub fn select(&mut self, row: usize) {
+        self.rows.clear();
+        self.rows.insert(row);
+        self.anchor = Some(row);
+    }
+
+    /// Adds the row to the selection, or removes it if it is already selected.
+    pub fn toggle(&mut self, row: usize) {
+        if !self.rows.remove(&row) {
+            self.rows.insert(row);
+        }
+
+        self.anchor = Some(row);
+    }
+
+    /// Selects every row between the anchor and the row.
+    pub fn extend_to(&mut self, row: usize) {
+        let anchor = self.anchor.unwrap_or(row);
+
+        self.rows.clear();
+        self.rows.extend(anchor.min(row)..=anchor.max(row));
+        self.anchor = Some(anchor);
+    }
+}
+
+/// The columns, widths and sort order of a table, saved by table name in
+/// `Models::table_layouts`.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+struct TableLayout<C>
+where
+    C: Column,
+{
+    columns: Vec<(C, f32)>,
+    sort: Option<TableSort<C>>,
+}
+
+/// A column that is being resized by dragging the edge of its header.
+#[derive(Clone, Copy)]
+struct ColumnResize<C> {
+    column: C,
+    start: Pixels,
+    width: f32,
+}
+
 #[derive(Clone)]
 pub struct Table<T, C>
 where
@@ -34,10 +120,15 @@ where
 {
     columns: Entity<Arc<IndexMap<C, f32, FnvBuildHasher>>>,
     views: Entity<RowMap<T, C>>,
+    prefetched: Entity<RowCache<T>>,
     render_counter: Entity<usize>,
     list_state: ListState,
     sort_method: Entity<Option<TableSort<C>>>,
     on_select: Option<OnSelectHandler<T, C>>,
+    filter: Option<RowFilter<T, C>>,
+    rows: Rc<Vec<T::Identifier>>,
+    selection: Entity<TableSelection>,
+    resizing: Option<ColumnResize<C>>,
 }
 
 pub enum TableEvent {
@@ -58,22 +149,33 @@ where
 {
     pub fn new(cx: &mut App, on_select: Option<OnSelectHandler<T, C>>) -> Entity<Self> {
         cx.new(|cx| {
-            let columns = cx.new(|_| Arc::new(T::default_columns()));
+            let layout = cx
+                .global::<Models>()
+                .table_layouts
+                .read(cx)
+                .get(T::get_table_name())
+                .and_then(|v| serde_json::from_value::<TableLayout<C>>(v.clone()).ok());
+
+            let (columns, sort) = match layout {
+                Some(layout) if !layout.columns.is_empty() => (
+                    layout.columns.into_iter().collect::<IndexMap<_, _, _>>(),
+                    layout.sort,
+                ),
+                _ => (T::default_columns(), None),
+            };
+
+            let columns = cx.new(|_| Arc::new(columns));
             let views = cx.new(|_| AHashMap::new());
+            let prefetched = cx.new(|_| AHashMap::new());
             let render_counter = cx.new(|_| 0);
-            let sort_method = cx.new(|_| None);
-
-            let list_state = Self::make_list_state(
-                cx,
-                views.clone(),
-                render_counter.clone(),
-                &sort_method,
-                columns.clone(),
-                on_select.clone(),
-            );
+            let sort_method = cx.new(|_| sort);
+            let selection = cx.new(|_| TableSelection::default());
+
+            cx.observe(&selection, |_, _, cx| cx.notify()).detach();
 
             cx.observe(&sort_method, |this, _, cx| {
                 this.regenerate_list_state(cx);
+                this.save_layout(cx);
                 cx.notify();
             })
             .detach();
@@ -83,55 +185,79 @@ where
             })
             .detach();
 
-            Self {
+            let mut table = Self {
                 columns,
                 views,
+                prefetched,
                 render_counter,
-                list_state,
+                list_state: ListState::new(0, ListAlignment::Top, px(64.0), move |_, _, _| {
+                    div().into_any_element()
+                }),
                 sort_method,
                 on_select,
-            }
+                filter: None,
+                rows: Rc::new(Vec::new()),
+                selection,
+                resizing: None,
+            };
+
+            table.regenerate_list_state(cx);
+            table
         })
     }
 
+    /// Retrieves the identifiers of the selected rows, in the order they are shown.
+    pub fn selected(&self, cx: &App) -> Vec<T::Identifier> {
+        self.selection
+            .read(cx)
+            .rows
+            .iter()
+            .filter_map(|idx| self.rows.get(*idx).cloned())
+            .collect()
+    }
+
+    /// Shows only the rows the filter returns true for, or every row if there is no filter.
+    pub fn set_filter(&mut self, filter: Option<RowFilter<T, C>>, cx: &mut Context<'_, Self>) {
+        self.filter = filter;
+        self.regenerate_list_state(cx);
+    }
+
     fn regenerate_list_state(&mut self, cx: &mut Context<'_, Self>) {
         let curr_scroll = self.list_state.logical_scroll_top();
         self.views = cx.new(|_| AHashMap::new());
+        self.prefetched = cx.new(|_| AHashMap::new());
         self.render_counter = cx.new(|_| 0);
 
-        self.list_state = Self::make_list_state(
-            cx,
-            self.views.clone(),
-            self.render_counter.clone(),
-            &self.sort_method,
-            self.columns.clone(),
-            self.on_select.clone(),
-        );
-
-        self.list_state.scroll_to(curr_scroll);
+        // positions are only meaningful for the rows they were selected in
+        self.selection.update(cx, |selection, cx| {
+            *selection = TableSelection::default();
+            cx.notify();
+        });
 
-        cx.notify();
-    }
+        let sort_method = *self.sort_method.read(cx);
+        self.rows = match T::get_rows(cx, sort_method) {
+            Ok(mut rows) => {
+                if let Some(filter) = self.filter.as_ref() {
+                    rows.retain(|row| filter(row));
+                }
 
-    fn make_list_state(
-        cx: &mut Context<'_, Self>,
-        views: Entity<RowMap<T, C>>,
-        render_counter: Entity<usize>,
-        sort_method_entity: &Entity<Option<TableSort<C>>>,
-        columns: Entity<Arc<IndexMap<C, f32, FnvBuildHasher>>>,
-        handler: Option<OnSelectHandler<T, C>>,
-    ) -> ListState {
-        let sort_method = sort_method_entity.read(cx).clone();
-        let Ok(rows) = T::get_rows(cx, sort_method) else {
-            warn!("Failed to get rows");
-            return ListState::new(0, ListAlignment::Top, px(64.0), move |_, _, _| {
-                div().into_any_element()
-            });
+                Rc::new(rows)
+            }
+            Err(e) => {
+                warn!("Failed to get rows: {}", e);
+                Rc::new(Vec::new())
+            }
         };
 
-        let idents_rc = Rc::new(rows);
+        let idents_rc = self.rows.clone();
+        let views = self.views.clone();
+        let prefetched = self.prefetched.clone();
+        let render_counter = self.render_counter.clone();
+        let columns = self.columns.clone();
+        let selection = self.selection.clone();
+        let handler = self.on_select.clone();
 
-        ListState::new(
+        self.list_state = ListState::new(
             idents_rc.len(),
             ListAlignment::Top,
             px(300.0),
@@ -144,13 +270,121 @@ where
                     .child(create_or_retrieve_view(
                         &views,
                         idx,
-                        |cx| TableItem::new(cx, idents_rc[idx].clone(), &columns, handler.clone()),
+                        |cx| {
+                            let row = take_row(&idents_rc, &prefetched, idx, cx);
+
+                            TableItem::new(cx, row, idx, &columns, &selection, handler.clone())
+                        },
                         cx,
                     ))
                     .into_any_element()
             },
-        )
+        );
+
+        self.list_state.scroll_to(curr_scroll);
+
+        cx.notify();
+    }
+
+    /// Shows the column if it is hidden, or hides it if it is shown. The last column can't be
+    /// hidden.
+    fn toggle_column(&mut self, column: C, cx: &mut Context<'_, Self>) {
+        self.columns.update(cx, |columns, cx| {
+            let mut new = (**columns).clone();
+
+            if new.contains_key(&column) {
+                if new.len() > 1 {
+                    new.shift_remove(&column);
+                }
+            } else {
+                let width = T::default_columns()
+                    .get(&column)
+                    .copied()
+                    .unwrap_or(DEFAULT_COLUMN_WIDTH);
+                new.insert(column, width);
+            }
+
+            *columns = Arc::new(new);
+            cx.notify();
+        });
+
+        self.save_layout(cx);
+        cx.notify();
+    }
+
+    fn resize_column(&mut self, position: Pixels, cx: &mut Context<'_, Self>) {
+        let Some(resize) = self.resizing else {
+            return;
+        };
+
+        let width = (resize.width + (position - resize.start) / px(1.0)).max(MIN_COLUMN_WIDTH);
+
+        self.columns.update(cx, |columns, cx| {
+            let mut new = (**columns).clone();
+            new.insert(resize.column, width);
+            *columns = Arc::new(new);
+            cx.notify();
+        });
+
+        cx.notify();
+    }
+
+    fn save_layout(&self, cx: &mut Context<'_, Self>) {
+        let layout = TableLayout {
+            columns: self
+                .columns
+                .read(cx)
+                .iter()
+                .map(|(column, width)| (*column, *width))
+                .collect(),
+            sort: *self.sort_method.read(cx),
+        };
+
+        match serde_json::to_value(layout) {
+            Ok(value) => {
+                let table_layouts = cx.global::<Models>().table_layouts.clone();
+
+                table_layouts.update(cx, |layouts, _| {
+                    layouts.insert(T::get_table_name().to_string(), value);
+                });
+            }
+            Err(e) => warn!("Failed to save table layout: {}", e),
+        }
+    }
+}
+
+/// Takes the row at the position from the rows that were retrieved ahead of time. If it hasn't
+/// been retrieved, the batch it is in is retrieved first, and batches far from it are dropped.
+fn take_row<T, C>(
+    rows: &[T::Identifier],
+    prefetched: &Entity<RowCache<T>>,
+    idx: usize,
+    cx: &mut App,
+) -> Option<Arc<T>>
+where
+    T: TableData<C> + 'static,
+    C: Column + 'static,
+{
+    if let Some(row) = prefetched.update(cx, |rows, _| rows.remove(&idx)) {
+        return row;
     }
+
+    let start = idx - idx % ROW_BATCH;
+    let end = (start + ROW_BATCH).min(rows.len());
+
+    let batch = match T::get_rows_by_id(cx, &rows[start..end]) {
+        Ok(batch) => batch,
+        Err(e) => {
+            warn!("Failed to get rows: {}", e);
+            return None;
+        }
+    };
+
+    prefetched.update(cx, |rows, _| {
+        rows.retain(|i, _| *i + ROW_BATCH >= start && *i < end + ROW_BATCH);
+        rows.extend((start..end).zip(batch));
+        rows.remove(&idx).flatten()
+    })
 }
 
 impl<T, C> Render for Table<T, C>
@@ -205,7 +439,31 @@ where
                     .border_b_1()
                     .border_color(theme.border_color)
                     .font_weight(FontWeight::BOLD)
+                    .relative()
                     .child(SharedString::new_static(column_id.get_column_name()))
+                    .child(
+                        div()
+                            .id(("resize", i))
+                            .absolute()
+                            .top_0()
+                            .right_0()
+                            .h_full()
+                            .w(px(6.0))
+                            .cursor_col_resize()
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |this, ev: &MouseDownEvent, _, cx| {
+                                    // keeps the header from being clicked, which would sort it
+                                    cx.stop_propagation();
+
+                                    this.resizing = Some(ColumnResize {
+                                        column: column_id,
+                                        start: ev.position.x,
+                                        width,
+                                    });
+                                }),
+                            ),
+                    )
                     .when_some(sort_method.as_ref(), |this, method| {
                         this.when(method.column == column_id, |this| {
                             this.child(
@@ -244,11 +502,48 @@ where
             );
         }
 
+        let table = cx.entity();
+        let shown_columns = self.columns.read(cx).clone();
+        let mut column_menu = menu();
+
+        for (i, column) in C::all().iter().enumerate() {
+            let column = *column;
+            let table = table.clone();
+
+            column_menu = column_menu.item(menu_item(
+                ("column", i),
+                shown_columns.contains_key(&column).then_some("\u{f00c}"),
+                column.get_column_name(),
+                move |_, _, cx| table.update(cx, |this, cx| this.toggle_column(column, cx)),
+            ));
+        }
+
         div()
             .id(T::get_table_name())
             .overflow_x_scroll()
             .w_full()
             .h_full()
+            .on_mouse_move(cx.listener(|this, ev: &MouseMoveEvent, _, cx| {
+                if this.resizing.is_some() {
+                    this.resize_column(ev.position.x, cx);
+                }
+            }))
+            .on_mouse_up(
+                MouseButton::Left,
+                cx.listener(|this, _, _, cx| {
+                    if this.resizing.take().is_some() {
+                        this.save_layout(cx);
+                    }
+                }),
+            )
+            .on_mouse_up_out(
+                MouseButton::Left,
+                cx.listener(|this, _, _, cx| {
+                    if this.resizing.take().is_some() {
+                        this.save_layout(cx);
+                    }
+                }),
+            )
             .child(
                 div()
                     .w_full()
@@ -259,7 +554,11 @@ where
                     .text_size(px(26.0))
                     .child(T::get_table_name()),
             )
-            .child(header)
+            .child(
+                context("table-columns")
+                    .with(header)
+                    .child(div().bg(theme.elevated_background).child(column_menu)),
+            )
             .child(list(self.list_state.clone()).w_full().h_full())
     }
 }
@@ -3,13 +3,21 @@ use std::{fmt::Debug, hash::Hash, sync::Arc};
 use fnv::FnvBuildHasher;
 use gpui::{App, ElementId, RenderImage, SharedString};
 use indexmap::IndexMap;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-pub trait Column: Clone + Copy + Debug + Hash + PartialEq + Eq {
+/// A column of a table. Columns are serialized to save the layout of the table.
+pub trait Column:
+    Clone + Copy + Debug + Hash + PartialEq + Eq + Serialize + DeserializeOwned
+{
     /// Retrieves the friendly name text of the column.
     fn get_column_name(&self) -> &'static str;
+
+    /// Retrieves every column the table can show, in the order they are offered to the user.
+    fn all() -> &'static [Self];
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct TableSort<C>
 where
     C: Column,
@@ -34,10 +42,13 @@ where
     /// sorting order of the rows.
     fn get_rows(cx: &mut App, sort: Option<TableSort<C>>) -> anyhow::Result<Vec<Self::Identifier>>;
 
-    /// Retrieves a specific row of the table. The row is returned as an Arc to the table data,
-    /// which can be used to retrieve the row data as SharedStrings. The id parameter is used to
-    /// identify the row to retrieve.
-    fn get_row(cx: &mut App, id: Self::Identifier) -> anyhow::Result<Option<Arc<Self>>>;
+    /// Retrieves the rows with the given identifiers, in the same order. Rows are returned as an
+    /// Arc to the table data, which can be used to retrieve the row data as SharedStrings. Rows
+    /// that no longer exist are None. The table retrieves the rows it shows in batches.
+    fn get_rows_by_id(
+        cx: &mut App,
+        ids: &[Self::Identifier],
+    ) -> anyhow::Result<Vec<Option<Arc<Self>>>>;
 
     /// Retrieves a column from the row.
     fn get_column(&self, cx: &mut App, column: C) -> Option<SharedString>;
@@ -64,4 +75,12 @@ where
 
     /// Retrieves the table ID for the row.
     fn get_table_id(&self) -> Self::Identifier;
+
+    /// Returns true if more than one row can be selected. Rows of these tables are selected by
+    /// clicking them, and the select handler is called when they are double clicked.
+    ///
+    /// Rows of other tables call the select handler when they are clicked.
+    fn multi_select() -> bool {
+        false
+    }
 }
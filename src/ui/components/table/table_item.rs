@@ -8,7 +8,7 @@ use crate::ui::{theme::Theme, util::drop_image_from_app};
 
 use super::{
     table_data::{Column, TableData},
-    OnSelectHandler,
+    OnSelectHandler, TableSelection,
 };
 
 #[derive(Clone)]
@@ -23,6 +23,9 @@ where
     on_select: Option<OnSelectHandler<T, C>>,
     row: Option<Arc<T>>,
     id: Option<ElementId>,
+    /// The position of the row in the table.
+    index: usize,
+    selection: Entity<TableSelection>,
 }
 
 impl<T, C> TableItem<T, C>
@@ -32,12 +35,12 @@ where
 {
     pub fn new(
         cx: &mut App,
-        id: T::Identifier,
+        row: Option<Arc<T>>,
+        index: usize,
         columns: &Entity<Arc<IndexMap<C, f32, FnvBuildHasher>>>,
+        selection: &Entity<TableSelection>,
         on_select: Option<OnSelectHandler<T, C>>,
     ) -> Entity<Self> {
-        let row = T::get_row(cx, id).ok().flatten();
-
         let id = row.as_ref().map(|row| row.get_element_id().into());
 
         let columns_read = columns.read(cx).clone();
@@ -73,6 +76,8 @@ where
             })
             .detach();
 
+            cx.observe(selection, |_, _, cx| cx.notify()).detach();
+
             Self {
                 data,
                 image,
@@ -80,6 +85,8 @@ where
                 on_select,
                 id,
                 row,
+                index,
+                selection: selection.clone(),
             }
         })
     }
@@ -93,18 +100,53 @@ where
     fn render(&mut self, _: &mut Window, cx: &mut Context<'_, Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
         let row_data = self.row.clone();
+        let index = self.index;
+        let selected = self.selection.read(cx).contains(index);
         let mut row = div()
             .w_full()
             .flex()
             .id(self.id.clone().unwrap_or("bad".into()))
-            .when_some(self.on_select.clone(), move |div, on_select| {
-                div.on_click(move |_, _, cx| {
-                    let id = row_data.as_ref().unwrap().get_table_id();
-                    on_select(cx, &id)
-                })
+            .when(selected, |div| div.bg(theme.queue_item_current))
+            .when(T::multi_select(), |div| {
+                let on_select = self.on_select.clone();
+
+                div.on_click(cx.listener(move |this, ev: &ClickEvent, _, cx| {
+                    let modifiers = ev.down.modifiers;
+
+                    this.selection.update(cx, |selection, cx| {
+                        if modifiers.shift {
+                            selection.extend_to(index);
+                        } else if modifiers.secondary() {
+                            selection.toggle(index);
+                        } else {
+                            selection.select(index);
+                        }
+
+                        cx.notify();
+                    });
+
+                    if ev.down.click_count == 2 && !modifiers.shift && !modifiers.secondary() {
+                        if let (Some(on_select), Some(row)) =
+                            (on_select.as_ref(), this.row.as_ref())
+                        {
+                            on_select(cx, &row.get_table_id());
+                        }
+                    }
+                }))
                 .hover(|this| this.bg(theme.nav_button_hover))
                 .active(|this| this.bg(theme.nav_button_active))
-            });
+            })
+            .when_some(
+                self.on_select.clone().filter(|_| !T::multi_select()),
+                move |div, on_select| {
+                    div.on_click(move |_, _, cx| {
+                        let id = row_data.as_ref().unwrap().get_table_id();
+                        on_select(cx, &id)
+                    })
+                    .hover(|this| this.bg(theme.nav_button_hover))
+                    .active(|this| this.bg(theme.nav_button_active))
+                },
+            );
 
         if T::has_images() {
             row = row.child(
@@ -0,0 +1,171 @@
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Sender},
+};
+
+use async_std::channel;
+use gpui::*;
+use tracing::{error, info, warn};
+
+use crate::{
+    cli::absolute_paths,
+    control::{
+        errors::ControlError, send_request, start_server, ControlRequest, ControlResponse,
+        OpenMode, PlaybackStatus,
+    },
+    library::scan::ScanInterface,
+    playback::{interface::GPUIPlaybackInterface, queue::QueueItemData},
+};
+
+use super::models::{Models, PlaybackInfo};
+
+type PendingRequest = (ControlRequest, Sender<ControlResponse>);
+
+/// Adds the files to the queue according to the mode.
+pub fn open_files(cx: &App, paths: Vec<std::path::PathBuf>, mode: OpenMode) {
+    if paths.is_empty() {
+        return;
+    }
+
+    let interface = cx.global::<GPUIPlaybackInterface>();
+    let items = paths
+        .into_iter()
+        .map(|path| QueueItemData::new(path, None, None))
+        .collect();
+
+    match mode {
+        OpenMode::Enqueue => interface.queue_list(items),
+        OpenMode::Next => interface.insert_next(items),
+        OpenMode::Replace => interface.replace_queue(items),
+    }
+}
+
+fn bring_to_front(cx: &mut App) {
+    cx.activate(true);
+
+    if let Some(window) = cx.windows().first() {
+        window
+            .update(cx, |_, window, _| window.activate_window())
+            .expect("failed to activate window");
+    }
+}
+
+fn status(cx: &App) -> PlaybackStatus {
+    let info = cx.global::<PlaybackInfo>();
+    let models = cx.global::<Models>();
+    let metadata = models.metadata.read(cx);
+    let queue = models.queue.read(cx);
+
+    PlaybackStatus {
+        state: *info.playback_state.read(cx),
+        track: info
+            .current_track
+            .read(cx)
+            .as_ref()
+            .map(|v| v.get_path().clone()),
+        title: metadata.name.clone(),
+        artist: metadata.artist.clone(),
+        album: metadata.album.clone(),
+        position: *info.position.read(cx),
+        duration: *info.duration.read(cx),
+        volume: *info.volume.read(cx),
+        speed: *info.speed.read(cx),
+        shuffling: *info.shuffling.read(cx),
+        queue_position: queue.position,
+        queue_length: queue.data.read().expect("couldn't get queue").len(),
+        scan: *models.scan_state.read(cx),
+    }
+}
+
+fn handle(cx: &mut App, request: ControlRequest) -> ControlResponse {
+    let interface = cx.global::<GPUIPlaybackInterface>();
+
+    match request {
+        ControlRequest::Play { paths } if paths.is_empty() => interface.play(),
+        ControlRequest::Play { paths } => open_files(cx, paths, OpenMode::Replace),
+        ControlRequest::Pause => interface.pause(),
+        ControlRequest::Next => interface.next(),
+        ControlRequest::Previous => interface.previous(),
+        ControlRequest::Stop => interface.stop(),
+        ControlRequest::Scan => cx.global::<ScanInterface>().scan(),
+        ControlRequest::PrivateSession { enabled } => {
+            let private_session = cx.global::<Models>().private_session.clone();
+            private_session.update(cx, |m, cx| {
+                *m = enabled;
+                cx.notify();
+            });
+        }
+        ControlRequest::Status => return ControlResponse::Status(Box::new(status(cx))),
+        ControlRequest::Open { paths, mode } => {
+            info!("Opening files forwarded from another instance: {:?}", paths);
+            open_files(cx, paths, mode);
+            bring_to_front(cx);
+        }
+    }
+
+    ControlResponse::Ok
+}
+
+/// Starts the control server, so that the running window can be controlled with the `muzak`
+/// subcommands and receives the files from later launches of Muzak. Requests are answered on the
+/// main thread, once the application has finished starting.
+///
+/// This has to be called before any threads or servers are started, so that a second instance
+/// quits before it scans the library or binds any ports.
+///
+/// If another instance started after this one checked for it, the files are forwarded to that
+/// instance and the application quits. Returns false in that case.
+pub fn setup_control_server(cx: &mut App, files: &[PathBuf], mode: OpenMode) -> bool {
+    let (requests_tx, requests_rx) = channel::unbounded::<PendingRequest>();
+
+    let result = start_server(move |request| {
+        let (response_tx, response_rx) = mpsc::channel();
+
+        if requests_tx.try_send((request, response_tx)).is_err() {
+            return ControlResponse::Error {
+                message: "the application is shutting down".to_string(),
+            };
+        }
+
+        response_rx.recv().unwrap_or(ControlResponse::Error {
+            message: "the request was not answered".to_string(),
+        })
+    });
+
+    match result {
+        Ok(_) => (),
+        Err(ControlError::AlreadyRunning) => {
+            warn!("Another instance of Muzak is already running, forwarding the files to it");
+
+            let request = ControlRequest::Open {
+                paths: absolute_paths(files.to_vec()),
+                mode,
+            };
+
+            if let Err(e) = send_request(&request) {
+                error!("Unable to forward the files to the running instance: {}", e);
+            }
+
+            cx.quit();
+            return false;
+        }
+        Err(e) => {
+            error!("Unable to start the control server: {}", e);
+            return true;
+        }
+    }
+
+    cx.spawn(async move |cx| {
+        while let Ok((request, response_tx)) = requests_rx.recv().await {
+            let response = cx
+                .update(|cx| handle(cx, request))
+                .expect("failed to handle control request");
+
+            // the client may have disconnected in the meantime
+            let _ = response_tx.send(response);
+        }
+    })
+    .detach();
+
+    true
+}
@@ -1,12 +1,20 @@
-use crate::playback::{interface::GPUIPlaybackInterface, thread::PlaybackState};
+use std::sync::Arc;
+
+use crate::{
+    library::types::Bookmark,
+    playback::{interface::GPUIPlaybackInterface, thread::PlaybackState},
+};
 use gpui::*;
 use prelude::FluentBuilder;
+use tracing::warn;
 
 use super::{
+    bookmarks::BookmarkView,
     components::slider::slider,
     constants::{APP_ROUNDING, FONT_AWESOME},
     global_actions::{Next, PlayPause, Previous},
-    models::{Models, PlaybackInfo},
+    library::open_track_artist,
+    models::{create_last_fm_client, LastFMState, Models, PlaybackInfo},
     theme::Theme,
 };
 
@@ -58,6 +66,13 @@ pub struct InfoSection {
     artist_name: Option<SharedString>,
     albumart_actual: Option<ImageSource>,
     playback_info: PlaybackInfo,
+    /// The artist and title of the current track, as sent to last.fm.
+    lastfm_track: Option<(String, String)>,
+    /// Whether the user has loved the current track on last.fm. None if the user isn't signed in,
+    /// or the track info hasn't been retrieved yet.
+    loved: Option<bool>,
+    /// The number of times the user has scrobbled the current track.
+    play_count: Option<u64>,
 }
 
 impl InfoSection {
@@ -78,6 +93,20 @@ impl InfoSection {
                 this.track_name = metadata.name.clone().map(SharedString::from);
                 this.artist_name = metadata.artist.clone().map(SharedString::from);
 
+                let lastfm_track = metadata.artist.clone().zip(metadata.name.clone());
+                if lastfm_track != this.lastfm_track {
+                    this.lastfm_track = lastfm_track;
+                    this.fetch_track_info(cx);
+                }
+
+                cx.notify();
+            })
+            .detach();
+
+            let lastfm_model = cx.global::<Models>().lastfm.clone();
+
+            cx.observe(&lastfm_model, |this: &mut Self, _, cx| {
+                this.fetch_track_info(cx);
                 cx.notify();
             })
             .detach();
@@ -95,9 +124,81 @@ impl InfoSection {
                 track_name: None,
                 albumart_actual: None,
                 playback_info,
+                lastfm_track: None,
+                loved: None,
+                play_count: None,
             }
         })
     }
+
+    /// Retrieves the user's play count and loved status for the current track from last.fm.
+    fn fetch_track_info(&mut self, cx: &mut Context<Self>) {
+        self.loved = None;
+        self.play_count = None;
+
+        let Some((artist, track)) = self.lastfm_track.clone() else {
+            return;
+        };
+        let LastFMState::Connected(session) = cx.global::<Models>().lastfm.read(cx) else {
+            return;
+        };
+        let user = session.name.clone();
+        let Some(mut client) = create_last_fm_client(cx) else {
+            return;
+        };
+
+        cx.spawn(async move |weak, cx| {
+            let info = client
+                .get_track_info(artist.clone(), track.clone(), Some(user))
+                .await;
+
+            match info {
+                Ok(info) => {
+                    let _ = weak.update(cx, |this: &mut Self, cx| {
+                        // the track may have changed while the request was in flight
+                        if this.lastfm_track == Some((artist, track)) {
+                            this.loved = Some(info.userloved.unwrap_or(false));
+                            this.play_count = info.userplaycount;
+                            cx.notify();
+                        }
+                    });
+                }
+                Err(e) => warn!("Could not get track info from last.fm: {}", e),
+            }
+        })
+        .detach();
+    }
+
+    fn toggle_loved(&mut self, cx: &mut Context<Self>) {
+        let (Some(loved), Some((artist, track))) = (self.loved, self.lastfm_track.clone()) else {
+            return;
+        };
+        let Some(mut client) = create_last_fm_client(cx) else {
+            return;
+        };
+
+        // update the button immediately, and revert it if the request fails
+        self.loved = Some(!loved);
+        cx.notify();
+
+        cx.spawn(async move |weak, cx| {
+            let result = client
+                .set_loved(artist.clone(), track.clone(), !loved)
+                .await;
+
+            if let Err(e) = result {
+                warn!("Could not update loved track on last.fm: {}", e);
+
+                let _ = weak.update(cx, |this: &mut Self, cx| {
+                    if this.lastfm_track == Some((artist, track)) {
+                        this.loved = Some(loved);
+                        cx.notify();
+                    }
+                });
+            }
+        })
+        .detach();
+    }
 }
 
 impl Render for InfoSection {
@@ -174,10 +275,20 @@ impl Render for InfoSection {
                                 )
                                 .child(
                                     div()
+                                        .id("info-artist")
                                         .overflow_x_hidden()
                                         .pb(px(6.0))
                                         .text_ellipsis()
                                         .overflow_x_hidden()
+                                        .cursor_pointer()
+                                        .on_click(cx.listener(|this: &mut Self, _, _, cx| {
+                                            let track =
+                                                this.playback_info.current_track.read(cx).clone();
+
+                                            if let Some(track) = track {
+                                                open_track_artist(cx, track.get_path());
+                                            }
+                                        }))
                                         .child(
                                             self.artist_name
                                                 .clone()
@@ -185,7 +296,56 @@ impl Render for InfoSection {
                                         ),
                                 ),
                         )
-                    }),
+                    })
+                    .when_some(
+                        self.loved.filter(|_| *state != PlaybackState::Stopped),
+                        |e, loved| {
+                            e.child(
+                                div()
+                                    .ml_auto()
+                                    .flex()
+                                    .flex_col()
+                                    .items_center()
+                                    .flex_shrink_0()
+                                    .gap_1()
+                                    .child(
+                                        div()
+                                            .id("lastfm-love-button")
+                                            .font_family(FONT_AWESOME)
+                                            .text_size(px(12.0))
+                                            .rounded(px(3.0))
+                                            .px(px(4.0))
+                                            .py(px(2.0))
+                                            .text_color(if loved {
+                                                theme.button_danger
+                                            } else {
+                                                theme.text_secondary
+                                            })
+                                            .hover(|style| {
+                                                style
+                                                    .bg(theme.playback_button_hover)
+                                                    .cursor_pointer()
+                                            })
+                                            .on_click(cx.listener(|this, _, _, cx| {
+                                                this.toggle_loved(cx);
+                                            }))
+                                            .child("\u{f004}"),
+                                    )
+                                    .when_some(self.play_count, |e, count| {
+                                        e.child(
+                                            div()
+                                                .text_size(px(10.0))
+                                                .text_color(theme.text_secondary)
+                                                .child(if count == 1 {
+                                                    "1 play".to_string()
+                                                } else {
+                                                    format!("{} plays", count)
+                                                }),
+                                        )
+                                    }),
+                            )
+                        },
+                    ),
             )
     }
 }
@@ -343,6 +503,12 @@ impl Render for PlaybackSection {
 pub struct Scrubber {
     position: Entity<u64>,
     duration: Entity<u64>,
+    loop_points: Entity<Option<(f64, f64)>>,
+    bookmarks: Entity<Arc<Vec<Bookmark>>>,
+    /// Whether the start of the A-B loop has been marked, but not its end.
+    loop_start: bool,
+    show_bookmarks: Entity<bool>,
+    bookmark_view: Entity<BookmarkView>,
     playback_section: Entity<PlaybackSection>,
 }
 
@@ -351,6 +517,10 @@ impl Scrubber {
         cx.new(|cx| {
             let position_model = cx.global::<PlaybackInfo>().position.clone();
             let duration_model = cx.global::<PlaybackInfo>().duration.clone();
+            let loop_points = cx.global::<PlaybackInfo>().loop_points.clone();
+            let current_track = cx.global::<PlaybackInfo>().current_track.clone();
+            let bookmarks = cx.global::<Models>().bookmarks.clone();
+            let show_bookmarks = cx.new(|_| false);
 
             cx.observe(&position_model, |_, _, cx| {
                 cx.notify();
@@ -362,13 +532,51 @@ impl Scrubber {
             })
             .detach();
 
+            cx.observe(&loop_points, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            cx.observe(&bookmarks, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            cx.observe(&current_track, |this: &mut Self, _, cx| {
+                this.loop_start = false;
+                cx.notify();
+            })
+            .detach();
+
             Self {
                 position: position_model,
                 duration: duration_model,
+                loop_points,
+                bookmarks,
+                loop_start: false,
+                bookmark_view: BookmarkView::new(cx, show_bookmarks.clone()),
+                show_bookmarks,
                 playback_section: PlaybackSection::new(cx),
             }
         })
     }
+
+    /// Cycles the A-B loop: the first press marks the start of the loop, the second marks the end
+    /// and starts looping, and the third clears the loop.
+    fn cycle_loop(&mut self, cx: &mut Context<Self>) {
+        let interface = cx.global::<GPUIPlaybackInterface>();
+
+        if self.loop_points.read(cx).is_some() {
+            interface.set_loop(None);
+        } else if std::mem::take(&mut self.loop_start) {
+            interface.mark_loop_end();
+        } else {
+            interface.mark_loop_start();
+            self.loop_start = true;
+        }
+
+        cx.notify();
+    }
 }
 
 impl Render for Scrubber {
@@ -377,6 +585,11 @@ impl Render for Scrubber {
         let position = *self.position.read(cx);
         let duration = *self.duration.read(cx);
         let remaining = duration - position;
+        let loop_points = *self.loop_points.read(cx);
+        let looping = loop_points.is_some() || self.loop_start;
+        let bookmarks = self.bookmarks.read(cx).clone();
+        let show_bookmarks = self.show_bookmarks.clone();
+
         div()
             .pl(px(13.0))
             .pr(px(13.0))
@@ -411,31 +624,115 @@ impl Render for Scrubber {
                     )))
                     .child(deferred(self.playback_section.clone()))
                     .child(div().h(px(30.0)))
-                    .child(div().ml(auto()).child(format!(
-                        "-{:02}:{:02}",
-                        remaining / 60,
-                        remaining % 60
-                    ))),
+                    .child(
+                        div()
+                            .ml(auto())
+                            .flex()
+                            .items_center()
+                            .gap(px(6.0))
+                            .child(
+                                div()
+                                    .id("loop-button")
+                                    .px(px(4.0))
+                                    .rounded(px(3.0))
+                                    .text_size(px(11.0))
+                                    .bg(theme.playback_button)
+                                    .hover(|this| this.bg(theme.playback_button_hover))
+                                    .active(|this| this.bg(theme.playback_button_active))
+                                    .when(looping, |this| this.text_color(theme.scrubber_bookmark))
+                                    .child(match (loop_points, self.loop_start) {
+                                        (None, true) => "A-",
+                                        _ => "A-B",
+                                    })
+                                    .on_click(cx.listener(|this, _, _, cx| {
+                                        this.cycle_loop(cx);
+                                    })),
+                            )
+                            .child(
+                                div()
+                                    .id("bookmark-button")
+                                    .px(px(4.0))
+                                    .rounded(px(3.0))
+                                    .font_family(FONT_AWESOME)
+                                    .text_size(px(11.0))
+                                    .bg(theme.playback_button)
+                                    .hover(|this| this.bg(theme.playback_button_hover))
+                                    .active(|this| this.bg(theme.playback_button_active))
+                                    // icon: `bookmark`
+                                    // https://fontawesome.com/icons/bookmark?f=classic&s=solid
+                                    .child("\u{f02e}")
+                                    .on_click(move |_, _, cx| {
+                                        show_bookmarks.update(cx, |m, cx| {
+                                            *m = true;
+                                            cx.notify();
+                                        })
+                                    }),
+                            )
+                            .child(format!("-{:02}:{:02}", remaining / 60, remaining % 60)),
+                    ),
             )
             .child(
-                slider()
+                div()
+                    .relative()
                     .w_full()
-                    .h(px(6.0))
-                    .rounded(px(3.0))
-                    .id("scrubber-back")
-                    .value(position as f32 / duration as f32)
-                    .on_change(move |v, _, cx| {
-                        let info = cx.global::<PlaybackInfo>().clone();
-
-                        if duration > 0 && *info.playback_state.read(cx) != PlaybackState::Stopped {
-                            cx.global::<GPUIPlaybackInterface>()
-                                .seek(v as f64 * duration as f64);
-                        }
+                    .child(
+                        slider()
+                            .w_full()
+                            .h(px(6.0))
+                            .rounded(px(3.0))
+                            .id("scrubber-back")
+                            .value(position as f32 / duration as f32)
+                            .on_change(move |v, _, cx| {
+                                let info = cx.global::<PlaybackInfo>().clone();
+
+                                if duration > 0
+                                    && *info.playback_state.read(cx) != PlaybackState::Stopped
+                                {
+                                    cx.global::<GPUIPlaybackInterface>()
+                                        .seek(v as f64 * duration as f64);
+                                }
+                            }),
+                    )
+                    .when(duration > 0, |this| {
+                        this.when_some(loop_points, |this, (start, end)| {
+                            this.child(
+                                div()
+                                    .absolute()
+                                    .top_0()
+                                    .h(px(6.0))
+                                    .rounded(px(3.0))
+                                    .left(relative(start as f32 / duration as f32))
+                                    .w(relative((end - start) as f32 / duration as f32))
+                                    .bg(theme.scrubber_loop),
+                            )
+                        })
+                        .children(bookmarks.iter().map(|bookmark| {
+                            div()
+                                .absolute()
+                                .top(px(-2.0))
+                                .h(px(10.0))
+                                .w(px(2.0))
+                                .left(relative(bookmark.position as f32 / duration as f32))
+                                .bg(theme.scrubber_bookmark)
+                        }))
                     }),
             )
+            .child(self.bookmark_view.clone())
     }
 }
 
+/// The speeds the speed button cycles through.
+const SPEED_PRESETS: [f64; 7] = [0.5, 0.75, 1.0, 1.25, 1.5, 2.0, 3.0];
+
+/// Returns the preset after the given speed, wrapping back around to the slowest preset.
+fn next_speed_preset(speed: f64) -> f64 {
+    SPEED_PRESETS
+        .iter()
+        .copied()
+        .find(|v| *v > speed + f64::EPSILON)
+        .unwrap_or(SPEED_PRESETS[0])
+}
+
 pub struct SecondaryControls {
     info: PlaybackInfo,
     show_queue: Entity<bool>,
@@ -446,12 +743,18 @@ impl SecondaryControls {
         cx.new(|cx| {
             let info = cx.global::<PlaybackInfo>().clone();
             let volume = info.volume.clone();
+            let speed = info.speed.clone();
 
             cx.observe(&volume, |_, _, cx| {
                 cx.notify();
             })
             .detach();
 
+            cx.observe(&speed, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
             Self { info, show_queue }
         })
     }
@@ -462,6 +765,7 @@ impl Render for SecondaryControls {
         let theme = cx.global::<Theme>();
         let volume = *self.info.volume.read(cx);
         let prev_volume = *self.info.prev_volume.read(cx);
+        let speed = *self.info.speed.read(cx);
         let show_queue = self.show_queue.clone();
 
         div().px(px(18.0)).flex().child(
@@ -513,6 +817,29 @@ impl Render for SecondaryControls {
                             cx.global::<GPUIPlaybackInterface>().set_volume(v as f64);
                         }),
                 )
+                .child(
+                    div()
+                        .rounded(px(3.0))
+                        .min_w(px(28.0))
+                        .px(px(4.0))
+                        .h(px(25.0))
+                        .mt(px(2.0))
+                        .text_size(px(11.0))
+                        .font_weight(FontWeight::SEMIBOLD)
+                        .flex()
+                        .items_center()
+                        .justify_center()
+                        .border_color(theme.playback_button_border)
+                        .id("speed-button")
+                        .bg(theme.playback_button)
+                        .hover(|this| this.bg(theme.playback_button_hover))
+                        .active(|this| this.bg(theme.playback_button_active))
+                        .child(format!("{}×", speed))
+                        .on_click(move |_, _, cx| {
+                            cx.global::<GPUIPlaybackInterface>()
+                                .set_speed(next_speed_preset(speed));
+                        }),
+                )
                 .child(
                     div()
                         .rounded(px(3.0))
@@ -0,0 +1,252 @@
+use gpui::*;
+use prelude::FluentBuilder;
+use tracing::error;
+
+use crate::settings::{scan::LibraryRoot, update_settings, Settings, SettingsGlobal};
+
+use super::{
+    components::{
+        button::{button, ButtonIntent},
+        modal::modal,
+    },
+    constants::FONT_AWESOME,
+    theme::Theme,
+};
+
+/// A modal used to add and remove the folders the library is scanned from. The other rules of
+/// each folder are set in the settings file.
+pub struct FoldersView {
+    show: Entity<bool>,
+    settings: Entity<Settings>,
+}
+
+impl FoldersView {
+    pub fn new(cx: &mut App, show: Entity<bool>) -> Entity<Self> {
+        cx.new(|cx| {
+            let settings = cx.global::<SettingsGlobal>().model.clone();
+
+            cx.observe(&show, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            cx.observe(&settings, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            FoldersView { show, settings }
+        })
+    }
+
+    fn add_folders(&mut self, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: true,
+        });
+
+        cx.spawn(async move |_, cx| {
+            let paths = match paths.await {
+                Ok(Ok(Some(paths))) => paths,
+                Ok(Ok(None)) | Err(_) => return,
+                Ok(Err(e)) => {
+                    error!("Could not pick library folders: {:?}", e);
+                    return;
+                }
+            };
+
+            let result = cx.update(|cx| {
+                update_settings(cx, |settings| {
+                    for path in paths {
+                        if !settings.scanning.roots.iter().any(|v| v.path == path) {
+                            settings.scanning.roots.push(LibraryRoot::new(&path));
+                        }
+                    }
+                })
+            });
+
+            if let Err(e) = result {
+                error!("Could not add library folders: {:?}", e);
+            }
+        })
+        .detach();
+    }
+
+    fn hide(&mut self, cx: &mut Context<Self>) {
+        self.show.update(cx, |m, cx| {
+            *m = false;
+            cx.notify();
+        })
+    }
+}
+
+/// A short description of the rules of a folder, if it has any.
+fn describe_rules(root: &LibraryRoot) -> Option<String> {
+    let mut rules = Vec::new();
+
+    match root.exclude.len() {
+        0 => (),
+        1 => rules.push("1 exclusion".to_string()),
+        count => rules.push(format!("{} exclusions", count)),
+    }
+    if root.min_file_size > 0 {
+        rules.push(format!("at least {} KB", root.min_file_size / 1024));
+    }
+    if let Some(depth) = root.max_depth {
+        rules.push(format!("{} levels deep", depth));
+    }
+    if root.skip_hidden {
+        rules.push("skips hidden".to_string());
+    }
+    if !root.follow_symlinks {
+        rules.push("ignores symlinks".to_string());
+    }
+
+    (!rules.is_empty()).then(|| rules.join(", "))
+}
+
+impl Render for FoldersView {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if !*self.show.read(cx) {
+            return div().into_any_element();
+        }
+
+        let theme = cx.global::<Theme>();
+        let roots = self.settings.read(cx).scanning.roots.clone();
+        let weak = cx.weak_entity();
+
+        modal()
+            .on_exit(move |_, cx| {
+                weak.update(cx, |this, cx| {
+                    this.hide(cx);
+                })
+                .expect("failed to update folders view")
+            })
+            .child(
+                div()
+                    .w(px(500.0))
+                    .max_h(px(400.0))
+                    .overflow_hidden()
+                    .flex_col()
+                    .font_family("Inter")
+                    .font_weight(FontWeight::NORMAL)
+                    .child(
+                        div()
+                            .w_full()
+                            .p(px(12.0))
+                            .line_height(px(14.0))
+                            .text_sm()
+                            .font_weight(FontWeight::BOLD)
+                            .border_b(px(1.0))
+                            .border_color(theme.border_color)
+                            .child("Library folders"),
+                    )
+                    .child(
+                        div()
+                            .id("folder-list")
+                            .flex()
+                            .flex_col()
+                            .overflow_y_scroll()
+                            .p(px(4.0))
+                            .when(roots.is_empty(), |this| {
+                                this.child(
+                                    div()
+                                        .p(px(8.0))
+                                        .text_sm()
+                                        .text_color(theme.text_secondary)
+                                        .child("Add a folder to start building your library"),
+                                )
+                            })
+                            .children(roots.into_iter().enumerate().map(|(idx, root)| {
+                                let rules = describe_rules(&root);
+                                let path = root.path.clone();
+
+                                div()
+                                    .id(("folder", idx))
+                                    .flex()
+                                    .items_center()
+                                    .px(px(8.0))
+                                    .py(px(6.0))
+                                    .gap(px(8.0))
+                                    .rounded(px(4.0))
+                                    .text_sm()
+                                    .hover(|this| this.bg(theme.palette_item_hover))
+                                    .child(
+                                        div()
+                                            .font_family(FONT_AWESOME)
+                                            .text_size(px(11.0))
+                                            .text_color(theme.text_secondary)
+                                            // icon: `folder`
+                                            .child("\u{f07b}"),
+                                    )
+                                    .child(
+                                        div()
+                                            .flex_grow()
+                                            .overflow_x_hidden()
+                                            .text_ellipsis()
+                                            .child(root.path.to_string_lossy().to_string()),
+                                    )
+                                    .when_some(rules, |this, rules| {
+                                        this.child(
+                                            div()
+                                                .flex_shrink_0()
+                                                .text_color(theme.text_secondary)
+                                                .child(rules),
+                                        )
+                                    })
+                                    .child(
+                                        div()
+                                            .id(("folder-remove", idx))
+                                            .font_family(FONT_AWESOME)
+                                            .text_size(px(11.0))
+                                            .text_color(theme.text_secondary)
+                                            .px(px(4.0))
+                                            .rounded(px(3.0))
+                                            .cursor_pointer()
+                                            .hover(|this| this.bg(theme.button_danger))
+                                            // icon: `xmark`
+                                            // https://fontawesome.com/icons/xmark?f=classic&s=solid
+                                            .child("\u{f00d}")
+                                            .on_click(move |_, _, cx| {
+                                                let path = path.clone();
+                                                update_settings(cx, |settings| {
+                                                    settings
+                                                        .scanning
+                                                        .roots
+                                                        .retain(|v| v.path != path)
+                                                });
+                                            }),
+                                    )
+                            })),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .p(px(12.0))
+                            .gap(px(8.0))
+                            .border_t(px(1.0))
+                            .border_color(theme.border_color)
+                            .child(
+                                div()
+                                    .text_xs()
+                                    .text_color(theme.text_secondary)
+                                    .child("Tracks outside of these folders are removed"),
+                            )
+                            .child(
+                                button()
+                                    .intent(ButtonIntent::Primary)
+                                    .ml_auto()
+                                    .id("folder-add-button")
+                                    .child(div().font_family(FONT_AWESOME).child("\u{2b}"))
+                                    .child("Add folder")
+                                    .on_click(cx.listener(|this: &mut FoldersView, _, _, cx| {
+                                        this.add_folders(cx);
+                                    })),
+                            ),
+                    ),
+            )
+            .into_any_element()
+    }
+}
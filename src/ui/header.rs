@@ -6,17 +6,22 @@ use prelude::FluentBuilder;
 use crate::{
     library::scan::ScanEvent,
     services::mmb::lastfm::{LASTFM_API_KEY, LASTFM_API_SECRET},
+    settings::SettingsGlobal,
 };
 
 use super::{
     constants::{APP_ROUNDING, FONT_AWESOME},
+    folders::FoldersView,
     models::Models,
     theme::Theme,
 };
 
 pub struct Header {
     scan_status: Entity<ScanStatus>,
+    show_folders: Entity<bool>,
+    folders: Entity<FoldersView>,
     lastfm: Option<Entity<lastfm::LastFM>>,
+    private_session: Option<Entity<PrivateSession>>,
 }
 
 impl Header {
@@ -27,9 +32,29 @@ impl Header {
             None
         };
 
+        // the toggle is only useful if something is being scrobbled to
+        let listenbrainz = cx
+            .global::<SettingsGlobal>()
+            .model
+            .read(cx)
+            .listenbrainz
+            .token
+            .as_ref()
+            .is_some_and(|v| !v.is_empty());
+        let private_session = if lastfm.is_some() || listenbrainz {
+            Some(PrivateSession::new(cx))
+        } else {
+            None
+        };
+
+        let show_folders = cx.new(|_| false);
+
         cx.new(|cx| Self {
             scan_status: ScanStatus::new(cx),
+            folders: FoldersView::new(cx, show_folders.clone()),
+            show_folders,
             lastfm,
+            private_session,
         })
     }
 }
@@ -90,6 +115,42 @@ impl Render for Header {
                     .child(self.scan_status.clone()),
             )
             .child(div().ml_auto())
+            .child(
+                div()
+                    .flex()
+                    .text_sm()
+                    .px(px(12.0))
+                    .pb(px(6.0))
+                    .pt(px(5.0))
+                    .text_color(theme.text_secondary)
+                    .bg(theme.window_button)
+                    .id("folders-button")
+                    .hover(|this| this.bg(theme.window_button_hover))
+                    .active(|this| this.bg(theme.window_button_active))
+                    .on_mouse_down(MouseButton::Left, |_, window, cx| {
+                        window.prevent_default();
+                        cx.stop_propagation();
+                    })
+                    .child(
+                        div()
+                            .font_family(FONT_AWESOME)
+                            .pt(px(3.0))
+                            .text_size(px(11.0))
+                            .h_full()
+                            // icon: `folder-open`
+                            .child("\u{f07c}"),
+                    )
+                    .on_click(cx.listener(|this: &mut Header, _, _, cx| {
+                        this.show_folders.update(cx, |m, cx| {
+                            *m = true;
+                            cx.notify();
+                        })
+                    })),
+            )
+            .child(self.folders.clone())
+            .when_some(self.private_session.clone(), |this, private_session| {
+                this.child(private_session)
+            })
             .when_some(self.lastfm.clone(), |this, lastfm| this.child(lastfm))
             .when(cfg!(not(target_os = "macos")), |this| {
                 this.child(
@@ -160,6 +221,71 @@ impl Render for ScanStatus {
     }
 }
 
+/// Toggles the private session, which suspends scrobbling to every service.
+pub struct PrivateSession {
+    private_session: Entity<bool>,
+}
+
+impl PrivateSession {
+    pub fn new(cx: &mut App) -> Entity<Self> {
+        let private_session = cx.global::<Models>().private_session.clone();
+
+        cx.new(|cx| {
+            cx.observe(&private_session, |_, _, cx| {
+                cx.notify();
+            })
+            .detach();
+
+            Self { private_session }
+        })
+    }
+}
+
+impl Render for PrivateSession {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let private_session = self.private_session.clone();
+        let enabled = *private_session.read(cx);
+
+        div()
+            .flex()
+            .text_sm()
+            .px(px(12.0))
+            .pb(px(6.0))
+            .pt(px(5.0))
+            .text_color(if enabled {
+                theme.text
+            } else {
+                theme.text_secondary
+            })
+            .bg(theme.window_button)
+            .id("private-session-button")
+            .hover(|this| this.bg(theme.window_button_hover))
+            .active(|this| this.bg(theme.window_button_active))
+            .on_mouse_down(MouseButton::Left, |_, window, cx| {
+                window.prevent_default();
+                cx.stop_propagation();
+            })
+            .child(
+                div()
+                    .font_family(FONT_AWESOME)
+                    .pt(px(3.0))
+                    .text_size(px(11.0))
+                    .h_full()
+                    .child(if enabled { "\u{f070}" } else { "\u{f06e}" }),
+            )
+            .when(enabled, |this| {
+                this.child(div().ml(px(8.0)).child("Private session"))
+            })
+            .on_click(move |_, _, cx| {
+                private_session.update(cx, |m, cx| {
+                    *m = !*m;
+                    cx.notify();
+                })
+            })
+    }
+}
+
 #[derive(PartialEq, Clone, Copy, IntoElement)]
 pub enum WindowButton {
     Close,
@@ -1,18 +1,22 @@
 use gpui::*;
-use tracing::error;
+use prelude::FluentBuilder;
+use tracing::{error, warn};
 
 use crate::{
-    services::mmb::lastfm::{client::LastFMClient, LASTFM_API_KEY, LASTFM_API_SECRET},
+    services::mmb::lastfm::LASTFM_API_KEY,
     ui::{
         constants::FONT_AWESOME_BRANDS,
-        models::{LastFMState, Models},
+        models::{create_last_fm_client, LastFMState, Models},
         theme::Theme,
     },
 };
 
 pub struct LastFM {
     state: Entity<LastFMState>,
+    pending: Entity<usize>,
     name: Option<SharedString>,
+    /// The total number of tracks the user has scrobbled.
+    scrobbles: Option<u64>,
 }
 
 impl LastFM {
@@ -20,30 +24,64 @@ impl LastFM {
         cx.new(|cx| {
             let models = cx.global::<Models>();
             let state = models.lastfm.clone();
+            let pending = models.lastfm_pending.clone();
+
+            cx.observe(&pending, |_, _, cx| cx.notify()).detach();
 
             cx.observe(&state, |this: &mut LastFM, m, cx| {
                 this.name = match m.read(cx) {
                     LastFMState::Connected(session) => Some(session.name.clone().into()),
                     _ => None,
-                }
+                };
+                this.fetch_user_info(cx);
             })
             .detach();
 
-            LastFM {
+            let mut this = LastFM {
                 name: match state.read(cx) {
                     LastFMState::Connected(session) => Some(session.name.clone().into()),
                     _ => None,
                 },
+                scrobbles: None,
                 state,
-            }
+                pending,
+            };
+
+            this.fetch_user_info(cx);
+            this
         })
     }
+
+    fn fetch_user_info(&mut self, cx: &mut Context<Self>) {
+        self.scrobbles = None;
+
+        let Some(name) = self.name.clone() else {
+            return;
+        };
+        let Some(mut client) = create_last_fm_client(cx) else {
+            return;
+        };
+
+        cx.spawn(
+            async move |weak, cx| match client.get_user_info(name.to_string()).await {
+                Ok(info) => {
+                    let _ = weak.update(cx, |this: &mut LastFM, cx| {
+                        this.scrobbles = info.playcount;
+                        cx.notify();
+                    });
+                }
+                Err(e) => warn!("Could not get user info from last.fm: {}", e),
+            },
+        )
+        .detach();
+    }
 }
 
 impl Render for LastFM {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
         let state = self.state.clone();
+        let pending = *self.pending.read(cx);
 
         div()
             .flex()
@@ -82,6 +120,16 @@ impl Render for LastFM {
                         .into_any_element(),
                 }),
             )
+            .when_some(self.scrobbles, |this, scrobbles| {
+                this.child(
+                    div()
+                        .ml(px(6.0))
+                        .child(format!("· {} scrobbles", scrobbles)),
+                )
+            })
+            .when(pending > 0, |this| {
+                this.child(div().ml(px(6.0)).child(format!("({} pending)", pending)))
+            })
             .on_click(move |_, _, cx| {
                 let state = state.clone();
                 let read = state.read(cx).clone();
@@ -96,12 +144,9 @@ impl Render for LastFM {
 }
 
 fn get_token(cx: &mut App, state: Entity<LastFMState>) {
-    cx.spawn(async move |cx| {
-        let mut client = LastFMClient::new(
-            LASTFM_API_KEY.unwrap().to_string(),
-            LASTFM_API_SECRET.unwrap(),
-        );
+    let mut client = create_last_fm_client(cx).expect("last.fm API keys are not set");
 
+    cx.spawn(async move |cx| {
         if let Ok(token) = client.get_token().await {
             let path = format!(
                 "http://last.fm/api/auth/?api_key={}&token={}",
@@ -128,12 +173,9 @@ fn get_token(cx: &mut App, state: Entity<LastFMState>) {
 }
 
 fn confirm(cx: &mut App, state: Entity<LastFMState>, token: String) {
-    cx.spawn(async move |cx| {
-        let mut client = LastFMClient::new(
-            LASTFM_API_KEY.unwrap().to_string(),
-            LASTFM_API_SECRET.unwrap(),
-        );
+    let mut client = create_last_fm_client(cx).expect("last.fm API keys are not set");
 
+    cx.spawn(async move |cx| {
         if let Ok(session) = client.get_session(token).await {
             state
                 .update(cx, move |_, cx| {
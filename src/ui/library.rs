@@ -1,21 +1,44 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, path::Path};
 
 use album_view::AlbumView;
+use artist_list_view::ArtistListView;
+use artist_view::ArtistView;
+use genre_list_view::GenreListView;
+use genre_view::GenreView;
 use gpui::*;
 use navigation::NavigationView;
 use release_view::ReleaseView;
-use tracing::debug;
+use stats_view::StatsView;
+use tracing::{debug, error};
+use track_view::TrackView;
+
+use crate::{
+    library::db::LibraryAccess,
+    playback::{interface::GPUIPlaybackInterface, queue::QueueItemData},
+};
 
 use super::models::Models;
 
 mod album_view;
+mod artist_list_view;
+mod artist_view;
+mod genre_list_view;
+mod genre_view;
 mod navigation;
 mod release_view;
+mod stats_view;
+mod track_view;
 
 #[derive(Clone)]
 enum LibraryView {
     Album(Entity<AlbumView>),
+    Artists(Entity<ArtistListView>),
+    Artist(Entity<ArtistView>),
+    Tracks(Entity<TrackView>),
+    Genres(Entity<GenreListView>),
+    Genre(Entity<GenreView>),
     Release(Entity<ReleaseView>),
+    Stats(Entity<StatsView>),
 }
 
 pub struct Library {
@@ -26,12 +49,47 @@ pub struct Library {
 #[derive(Clone, Copy, Debug)]
 pub enum ViewSwitchMessage {
     Albums,
+    Artists,
+    Artist(i64),
+    Tracks,
+    Genres,
+    Genre(i64),
     Release(i64),
+    Stats,
     Back,
 }
 
 impl EventEmitter<ViewSwitchMessage> for VecDeque<ViewSwitchMessage> {}
 
+/// Opens an artist's page, from anywhere an artist's name is shown.
+pub fn open_artist(cx: &mut App, artist_id: i64) {
+    let switcher_model = cx.global::<Models>().switcher_model.clone();
+    switcher_model.update(cx, |_, cx| cx.emit(ViewSwitchMessage::Artist(artist_id)));
+}
+
+/// Opens the page of the first primary artist of a track, if the track is in the library.
+pub fn open_track_artist(cx: &mut App, location: &Path) {
+    match cx.get_track_artist_id(location) {
+        Ok(Some(artist_id)) => open_artist(cx, artist_id),
+        Ok(None) => debug!("{:?} has no artist in the library", location),
+        Err(e) => error!("Could not retrieve the artist of {:?}: {}", location, e),
+    }
+}
+
+/// Adds the track to the end of the queue and starts playing it.
+pub fn play_track(cx: &mut App, track_id: i64) {
+    let track = match cx.get_track_by_id(track_id) {
+        Ok(track) => track,
+        Err(e) => {
+            error!("Could not retrieve track {}: {}", track_id, e);
+            return;
+        }
+    };
+
+    let data = QueueItemData::new(track.location.clone(), Some(track.id), track.album_id);
+    cx.global::<GPUIPlaybackInterface>().queue_and_play(data);
+}
+
 fn make_view(
     message: &ViewSwitchMessage,
     cx: &mut App,
@@ -39,7 +97,15 @@ fn make_view(
 ) -> LibraryView {
     match message {
         ViewSwitchMessage::Albums => LibraryView::Album(AlbumView::new(cx, model.clone())),
+        ViewSwitchMessage::Artists => LibraryView::Artists(ArtistListView::new(cx, model.clone())),
+        ViewSwitchMessage::Artist(id) => {
+            LibraryView::Artist(ArtistView::new(cx, model.clone(), *id))
+        }
+        ViewSwitchMessage::Tracks => LibraryView::Tracks(TrackView::new(cx)),
+        ViewSwitchMessage::Genres => LibraryView::Genres(GenreListView::new(cx, model.clone())),
+        ViewSwitchMessage::Genre(id) => LibraryView::Genre(GenreView::new(cx, model.clone(), *id)),
         ViewSwitchMessage::Release(id) => LibraryView::Release(ReleaseView::new(cx, *id)),
+        ViewSwitchMessage::Stats => LibraryView::Stats(StatsView::new(cx, model.clone())),
         ViewSwitchMessage::Back => panic!("improper use of make_view (cannot make Back)"),
     }
 }
@@ -112,7 +178,15 @@ impl Render for Library {
             .child(self.navigation_view.clone())
             .child(match &self.view {
                 LibraryView::Album(album_view) => album_view.clone().into_any_element(),
+                LibraryView::Tracks(track_view) => track_view.clone().into_any_element(),
+                LibraryView::Genres(genre_list_view) => genre_list_view.clone().into_any_element(),
+                LibraryView::Genre(genre_view) => genre_view.clone().into_any_element(),
+                LibraryView::Artists(artist_list_view) => {
+                    artist_list_view.clone().into_any_element()
+                }
+                LibraryView::Artist(artist_view) => artist_view.clone().into_any_element(),
                 LibraryView::Release(release_view) => release_view.clone().into_any_element(),
+                LibraryView::Stats(stats_view) => stats_view.clone().into_any_element(),
             })
     }
 }
@@ -1,15 +1,25 @@
 use std::{collections::VecDeque, rc::Rc};
 
+use ahash::AHashSet;
 use gpui::*;
+use prelude::FluentBuilder;
+use tracing::error;
 
 use crate::{
     library::{
+        db::LibraryAccess,
         scan::ScanEvent,
-        types::{table::AlbumColumn, Album},
+        types::{table::AlbumColumn, Album, GenreSummary},
     },
     ui::{
-        components::table::{Table, TableEvent},
+        components::{
+            button::{button, ButtonIntent},
+            menu::{menu, menu_item},
+            table::{RowFilter, Table, TableEvent},
+        },
+        constants::FONT_AWESOME,
         models::Models,
+        theme::Theme,
     },
 };
 
@@ -18,6 +28,10 @@ use super::ViewSwitchMessage;
 #[derive(Clone)]
 pub struct AlbumView {
     table: Entity<Table<Album, AlbumColumn>>,
+    /// The genre the table is filtered by.
+    genre: Option<GenreSummary>,
+    /// The genres offered in the genre filter, loaded when it is opened.
+    genres: Option<Rc<Vec<GenreSummary>>>,
 }
 
 impl AlbumView {
@@ -37,11 +51,16 @@ impl AlbumView {
 
             let table_clone = table.clone();
 
-            cx.observe(&state, move |_: &mut AlbumView, e, cx| {
+            cx.observe(&state, move |this: &mut AlbumView, e, cx| {
                 let value = e.read(cx);
                 match value {
-                    ScanEvent::ScanCompleteIdle => {
-                        table_clone.update(cx, |_, cx| cx.emit(TableEvent::NewRows));
+                    ScanEvent::ScanCompleteIdle | ScanEvent::ScanCompleteWatching => {
+                        // the albums in the genre may have changed
+                        if this.genre.is_some() {
+                            this.apply_genre_filter(cx);
+                        } else {
+                            table_clone.update(cx, |_, cx| cx.emit(TableEvent::NewRows));
+                        }
                     }
                     ScanEvent::ScanProgress { current, .. } => {
                         if current % 100 == 0 {
@@ -53,13 +72,87 @@ impl AlbumView {
             })
             .detach();
 
-            AlbumView { table }
+            AlbumView {
+                table,
+                genre: None,
+                genres: None,
+            }
         })
     }
+
+    fn set_genre(&mut self, genre: Option<GenreSummary>, cx: &mut Context<Self>) {
+        self.genre = genre;
+        self.genres = None;
+        self.apply_genre_filter(cx);
+        cx.notify();
+    }
+
+    fn apply_genre_filter(&mut self, cx: &mut Context<Self>) {
+        let filter: Option<RowFilter<Album, AlbumColumn>> = match self.genre.as_ref() {
+            Some(genre) => match cx.list_genre_album_ids(genre.id) {
+                Ok(ids) => {
+                    let ids: AHashSet<i64> = ids.into_iter().collect();
+                    Some(Rc::new(move |id: &(u32, String)| {
+                        ids.contains(&(id.0 as i64))
+                    }))
+                }
+                Err(e) => {
+                    error!("Could not load the albums of genre {}: {}", genre.id, e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        self.table
+            .update(cx, |table, cx| table.set_filter(filter, cx));
+    }
+
+    fn toggle_genres(&mut self, cx: &mut Context<Self>) {
+        if self.genres.is_some() {
+            self.genres = None;
+        } else {
+            match cx.list_genre_summaries() {
+                Ok(genres) => self.genres = Some(Rc::new(genres)),
+                Err(e) => error!("Could not load genres: {}", e),
+            }
+        }
+
+        cx.notify();
+    }
 }
 
 impl Render for AlbumView {
-    fn render(&mut self, _: &mut Window, _: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let view = cx.entity();
+
+        let mut genre_menu = menu().item(menu_item(
+            "genre-all",
+            self.genre.is_none().then_some("\u{f00c}"),
+            "All genres",
+            {
+                let view = view.clone();
+                move |_, _, cx| view.update(cx, |this, cx| this.set_genre(None, cx))
+            },
+        ));
+
+        for genre in self.genres.iter().flat_map(|v| v.iter()) {
+            let selected = self.genre.as_ref().is_some_and(|v| v.id == genre.id);
+            let view = view.clone();
+            let genre = genre.clone();
+
+            genre_menu = genre_menu.item(menu_item(
+                ("genre", genre.id as u64),
+                selected.then_some("\u{f00c}"),
+                genre.name.0.clone(),
+                move |_, _, cx| {
+                    let genre = genre.clone();
+                    view.update(cx, |this, cx| this.set_genre(Some(genre), cx))
+                },
+            ));
+        }
+
         div()
             .flex()
             .flex_col()
@@ -69,6 +162,68 @@ impl Render for AlbumView {
             .mx_auto()
             .pt(px(24.0))
             .pb(px(0.0))
+            .relative()
             .child(self.table.clone())
+            .child(
+                div()
+                    .absolute()
+                    .top(px(22.0))
+                    .right(px(24.0))
+                    .flex()
+                    .flex_col()
+                    .items_end()
+                    .when(self.genres.is_some(), |this| {
+                        // the button is inside this, so clicking it to close the menu doesn't reopen it
+                        this.on_mouse_down_out(cx.listener(|this: &mut AlbumView, _, _, cx| {
+                            this.genres = None;
+                            cx.notify();
+                        }))
+                    })
+                    .child(
+                        button()
+                            .intent(if self.genre.is_some() {
+                                ButtonIntent::Primary
+                            } else {
+                                ButtonIntent::Secondary
+                            })
+                            .id("album-genre-button")
+                            .child(
+                                self.genre
+                                    .as_ref()
+                                    .map(|v| v.name.0.clone())
+                                    .unwrap_or("All genres".into()),
+                            )
+                            .child(
+                                div()
+                                    .font_family(FONT_AWESOME)
+                                    .text_size(px(10.0))
+                                    .my_auto()
+                                    .child(if self.genres.is_some() {
+                                        "\u{f077}"
+                                    } else {
+                                        "\u{f078}"
+                                    }),
+                            )
+                            .on_click(cx.listener(|this: &mut AlbumView, _, _, cx| {
+                                this.toggle_genres(cx);
+                            })),
+                    )
+                    .when(self.genres.is_some(), |this| {
+                        this.child(
+                            div()
+                                .id("album-genre-menu")
+                                .occlude()
+                                .mt(px(4.0))
+                                .max_h(px(360.0))
+                                .overflow_y_scroll()
+                                .border_1()
+                                .shadow_sm()
+                                .rounded(px(4.0))
+                                .border_color(theme.elevated_border_color)
+                                .bg(theme.elevated_background)
+                                .child(genre_menu),
+                        )
+                    }),
+            )
     }
 }
@@ -0,0 +1,329 @@
+use std::{collections::VecDeque, rc::Rc, sync::Arc};
+
+use gpui::*;
+use prelude::FluentBuilder;
+use tracing::error;
+
+use crate::{
+    library::{db::LibraryAccess, scan::ScanEvent, types::ArtistSummary},
+    ui::{
+        components::{
+            button::{button, ButtonIntent},
+            input::TextInput,
+        },
+        constants::FONT_AWESOME,
+        models::Models,
+        theme::Theme,
+    },
+};
+
+use super::ViewSwitchMessage;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ArtistSort {
+    Name,
+    Albums,
+    Tracks,
+    Plays,
+}
+
+impl ArtistSort {
+    const ALL: [ArtistSort; 4] = [
+        ArtistSort::Name,
+        ArtistSort::Albums,
+        ArtistSort::Tracks,
+        ArtistSort::Plays,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            ArtistSort::Name => "Name",
+            ArtistSort::Albums => "Albums",
+            ArtistSort::Tracks => "Tracks",
+            ArtistSort::Plays => "Plays",
+        }
+    }
+
+    /// Names are sorted from A to Z and counts from highest to lowest, unless reversed.
+    fn sort(&self, artists: &mut [ArtistSummary], reversed: bool) {
+        match self {
+            ArtistSort::Name => artists.sort_by_cached_key(|v| v.name_sortable.to_lowercase()),
+            ArtistSort::Albums => artists.sort_by_key(|v| std::cmp::Reverse(v.albums)),
+            ArtistSort::Tracks => artists.sort_by_key(|v| std::cmp::Reverse(v.tracks)),
+            ArtistSort::Plays => artists.sort_by_key(|v| std::cmp::Reverse(v.plays)),
+        }
+
+        if reversed {
+            artists.reverse();
+        }
+    }
+}
+
+pub struct ArtistListView {
+    view_switch_model: Entity<VecDeque<ViewSwitchMessage>>,
+    artists: Arc<Vec<ArtistSummary>>,
+    input: Entity<TextInput>,
+    focus_handle: FocusHandle,
+    query: String,
+    sort: ArtistSort,
+    reversed: bool,
+    list_state: ListState,
+    /// The number of artists matching the query.
+    shown: usize,
+}
+
+impl ArtistListView {
+    pub(super) fn new(
+        cx: &mut App,
+        view_switch_model: Entity<VecDeque<ViewSwitchMessage>>,
+    ) -> Entity<Self> {
+        cx.new(|cx| {
+            let focus_handle = cx.focus_handle();
+            let input = TextInput::new(
+                cx,
+                focus_handle.clone(),
+                None,
+                Some("Search artists".into()),
+                None,
+            );
+
+            cx.subscribe(&input, |this: &mut ArtistListView, _, ev: &String, cx| {
+                this.query = ev.clone();
+                this.regenerate_list_state(cx);
+            })
+            .detach();
+
+            let state = cx.global::<Models>().scan_state.clone();
+
+            cx.observe(&state, |this: &mut ArtistListView, e, cx| {
+                if matches!(
+                    e.read(cx),
+                    ScanEvent::ScanCompleteIdle | ScanEvent::ScanCompleteWatching
+                ) {
+                    this.load(cx);
+                }
+            })
+            .detach();
+
+            let mut view = ArtistListView {
+                view_switch_model,
+                artists: Arc::new(Vec::new()),
+                input,
+                focus_handle,
+                query: String::new(),
+                sort: ArtistSort::Name,
+                reversed: false,
+                list_state: ListState::new(0, ListAlignment::Top, px(300.0), |_, _, _| {
+                    div().into_any_element()
+                }),
+                shown: 0,
+            };
+
+            view.load(cx);
+            view
+        })
+    }
+
+    fn load(&mut self, cx: &mut Context<Self>) {
+        match cx.list_artist_summaries() {
+            Ok(artists) => self.artists = Arc::new(artists),
+            Err(e) => error!("Could not load artists: {}", e),
+        }
+
+        self.regenerate_list_state(cx);
+    }
+
+    fn set_sort(&mut self, sort: ArtistSort, cx: &mut Context<Self>) {
+        if self.sort == sort {
+            self.reversed = !self.reversed;
+        } else {
+            self.sort = sort;
+            self.reversed = false;
+        }
+
+        self.regenerate_list_state(cx);
+    }
+
+    fn regenerate_list_state(&mut self, cx: &mut Context<Self>) {
+        let query = self.query.trim().to_lowercase();
+        let mut artists: Vec<ArtistSummary> = self
+            .artists
+            .iter()
+            .filter(|v| query.is_empty() || v.name.0.to_lowercase().contains(&query))
+            .cloned()
+            .collect();
+
+        self.sort.sort(&mut artists, self.reversed);
+        self.shown = artists.len();
+
+        let artists = Rc::new(artists);
+        let view_switch_model = self.view_switch_model.clone();
+
+        self.list_state = ListState::new(
+            artists.len(),
+            ListAlignment::Top,
+            px(300.0),
+            move |idx, _, _| {
+                ArtistRow {
+                    artist: artists[idx].clone(),
+                    view_switch_model: view_switch_model.clone(),
+                }
+                .into_any_element()
+            },
+        );
+
+        cx.notify();
+    }
+}
+
+fn count(value: i64, singular: &str, plural: &str) -> String {
+    if value == 1 {
+        format!("1 {}", singular)
+    } else {
+        format!("{} {}", value, plural)
+    }
+}
+
+#[derive(IntoElement)]
+struct ArtistRow {
+    artist: ArtistSummary,
+    view_switch_model: Entity<VecDeque<ViewSwitchMessage>>,
+}
+
+impl RenderOnce for ArtistRow {
+    fn render(self, _: &mut Window, cx: &mut App) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let id = self.artist.id;
+        let view_switch_model = self.view_switch_model;
+
+        div()
+            .id(("artist", id as u64))
+            .flex()
+            .w_full()
+            .px(px(24.0))
+            .py(px(8.0))
+            .gap(px(12.0))
+            .border_b_1()
+            .border_color(theme.border_color)
+            .cursor_pointer()
+            .hover(|this| this.bg(theme.nav_button_hover))
+            .active(|this| this.bg(theme.nav_button_active))
+            .on_click(move |_, _, cx| {
+                view_switch_model.update(cx, |_, cx| cx.emit(ViewSwitchMessage::Artist(id)))
+            })
+            .child(
+                div()
+                    .font_weight(FontWeight::BOLD)
+                    .overflow_x_hidden()
+                    .text_ellipsis()
+                    .child(self.artist.name.0.clone()),
+            )
+            .child(
+                div()
+                    .ml_auto()
+                    .flex()
+                    .flex_shrink_0()
+                    .gap(px(16.0))
+                    .text_sm()
+                    .text_color(theme.text_secondary)
+                    .child(count(self.artist.albums, "album", "albums"))
+                    .child(count(self.artist.tracks, "track", "tracks"))
+                    .child(count(self.artist.plays, "play", "plays")),
+            )
+    }
+}
+
+impl Render for ArtistListView {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+
+        let mut sorts = div().flex().gap(px(6.0)).ml_auto();
+
+        for sort in ArtistSort::ALL {
+            sorts = sorts.child(
+                button()
+                    .intent(if sort == self.sort {
+                        ButtonIntent::Primary
+                    } else {
+                        ButtonIntent::Secondary
+                    })
+                    .id(sort.label())
+                    .child(sort.label())
+                    .when(sort == self.sort, |this| {
+                        this.child(
+                            div()
+                                .font_family(FONT_AWESOME)
+                                .text_size(px(10.0))
+                                .my_auto()
+                                .child(if self.reversed {
+                                    "\u{f077}"
+                                } else {
+                                    "\u{f078}"
+                                }),
+                        )
+                    })
+                    .on_click(cx.listener(move |this: &mut ArtistListView, _, _, cx| {
+                        this.set_sort(sort, cx);
+                    })),
+            );
+        }
+
+        div()
+            .flex()
+            .flex_col()
+            .w_full()
+            .h_full()
+            .max_w(px(1000.0))
+            .mx_auto()
+            .pt(px(24.0))
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .px(px(24.0))
+                    .pb(px(11.0))
+                    .child(
+                        div()
+                            .line_height(px(26.0))
+                            .font_weight(FontWeight::BOLD)
+                            .text_size(px(26.0))
+                            .child("Artists"),
+                    )
+                    .child(sorts),
+            )
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .mx(px(24.0))
+                    .mb(px(12.0))
+                    .px(px(10.0))
+                    .py(px(6.0))
+                    .gap(px(8.0))
+                    .text_sm()
+                    .rounded(px(4.0))
+                    .border_1()
+                    .border_color(theme.border_color)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this: &mut ArtistListView, _, window, _| {
+                            this.focus_handle.focus(window);
+                        }),
+                    )
+                    .child(
+                        div()
+                            .font_family(FONT_AWESOME)
+                            .text_color(theme.text_secondary)
+                            .child("\u{f002}"),
+                    )
+                    .child(div().flex_1().child(self.input.clone()))
+                    .child(
+                        div()
+                            .flex_shrink_0()
+                            .text_color(theme.text_secondary)
+                            .child(count(self.shown as i64, "artist", "artists")),
+                    ),
+            )
+            .child(list(self.list_state.clone()).w_full().h_full())
+    }
+}
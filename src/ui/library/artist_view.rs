@@ -0,0 +1,380 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use gpui::*;
+use prelude::FluentBuilder;
+use tracing::error;
+
+use crate::{
+    library::{
+        db::LibraryAccess,
+        types::{Artist, ArtistRelease, TrackPlays},
+    },
+    ui::{data::Decode, theme::Theme, util::drop_image_from_app},
+};
+
+use super::{play_track, ViewSwitchMessage};
+
+/// The number of tracks shown in the top tracks section.
+const TOP_TRACKS_LIMIT: u32 = 10;
+
+/// The sections of the discography, in the order they are shown.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ReleaseGroup {
+    Albums,
+    Eps,
+    Singles,
+    Live,
+    Compilations,
+    Other,
+}
+
+impl ReleaseGroup {
+    const ALL: [ReleaseGroup; 6] = [
+        ReleaseGroup::Albums,
+        ReleaseGroup::Eps,
+        ReleaseGroup::Singles,
+        ReleaseGroup::Live,
+        ReleaseGroup::Compilations,
+        ReleaseGroup::Other,
+    ];
+
+    /// Groups a release by its MusicBrainz release type. Secondary types like "album; live" take
+    /// precedence over the primary type, and releases without a type are treated as albums.
+    fn of(release_type: Option<&str>) -> Self {
+        let Some(release_type) = release_type else {
+            return ReleaseGroup::Albums;
+        };
+
+        let types: Vec<&str> = release_type.split([';', '/', ',']).map(str::trim).collect();
+
+        if types.contains(&"compilation") {
+            ReleaseGroup::Compilations
+        } else if types.contains(&"live") {
+            ReleaseGroup::Live
+        } else {
+            match types.first().copied().unwrap_or_default() {
+                "album" | "" => ReleaseGroup::Albums,
+                "ep" => ReleaseGroup::Eps,
+                "single" => ReleaseGroup::Singles,
+                _ => ReleaseGroup::Other,
+            }
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            ReleaseGroup::Albums => "Albums",
+            ReleaseGroup::Eps => "EPs",
+            ReleaseGroup::Singles => "Singles",
+            ReleaseGroup::Live => "Live",
+            ReleaseGroup::Compilations => "Compilations",
+            ReleaseGroup::Other => "Other Releases",
+        }
+    }
+}
+
+pub struct ArtistView {
+    view_switch_model: Entity<VecDeque<ViewSwitchMessage>>,
+    artist: Option<Arc<Artist>>,
+    image: Entity<Option<Arc<RenderImage>>>,
+    discography: Vec<(ReleaseGroup, Vec<ArtistRelease>)>,
+    appearances: Vec<ArtistRelease>,
+    top_tracks: Vec<TrackPlays>,
+}
+
+impl ArtistView {
+    pub(super) fn new(
+        cx: &mut App,
+        view_switch_model: Entity<VecDeque<ViewSwitchMessage>>,
+        artist_id: i64,
+    ) -> Entity<Self> {
+        cx.new(|cx| {
+            let image = cx.new(|_| None);
+
+            cx.on_release(|this: &mut Self, cx: &mut App| {
+                if let Some(image) = this.image.read(cx).clone() {
+                    drop_image_from_app(cx, image);
+                }
+            })
+            .detach();
+
+            let mut view = ArtistView {
+                view_switch_model,
+                artist: None,
+                image,
+                discography: Vec::new(),
+                appearances: Vec::new(),
+                top_tracks: Vec::new(),
+            };
+
+            if let Err(e) = view.load(cx, artist_id) {
+                error!("Could not load artist {}: {}", artist_id, e);
+            }
+
+            view
+        })
+    }
+
+    fn load(&mut self, cx: &mut App, artist_id: i64) -> Result<(), sqlx::Error> {
+        let artist = cx.get_artist_by_id(artist_id)?;
+
+        if let Some(image) = artist.image.clone() {
+            cx.decode_image(image, false, self.image.clone()).detach();
+        }
+
+        self.artist = Some(artist);
+
+        let mut discography: Vec<(ReleaseGroup, Vec<ArtistRelease>)> = ReleaseGroup::ALL
+            .iter()
+            .map(|group| (*group, Vec::new()))
+            .collect();
+
+        for release in cx.list_artist_releases(artist_id)? {
+            let group = ReleaseGroup::of(release.release_type.as_deref());
+            discography[group as usize].1.push(release);
+        }
+
+        discography.retain(|(_, releases)| !releases.is_empty());
+
+        self.discography = discography;
+        self.appearances = cx.list_artist_appearances(artist_id)?;
+        self.top_tracks = cx.list_artist_top_tracks(artist_id, TOP_TRACKS_LIMIT)?;
+
+        Ok(())
+    }
+}
+
+pub(super) fn section(title: &'static str) -> Div {
+    div().flex().flex_col().child(
+        div()
+            .font_weight(FontWeight::BOLD)
+            .text_size(px(18.0))
+            .mb(px(8.0))
+            .child(title),
+    )
+}
+
+pub(super) fn release_grid(
+    id: &'static str,
+    releases: &[ArtistRelease],
+    view_switch_model: &Entity<VecDeque<ViewSwitchMessage>>,
+    theme: &Theme,
+) -> Div {
+    div()
+        .flex()
+        .flex_wrap()
+        .gap(px(8.0))
+        .children(releases.iter().map(|release| {
+            let album_id = release.id;
+            let view_switch_model = view_switch_model.clone();
+
+            div()
+                .id((id, album_id as u64))
+                .flex()
+                .items_center()
+                .w(px(236.0))
+                .p(px(6.0))
+                .gap(px(10.0))
+                .rounded(px(4.0))
+                .cursor_pointer()
+                .hover(|this| this.bg(theme.nav_button_hover))
+                .active(|this| this.bg(theme.nav_button_active))
+                .on_click(move |_, _, cx| {
+                    view_switch_model
+                        .update(cx, |_, cx| cx.emit(ViewSwitchMessage::Release(album_id)))
+                })
+                .child(
+                    div()
+                        .w(px(48.0))
+                        .h(px(48.0))
+                        .flex_shrink_0()
+                        .rounded(px(3.0))
+                        .overflow_hidden()
+                        .bg(theme.album_art_background)
+                        .when_some(release.thumb.clone(), |this, thumb| {
+                            this.child(img(thumb.0).w(px(48.0)).h(px(48.0)))
+                        }),
+                )
+                .child(
+                    div()
+                        .flex()
+                        .flex_col()
+                        .overflow_x_hidden()
+                        .text_sm()
+                        .child(
+                            div()
+                                .font_weight(FontWeight::BOLD)
+                                .text_ellipsis()
+                                .child(release.title.0.clone()),
+                        )
+                        .when_some(release.release_date, |this, date| {
+                            this.child(
+                                div()
+                                    .text_color(theme.text_secondary)
+                                    .child(date.format("%Y").to_string()),
+                            )
+                        }),
+                )
+        }))
+}
+
+impl Render for ArtistView {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let image = self.image.read(cx).clone();
+
+        let Some(artist) = self.artist.clone() else {
+            return div()
+                .w_full()
+                .max_w(px(1000.0))
+                .mx_auto()
+                .p(px(24.0))
+                .text_color(theme.text_secondary)
+                .child("This artist is no longer in the library.")
+                .into_any_element();
+        };
+
+        let mut top_tracks = section("Top Tracks");
+
+        for (i, track) in self.top_tracks.iter().enumerate() {
+            let track_id = track.id;
+
+            top_tracks = top_tracks.child(
+                div()
+                    .id(("artist-top-track", track_id as u64))
+                    .flex()
+                    .text_sm()
+                    .py(px(4.0))
+                    .px(px(6.0))
+                    .gap(px(8.0))
+                    .rounded(px(4.0))
+                    .cursor_pointer()
+                    .hover(|this| this.bg(theme.nav_button_hover))
+                    .active(|this| this.bg(theme.nav_button_active))
+                    .on_click(move |_, _, cx| play_track(cx, track_id))
+                    .child(
+                        div()
+                            .w(px(20.0))
+                            .flex_shrink_0()
+                            .text_color(theme.text_secondary)
+                            .child(format!("{}", i + 1)),
+                    )
+                    .child(
+                        div()
+                            .font_weight(FontWeight::BOLD)
+                            .text_ellipsis()
+                            .child(track.title.0.clone()),
+                    )
+                    .when_some(track.artist_name.clone(), |this, artist| {
+                        this.child(
+                            div()
+                                .text_ellipsis()
+                                .text_color(theme.text_secondary)
+                                .child(artist.0),
+                        )
+                    })
+                    .child(
+                        div()
+                            .ml_auto()
+                            .flex_shrink_0()
+                            .text_color(theme.text_secondary)
+                            .child(if track.plays == 1 {
+                                "1 play".to_string()
+                            } else {
+                                format!("{} plays", track.plays)
+                            }),
+                    ),
+            );
+        }
+
+        let mut discography = div().flex().flex_col().gap(px(24.0));
+
+        for (group, releases) in &self.discography {
+            discography = discography.child(section(group.label()).child(release_grid(
+                group.label(),
+                releases,
+                &self.view_switch_model,
+                theme,
+            )));
+        }
+
+        div()
+            .id("artist-view")
+            .flex()
+            .flex_col()
+            .w_full()
+            .h_full()
+            .overflow_y_scroll()
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .w_full()
+                    .max_w(px(1000.0))
+                    .mx_auto()
+                    .px(px(24.0))
+                    .py(px(24.0))
+                    .gap(px(24.0))
+                    .child(
+                        div()
+                            .flex()
+                            .child(
+                                div()
+                                    .rounded(px(4.0))
+                                    .bg(theme.album_art_background)
+                                    .shadow_sm()
+                                    .w(px(160.0))
+                                    .h(px(160.0))
+                                    .flex_shrink_0()
+                                    .overflow_hidden()
+                                    .when_some(image, |this, image| {
+                                        this.child(
+                                            img(image)
+                                                .w(px(160.0))
+                                                .h(px(160.0))
+                                                .object_fit(ObjectFit::Cover)
+                                                .rounded(px(4.0)),
+                                        )
+                                    }),
+                            )
+                            .child(
+                                div()
+                                    .ml(px(18.0))
+                                    .mt_auto()
+                                    .flex()
+                                    .flex_col()
+                                    .overflow_x_hidden()
+                                    .child(
+                                        div()
+                                            .font_weight(FontWeight::EXTRA_BOLD)
+                                            .text_size(rems(2.5))
+                                            .line_height(rems(2.75))
+                                            .text_ellipsis()
+                                            .when_some(artist.name.clone(), |this, name| {
+                                                this.child(name.0)
+                                            }),
+                                    ),
+                            ),
+                    )
+                    .when_some(artist.bio.clone(), |this, bio| {
+                        this.child(
+                            div()
+                                .text_sm()
+                                .text_color(theme.text_secondary)
+                                .child(bio.0),
+                        )
+                    })
+                    .when(!self.top_tracks.is_empty(), |this| this.child(top_tracks))
+                    .child(discography)
+                    .when(!self.appearances.is_empty(), |this| {
+                        this.child(section("Appears On").child(release_grid(
+                            "appearance",
+                            &self.appearances,
+                            &self.view_switch_model,
+                            theme,
+                        )))
+                    }),
+            )
+            .into_any_element()
+    }
+}
@@ -0,0 +1,153 @@
+use std::{collections::VecDeque, rc::Rc};
+
+use gpui::*;
+use tracing::error;
+
+use crate::{
+    library::{db::LibraryAccess, scan::ScanEvent, types::GenreSummary},
+    ui::{models::Models, theme::Theme},
+};
+
+use super::ViewSwitchMessage;
+
+pub struct GenreListView {
+    view_switch_model: Entity<VecDeque<ViewSwitchMessage>>,
+    list_state: ListState,
+}
+
+impl GenreListView {
+    pub(super) fn new(
+        cx: &mut App,
+        view_switch_model: Entity<VecDeque<ViewSwitchMessage>>,
+    ) -> Entity<Self> {
+        cx.new(|cx| {
+            let state = cx.global::<Models>().scan_state.clone();
+
+            cx.observe(&state, |this: &mut GenreListView, e, cx| {
+                if matches!(
+                    e.read(cx),
+                    ScanEvent::ScanCompleteIdle | ScanEvent::ScanCompleteWatching
+                ) {
+                    this.load(cx);
+                }
+            })
+            .detach();
+
+            let mut view = GenreListView {
+                view_switch_model,
+                list_state: ListState::new(0, ListAlignment::Top, px(300.0), |_, _, _| {
+                    div().into_any_element()
+                }),
+            };
+
+            view.load(cx);
+            view
+        })
+    }
+
+    fn load(&mut self, cx: &mut Context<Self>) {
+        let genres = match cx.list_genre_summaries() {
+            Ok(genres) => Rc::new(genres),
+            Err(e) => {
+                error!("Could not load genres: {}", e);
+                Rc::new(Vec::new())
+            }
+        };
+
+        let view_switch_model = self.view_switch_model.clone();
+
+        self.list_state = ListState::new(
+            genres.len(),
+            ListAlignment::Top,
+            px(300.0),
+            move |idx, _, _| {
+                GenreRow {
+                    genre: genres[idx].clone(),
+                    view_switch_model: view_switch_model.clone(),
+                }
+                .into_any_element()
+            },
+        );
+
+        cx.notify();
+    }
+}
+
+fn count(value: i64, singular: &str, plural: &str) -> String {
+    if value == 1 {
+        format!("1 {}", singular)
+    } else {
+        format!("{} {}", value, plural)
+    }
+}
+
+#[derive(IntoElement)]
+struct GenreRow {
+    genre: GenreSummary,
+    view_switch_model: Entity<VecDeque<ViewSwitchMessage>>,
+}
+
+impl RenderOnce for GenreRow {
+    fn render(self, _: &mut Window, cx: &mut App) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let id = self.genre.id;
+        let view_switch_model = self.view_switch_model;
+
+        div()
+            .id(("genre", id as u64))
+            .flex()
+            .w_full()
+            .px(px(24.0))
+            .py(px(8.0))
+            .gap(px(12.0))
+            .border_b_1()
+            .border_color(theme.border_color)
+            .cursor_pointer()
+            .hover(|this| this.bg(theme.nav_button_hover))
+            .active(|this| this.bg(theme.nav_button_active))
+            .on_click(move |_, _, cx| {
+                view_switch_model.update(cx, |_, cx| cx.emit(ViewSwitchMessage::Genre(id)))
+            })
+            .child(
+                div()
+                    .font_weight(FontWeight::BOLD)
+                    .overflow_x_hidden()
+                    .text_ellipsis()
+                    .child(self.genre.name.0.clone()),
+            )
+            .child(
+                div()
+                    .ml_auto()
+                    .flex()
+                    .flex_shrink_0()
+                    .gap(px(16.0))
+                    .text_sm()
+                    .text_color(theme.text_secondary)
+                    .child(count(self.genre.albums, "album", "albums"))
+                    .child(count(self.genre.tracks, "track", "tracks")),
+            )
+    }
+}
+
+impl Render for GenreListView {
+    fn render(&mut self, _: &mut Window, _: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .flex()
+            .flex_col()
+            .w_full()
+            .h_full()
+            .max_w(px(1000.0))
+            .mx_auto()
+            .pt(px(24.0))
+            .child(
+                div()
+                    .px(px(24.0))
+                    .pb(px(11.0))
+                    .line_height(px(26.0))
+                    .font_weight(FontWeight::BOLD)
+                    .text_size(px(26.0))
+                    .child("Genres"),
+            )
+            .child(list(self.list_state.clone()).w_full().h_full())
+    }
+}
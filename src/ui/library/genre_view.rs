@@ -0,0 +1,199 @@
+use std::{collections::VecDeque, rc::Rc};
+
+use gpui::*;
+use prelude::FluentBuilder;
+use tracing::error;
+
+use crate::{
+    library::{
+        db::LibraryAccess,
+        types::{ArtistRelease, TrackListing},
+    },
+    ui::theme::Theme,
+};
+
+use super::{
+    artist_view::{release_grid, section},
+    play_track, ViewSwitchMessage,
+};
+
+pub struct GenreView {
+    list_state: ListState,
+}
+
+impl GenreView {
+    pub(super) fn new(
+        cx: &mut App,
+        view_switch_model: Entity<VecDeque<ViewSwitchMessage>>,
+        genre_id: i64,
+    ) -> Entity<Self> {
+        let name = match cx.get_genre_name_by_id(genre_id) {
+            Ok(name) => Some(SharedString::from((*name).clone())),
+            Err(e) => {
+                error!("Could not load genre {}: {}", genre_id, e);
+                None
+            }
+        };
+
+        let albums = cx.list_genre_albums(genre_id).unwrap_or_else(|e| {
+            error!("Could not load the albums of genre {}: {}", genre_id, e);
+            Vec::new()
+        });
+
+        let tracks = cx.list_genre_tracks(genre_id).unwrap_or_else(|e| {
+            error!("Could not load the tracks of genre {}: {}", genre_id, e);
+            Vec::new()
+        });
+
+        let header = Rc::new(GenreHeader {
+            name,
+            albums,
+            has_tracks: !tracks.is_empty(),
+            view_switch_model,
+        });
+        let tracks = Rc::new(tracks);
+
+        // the header and albums are the first item, so the tracks, which there can be many of, are
+        // virtualized
+        cx.new(|_| GenreView {
+            list_state: ListState::new(
+                tracks.len() + 1,
+                ListAlignment::Top,
+                px(300.0),
+                move |idx, _, _| {
+                    if idx == 0 {
+                        (*header).clone().into_any_element()
+                    } else {
+                        GenreTrack {
+                            track: tracks[idx - 1].clone(),
+                        }
+                        .into_any_element()
+                    }
+                },
+            ),
+        })
+    }
+}
+
+#[derive(IntoElement, Clone)]
+struct GenreHeader {
+    name: Option<SharedString>,
+    albums: Vec<ArtistRelease>,
+    has_tracks: bool,
+    view_switch_model: Entity<VecDeque<ViewSwitchMessage>>,
+}
+
+impl RenderOnce for GenreHeader {
+    fn render(self, _: &mut Window, cx: &mut App) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+
+        let Some(name) = self.name else {
+            return div()
+                .p(px(24.0))
+                .text_color(theme.text_secondary)
+                .child("This genre is no longer in the library.");
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .px(px(24.0))
+            .pt(px(24.0))
+            .gap(px(24.0))
+            .child(
+                div()
+                    .font_weight(FontWeight::EXTRA_BOLD)
+                    .text_size(rems(2.5))
+                    .line_height(rems(2.75))
+                    .text_ellipsis()
+                    .child(name),
+            )
+            .when(!self.albums.is_empty(), |this| {
+                this.child(section("Albums").child(release_grid(
+                    "genre-album",
+                    &self.albums,
+                    &self.view_switch_model,
+                    theme,
+                )))
+            })
+            .when(self.has_tracks, |this| this.child(section("Tracks")))
+    }
+}
+
+#[derive(IntoElement)]
+struct GenreTrack {
+    track: TrackListing,
+}
+
+impl RenderOnce for GenreTrack {
+    fn render(self, _: &mut Window, cx: &mut App) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let track_id = self.track.id;
+        let artist = self
+            .track
+            .artist_names
+            .clone()
+            .or(self.track.album_artist.clone());
+
+        div()
+            .id(("genre-track", track_id as u64))
+            .flex()
+            .text_sm()
+            .mx(px(24.0))
+            .py(px(4.0))
+            .px(px(6.0))
+            .gap(px(8.0))
+            .rounded(px(4.0))
+            .cursor_pointer()
+            .hover(|this| this.bg(theme.nav_button_hover))
+            .active(|this| this.bg(theme.nav_button_active))
+            .on_click(move |_, _, cx| play_track(cx, track_id))
+            .child(
+                div()
+                    .font_weight(FontWeight::BOLD)
+                    .text_ellipsis()
+                    .child(self.track.title.0.clone()),
+            )
+            .when_some(artist, |this, artist| {
+                this.child(
+                    div()
+                        .text_ellipsis()
+                        .text_color(theme.text_secondary)
+                        .child(artist.0),
+                )
+            })
+            .when_some(self.track.album_title.clone(), |this, album| {
+                this.child(
+                    div()
+                        .text_ellipsis()
+                        .text_color(theme.text_secondary)
+                        .child(album.0),
+                )
+            })
+            .child(
+                div()
+                    .font_family("Roboto Mono")
+                    .ml_auto()
+                    .flex_shrink_0()
+                    .text_color(theme.text_secondary)
+                    .child(format!(
+                        "{}:{:02}",
+                        self.track.duration / 60,
+                        self.track.duration % 60
+                    )),
+            )
+    }
+}
+
+impl Render for GenreView {
+    fn render(&mut self, _: &mut Window, _: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .flex()
+            .flex_col()
+            .w_full()
+            .h_full()
+            .max_w(px(1000.0))
+            .mx_auto()
+            .child(list(self.list_state.clone()).w_full().h_full())
+    }
+}
@@ -41,6 +41,14 @@ impl NavigationView {
                         .get_album_by_id(id, AlbumMethod::Thumbnail)
                         .ok()
                         .map(|v| SharedString::from(v.title.clone())),
+                    ViewSwitchMessage::Artist(id) => cx
+                        .get_artist_name_by_id(id)
+                        .ok()
+                        .map(|v| SharedString::from((*v).clone())),
+                    ViewSwitchMessage::Genre(id) => cx
+                        .get_genre_name_by_id(id)
+                        .ok()
+                        .map(|v| SharedString::from((*v).clone())),
                     _ => None,
                 }
             })
@@ -97,7 +105,13 @@ impl Render for NavigationView {
                             .flex()
                             .child(div().text_sm().child(match self.current_message {
                                 ViewSwitchMessage::Albums => "Albums",
+                                ViewSwitchMessage::Artists => "Artists",
+                                ViewSwitchMessage::Artist(_) => "Artist",
+                                ViewSwitchMessage::Tracks => "Tracks",
+                                ViewSwitchMessage::Genres => "Genres",
+                                ViewSwitchMessage::Genre(_) => "Genre",
                                 ViewSwitchMessage::Release(_) => "Release",
+                                ViewSwitchMessage::Stats => "Statistics",
                                 ViewSwitchMessage::Back => {
                                     panic!("back should not be in VecDeque<ViewSwitchMessage>")
                                 }
@@ -111,7 +125,109 @@ impl Render for NavigationView {
                                         .child(description),
                                 )
                             }),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .ml_auto()
+                            .when(
+                                !matches!(self.current_message, ViewSwitchMessage::Albums),
+                                |this| {
+                                    this.child(nav_button(
+                                        "albums",
+                                        "\u{f51f}",
+                                        "Albums",
+                                        ViewSwitchMessage::Albums,
+                                        cx,
+                                    ))
+                                },
+                            )
+                            .when(
+                                !matches!(self.current_message, ViewSwitchMessage::Artists),
+                                |this| {
+                                    this.child(nav_button(
+                                        "artists",
+                                        "\u{f0c0}",
+                                        "Artists",
+                                        ViewSwitchMessage::Artists,
+                                        cx,
+                                    ))
+                                },
+                            )
+                            .when(
+                                !matches!(self.current_message, ViewSwitchMessage::Tracks),
+                                |this| {
+                                    this.child(nav_button(
+                                        "tracks",
+                                        "\u{f001}",
+                                        "Tracks",
+                                        ViewSwitchMessage::Tracks,
+                                        cx,
+                                    ))
+                                },
+                            )
+                            .when(
+                                !matches!(self.current_message, ViewSwitchMessage::Genres),
+                                |this| {
+                                    this.child(nav_button(
+                                        "genres",
+                                        "\u{f02c}",
+                                        "Genres",
+                                        ViewSwitchMessage::Genres,
+                                        cx,
+                                    ))
+                                },
+                            )
+                            .when(
+                                !matches!(self.current_message, ViewSwitchMessage::Stats),
+                                |this| {
+                                    this.child(nav_button(
+                                        "stats",
+                                        "\u{f080}",
+                                        "Statistics",
+                                        ViewSwitchMessage::Stats,
+                                        cx,
+                                    ))
+                                },
+                            ),
                     ),
             )
     }
 }
+
+/// A button on the right side of the navigation bar that switches to one of the main views.
+fn nav_button(
+    id: &'static str,
+    icon: &'static str,
+    label: &'static str,
+    message: ViewSwitchMessage,
+    cx: &Context<NavigationView>,
+) -> Stateful<Div> {
+    let theme = cx.global::<Theme>();
+
+    div()
+        .flex()
+        .id(id)
+        .px(px(12.0))
+        .py(px(5.0))
+        .text_sm()
+        .border_l_1()
+        .border_color(theme.border_color)
+        .hover(|this| this.bg(theme.nav_button_hover))
+        .active(|this| this.bg(theme.nav_button_active))
+        .cursor_pointer()
+        .on_click(cx.listener(move |this, _, _, cx| {
+            this.view_switcher_model.update(cx, |_, cx| {
+                cx.emit(message);
+            })
+        }))
+        .child(
+            div()
+                .font_family(FONT_AWESOME)
+                .text_size(px(11.0))
+                .pt(px(2.0))
+                .mr(px(8.0))
+                .child(icon),
+        )
+        .child(label)
+}
@@ -28,6 +28,8 @@ use crate::{
     },
 };
 
+use super::open_artist;
+
 pub struct ReleaseView {
     album: Arc<Album>,
     image: Entity<Option<Arc<RenderImage>>>,
@@ -189,10 +191,20 @@ impl Render for ReleaseView {
                             .flex_col()
                             .w_full()
                             .overflow_x_hidden()
-                            .child(div().font_weight(FontWeight::SEMIBOLD).when_some(
-                                self.artist.as_ref().map(|v| v.name.clone()),
-                                |this, artist| this.child(artist.unwrap()),
-                            ))
+                            .child(
+                                div()
+                                    .id("release-artist")
+                                    .font_weight(FontWeight::SEMIBOLD)
+                                    .when_some(self.artist.clone(), |this, artist| {
+                                        let id = artist.id;
+
+                                        this.cursor_pointer()
+                                            .on_click(move |_, _, cx| open_artist(cx, id))
+                                            .when_some(artist.name.clone(), |this, name| {
+                                                this.child(name)
+                                            })
+                                    }),
+                            )
                             .child(
                                 div()
                                     .font_weight(FontWeight::EXTRA_BOLD)
@@ -223,7 +235,6 @@ impl Render for ReleaseView {
                                                             .iter()
                                                             .map(|track| {
                                                                 QueueItemData::new(
-                                                                    cx,
                                                                     track.location.clone(),
                                                                     Some(track.id),
                                                                     track.album_id,
@@ -268,7 +279,6 @@ impl Render for ReleaseView {
                                                         .iter()
                                                         .map(|track| {
                                                             QueueItemData::new(
-                                                                cx,
                                                                 track.location.clone(),
                                                                 Some(track.id),
                                                                 track.album_id,
@@ -295,7 +305,6 @@ impl Render for ReleaseView {
                                                         .iter()
                                                         .map(|track| {
                                                             QueueItemData::new(
-                                                                cx,
                                                                 track.location.clone(),
                                                                 Some(track.id),
                                                                 track.album_id,
@@ -464,7 +473,6 @@ impl RenderOnce for TrackItem {
                             "Play",
                             move |_, _, cx| {
                                 let data = QueueItemData::new(
-                                    cx,
                                     track_location.clone(),
                                     Some(track_id),
                                     album_id,
@@ -494,7 +502,6 @@ impl RenderOnce for TrackItem {
                             "Add to queue",
                             move |_, _, cx| {
                                 let data = QueueItemData::new(
-                                    cx,
                                     track_location_2.clone(),
                                     Some(track_id),
                                     album_id,
@@ -513,13 +520,10 @@ fn play_from_track(cx: &mut App, track: &Track) {
         cx.list_tracks_in_album(album_id)
             .expect("Failed to retrieve tracks")
             .iter()
-            .map(|track| {
-                QueueItemData::new(cx, track.location.clone(), Some(track.id), track.album_id)
-            })
+            .map(|track| QueueItemData::new(track.location.clone(), Some(track.id), track.album_id))
             .collect()
     } else {
         Vec::from([QueueItemData::new(
-            cx,
             track.location.clone(),
             Some(track.id),
             track.album_id,
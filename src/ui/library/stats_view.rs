@@ -0,0 +1,447 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Days, Local, NaiveDate, Utc};
+use gpui::*;
+use prelude::FluentBuilder;
+use tracing::error;
+
+use crate::{
+    library::{
+        db::LibraryAccess,
+        types::{AlbumPlays, ArtistPlays, DailyListening, RecentPlay, TrackPlays},
+    },
+    ui::{
+        components::button::{button, ButtonIntent},
+        theme::Theme,
+    },
+};
+
+use super::ViewSwitchMessage;
+
+/// The number of entries shown in each of the top lists.
+const TOP_LIMIT: u32 = 10;
+const RECENT_LIMIT: u32 = 25;
+/// The height of the listening time chart.
+const CHART_HEIGHT: f32 = 100.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum StatsRange {
+    Week,
+    Month,
+    Year,
+    AllTime,
+}
+
+impl StatsRange {
+    const ALL: [StatsRange; 4] = [
+        StatsRange::Week,
+        StatsRange::Month,
+        StatsRange::Year,
+        StatsRange::AllTime,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            StatsRange::Week => "7 days",
+            StatsRange::Month => "30 days",
+            StatsRange::Year => "365 days",
+            StatsRange::AllTime => "All time",
+        }
+    }
+
+    fn start(&self) -> DateTime<Utc> {
+        let days = match self {
+            StatsRange::Week => 7,
+            StatsRange::Month => 30,
+            StatsRange::Year => 365,
+            StatsRange::AllTime => return DateTime::UNIX_EPOCH,
+        };
+
+        Utc::now() - Days::new(days)
+    }
+}
+
+pub struct StatsView {
+    view_switch_model: Entity<VecDeque<ViewSwitchMessage>>,
+    range: StatsRange,
+    artists: Vec<ArtistPlays>,
+    albums: Vec<AlbumPlays>,
+    tracks: Vec<TrackPlays>,
+    /// The time spent listening on every day in the range, including days without plays.
+    days: Vec<(NaiveDate, i64)>,
+    recent: Vec<RecentPlay>,
+}
+
+impl StatsView {
+    pub(super) fn new(
+        cx: &mut App,
+        view_switch_model: Entity<VecDeque<ViewSwitchMessage>>,
+    ) -> Entity<Self> {
+        cx.new(|cx| {
+            let mut view = StatsView {
+                view_switch_model,
+                range: StatsRange::Month,
+                artists: Vec::new(),
+                albums: Vec::new(),
+                tracks: Vec::new(),
+                days: Vec::new(),
+                recent: Vec::new(),
+            };
+
+            view.load(cx);
+            view
+        })
+    }
+
+    fn set_range(&mut self, range: StatsRange, cx: &mut Context<Self>) {
+        self.range = range;
+        self.load(cx);
+        cx.notify();
+    }
+
+    fn load(&mut self, cx: &mut App) {
+        if let Err(e) = self.try_load(cx) {
+            error!("Could not load listening statistics: {}", e);
+        }
+    }
+
+    fn try_load(&mut self, cx: &mut App) -> Result<(), sqlx::Error> {
+        let start = self.range.start();
+        let end = Utc::now();
+
+        self.artists = cx.list_top_artists(start, end, TOP_LIMIT)?;
+        self.albums = cx.list_top_albums(start, end, TOP_LIMIT)?;
+        self.tracks = cx.list_top_tracks(start, end, TOP_LIMIT)?;
+        self.days = fill_days(cx.list_listening_time_by_day(start, end)?, self.range);
+        self.recent = cx.list_recent_plays(RECENT_LIMIT)?;
+
+        Ok(())
+    }
+}
+
+/// Adds the days without plays to the listening time per day, so that the chart has a bar for
+/// every day in the range. For all time, the chart starts at the first day with plays.
+fn fill_days(days: Vec<DailyListening>, range: StatsRange) -> Vec<(NaiveDate, i64)> {
+    let parsed: Vec<(NaiveDate, i64)> = days
+        .into_iter()
+        .filter_map(|v| {
+            NaiveDate::parse_from_str(&v.day, "%Y-%m-%d")
+                .ok()
+                .map(|day| (day, v.listened))
+        })
+        .collect();
+
+    let today = Local::now().date_naive();
+    let first = match range {
+        StatsRange::AllTime => match parsed.first() {
+            Some((day, _)) => *day,
+            None => return Vec::new(),
+        },
+        _ => range.start().with_timezone(&Local).date_naive(),
+    };
+
+    let mut filled = Vec::new();
+    let mut parsed = parsed.into_iter().peekable();
+
+    for day in first.iter_days().take_while(|v| *v <= today) {
+        let mut listened = 0;
+
+        while let Some((_, value)) = parsed.next_if(|(v, _)| *v <= day) {
+            listened += value;
+        }
+
+        filled.push((day, listened));
+    }
+
+    filled
+}
+
+fn format_listening_time(seconds: i64) -> String {
+    let hours = seconds / 3600;
+    let minutes = (seconds % 3600) / 60;
+
+    if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+fn format_plays(plays: i64) -> String {
+    if plays == 1 {
+        "1 play".to_string()
+    } else {
+        format!("{} plays", plays)
+    }
+}
+
+fn section(title: &'static str) -> Div {
+    div().flex().flex_col().child(
+        div()
+            .font_weight(FontWeight::BOLD)
+            .text_size(px(18.0))
+            .mb(px(8.0))
+            .child(title),
+    )
+}
+
+fn top_row(
+    id: impl Into<ElementId>,
+    position: usize,
+    name: SharedString,
+    detail: Option<SharedString>,
+    plays: i64,
+    theme: &Theme,
+) -> Stateful<Div> {
+    div()
+        .id(id)
+        .flex()
+        .text_sm()
+        .py(px(4.0))
+        .px(px(6.0))
+        .gap(px(8.0))
+        .rounded(px(4.0))
+        .child(
+            div()
+                .w(px(20.0))
+                .flex_shrink_0()
+                .text_color(theme.text_secondary)
+                .child(format!("{}", position + 1)),
+        )
+        .child(
+            div()
+                .flex()
+                .flex_col()
+                .overflow_x_hidden()
+                .child(div().text_ellipsis().child(name))
+                .when_some(detail, |this, detail| {
+                    this.child(
+                        div()
+                            .text_ellipsis()
+                            .text_color(theme.text_secondary)
+                            .child(detail),
+                    )
+                }),
+        )
+        .child(
+            div()
+                .ml_auto()
+                .flex_shrink_0()
+                .text_color(theme.text_secondary)
+                .child(format_plays(plays)),
+        )
+}
+
+impl Render for StatsView {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let total_listened: i64 = self.days.iter().map(|(_, v)| v).sum();
+        let max_listened = self.days.iter().map(|(_, v)| *v).max().unwrap_or(0).max(1);
+
+        let mut ranges = div().flex().gap(px(6.0)).ml_auto();
+
+        for range in StatsRange::ALL {
+            ranges = ranges.child(
+                button()
+                    .intent(if range == self.range {
+                        ButtonIntent::Primary
+                    } else {
+                        ButtonIntent::Secondary
+                    })
+                    .id(range.label())
+                    .child(range.label())
+                    .on_click(cx.listener(move |this: &mut StatsView, _, _, cx| {
+                        this.set_range(range, cx);
+                    })),
+            );
+        }
+
+        let mut chart = div()
+            .flex()
+            .items_end()
+            .h(px(CHART_HEIGHT))
+            .gap(px(1.0))
+            .border_b_1()
+            .border_color(theme.border_color);
+
+        for (_, listened) in &self.days {
+            chart = chart.child(
+                div()
+                    .flex_1()
+                    .h(px(CHART_HEIGHT * *listened as f32 / max_listened as f32))
+                    .bg(theme.slider_foreground)
+                    .rounded_t(px(2.0)),
+            );
+        }
+
+        let mut artists = section("Top Artists").flex_1().min_w(px(0.0));
+
+        for (i, artist) in self.artists.iter().enumerate() {
+            let id = artist.id;
+            let view_switch_model = self.view_switch_model.clone();
+
+            artists = artists.child(
+                top_row(
+                    ("stats-artist", i),
+                    i,
+                    artist
+                        .name
+                        .clone()
+                        .map(SharedString::from)
+                        .unwrap_or("Unknown Artist".into()),
+                    Some(format_listening_time(artist.listened).into()),
+                    artist.plays,
+                    theme,
+                )
+                .cursor_pointer()
+                .hover(|this| this.bg(theme.nav_button_hover))
+                .on_click(move |_, _, cx| {
+                    view_switch_model.update(cx, |_, cx| cx.emit(ViewSwitchMessage::Artist(id)))
+                }),
+            );
+        }
+
+        let mut albums = section("Top Albums").flex_1().min_w(px(0.0));
+
+        for (i, album) in self.albums.iter().enumerate() {
+            let id = album.id;
+            let view_switch_model = self.view_switch_model.clone();
+
+            albums = albums.child(
+                top_row(
+                    ("stats-album", i),
+                    i,
+                    album.title.0.clone(),
+                    album.artist_name.clone().map(SharedString::from),
+                    album.plays,
+                    theme,
+                )
+                .cursor_pointer()
+                .hover(|this| this.bg(theme.nav_button_hover))
+                .on_click(move |_, _, cx| {
+                    view_switch_model.update(cx, |_, cx| cx.emit(ViewSwitchMessage::Release(id)))
+                }),
+            );
+        }
+
+        let mut tracks = section("Top Tracks").flex_1().min_w(px(0.0));
+
+        for (i, track) in self.tracks.iter().enumerate() {
+            tracks = tracks.child(top_row(
+                ("stats-track", i),
+                i,
+                track.title.0.clone(),
+                track.artist_name.clone().map(SharedString::from),
+                track.plays,
+                theme,
+            ));
+        }
+
+        let mut recent = section("Recently Played");
+
+        for play in &self.recent {
+            let title = play
+                .title
+                .clone()
+                .map(SharedString::from)
+                .or_else(|| {
+                    play.location
+                        .file_name()
+                        .map(|v| v.to_string_lossy().to_string().into())
+                })
+                .unwrap_or("Unknown Track".into());
+
+            recent = recent.child(
+                div()
+                    .flex()
+                    .text_sm()
+                    .py(px(4.0))
+                    .px(px(6.0))
+                    .gap(px(8.0))
+                    .child(div().text_ellipsis().child(title))
+                    .when_some(play.artist_name.clone(), |this, artist| {
+                        this.child(
+                            div()
+                                .text_ellipsis()
+                                .text_color(theme.text_secondary)
+                                .child(SharedString::from(artist)),
+                        )
+                    })
+                    .child(
+                        div()
+                            .ml_auto()
+                            .flex_shrink_0()
+                            .text_color(theme.text_secondary)
+                            .when(play.skipped, |this| this.child("Skipped · "))
+                            .child(
+                                play.started_at
+                                    .with_timezone(&Local)
+                                    .format("%x %R")
+                                    .to_string(),
+                            ),
+                    ),
+            );
+        }
+
+        div()
+            .id("stats-view")
+            .flex()
+            .flex_col()
+            .w_full()
+            .h_full()
+            .overflow_y_scroll()
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .w_full()
+                    .max_w(px(1000.0))
+                    .mx_auto()
+                    .px(px(24.0))
+                    .py(px(24.0))
+                    .gap(px(24.0))
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .child(
+                                div()
+                                    .font_weight(FontWeight::EXTRA_BOLD)
+                                    .text_size(px(28.0))
+                                    .child("Statistics"),
+                            )
+                            .child(ranges),
+                    )
+                    .child(
+                        section("Listening Time")
+                            .child(
+                                div()
+                                    .text_sm()
+                                    .text_color(theme.text_secondary)
+                                    .mb(px(8.0))
+                                    .child(match self.range {
+                                        StatsRange::AllTime => format!(
+                                            "{} in total",
+                                            format_listening_time(total_listened)
+                                        ),
+                                        range => format!(
+                                            "{} in the last {}",
+                                            format_listening_time(total_listened),
+                                            range.label()
+                                        ),
+                                    }),
+                            )
+                            .child(chart),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .gap(px(24.0))
+                            .child(artists)
+                            .child(albums)
+                            .child(tracks),
+                    )
+                    .child(recent),
+            )
+    }
+}
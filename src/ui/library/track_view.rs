@@ -0,0 +1,134 @@
+use std::{path::PathBuf, rc::Rc};
+
+use gpui::*;
+use prelude::FluentBuilder;
+
+use crate::{
+    library::{
+        scan::ScanEvent,
+        types::{table::TrackColumn, TrackRow},
+    },
+    playback::{
+        interface::{replace_queue, GPUIPlaybackInterface},
+        queue::QueueItemData,
+    },
+    ui::{
+        components::{
+            button::{button, ButtonIntent},
+            table::{Table, TableEvent},
+        },
+        constants::FONT_AWESOME,
+        models::Models,
+    },
+};
+
+#[derive(Clone)]
+pub struct TrackView {
+    table: Entity<Table<TrackRow, TrackColumn>>,
+}
+
+fn queue_item(id: &(i64, PathBuf, Option<i64>)) -> QueueItemData {
+    QueueItemData::new(id.1.clone(), Some(id.0), id.2)
+}
+
+impl TrackView {
+    pub(super) fn new(cx: &mut App) -> Entity<Self> {
+        cx.new(|cx| {
+            let state = cx.global::<Models>().scan_state.clone();
+
+            // double clicking a track adds it to the end of the queue and starts playing it
+            let handler = Rc::new(move |cx: &mut App, id: &(i64, PathBuf, Option<i64>)| {
+                cx.global::<GPUIPlaybackInterface>()
+                    .queue_and_play(queue_item(id));
+            });
+
+            let table = Table::new(cx, Some(handler));
+
+            let table_clone = table.clone();
+
+            // unlike albums, the track list isn't refreshed during scans, since it can be very
+            // long and refreshing it clears the selection
+            cx.observe(&state, move |_: &mut TrackView, e, cx| {
+                if matches!(
+                    e.read(cx),
+                    ScanEvent::ScanCompleteIdle | ScanEvent::ScanCompleteWatching
+                ) {
+                    table_clone.update(cx, |_, cx| cx.emit(TableEvent::NewRows));
+                }
+            })
+            .detach();
+
+            cx.observe(&table, |_, _, cx| cx.notify()).detach();
+
+            TrackView { table }
+        })
+    }
+}
+
+impl Render for TrackView {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let selected = self.table.read(cx).selected(cx).len();
+
+        div()
+            .flex()
+            .flex_col()
+            .w_full()
+            .h_full()
+            .max_w(px(1000.0))
+            .mx_auto()
+            .pt(px(24.0))
+            .pb(px(0.0))
+            .relative()
+            .child(self.table.clone())
+            .when(selected > 0, |this| {
+                this.child(
+                    div()
+                        .absolute()
+                        .top(px(22.0))
+                        .right(px(24.0))
+                        .flex()
+                        .gap(px(6.0))
+                        .child(
+                            button()
+                                .intent(ButtonIntent::Primary)
+                                .id("tracks-play-button")
+                                .child(div().font_family(FONT_AWESOME).child("\u{f04b}"))
+                                .child(if selected == 1 {
+                                    "Play".to_string()
+                                } else {
+                                    format!("Play {} tracks", selected)
+                                })
+                                .on_click(cx.listener(|this: &mut TrackView, _, _, cx| {
+                                    let items: Vec<QueueItemData> = this
+                                        .table
+                                        .read(cx)
+                                        .selected(cx)
+                                        .iter()
+                                        .map(queue_item)
+                                        .collect();
+
+                                    replace_queue(items, cx);
+                                })),
+                        )
+                        .child(
+                            button()
+                                .intent(ButtonIntent::Secondary)
+                                .id("tracks-add-button")
+                                .child(div().font_family(FONT_AWESOME).child("\u{2b}"))
+                                .child("Add to queue")
+                                .on_click(cx.listener(|this: &mut TrackView, _, _, cx| {
+                                    let items: Vec<QueueItemData> = this
+                                        .table
+                                        .read(cx)
+                                        .selected(cx)
+                                        .iter()
+                                        .map(queue_item)
+                                        .collect();
+
+                                    cx.global::<GPUIPlaybackInterface>().queue_list(items);
+                                })),
+                        ),
+                )
+            })
+    }
+}
@@ -1,8 +1,12 @@
 use std::{
-    collections::VecDeque,
+    collections::{BTreeMap, VecDeque},
     fs::{File, OpenOptions},
     path::PathBuf,
-    sync::{Arc, RwLock},
+    sync::{
+        mpsc::{self, TryRecvError},
+        Arc, RwLock,
+    },
+    time::Duration,
 };
 
 use ahash::AHashMap;
@@ -12,17 +16,22 @@ use serde::{Deserialize, Serialize};
 use tracing::{debug, error, warn};
 
 use crate::{
-    library::scan::ScanEvent,
+    library::{scan::ScanEvent, types::Bookmark},
     media::metadata::Metadata,
     playback::{
         queue::{QueueItemData, QueueItemUIData},
         thread::PlaybackState,
     },
     services::mmb::{
-        lastfm::{client::LastFMClient, types::Session, LastFM, LASTFM_API_KEY, LASTFM_API_SECRET},
-        MediaMetadataBroadcastService,
+        lastfm::{
+            client::LastFMClient, types::Session, LastFM, LastFMStatus, LASTFM_API_KEY,
+            LASTFM_API_SECRET,
+        },
+        listenbrainz::{client::ListenBrainzClient, ListenBrainz},
+        scrobbling::ScrobbleFilter,
+        MediaMetadataBroadcastService, NowPlaying, PlayerHandle,
     },
-    settings::storage::StorageData,
+    settings::{storage::StorageData, SettingsGlobal},
     ui::{app::get_dirs, data::Decode, library::ViewSwitchMessage},
 };
 
@@ -50,7 +59,14 @@ pub struct Models {
     pub scan_state: Entity<ScanEvent>,
     pub mmbs: Entity<MMBSList>,
     pub lastfm: Entity<LastFMState>,
+    /// The number of scrobbles waiting to be submitted to last.fm.
+    pub lastfm_pending: Entity<usize>,
+    /// Whether scrobbling is suspended for every service.
+    pub private_session: Entity<bool>,
     pub switcher_model: Entity<VecDeque<ViewSwitchMessage>>,
+    pub bookmarks: Entity<Arc<Vec<Bookmark>>>,
+    /// The saved layouts of tables, by table name. See `Table`.
+    pub table_layouts: Entity<BTreeMap<String, serde_json::Value>>,
 }
 
 impl Global for Models {}
@@ -83,6 +99,8 @@ pub struct PlaybackInfo {
     pub shuffling: Entity<bool>,
     pub volume: Entity<f64>,
     pub prev_volume: Entity<f64>,
+    pub speed: Entity<f64>,
+    pub loop_points: Entity<Option<(f64, f64)>>,
 }
 
 impl Global for PlaybackInfo {}
@@ -101,7 +119,29 @@ pub struct Queue {
 impl EventEmitter<(PathBuf, QueueItemUIData)> for Queue {}
 
 #[derive(Clone)]
-pub struct MMBSList(pub AHashMap<String, Arc<Mutex<dyn MediaMetadataBroadcastService>>>);
+pub struct MMBSList {
+    pub services: AHashMap<String, Arc<Mutex<dyn MediaMetadataBroadcastService>>>,
+    pub handle: PlayerHandle,
+}
+
+impl MMBSList {
+    /// Registers the service, replacing any service with the same name. The service is attached
+    /// to the player before it receives any events.
+    pub fn insert(
+        &mut self,
+        name: &str,
+        mut service: impl MediaMetadataBroadcastService + 'static,
+    ) {
+        service.attach(self.handle.clone());
+        self.services
+            .insert(name.to_string(), Arc::new(Mutex::new(service)));
+    }
+
+    /// Unregisters the service. It is dropped once it has handled the events already sent to it.
+    pub fn remove(&mut self, name: &str) {
+        self.services.remove(name);
+    }
+}
 
 #[derive(Clone)]
 pub enum MMBSEvent {
@@ -110,17 +150,54 @@ pub enum MMBSEvent {
     StateChanged(PlaybackState),
     PositionChanged(u64),
     DurationChanged(u64),
+    SpeedChanged(f64),
+    VolumeChanged(f64),
+    AlbumArtChanged(Option<Arc<[u8]>>),
+    NowPlaying(Arc<NowPlaying>),
+    QueuePositionChanged(usize),
+    QueueChanged(usize),
 }
 
 impl EventEmitter<MMBSEvent> for MMBSList {}
 
-pub fn build_models(cx: &mut App, queue: Queue, storage_data: &StorageData) {
+pub fn build_models(
+    cx: &mut App,
+    queue: Queue,
+    storage_data: &StorageData,
+    player_handle: PlayerHandle,
+) {
     debug!("Building models");
     let metadata: Entity<Metadata> = cx.new(|_| Metadata::default());
     let albumart: Entity<Option<Arc<RenderImage>>> = cx.new(|_| None);
     let queue: Entity<Queue> = cx.new(move |_| queue);
     let scan_state: Entity<ScanEvent> = cx.new(|_| ScanEvent::ScanCompleteIdle);
-    let mmbs: Entity<MMBSList> = cx.new(|_| MMBSList(AHashMap::new()));
+    let mmbs: Entity<MMBSList> = cx.new(|_| MMBSList {
+        services: AHashMap::new(),
+        handle: player_handle,
+    });
+    let scrobble_filter = {
+        let settings = cx.global::<SettingsGlobal>().model.clone();
+        let filter = ScrobbleFilter::new(&settings.read(cx).scrobbling);
+        let filter_clone = filter.clone();
+
+        cx.observe(&settings, move |m, cx| {
+            filter_clone.set_rules(&m.read(cx).scrobbling);
+        })
+        .detach();
+
+        filter
+    };
+    let private_session: Entity<bool> = cx.new(|_| false);
+    let filter_clone = scrobble_filter.clone();
+
+    cx.observe(&private_session, move |m, cx| {
+        let private = *m.read(cx);
+        debug!("private session: {}", private);
+        filter_clone.set_private_session(private);
+    })
+    .detach();
+
+    let lastfm_pending: Entity<usize> = cx.new(|_| 0);
     let lastfm: Entity<LastFMState> = cx.new(|cx| {
         let dirs = get_dirs();
         let directory = dirs.data_dir().to_path_buf();
@@ -130,7 +207,13 @@ pub fn build_models(cx: &mut App, queue: Queue, storage_data: &StorageData) {
             let reader = std::io::BufReader::new(file);
 
             if let Ok(session) = serde_json::from_reader::<std::io::BufReader<File>, Session>(reader) {
-                create_last_fm_mmbs(cx, &mmbs, session.key.clone());
+                create_last_fm_mmbs(
+                    cx,
+                    &mmbs,
+                    &lastfm_pending,
+                    session.key.clone(),
+                    scrobble_filter.clone(),
+                );
                 LastFMState::Connected(session)
             } else {
                 error!("The last.fm session information is stored on disk but the file could not be opened.");
@@ -149,10 +232,18 @@ pub fn build_models(cx: &mut App, queue: Queue, storage_data: &StorageData) {
     .detach();
 
     let mmbs_clone = mmbs.clone();
+    let pending_clone = lastfm_pending.clone();
+    let filter_clone = scrobble_filter.clone();
 
     cx.subscribe(&lastfm, move |m, ev, cx| {
         let session_clone = ev.clone();
-        create_last_fm_mmbs(cx, &mmbs_clone, session_clone.key.clone());
+        create_last_fm_mmbs(
+            cx,
+            &mmbs_clone,
+            &pending_clone,
+            session_clone.key.clone(),
+            filter_clone.clone(),
+        );
         m.update(cx, |m, cx| {
             *m = LastFMState::Connected(session_clone);
             cx.notify();
@@ -180,12 +271,17 @@ pub fn build_models(cx: &mut App, queue: Queue, storage_data: &StorageData) {
     })
     .detach();
 
+    create_listenbrainz_mmbs(cx, &mmbs, scrobble_filter);
+
+    #[cfg(target_os = "linux")]
+    create_mpris_mmbs(cx, &mmbs);
+
     cx.subscribe(&mmbs, |m, ev, cx| {
         let list = m.read(cx);
 
         // cloning actually is neccesary because of the async move closure
         #[allow(clippy::unnecessary_to_owned)]
-        for mmbs in list.0.values().cloned() {
+        for mmbs in list.services.values().cloned() {
             let ev = ev.clone();
             cx.spawn(async move |_| {
                 let mut borrow = mmbs.lock().await;
@@ -195,6 +291,14 @@ pub fn build_models(cx: &mut App, queue: Queue, storage_data: &StorageData) {
                     MMBSEvent::StateChanged(state) => borrow.state_changed(state),
                     MMBSEvent::PositionChanged(position) => borrow.position_changed(position),
                     MMBSEvent::DurationChanged(duration) => borrow.duration_changed(duration),
+                    MMBSEvent::SpeedChanged(speed) => borrow.speed_changed(speed),
+                    MMBSEvent::VolumeChanged(volume) => borrow.volume_changed(volume),
+                    MMBSEvent::AlbumArtChanged(art) => borrow.album_art_changed(art),
+                    MMBSEvent::NowPlaying(info) => borrow.now_playing(info),
+                    MMBSEvent::QueuePositionChanged(position) => {
+                        borrow.queue_position_changed(position)
+                    }
+                    MMBSEvent::QueueChanged(length) => borrow.queue_changed(length),
                 }
                 .await;
             })
@@ -209,6 +313,9 @@ pub fn build_models(cx: &mut App, queue: Queue, storage_data: &StorageData) {
         deque
     });
 
+    let bookmarks = cx.new(|_| Arc::new(Vec::new()));
+    let table_layouts = cx.new(|_| storage_data.table_layouts.clone());
+
     cx.set_global(Models {
         metadata,
         albumart,
@@ -216,7 +323,11 @@ pub fn build_models(cx: &mut App, queue: Queue, storage_data: &StorageData) {
         scan_state,
         mmbs,
         lastfm,
+        lastfm_pending,
+        private_session,
         switcher_model,
+        bookmarks,
+        table_layouts,
     });
 
     const DEFAULT_VOLUME: f64 = 1.0;
@@ -229,6 +340,8 @@ pub fn build_models(cx: &mut App, queue: Queue, storage_data: &StorageData) {
     let shuffling: Entity<bool> = cx.new(|_| false);
     let volume: Entity<f64> = cx.new(|_| DEFAULT_VOLUME);
     let prev_volume: Entity<f64> = cx.new(|_| DEFAULT_VOLUME);
+    let speed: Entity<f64> = cx.new(|_| 1.0);
+    let loop_points: Entity<Option<(f64, f64)>> = cx.new(|_| None);
 
     cx.set_global(PlaybackInfo {
         position,
@@ -238,16 +351,139 @@ pub fn build_models(cx: &mut App, queue: Queue, storage_data: &StorageData) {
         shuffling,
         volume,
         prev_volume,
+        speed,
+        loop_points,
     });
 }
 
-pub fn create_last_fm_mmbs(cx: &mut App, mmbs_list: &Entity<MMBSList>, session: String) {
+/// Creates a last.fm client for use by the user interface, or None if Muzak was built without
+/// last.fm API keys. The client is signed in if an account has been connected.
+pub fn create_last_fm_client(cx: &App) -> Option<LastFMClient> {
+    let (Some(key), Some(secret)) = (LASTFM_API_KEY, LASTFM_API_SECRET) else {
+        return None;
+    };
+
+    let mut client = LastFMClient::new(key.to_string(), secret);
+    let settings = cx.global::<SettingsGlobal>().model.read(cx);
+    client.set_api_url(settings.lastfm.api_url.clone());
+
+    if let LastFMState::Connected(session) = cx.global::<Models>().lastfm.read(cx) {
+        client.set_session(session.key.clone());
+    }
+
+    Some(client)
+}
+
+pub fn create_last_fm_mmbs(
+    cx: &mut App,
+    mmbs_list: &Entity<MMBSList>,
+    pending: &Entity<usize>,
+    session: String,
+    filter: ScrobbleFilter,
+) {
     if let (Some(key), Some(secret)) = (LASTFM_API_KEY, LASTFM_API_SECRET) {
         let mut client = LastFMClient::new(key.to_string(), secret);
         client.set_session(session);
-        let mmbs = LastFM::new(client);
-        mmbs_list.update(cx, |m, _| {
-            m.0.insert("lastfm".to_string(), Arc::new(Mutex::new(mmbs)));
+        client.set_api_url(
+            cx.global::<SettingsGlobal>()
+                .model
+                .read(cx)
+                .lastfm
+                .api_url
+                .clone(),
+        );
+
+        let cache_path = get_dirs().data_dir().join("lastfm-scrobbles.json");
+        let mut mmbs = LastFM::new(client, cache_path, filter);
+
+        let (status_tx, status_rx) = mpsc::channel();
+        mmbs.set_status_sender(status_tx);
+
+        let pending = pending.clone();
+        cx.spawn(async move |cx| loop {
+            match status_rx.try_recv() {
+                Ok(LastFMStatus::Pending(count)) => pending
+                    .update(cx, |m, cx| {
+                        *m = count;
+                        cx.notify();
+                    })
+                    .expect("failed to update pending scrobbles"),
+                Ok(LastFMStatus::SessionRejected) => {
+                    cx.update(last_fm_session_rejected)
+                        .expect("failed to sign out of last.fm");
+                    break;
+                }
+                Err(TryRecvError::Empty) => {
+                    cx.background_executor()
+                        .timer(Duration::from_millis(100))
+                        .await
+                }
+                // the service was replaced or dropped
+                Err(TryRecvError::Disconnected) => break,
+            }
         })
+        .detach();
+
+        mmbs_list.update(cx, |m, _| m.insert("lastfm", mmbs))
+    }
+}
+
+/// Forgets a session that last.fm has rejected, so that the user is asked to sign in again. The
+/// pending scrobbles are kept, and submitted once they have.
+fn last_fm_session_rejected(cx: &mut App) {
+    let models = cx.global::<Models>();
+    let lastfm = models.lastfm.clone();
+    let mmbs = models.mmbs.clone();
+
+    mmbs.update(cx, |m, _| m.remove("lastfm"));
+    lastfm.update(cx, |m, cx| {
+        *m = LastFMState::Disconnected;
+        cx.notify();
+    });
+
+    let path = get_dirs().data_dir().join("lastfm.json");
+    if let Err(e) = std::fs::remove_file(&path) {
+        warn!("Could not remove the last.fm session at {:?}: {}", path, e);
     }
 }
+
+/// Registers the ListenBrainz service, if a token has been set. Changes to the settings take
+/// effect when Muzak is restarted.
+pub fn create_listenbrainz_mmbs(
+    cx: &mut App,
+    mmbs_list: &Entity<MMBSList>,
+    filter: ScrobbleFilter,
+) {
+    let settings = &cx.global::<SettingsGlobal>().model.read(cx).listenbrainz;
+    let Some(token) = settings.token.clone().filter(|v| !v.is_empty()) else {
+        return;
+    };
+
+    let mut client = ListenBrainzClient::new(token);
+    client.set_api_url(settings.api_url.clone());
+
+    let cache_path = get_dirs().data_dir().join("listenbrainz-listens.json");
+    let mmbs = ListenBrainz::new(client, cache_path, filter);
+    mmbs_list.update(cx, |m, _| m.insert("listenbrainz", mmbs))
+}
+
+/// Registers the MPRIS player on the session bus. This happens asynchronously, and failures (for
+/// example, when there is no session bus) are only logged.
+#[cfg(target_os = "linux")]
+pub fn create_mpris_mmbs(cx: &mut App, mmbs_list: &Entity<MMBSList>) {
+    use crate::services::mmb::mpris::Mpris;
+
+    let mmbs_list = mmbs_list.clone();
+
+    cx.spawn(async move |cx| match Mpris::new().await {
+        Ok(mpris) => {
+            mmbs_list
+                .update(cx, |m, _| m.insert("mpris", mpris))
+                .expect("failed to register MPRIS service");
+        }
+        Err(e) => {
+            warn!("Unable to register MPRIS player: {}", e);
+        }
+    })
+    .detach();
+}
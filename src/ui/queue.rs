@@ -9,6 +9,7 @@ use prelude::FluentBuilder;
 use super::{
     components::button::{button, ButtonSize, ButtonStyle},
     constants::FONT_AWESOME,
+    library::open_track_artist,
     models::{Models, PlaybackInfo},
     theme::Theme,
     util::{create_or_retrieve_view, drop_image_from_app, prune_views},
@@ -80,6 +81,7 @@ impl Render for QueueItem {
             let album_art = item.image.as_ref().cloned();
 
             let idx = self.idx;
+            let path = self.item.as_ref().map(|v| v.get_path().clone());
 
             div()
                 .w_full()
@@ -132,9 +134,16 @@ impl Render for QueueItem {
                         )
                         .child(
                             div()
+                                .id("artist")
                                 .text_ellipsis()
                                 .when_some(item.artist_name.clone(), |this, string| {
                                     this.child(string)
+                                })
+                                .when_some(path, |this, path| {
+                                    this.on_click(move |_, _, cx| {
+                                        cx.stop_propagation();
+                                        open_track_artist(cx, &path);
+                                    })
                                 }),
                         ),
                 )
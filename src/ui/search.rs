@@ -3,13 +3,13 @@ pub mod model;
 use std::collections::VecDeque;
 
 use gpui::*;
-use model::SearchModel;
+use model::{SearchAccept, SearchModel};
 use tracing::debug;
 
 use super::{
     components::{input::TextInput, modal::modal},
     global_actions::Search,
-    library::ViewSwitchMessage,
+    library::{play_track, ViewSwitchMessage},
     models::Models,
     theme::Theme,
 };
@@ -35,7 +35,13 @@ impl SearchView {
                     cx.emit(action);
                 })
             };
-            let input = TextInput::new(cx, handle.clone(), None, None, Some(Box::new(handler)));
+            let input = TextInput::new(
+                cx,
+                handle.clone(),
+                None,
+                Some("Search, or narrow with a: artists, al: albums, t: tracks, l: labels".into()),
+                Some(Box::new(handler)),
+            );
 
             App::on_action(cx, move |_: &Search, cx| {
                 show_clone.update(cx, |m, cx| {
@@ -55,10 +61,15 @@ impl SearchView {
 
             cx.subscribe(
                 &search,
-                |this: &mut SearchView, _, ev: &ViewSwitchMessage, cx| {
-                    this.view_switcher.update(cx, |_, cx| {
-                        cx.emit(*ev);
-                    });
+                |this: &mut SearchView, _, ev: &SearchAccept, cx| {
+                    match *ev {
+                        SearchAccept::Open(message) => {
+                            this.view_switcher.update(cx, |_, cx| {
+                                cx.emit(message);
+                            });
+                        }
+                        SearchAccept::PlayTrack(id) => play_track(cx, id),
+                    }
                     this.reset(cx);
                 },
             )
@@ -1,5 +1,4 @@
 use std::{
-    ops::{AddAssign, Deref, SubAssign},
     rc::Rc,
     sync::{mpsc::channel, Arc},
     time::Duration,
@@ -9,19 +8,21 @@ use ahash::AHashMap;
 use gpui::*;
 use nucleo::{
     pattern::{CaseMatching, Normalization},
-    Config, Nucleo, Utf32String,
+    Config, Injector, Nucleo, Utf32String,
 };
 use prelude::FluentBuilder;
-use tracing::debug;
+use tracing::{debug, error};
 
 use crate::{
     library::{
         db::{AlbumMethod, LibraryAccess},
         scan::ScanEvent,
-        types::Album,
+        search::SearchQuery,
+        types::{SearchEntry, SearchKind, Thumbnail},
     },
     ui::{
         components::input::EnrichedInputAction,
+        constants::FONT_AWESOME,
         library::ViewSwitchMessage,
         models::Models,
         theme::Theme,
@@ -29,22 +30,138 @@ use crate::{
     },
 };
 
+/// The sections results are grouped into, in the order they are shown.
+const SECTIONS: [(SearchKind, &str); 3] = [
+    (SearchKind::Artist, "Artists"),
+    (SearchKind::Album, "Albums"),
+    (SearchKind::Track, "Tracks"),
+];
+
+/// The number of results shown when a prefix limits the search to one kind of result.
+const SCOPED_LIMIT: usize = 100;
+
+/// How many of the best matches are grouped into sections. Matches past this are never shown, so
+/// there's no need to walk through all of them when the library is large.
+const MATCH_WINDOW: u32 = 2000;
+
+/// What a query searches, chosen by a prefix at its start, such as `a:` for artists.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum SearchScope {
+    All,
+    Artists,
+    Albums,
+    Tracks,
+    /// Albums, by the name of their record label.
+    Labels,
+}
+
+impl SearchScope {
+    /// Splits the scope prefix off a query, if it has one.
+    fn parse(query: &str) -> (SearchScope, &str) {
+        let trimmed = query.trim_start();
+
+        // `al:` has to be checked before `a:`
+        for (prefix, scope) in [
+            ("al:", SearchScope::Albums),
+            ("a:", SearchScope::Artists),
+            ("t:", SearchScope::Tracks),
+            ("l:", SearchScope::Labels),
+        ] {
+            if let Some(rest) = trimmed.strip_prefix(prefix) {
+                return (scope, rest.trim_start());
+            }
+        }
+
+        (SearchScope::All, query)
+    }
+
+    /// The number of results of the given kind shown for this scope.
+    fn limit(&self, kind: SearchKind) -> usize {
+        match (self, kind) {
+            (SearchScope::All, SearchKind::Artist) => 5,
+            (SearchScope::All, SearchKind::Album) => 10,
+            (SearchScope::All, SearchKind::Track) => 25,
+            (SearchScope::Artists, SearchKind::Artist)
+            | (SearchScope::Albums, SearchKind::Album)
+            | (SearchScope::Labels, SearchKind::Album)
+            | (SearchScope::Tracks, SearchKind::Track) => SCOPED_LIMIT,
+            _ => 0,
+        }
+    }
+}
+
+/// What accepting a search result does, carried out by the search view.
+#[derive(Clone, Copy, Debug)]
+pub enum SearchAccept {
+    Open(ViewSwitchMessage),
+    PlayTrack(i64),
+}
+
+/// A row of the result list: either a section header, or the result at an index of the matches.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum SearchRow {
+    Header(&'static str),
+    Result(usize),
+}
+
 pub struct SearchModel {
     query: String,
-    matcher: Nucleo<(u32, String)>,
+    scope: SearchScope,
+    matcher: Nucleo<SearchEntry>,
     list_state: ListState,
-    views_model: Entity<AHashMap<usize, Entity<AlbumSearchResult>>>,
-    last_match: Vec<(u32, String)>,
+    views_model: Entity<AHashMap<usize, Entity<SearchResult>>>,
+    last_match: Rc<Vec<SearchEntry>>,
+    /// The results of a structured query, which are shown instead of the matcher's.
+    structured: Option<Vec<SearchEntry>>,
+    rows: Rc<Vec<SearchRow>>,
     render_counter: Entity<usize>,
     current_selection: Entity<usize>,
 }
 
+/// Adds the entries to the matcher. The first column is matched by queries without a label prefix,
+/// the second by those with one.
+fn inject(injector: Injector<SearchEntry>, entries: Vec<SearchEntry>) {
+    for entry in entries {
+        injector.push(entry, |v, dest| {
+            let mut text = v.title.clone();
+
+            for extra in [&v.artist_name, &v.album_title].into_iter().flatten() {
+                text.push(' ');
+                text.push_str(extra);
+            }
+
+            dest[0] = Utf32String::from(text);
+            dest[1] = Utf32String::from(v.label.clone().unwrap_or_default());
+        });
+    }
+}
+
+/// Groups the matches into their sections, each preceded by a header.
+fn make_rows(matches: &[SearchEntry]) -> Vec<SearchRow> {
+    let mut rows = Vec::new();
+
+    for (kind, name) in SECTIONS {
+        let mut results = matches
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.kind == kind)
+            .peekable();
+
+        if results.peek().is_some() {
+            rows.push(SearchRow::Header(name));
+            rows.extend(results.map(|(idx, _)| SearchRow::Result(idx)));
+        }
+    }
+
+    rows
+}
+
 impl SearchModel {
     pub fn new(cx: &mut App) -> Entity<SearchModel> {
         cx.new(|cx| {
-            let albums = cx
-                .list_albums_search()
-                .expect("could not retrieve albums from db");
+            let entries = cx
+                .list_search_entries()
+                .expect("could not retrieve search entries from db");
 
             let config = Config::DEFAULT;
 
@@ -74,8 +191,7 @@ impl SearchModel {
                 weak.update(cx, |this: &mut SearchModel, cx| {
                     if !did_regenerate {
                         let matches = this.get_matches();
-                        if matches != this.last_match {
-                            this.last_match = matches;
+                        if matches != *this.last_match {
                             this.regenerate_list_state(cx);
                             cx.notify();
                         }
@@ -95,51 +211,38 @@ impl SearchModel {
             })
             .detach();
 
-            cx.subscribe(&cx.entity(), |this, _, ev: &EnrichedInputAction, cx| {
-                match ev {
+            cx.subscribe(
+                &cx.entity(),
+                |this, _, ev: &EnrichedInputAction, cx| match ev {
                     EnrichedInputAction::Previous => {
                         this.current_selection.update(cx, |this, cx| {
-                            if *this != 0 {
-                                // kinda wacky but the only way I could find to do this
-                                this.sub_assign(1);
-                            }
+                            *this = this.saturating_sub(1);
                             cx.notify();
                         });
 
-                        let idx = this.current_selection.read(cx);
-                        this.list_state.scroll_to_reveal_item(*idx);
+                        this.reveal_selection(cx);
                     }
                     EnrichedInputAction::Next => {
-                        let len = this.list_state.item_count();
+                        let len = this.last_match.len();
                         this.current_selection.update(cx, |this, cx| {
-                            if *this < len - 1 {
-                                this.add_assign(1);
+                            if *this + 1 < len {
+                                *this += 1;
                             }
                             cx.notify();
                         });
 
-                        let idx = this.current_selection.read(cx);
-                        this.list_state.scroll_to_reveal_item(*idx);
+                        this.reveal_selection(cx);
                     }
                     EnrichedInputAction::Accept => {
-                        let idx = this.current_selection.read(cx);
-                        let id = this.last_match.get(*idx).unwrap().0;
-                        let ev = ViewSwitchMessage::Release(id as i64);
-
-                        cx.emit(ev);
+                        let idx = *this.current_selection.read(cx);
+                        this.accept(idx, cx);
                     }
-                }
-            })
+                },
+            )
             .detach();
 
-            let matcher = Nucleo::new(config, notify, None, 1);
-            let injector = matcher.injector();
-
-            for album in albums {
-                injector.push(album, |v, dest| {
-                    dest[0] = Utf32String::from(v.1.clone());
-                });
-            }
+            let matcher = Nucleo::new(config, notify, None, 2);
+            inject(matcher.injector(), entries);
 
             let current_selection = cx.new(|_| 0);
 
@@ -151,19 +254,15 @@ impl SearchModel {
                 if *state == ScanEvent::ScanCompleteIdle
                     || *state == ScanEvent::ScanCompleteWatching
                 {
-                    let albums = cx
-                        .list_albums_search()
-                        .expect("could not retrieve albums from db");
-
-                    this.matcher.restart(false);
-                    let injector = this.matcher.injector();
-
-                    for album in albums {
-                        injector.push(album, |v, dest| {
-                            dest[0] = Utf32String::from(v.1.clone());
-                        });
+                    match cx.list_search_entries() {
+                        Ok(entries) => {
+                            this.matcher.restart(false);
+                            inject(this.matcher.injector(), entries);
+                        }
+                        Err(e) => error!("Could not retrieve search entries: {}", e),
                     }
 
+                    this.run_structured_search(cx);
                     cx.notify();
                 }
             })
@@ -171,17 +270,21 @@ impl SearchModel {
 
             SearchModel {
                 query: String::new(),
+                scope: SearchScope::All,
                 matcher,
                 views_model: views_model.clone(),
                 render_counter: render_counter.clone(),
                 list_state: Self::make_list_state(
                     cx.weak_entity(),
-                    None,
+                    Rc::new(Vec::new()),
+                    Rc::new(Vec::new()),
                     views_model,
                     render_counter,
                     current_selection.clone(),
                 ),
-                last_match: Vec::new(),
+                last_match: Rc::new(Vec::new()),
+                structured: None,
+                rows: Rc::new(Vec::new()),
                 current_selection,
             }
         })
@@ -189,40 +292,141 @@ impl SearchModel {
 
     pub fn set_query(&mut self, query: String, cx: &mut Context<Self>) {
         self.query = query;
-        self.matcher.pattern.reparse(
-            0,
-            &self.query,
-            CaseMatching::Smart,
-            Normalization::Smart,
-            false,
-        );
-        self.current_selection = cx.new(|_| 0);
+
+        let (scope, pattern) = SearchScope::parse(&self.query);
+        let (text, label) = if scope == SearchScope::Labels {
+            ("", pattern)
+        } else {
+            (pattern, "")
+        };
+
+        self.matcher
+            .pattern
+            .reparse(0, text, CaseMatching::Smart, Normalization::Smart, false);
+        self.matcher
+            .pattern
+            .reparse(1, label, CaseMatching::Smart, Normalization::Smart, false);
+        self.scope = scope;
+        self.run_structured_search(cx);
+
+        self.current_selection.update(cx, |this, cx| {
+            *this = 0;
+            cx.notify();
+        });
         self.list_state.scroll_to_reveal_item(0);
     }
 
+    /// Searches the full-text index instead of matching in memory, if the query uses fields or
+    /// exclusions, such as `artist:"Miles Davis" -live`.
+    fn run_structured_search(&mut self, cx: &mut Context<Self>) {
+        self.structured = None;
+
+        if self.scope != SearchScope::All {
+            return;
+        }
+
+        let (_, pattern) = SearchScope::parse(&self.query);
+        let query = match SearchQuery::parse(pattern) {
+            Ok(query) if query.structured => query,
+            Ok(_) => return,
+            Err(e) => {
+                debug!("Not a structured query: {}", e);
+                return;
+            }
+        };
+
+        // artists and albums are found through their tracks, and shown first like other results
+        let artists = cx.search_artists(&query, SearchScope::All.limit(SearchKind::Artist) as u32);
+        let albums = cx.search_albums(&query, SearchScope::All.limit(SearchKind::Album) as u32);
+        let tracks = cx.search_tracks(&query, SCOPED_LIMIT as u32);
+
+        match (artists, albums, tracks) {
+            (Ok(artists), Ok(albums), Ok(tracks)) => {
+                let tracks = tracks.into_iter().map(|track| SearchEntry {
+                    kind: SearchKind::Track,
+                    id: track.id,
+                    title: track.title.0.to_string(),
+                    artist_name: track
+                        .artist_names
+                        .or(track.album_artist)
+                        .map(|v| v.0.to_string()),
+                    album_title: track.album_title.map(|v| v.0.to_string()),
+                    label: None,
+                });
+
+                self.structured = Some(artists.into_iter().chain(albums).chain(tracks).collect())
+            }
+            (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+                error!("Could not search the library: {}", e)
+            }
+        }
+    }
+
     fn tick(&mut self) {
         self.matcher.tick(10);
     }
 
-    fn get_matches(&self) -> Vec<(u32, String)> {
+    /// The best matches of each kind, grouped in the order of `SECTIONS`.
+    fn get_matches(&self) -> Vec<SearchEntry> {
+        if let Some(structured) = self.structured.as_ref() {
+            return structured.clone();
+        }
+
         let snapshot = self.matcher.snapshot();
-        snapshot
-            .matched_items(..100.min(snapshot.matched_item_count()))
-            .map(|item| item.data.clone())
-            .collect()
+        let mut groups: [Vec<SearchEntry>; SECTIONS.len()] = Default::default();
+
+        for item in snapshot.matched_items(..snapshot.matched_item_count().min(MATCH_WINDOW)) {
+            let kind = item.data.kind;
+            let Some(section) = SECTIONS.iter().position(|(v, _)| *v == kind) else {
+                continue;
+            };
+
+            if groups[section].len() < self.scope.limit(kind) {
+                groups[section].push(item.data.clone());
+            }
+        }
+
+        groups.into_iter().flatten().collect()
+    }
+
+    fn reveal_selection(&mut self, cx: &mut Context<Self>) {
+        let idx = *self.current_selection.read(cx);
+
+        if let Some(row) = self
+            .rows
+            .iter()
+            .position(|row| *row == SearchRow::Result(idx))
+        {
+            self.list_state.scroll_to_reveal_item(row);
+        }
+    }
+
+    fn accept(&mut self, idx: usize, cx: &mut Context<Self>) {
+        let Some(entry) = self.last_match.get(idx) else {
+            return;
+        };
+
+        let ev = match entry.kind {
+            SearchKind::Artist => SearchAccept::Open(ViewSwitchMessage::Artist(entry.id)),
+            SearchKind::Album => SearchAccept::Open(ViewSwitchMessage::Release(entry.id)),
+            SearchKind::Track => SearchAccept::PlayTrack(entry.id),
+        };
+
+        cx.emit(ev);
     }
 
     fn regenerate_list_state(&mut self, cx: &mut Context<Self>) {
         debug!("Regenerating list state");
         let curr_scroll = self.list_state.logical_scroll_top();
-        let album_ids = self.get_matches();
-        debug!("Album IDs: {:?}", album_ids);
+        self.last_match = Rc::new(self.get_matches());
+        self.rows = Rc::new(make_rows(&self.last_match));
         self.views_model = cx.new(|_| AHashMap::new());
         self.render_counter = cx.new(|_| 0);
 
         self.list_state = SearchModel::make_list_state(
             cx.weak_entity(),
-            Some(album_ids),
+            self.last_match.clone(),
+            self.rows.clone(),
             self.views_model.clone(),
             self.render_counter.clone(),
             self.current_selection.clone(),
@@ -235,56 +439,56 @@ impl SearchModel {
 
     fn make_list_state(
         weak_self: WeakEntity<Self>,
-        album_ids: Option<Vec<(u32, String)>>,
-        views_model: Entity<AHashMap<usize, Entity<AlbumSearchResult>>>,
+        matches: Rc<Vec<SearchEntry>>,
+        rows: Rc<Vec<SearchRow>>,
+        views_model: Entity<AHashMap<usize, Entity<SearchResult>>>,
         render_counter: Entity<usize>,
         current_selection: Entity<usize>,
     ) -> ListState {
-        match album_ids {
-            Some(album_ids) => {
-                let album_ids_copy = Rc::new(album_ids.clone());
-                let weak_self_copy = weak_self.clone();
-
-                ListState::new(
-                    album_ids.len(),
-                    ListAlignment::Top,
-                    px(300.0),
-                    move |idx, _, cx| {
-                        let album_ids = album_ids_copy.clone();
-                        let weak_self = weak_self_copy.clone();
-                        let selection_clone = current_selection.clone();
-
-                        prune_views(&views_model, &render_counter, idx, cx);
-                        // TODO: error handling
-                        div()
+        ListState::new(
+            rows.len(),
+            ListAlignment::Top,
+            px(300.0),
+            move |idx, _, cx| {
+                let result = match rows[idx] {
+                    SearchRow::Header(name) => {
+                        let theme = cx.global::<Theme>();
+
+                        return div()
                             .w_full()
-                            .child(create_or_retrieve_view(
-                                &views_model,
-                                idx,
-                                move |cx| {
-                                    AlbumSearchResult::new(
-                                        cx,
-                                        album_ids[idx].0 as i64,
-                                        weak_self,
-                                        &selection_clone,
-                                        idx,
-                                    )
-                                },
-                                cx,
-                            ))
-                            .into_any_element()
-                    },
-                )
-            }
-            None => ListState::new(0, ListAlignment::Top, px(64.0), move |_, _, _| {
-                div().into_any_element()
-            }),
-        }
+                            .px(px(8.0))
+                            .pt(px(8.0))
+                            .pb(px(2.0))
+                            .text_xs()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(theme.text_secondary)
+                            .child(name)
+                            .into_any_element();
+                    }
+                    SearchRow::Result(result) => result,
+                };
+
+                let entry = matches[result].clone();
+                let weak_self = weak_self.clone();
+                let selection_clone = current_selection.clone();
+
+                prune_views(&views_model, &render_counter, idx, cx);
+                div()
+                    .w_full()
+                    .child(create_or_retrieve_view(
+                        &views_model,
+                        idx,
+                        move |cx| SearchResult::new(cx, entry, weak_self, &selection_clone, result),
+                        cx,
+                    ))
+                    .into_any_element()
+            },
+        )
     }
 }
 
 impl EventEmitter<String> for SearchModel {}
-impl EventEmitter<ViewSwitchMessage> for SearchModel {}
+impl EventEmitter<SearchAccept> for SearchModel {}
 impl EventEmitter<EnrichedInputAction> for SearchModel {}
 
 impl Render for SearchModel {
@@ -299,36 +503,36 @@ impl Render for SearchModel {
     }
 }
 
-struct AlbumSearchResult {
-    album: Option<Arc<Album>>,
-    artist: Option<Arc<String>>,
+struct SearchResult {
+    entry: SearchEntry,
+    /// The album's cover, for album results.
+    thumb: Option<Thumbnail>,
     weak_parent: WeakEntity<SearchModel>,
     current_selection: usize,
     idx: usize,
 }
 
-impl AlbumSearchResult {
+impl SearchResult {
     fn new(
         cx: &mut App,
-        id: i64,
+        entry: SearchEntry,
         weak_parent: WeakEntity<SearchModel>,
         current_selection: &Entity<usize>,
         idx: usize,
-    ) -> Entity<AlbumSearchResult> {
+    ) -> Entity<SearchResult> {
         cx.new(|cx| {
-            let album = cx.get_album_by_id(id, AlbumMethod::Thumbnail).ok();
-
-            let artist = album
-                .as_ref()
-                .and_then(|album| cx.get_artist_name_by_id(album.artist_id).ok());
+            let thumb = if entry.kind == SearchKind::Album {
+                cx.get_album_by_id(entry.id, AlbumMethod::Thumbnail)
+                    .ok()
+                    .and_then(|album| album.thumb.clone())
+            } else {
+                None
+            };
 
             cx.on_release(|this: &mut Self, cx: &mut App| {
-                if let Some(album) = this.album.clone() {
-                    if let Some(image) = album.thumb.clone() {
-                        drop_image_from_app(cx, image.0);
-                        this.album = None;
-                        cx.refresh_windows();
-                    }
+                if let Some(image) = this.thumb.take() {
+                    drop_image_from_app(cx, image.0);
+                    cx.refresh_windows();
                 }
             })
             .detach();
@@ -342,9 +546,9 @@ impl AlbumSearchResult {
             )
             .detach();
 
-            AlbumSearchResult {
-                album,
-                artist,
+            SearchResult {
+                entry,
+                thumb,
                 weak_parent,
                 current_selection: *current_selection.read(cx),
                 idx,
@@ -353,73 +557,101 @@ impl AlbumSearchResult {
     }
 }
 
-impl Render for AlbumSearchResult {
+impl Render for SearchResult {
     fn render(&mut self, _: &mut Window, cx: &mut Context<'_, Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
+        let entry = &self.entry;
 
-        if let Some(album) = self.album.as_ref() {
-            div()
-                .px(px(8.0))
-                .py(px(8.0))
-                .flex()
-                .cursor_pointer()
-                .id(("searchresult", album.id as u64))
-                .hover(|this| this.bg(theme.palette_item_hover))
-                .active(|this| this.bg(theme.palette_item_active))
-                .when(self.current_selection == self.idx, |this| {
-                    this.bg(theme.palette_item_hover)
-                })
-                .rounded(px(4.0))
-                .on_click(cx.listener(|this, _, _, cx| {
-                    let id = this.album.as_ref().unwrap().id;
-                    let ev = ViewSwitchMessage::Release(id);
-
-                    this.weak_parent
-                        .update(cx, |_, cx| {
-                            cx.emit(ev);
-                        })
-                        .expect("album search result exists without searchmodel");
-                }))
-                .child(
+        let detail = match (entry.artist_name.as_ref(), entry.album_title.as_ref()) {
+            (Some(artist), Some(album)) => Some(format!("{} — {}", artist, album)),
+            (Some(artist), None) => Some(artist.clone()),
+            (None, Some(album)) => Some(album.clone()),
+            (None, None) => None,
+        };
+
+        let icon = match entry.kind {
+            SearchKind::Artist => "\u{f007}",
+            SearchKind::Album => "\u{f51f}",
+            SearchKind::Track => "\u{f001}",
+        };
+
+        div()
+            .px(px(8.0))
+            .py(px(8.0))
+            .flex()
+            .cursor_pointer()
+            .id(("searchresult", self.idx as u64))
+            .hover(|this| this.bg(theme.palette_item_hover))
+            .active(|this| this.bg(theme.palette_item_active))
+            .when(self.current_selection == self.idx, |this| {
+                this.bg(theme.palette_item_hover)
+            })
+            .rounded(px(4.0))
+            .on_click(cx.listener(|this, _, _, cx| {
+                let idx = this.idx;
+
+                this.weak_parent
+                    .update(cx, |parent, cx| parent.accept(idx, cx))
+                    .expect("search result exists without searchmodel");
+            }))
+            .child(
+                div()
+                    .rounded(px(2.0))
+                    .bg(theme.album_art_background)
+                    .shadow_sm()
+                    .w(px(18.0))
+                    .h(px(18.0))
+                    .flex_shrink_0()
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .text_size(px(9.0))
+                    .text_color(theme.text_secondary)
+                    .map(|this| match self.thumb.clone() {
+                        Some(thumb) => {
+                            this.child(img(thumb.0).w(px(18.0)).h(px(18.0)).rounded(px(2.0)))
+                        }
+                        None => this.font_family(FONT_AWESOME).child(icon),
+                    }),
+            )
+            .child(
+                div()
+                    .pl(px(8.0))
+                    .mt(px(2.0))
+                    .line_height(px(14.0))
+                    .font_weight(FontWeight::BOLD)
+                    .text_sm()
+                    .flex_shrink_0()
+                    .max_w(px(300.0))
+                    .overflow_x_hidden()
+                    .text_ellipsis()
+                    .child(entry.title.clone()),
+            )
+            .when_some(detail, |this, detail| {
+                this.child(
                     div()
-                        .rounded(px(2.0))
-                        .bg(theme.album_art_background)
-                        .shadow_sm()
-                        .w(px(18.0))
-                        .h(px(18.0))
-                        .flex_shrink_0()
-                        .when(album.thumb.is_some(), |div| {
-                            div.child(
-                                img(album.thumb.clone().unwrap().0)
-                                    .w(px(18.0))
-                                    .h(px(18.0))
-                                    .rounded(px(2.0)),
-                            )
-                        }),
+                        .ml_auto()
+                        .pl(px(12.0))
+                        .mt(px(2.0))
+                        .line_height(px(14.0))
+                        .text_sm()
+                        .overflow_x_hidden()
+                        .text_ellipsis()
+                        .text_color(theme.text_secondary)
+                        .child(detail),
                 )
-                .child(
+            })
+            .when_some(entry.label.clone(), |this, label| {
+                this.child(
                     div()
-                        .pl(px(8.0))
+                        .pl(px(12.0))
                         .mt(px(2.0))
                         .line_height(px(14.0))
-                        .font_weight(FontWeight::BOLD)
                         .text_sm()
-                        .child(album.title.clone()),
+                        .flex_shrink_0()
+                        .text_color(theme.text_secondary)
+                        .child(label),
                 )
-                .when_some(self.artist.as_ref(), |this, name| {
-                    this.child(
-                        div()
-                            .ml_auto()
-                            .mt(px(2.0))
-                            .line_height(px(14.0))
-                            .text_sm()
-                            .text_color(theme.text_secondary)
-                            .child(name.deref().clone()),
-                    )
-                })
-        } else {
-            debug!("Album not found");
-            div().id("badresult")
-        }
+            })
     }
 }
@@ -62,6 +62,9 @@ pub struct Theme {
     pub slider_foreground: Rgba,
     pub slider_background: Rgba,
 
+    pub scrubber_loop: Rgba,
+    pub scrubber_bookmark: Rgba,
+
     pub elevated_background: Rgba,
     pub elevated_border_color: Rgba,
 
@@ -136,6 +139,9 @@ impl Default for Theme {
             slider_foreground: rgb(0x0673C6),
             slider_background: rgb(0x37404E),
 
+            scrubber_loop: rgba(0xEDB40766),
+            scrubber_bookmark: rgb(0xEDB407),
+
             elevated_background: rgb(0x161A22),
             elevated_border_color: rgb(0x272D37),
 
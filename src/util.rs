@@ -1,3 +1,5 @@
+use std::net::IpAddr;
+
 use image::{Pixel, RgbaImage};
 
 pub fn rgb_to_bgr(image: &mut RgbaImage) {
@@ -29,3 +31,12 @@ macro_rules! make_unknown_error_unwrap {
 
 pub(crate) use make_unknown_error;
 pub(crate) use make_unknown_error_unwrap;
+
+/// Whether the address can only be reached from this computer.
+pub fn is_loopback(address: &str) -> bool {
+    address == "localhost"
+        || address
+            .parse::<IpAddr>()
+            .map(|v| v.is_loopback())
+            .unwrap_or(false)
+}